@@ -1,16 +1,68 @@
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use rayon::iter::ParallelIterator;
+use syndex::LLHDWorld;
 
-fn fibonacci(n: u64) -> u64 {
-    match n {
-        0 => 1,
-        1 => 1,
-        n => fibonacci(n - 1) + fibonacci(n - 2),
-    }
+/// Module of `units` entities, each a tree of `and`/`or` gates over 16 inputs.
+fn gate_tree_module(units: usize) -> llhd::ir::Module {
+    let inputs = (0..16)
+        .map(|input| format!("i1 %in{}", input))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let module = (0..units)
+        .map(|unit| {
+            let mut gates = vec!["    %null = const time 0s 1e".to_owned()];
+            let mut level = (0..16)
+                .map(|input| format!("%in{}", input))
+                .collect::<Vec<_>>();
+            let mut gate = 0;
+            while level.len() > 1 {
+                level = level
+                    .chunks(2)
+                    .map(|operands| {
+                        let opcode = if gate % 2 == 0 { "and" } else { "or" };
+                        gates.push(format!(
+                            "    %g{} = {} i1 {}, {}",
+                            gate, opcode, operands[0], operands[1]
+                        ));
+                        gate += 1;
+                        format!("%g{}", gate - 1)
+                    })
+                    .collect();
+            }
+            gates.push(format!("    drv i1$ %out1, {}, %null", level[0]));
+            format!(
+                "entity @unit{} ({}) -> (i1$ %out1) {{\n{}\n}}\n",
+                unit,
+                inputs,
+                gates.join("\n")
+            )
+        })
+        .collect::<String>();
+    llhd::assembly::parse_module(module).expect("Generated module should parse.")
 }
 
-fn criterion_benchmark(c: &mut Criterion) {
-    c.bench_function("fib 20", |b| b.iter(|| fibonacci(black_box(20))));
+fn llhd_world_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("llhd_world");
+    for units in [64, 512] {
+        group.bench_with_input(BenchmarkId::new("new", units), &units, |b, units| {
+            b.iter_batched(
+                || gate_tree_module(*units),
+                |module| LLHDWorld::new(black_box(module).into()),
+                criterion::BatchSize::LargeInput,
+            )
+        });
+        let llhd_world = LLHDWorld::new(gate_tree_module(units).into());
+        group.bench_with_input(BenchmarkId::new("par_units", units), &units, |b, _units| {
+            b.iter(|| {
+                llhd_world
+                    .par_units()
+                    .map(|(unit, _unit_entity)| unit.all_insts().count())
+                    .sum::<usize>()
+            })
+        });
+    }
+    group.finish();
 }
 
-criterion_group!(benches, criterion_benchmark);
+criterion_group!(benches, llhd_world_benchmark);
 criterion_main!(benches);
@@ -1,3 +1,4 @@
+pub(crate) mod analysis;
 pub mod netlist;
 #[macro_use]
 pub mod macros;
@@ -6,7 +7,9 @@ pub(crate) mod equations;
 mod evalexpr_doc_example;
 pub(crate) mod graph;
 mod mhgl_doc_example;
+pub(crate) mod models;
 pub(crate) mod nodes;
+pub(crate) mod values;
 
 /// Parser for SPICE Netlist
 ///
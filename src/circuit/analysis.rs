@@ -0,0 +1,667 @@
+pub(crate) mod dc;
+pub(crate) mod sources;
+pub(crate) mod transient;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+use self::dc::DcConfig;
+use self::transient::TransientStep;
+use super::equations::expression::{EquationError, Expression};
+use super::equations::DeviceEquation;
+use super::graph::nodes::{ElementHNode, ElementKind};
+use super::graph::LCircuit;
+use super::nodes::CircuitNode;
+use super::values::parse_spice_value;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnalysisError {
+    /// Element value is missing, zero where not allowed, or not a SPICE number, also names an
+    /// out of range `.tran` parameter(`tstep`, `tstop`, `tstart`, `tmax`).
+    InvalidValue(String),
+    /// Nonlinear element without a `DeviceEquation`.
+    MissingDeviceEquation(String),
+    DeviceEvaluation {
+        element: String,
+        message: String,
+    },
+    SingularMatrix,
+    NoConvergence {
+        iterations: usize,
+    },
+    /// Transient step size fell below the minimum step at `time`.
+    TimestepTooSmall {
+        time: f64,
+    },
+}
+
+impl fmt::Display for AnalysisError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidValue(name) => write!(f, "Invalid value for {}", name),
+            Self::MissingDeviceEquation(element) => {
+                write!(f, "No device equation for element {}", element)
+            }
+            Self::DeviceEvaluation { element, message } => {
+                write!(f, "Device equation of {} failed: {}", element, message)
+            }
+            Self::SingularMatrix => write!(f, "Singular MNA matrix"),
+            Self::NoConvergence { iterations } => {
+                write!(
+                    f,
+                    "Newton-Raphson did not converge in {} iterations",
+                    iterations
+                )
+            }
+            Self::TimestepTooSmall { time } => {
+                write!(f, "Timestep too small at t = {}", time)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AnalysisError {}
+
+/// How reactive elements and sources are stamped into the MNA system.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum MnaMode<'step> {
+    /// Capacitors open, inductors shorted, independent sources scaled by `source_scale`.
+    OperatingPoint { source_scale: f64 },
+    /// Capacitor and inductor companion models and source values of one integration step.
+    Transient(&'step TransientStep),
+}
+
+/// Controlling voltage of a device, its name and the terminals it is measured between, `None`
+/// for ground.
+type ControllingVoltage = (&'static str, usize, Option<usize>);
+
+/// Controlling voltages between the `d g s b` terminals of four-terminal devices.
+const FOUR_TERMINAL_VOLTAGES: [ControllingVoltage; 6] = [
+    ("vgs", 1, Some(2)),
+    ("vgd", 1, Some(0)),
+    ("vgb", 1, Some(3)),
+    ("vds", 0, Some(2)),
+    ("vdb", 0, Some(3)),
+    ("vsb", 2, Some(3)),
+];
+
+/// Controlling voltages of two-terminal devices, `vd` is the diode name of `vpn`.
+const TWO_TERMINAL_VOLTAGES: [ControllingVoltage; 4] = [
+    ("vp", 0, None),
+    ("vn", 1, None),
+    ("vpn", 0, Some(1)),
+    ("vd", 0, Some(1)),
+];
+
+/// `LCircuit` element resolved to MNA unknown indices, `None` terminals are ground.
+#[derive(Debug, Clone)]
+pub(crate) struct MnaElement {
+    pub(crate) element: ElementHNode,
+    pub(crate) terminals: Vec<Option<usize>>,
+    pub(crate) value: f64,
+    pub(crate) branch: Option<usize>,
+    device: Option<DeviceEquation>,
+    /// Partial derivatives of the device current by the controlling voltages it reads.
+    conductances: Vec<(ControllingVoltage, Expression)>,
+    params: Vec<(String, f64)>,
+}
+
+impl MnaElement {
+    pub(crate) fn name(&self) -> &str {
+        self.element.name()
+    }
+
+    pub(crate) fn kind(&self) -> ElementKind {
+        *self.element.kind()
+    }
+
+    fn terminal_voltage(&self, terminal: usize, x: &[f64]) -> f64 {
+        self.terminals
+            .get(terminal)
+            .copied()
+            .flatten()
+            .map_or(0.0, |idx| x[idx])
+    }
+
+    /// Terminals the element current flows between, drain to source for transistors.
+    fn current_terminals(&self) -> (usize, usize) {
+        if self.is_four_terminal() {
+            (0, 2)
+        } else {
+            (0, 1)
+        }
+    }
+
+    fn is_four_terminal(&self) -> bool {
+        matches!(
+            self.kind(),
+            ElementKind::MosTransistor | ElementKind::Subcircuit
+        ) && self.terminals.len() >= 4
+    }
+
+    fn controlling_voltage_terminals(&self) -> &'static [ControllingVoltage] {
+        if self.is_four_terminal() {
+            &FOUR_TERMINAL_VOLTAGES
+        } else {
+            &TWO_TERMINAL_VOLTAGES
+        }
+    }
+
+    fn controlling_voltages(&self, x: &[f64]) -> Vec<(&'static str, f64)> {
+        self.controlling_voltage_terminals()
+            .iter()
+            .map(|(name, p, n)| {
+                let vn = n.map_or(0.0, |n| self.terminal_voltage(n, x));
+                (*name, self.terminal_voltage(*p, x) - vn)
+            })
+            .collect()
+    }
+
+    fn evaluation_error(&self, error: EquationError) -> AnalysisError {
+        AnalysisError::DeviceEvaluation {
+            element: self.name().to_owned(),
+            message: error.to_string(),
+        }
+    }
+
+    /// Symbolic derivatives of the `DeviceEquation` by each controlling voltage it reads.
+    fn device_conductance_equations(
+        &self,
+        device: &DeviceEquation,
+    ) -> Result<Vec<(ControllingVoltage, Expression)>, AnalysisError> {
+        let variables = device.variables();
+        self.controlling_voltage_terminals()
+            .iter()
+            .filter(|(name, _p, _n)| variables.contains(*name))
+            .map(|voltage| {
+                device
+                    .partial_derivative(voltage.0)
+                    .map(|derivative| (*voltage, derivative))
+                    .map_err(|error| self.evaluation_error(error))
+            })
+            .collect()
+    }
+
+    /// Controlling voltages, numeric parameters and `T`.
+    fn device_variables(&self, x: &[f64], temperature: f64) -> HashMap<String, f64> {
+        std::iter::once(("T", temperature))
+            .chain(
+                self.params
+                    .iter()
+                    .map(|(name, value)| (name.as_str(), *value)),
+            )
+            .chain(self.controlling_voltages(x))
+            .map(|(name, value)| (name.to_owned(), value))
+            .collect()
+    }
+
+    /// Evaluates the `DeviceEquation` with controlling voltages, numeric parameters and `T`
+    /// bound, reading `I` or the value of the final expression.
+    fn device_current(&self, x: &[f64], temperature: f64) -> Result<f64, AnalysisError> {
+        let device = self
+            .device
+            .as_ref()
+            .ok_or_else(|| AnalysisError::MissingDeviceEquation(self.name().to_owned()))?;
+        device
+            .evaluate(&self.device_variables(x, temperature))
+            .map_err(|error| self.evaluation_error(error))
+    }
+
+    /// Derivative of the device current by each unknown of its terminals.
+    fn device_conductances(
+        &self,
+        x: &[f64],
+        temperature: f64,
+    ) -> Result<Vec<(usize, f64)>, AnalysisError> {
+        let variables = self.device_variables(x, temperature);
+        let terminal = |terminal: usize| self.terminals.get(terminal).copied().flatten();
+        self.conductances.iter().try_fold(
+            Vec::new(),
+            |mut conductances, ((_name, p, n), derivative)| {
+                let conductance = derivative
+                    .evaluate(&mut variables.to_owned())
+                    .map_err(|error| self.evaluation_error(error))?;
+                conductances.extend(terminal(*p).map(|idx| (idx, conductance)));
+                conductances.extend(n.and_then(terminal).map(|idx| (idx, -conductance)));
+                Ok(conductances)
+            },
+        )
+    }
+}
+
+/// Modified nodal analysis layout of an `LCircuit`.
+///
+/// Unknowns are the non-ground node voltages followed by one branch current per voltage source
+/// and inductor. Residuals are the currents leaving each node and the branch equations.
+#[derive(Debug, Clone)]
+pub(crate) struct MnaSystem {
+    pub(crate) nodes: BTreeMap<CircuitNode, usize>,
+    pub(crate) elements: Vec<MnaElement>,
+    pub(crate) temperature: f64,
+    /// Node unknowns attached to a nonlinear device, limited during Newton iterations.
+    limited: Vec<bool>,
+    size: usize,
+}
+
+impl MnaSystem {
+    pub(crate) fn new(circuit: &LCircuit, temperature: f64) -> Result<Self, AnalysisError> {
+        let circuit_elements = circuit
+            .elements()
+            .map(|(_element_id, element)| element)
+            .filter(|element| *element.kind() != ElementKind::Port)
+            .collect::<Vec<_>>();
+        let nodes = circuit_elements
+            .iter()
+            .flat_map(|element| element.terminals())
+            .filter(|node| !node.is_ground())
+            .cloned()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .enumerate()
+            .map(|(idx, node)| (node, idx))
+            .collect::<BTreeMap<_, _>>();
+        let mut size = nodes.len();
+        let mut limited = vec![false; nodes.len()];
+        let elements = circuit_elements
+            .into_iter()
+            .map(|element| {
+                let terminals = element
+                    .terminals()
+                    .iter()
+                    .map(|node| nodes.get(node).copied())
+                    .collect::<Vec<_>>();
+                let branch = match element.kind() {
+                    ElementKind::VoltageSource | ElementKind::Inductor => {
+                        size += 1;
+                        Some(size - 1)
+                    }
+                    _ => None,
+                };
+                let value = element_value(element)?;
+                let device = match element.kind() {
+                    ElementKind::Diode | ElementKind::MosTransistor | ElementKind::Subcircuit => {
+                        terminals
+                            .iter()
+                            .flatten()
+                            .for_each(|idx| limited[*idx] = true);
+                        Some(element.device().to_owned().ok_or_else(|| {
+                            AnalysisError::MissingDeviceEquation(element.name().to_owned())
+                        })?)
+                    }
+                    _ => None,
+                };
+                let params = element
+                    .params()
+                    .iter()
+                    .filter_map(|(name, value)| {
+                        parse_spice_value(value).map(|value| (name.to_ascii_lowercase(), value))
+                    })
+                    .collect();
+                let mut mna_element = MnaElement {
+                    element: element.to_owned(),
+                    terminals,
+                    value,
+                    branch,
+                    device: None,
+                    conductances: Vec::new(),
+                    params,
+                };
+                if let Some(device) = device {
+                    mna_element.conductances = mna_element.device_conductance_equations(&device)?;
+                    mna_element.device = Some(device);
+                }
+                Ok(mna_element)
+            })
+            .collect::<Result<Vec<_>, AnalysisError>>()?;
+        limited.resize(size, false);
+        Ok(Self {
+            nodes,
+            elements,
+            temperature,
+            limited,
+            size,
+        })
+    }
+
+    pub(crate) fn size(&self) -> usize {
+        self.size
+    }
+
+    pub(crate) fn device_current(
+        &self,
+        element: &MnaElement,
+        x: &[f64],
+    ) -> Result<f64, AnalysisError> {
+        element.device_current(x, self.temperature)
+    }
+
+    /// Current of `element` at `x`, `p` to `n` or drain to source. Sources and capacitors take
+    /// their value from the analysis.
+    pub(crate) fn element_current(
+        &self,
+        element: &MnaElement,
+        x: &[f64],
+        source_value: f64,
+        capacitor_current: f64,
+    ) -> Result<f64, AnalysisError> {
+        Ok(match element.kind() {
+            ElementKind::Resistor => {
+                (element.terminal_voltage(0, x) - element.terminal_voltage(1, x)) / element.value
+            }
+            ElementKind::VoltageSource | ElementKind::Inductor => {
+                element.branch.map_or(0.0, |branch| x[branch])
+            }
+            ElementKind::CurrentSource => source_value,
+            ElementKind::Capacitor => capacitor_current,
+            ElementKind::Diode | ElementKind::MosTransistor | ElementKind::Subcircuit => {
+                self.device_current(element, x)?
+            }
+            ElementKind::Port => 0.0,
+        })
+    }
+
+    /// Jacobian and residual of the MNA equations at `x`, with `gmin` from every node to ground.
+    pub(crate) fn assemble(
+        &self,
+        x: &[f64],
+        mode: &MnaMode,
+        gmin: f64,
+    ) -> Result<(Vec<Vec<f64>>, Vec<f64>), AnalysisError> {
+        let mut jacobian = vec![vec![0.0; self.size]; self.size];
+        let mut residual = vec![0.0; self.size];
+        self.nodes.values().for_each(|idx| {
+            jacobian[*idx][*idx] += gmin;
+            residual[*idx] += gmin * x[*idx];
+        });
+        self.elements
+            .iter()
+            .enumerate()
+            .try_for_each(|(element_idx, element)| {
+                let (p, n) = element.current_terminals();
+                let (p, n) = (
+                    element.terminals.get(p).copied().flatten(),
+                    element.terminals.get(n).copied().flatten(),
+                );
+                let source_value = match mode {
+                    MnaMode::OperatingPoint { source_scale } => source_scale * element.value,
+                    MnaMode::Transient(step) => step.source_values[element_idx],
+                };
+                match element.kind() {
+                    ElementKind::Resistor => {
+                        let conductance = 1.0 / element.value;
+                        let current = conductance
+                            * (element.terminal_voltage(0, x) - element.terminal_voltage(1, x));
+                        stamp_conductance(&mut jacobian, p, n, conductance);
+                        stamp_current(&mut residual, p, n, current);
+                    }
+                    ElementKind::Capacitor => {
+                        if let MnaMode::Transient(step) = mode {
+                            let (conductance, current) =
+                                step.capacitor_companion(element_idx, element, x);
+                            stamp_conductance(&mut jacobian, p, n, conductance);
+                            stamp_current(&mut residual, p, n, current);
+                        }
+                    }
+                    ElementKind::Port => {}
+                    ElementKind::VoltageSource => {
+                        stamp_branch(&mut jacobian, &mut residual, x, element, source_value);
+                    }
+                    ElementKind::Inductor => match (mode, element.branch) {
+                        (MnaMode::Transient(step), Some(branch)) => {
+                            let (resistance, voltage) =
+                                step.inductor_companion(element_idx, element);
+                            stamp_branch(&mut jacobian, &mut residual, x, element, voltage);
+                            jacobian[branch][branch] -= resistance;
+                            residual[branch] -= resistance * x[branch];
+                        }
+                        _ => stamp_branch(&mut jacobian, &mut residual, x, element, 0.0),
+                    },
+                    ElementKind::CurrentSource => {
+                        stamp_current(&mut residual, p, n, source_value);
+                    }
+                    ElementKind::Diode | ElementKind::MosTransistor | ElementKind::Subcircuit => {
+                        let current = self.device_current(element, x)?;
+                        stamp_current(&mut residual, p, n, current);
+                        element
+                            .device_conductances(x, self.temperature)?
+                            .into_iter()
+                            .for_each(|(idx, conductance)| {
+                                if let Some(p) = p {
+                                    jacobian[p][idx] += conductance;
+                                }
+                                if let Some(n) = n {
+                                    jacobian[n][idx] -= conductance;
+                                }
+                            });
+                    }
+                }
+                Ok::<(), AnalysisError>(())
+            })?;
+        Ok((jacobian, residual))
+    }
+
+    /// Newton-Raphson from `x`, limiting the voltage step of nodes attached to nonlinear devices.
+    pub(crate) fn newton(
+        &self,
+        mut x: Vec<f64>,
+        mode: &MnaMode,
+        config: &DcConfig,
+    ) -> Result<(Vec<f64>, usize), AnalysisError> {
+        for iteration in 1..=*config.max_iterations() {
+            let (jacobian, residual) = self.assemble(&x, mode, *config.gmin())?;
+            let delta = solve_linear(jacobian, residual.into_iter().map(|r| -r).collect())?;
+            let mut converged = true;
+            delta.into_iter().enumerate().for_each(|(idx, step)| {
+                let is_node = idx < self.nodes.len();
+                let step = if self.limited[idx] {
+                    step.clamp(-config.voltage_limit(), *config.voltage_limit())
+                } else {
+                    step
+                };
+                let updated = x[idx] + step;
+                let tolerance = if is_node {
+                    *config.vntol()
+                } else {
+                    *config.abstol()
+                } + config.reltol() * updated.abs().max(x[idx].abs());
+                if step.abs() > tolerance || step.is_nan() {
+                    converged = false;
+                }
+                x[idx] = updated;
+            });
+            if x.iter().any(|value| !value.is_finite()) {
+                break;
+            }
+            if converged {
+                return Ok((x, iteration));
+            }
+        }
+        Err(AnalysisError::NoConvergence {
+            iterations: *config.max_iterations(),
+        })
+    }
+}
+
+fn element_value(element: &ElementHNode) -> Result<f64, AnalysisError> {
+    let invalid_value = || AnalysisError::InvalidValue(element.name().to_owned());
+    match element.kind() {
+        ElementKind::Resistor => element
+            .value()
+            .as_deref()
+            .and_then(parse_spice_value)
+            .or_else(|| model_resistance(element))
+            .filter(|value| *value != 0.0)
+            .ok_or_else(invalid_value),
+        ElementKind::Capacitor | ElementKind::Inductor => element
+            .value()
+            .as_deref()
+            .and_then(parse_spice_value)
+            .filter(|value| *value != 0.0)
+            .ok_or_else(invalid_value),
+        ElementKind::VoltageSource | ElementKind::CurrentSource => {
+            source_dc_value(element).ok_or_else(invalid_value)
+        }
+        _ => Ok(0.0),
+    }
+}
+
+/// Resistance `R` of a resistor referencing a `.model` card, its `DeviceEquation` evaluated with
+/// the element parameters(`w`, `l`) and no voltage across it.
+fn model_resistance(element: &ElementHNode) -> Option<f64> {
+    let mut variables = element
+        .params()
+        .iter()
+        .filter_map(|(name, value)| {
+            parse_spice_value(value).map(|value| (name.to_ascii_lowercase(), value))
+        })
+        .chain(["vp", "vn", "vpn", "vd"].map(|name| (name.to_owned(), 0.0)))
+        .collect::<HashMap<_, _>>();
+    element
+        .device()
+        .as_ref()?
+        .expression()
+        .evaluate(&mut variables)
+        .ok()?;
+    variables.get("R").copied()
+}
+
+/// DC value of an independent source: its value, its `dc` parameter, or the `t=0` value of its
+/// `pulse`, `sin` or `pwl` function.
+pub(crate) fn source_dc_value(element: &ElementHNode) -> Option<f64> {
+    let first_number = |param_name: &str, position: usize| {
+        element
+            .param(param_name)
+            .and_then(|values| values.split_whitespace().nth(position))
+            .map(parse_spice_value)
+    };
+    match element.value() {
+        Some(value) => parse_spice_value(value),
+        None => element
+            .param("dc")
+            .map(parse_spice_value)
+            .or_else(|| first_number("pulse", 0))
+            .or_else(|| first_number("sin", 0))
+            .or_else(|| first_number("pwl", 1))
+            .unwrap_or(Some(0.0)),
+    }
+}
+
+/// Current leaving `p` and entering `n`.
+fn stamp_current(residual: &mut [f64], p: Option<usize>, n: Option<usize>, current: f64) {
+    if let Some(p) = p {
+        residual[p] += current;
+    }
+    if let Some(n) = n {
+        residual[n] -= current;
+    }
+}
+
+fn stamp_conductance(
+    jacobian: &mut [Vec<f64>],
+    p: Option<usize>,
+    n: Option<usize>,
+    conductance: f64,
+) {
+    if let Some(p) = p {
+        jacobian[p][p] += conductance;
+    }
+    if let Some(n) = n {
+        jacobian[n][n] += conductance;
+    }
+    if let (Some(p), Some(n)) = (p, n) {
+        jacobian[p][n] -= conductance;
+        jacobian[n][p] -= conductance;
+    }
+}
+
+/// Voltage source branch: current `x[branch]` flows from `p` through the element to `n`, and
+/// `v(p) - v(n) = voltage`.
+fn stamp_branch(
+    jacobian: &mut [Vec<f64>],
+    residual: &mut [f64],
+    x: &[f64],
+    element: &MnaElement,
+    voltage: f64,
+) {
+    let Some(branch) = element.branch else {
+        return;
+    };
+    let (p, n) = (element.terminals[0], element.terminals[1]);
+    stamp_current(residual, p, n, x[branch]);
+    residual[branch] += element.terminal_voltage(0, x) - element.terminal_voltage(1, x) - voltage;
+    if let Some(p) = p {
+        jacobian[p][branch] += 1.0;
+        jacobian[branch][p] += 1.0;
+    }
+    if let Some(n) = n {
+        jacobian[n][branch] -= 1.0;
+        jacobian[branch][n] -= 1.0;
+    }
+}
+
+/// Solves `a * x = b` by Gaussian elimination with partial pivoting.
+pub(crate) fn solve_linear(
+    mut a: Vec<Vec<f64>>,
+    mut b: Vec<f64>,
+) -> Result<Vec<f64>, AnalysisError> {
+    let size = b.len();
+    for column in 0..size {
+        let pivot = (column..size)
+            .max_by(|row1, row2| a[*row1][column].abs().total_cmp(&a[*row2][column].abs()))
+            .ok_or(AnalysisError::SingularMatrix)?;
+        if a[pivot][column].abs() <= 1e-300 || a[pivot][column].is_nan() {
+            return Err(AnalysisError::SingularMatrix);
+        }
+        a.swap(column, pivot);
+        b.swap(column, pivot);
+        let (pivot_rows, rows) = a.split_at_mut(column + 1);
+        let (pivot_b, rows_b) = b.split_at_mut(column + 1);
+        let pivot_row = &pivot_rows[column];
+        rows.iter_mut()
+            .zip(rows_b.iter_mut())
+            .for_each(|(row, row_b)| {
+                let factor = row[column] / pivot_row[column];
+                if factor != 0.0 {
+                    row.iter_mut()
+                        .zip(pivot_row)
+                        .skip(column)
+                        .for_each(|(value, pivot_value)| *value -= factor * pivot_value);
+                    *row_b -= factor * pivot_b[column];
+                }
+            });
+    }
+    let mut x = vec![0.0; size];
+    (0..size).rev().for_each(|row| {
+        let sum = (row + 1..size).fold(b[row], |sum, col| sum - a[row][col] * x[col]);
+        x[row] = sum / a[row][row];
+    });
+    Ok(x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn solve_pivoted_linear_system() {
+        let a = vec![
+            vec![0.0, 2.0, 1.0],
+            vec![1.0, 1.0, 0.0],
+            vec![2.0, 0.0, 3.0],
+        ];
+        let x = solve_linear(a, vec![7.0, 3.0, 11.0]).unwrap();
+        [1.0, 2.0, 3.0]
+            .iter()
+            .zip(x)
+            .for_each(|(expected, value)| assert!((expected - value).abs() < 1e-12));
+    }
+
+    #[test]
+    fn singular_linear_system() {
+        let a = vec![vec![1.0, 2.0], vec![2.0, 4.0]];
+        assert_eq!(
+            Err(AnalysisError::SingularMatrix),
+            solve_linear(a, vec![1.0, 2.0])
+        );
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use derive_getters::Getters;
+use typed_builder::TypedBuilder;
+
+use super::{AnalysisError, MnaMode, MnaSystem};
+use crate::circuit::graph::LCircuit;
+use crate::circuit::nodes::CircuitNode;
+
+/// Newton-Raphson options, shared by the analyses that solve an operating point.
+#[derive(Debug, Clone, TypedBuilder, Getters)]
+pub struct DcConfig {
+    #[builder(default = 100)]
+    max_iterations: usize,
+    /// Branch current tolerance, in A.
+    #[builder(default = 1e-12)]
+    abstol: f64,
+    /// Node voltage tolerance, in V.
+    #[builder(default = 1e-6)]
+    vntol: f64,
+    #[builder(default = 1e-3)]
+    reltol: f64,
+    /// Conductance from every node to ground, keeps floating nodes solvable.
+    #[builder(default = 1e-12)]
+    gmin: f64,
+    /// Largest Newton step of a node attached to a nonlinear device, in V.
+    #[builder(default = 0.5)]
+    voltage_limit: f64,
+    /// Source stepping increments tried when plain Newton-Raphson does not converge.
+    #[builder(default = 10)]
+    source_steps: usize,
+    /// Device temperature `T`, in K.
+    #[builder(default = 300.15)]
+    temperature: f64,
+}
+
+impl Default for DcConfig {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Getters)]
+pub struct DcSolution {
+    node_voltages: BTreeMap<CircuitNode, f64>,
+    /// Element currents, `p` to `n` for two-terminal elements and drain to source for
+    /// transistors.
+    branch_currents: BTreeMap<String, f64>,
+    iterations: usize,
+}
+
+impl DcSolution {
+    pub fn voltage(&self, node_name: &str) -> Option<f64> {
+        let node = CircuitNode::from_str(node_name).ok()?;
+        if node.is_ground() {
+            Some(0.0)
+        } else {
+            self.node_voltages.get(&node).copied()
+        }
+    }
+
+    pub fn current(&self, element_name: &str) -> Option<f64> {
+        self.branch_currents.get(element_name).copied()
+    }
+}
+
+/// DC operating point of an `LCircuit` by Newton-Raphson on its MNA equations.
+///
+/// Capacitors are open and inductors shorted. Falls back to source stepping when Newton-Raphson
+/// from zero does not converge.
+pub fn dc_operating_point(
+    circuit: &LCircuit,
+    config: &DcConfig,
+) -> Result<DcSolution, AnalysisError> {
+    let system = MnaSystem::new(circuit, *config.temperature())?;
+    let (x, iterations) = solve_operating_point(&system, config)?;
+    operating_point_solution(&system, &x, iterations)
+}
+
+pub(crate) fn solve_operating_point(
+    system: &MnaSystem,
+    config: &DcConfig,
+) -> Result<(Vec<f64>, usize), AnalysisError> {
+    let initial = vec![0.0; system.size()];
+    match system.newton(
+        initial.clone(),
+        &MnaMode::OperatingPoint { source_scale: 1.0 },
+        config,
+    ) {
+        Err(AnalysisError::NoConvergence { .. }) => source_stepping(system, initial, config),
+        solution => solution,
+    }
+}
+
+fn source_stepping(
+    system: &MnaSystem,
+    initial: Vec<f64>,
+    config: &DcConfig,
+) -> Result<(Vec<f64>, usize), AnalysisError> {
+    let steps = (*config.source_steps()).max(1);
+    (1..=steps).try_fold((initial, 0), |(x, iterations), step| {
+        let mode = MnaMode::OperatingPoint {
+            source_scale: step as f64 / steps as f64,
+        };
+        let (x, step_iterations) = system.newton(x, &mode, config)?;
+        Ok((x, iterations + step_iterations))
+    })
+}
+
+pub(crate) fn operating_point_solution(
+    system: &MnaSystem,
+    x: &[f64],
+    iterations: usize,
+) -> Result<DcSolution, AnalysisError> {
+    let node_voltages = system
+        .nodes
+        .iter()
+        .map(|(node, idx)| (node.clone(), x[*idx]))
+        .collect();
+    let branch_currents = system
+        .elements
+        .iter()
+        .map(|element| {
+            Ok((
+                element.name().to_owned(),
+                system.element_current(element, x, element.value, 0.0)?,
+            ))
+        })
+        .collect::<Result<_, AnalysisError>>()?;
+    Ok(DcSolution {
+        node_voltages,
+        branch_currents,
+        iterations,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::equations::DeviceEquation;
+    use crate::circuit::graph::nodes::{ElementHNode, ElementKind};
+
+    fn element(name: &str, kind: ElementKind, terminals: &[&str], value: &str) -> ElementHNode {
+        ElementHNode::builder()
+            .name(name)
+            .kind(kind)
+            .terminals(
+                terminals
+                    .iter()
+                    .map(|terminal| CircuitNode::from_str(terminal).unwrap())
+                    .collect(),
+            )
+            .value(value)
+            .build()
+    }
+
+    fn device(
+        name: &str,
+        kind: ElementKind,
+        terminals: &[&str],
+        device_equation: &str,
+    ) -> ElementHNode {
+        ElementHNode {
+            value: None,
+            device: Some(DeviceEquation::from_str(device_equation).unwrap()),
+            ..element(name, kind, terminals, "")
+        }
+    }
+
+    fn assert_close(expected: f64, value: Option<f64>) {
+        let value = value.unwrap();
+        assert!(
+            (expected - value).abs() < 1e-6,
+            "Expected {}, got {}.",
+            expected,
+            value
+        );
+    }
+
+    #[test]
+    fn resistor_divider() {
+        let circuit = LCircuit::from_elements([
+            element("v1", ElementKind::VoltageSource, &["1", "0"], "10"),
+            element("r1", ElementKind::Resistor, &["1", "2"], "1k"),
+            element("r2", ElementKind::Resistor, &["2", "0"], "3k"),
+        ]);
+        let solution = dc_operating_point(&circuit, &DcConfig::default()).unwrap();
+        assert_close(10.0, solution.voltage("1"));
+        assert_close(7.5, solution.voltage("2"));
+        assert_close(0.0, solution.voltage("0"));
+        assert_close(-2.5e-3, solution.current("v1"));
+        assert_close(2.5e-3, solution.current("r2"));
+    }
+
+    #[test]
+    fn current_source_into_resistor() {
+        let circuit = LCircuit::from_elements([
+            element("i1", ElementKind::CurrentSource, &["0", "1"], "1m"),
+            element("r1", ElementKind::Resistor, &["1", "0"], "2k"),
+            element("c1", ElementKind::Capacitor, &["1", "0"], "1u"),
+        ]);
+        let solution = dc_operating_point(&circuit, &DcConfig::default()).unwrap();
+        assert_close(2.0, solution.voltage("1"));
+        assert_close(0.0, solution.current("c1"));
+    }
+
+    #[test]
+    fn diode_resistor_newton() {
+        let diode_equation = indoc::indoc! {"
+            e = 2.718281828459045;
+            Is = 1e-12;
+            eta = 1.5;
+            Vt = T/11586;
+            I = Is*(e^(vd/(eta*Vt)) - 1)
+        "};
+        let circuit = LCircuit::from_elements([
+            element("v1", ElementKind::VoltageSource, &["1", "0"], "5"),
+            element("r1", ElementKind::Resistor, &["1", "2"], "1k"),
+            device("d1", ElementKind::Diode, &["2", "0"], diode_equation),
+        ]);
+        let config = DcConfig::builder().reltol(1e-9).build();
+        let solution = dc_operating_point(&circuit, &config).unwrap();
+        let vd = solution.voltage("2").unwrap();
+        let vt = 300.15 / 11586.0;
+        let diode_current = 1e-12 * ((vd / (1.5 * vt)).exp() - 1.0);
+        assert!(vd > 0.8 && vd < 0.9, "Diode voltage {} out of range.", vd);
+        assert!(
+            ((5.0 - vd) / 1e3 - diode_current).abs() < 1e-9,
+            "KCL should hold at the diode node."
+        );
+        assert_close(diode_current, solution.current("d1"));
+        assert!(*solution.iterations() > 2);
+    }
+
+    #[test]
+    fn nmos_common_source() {
+        let nmos_equation = "vov = max(vgs - 1.0, 0.0); I = 0.5e-3 * vov * vov";
+        let circuit = LCircuit::from_elements([
+            element("vdd", ElementKind::VoltageSource, &["1", "0"], "5"),
+            element("vg", ElementKind::VoltageSource, &["2", "0"], "2"),
+            element("rd", ElementKind::Resistor, &["1", "3"], "1k"),
+            device(
+                "m1",
+                ElementKind::MosTransistor,
+                &["3", "2", "0", "0"],
+                nmos_equation,
+            ),
+        ]);
+        let solution = dc_operating_point(&circuit, &DcConfig::default()).unwrap();
+        assert_close(4.5, solution.voltage("3"));
+        assert_close(0.5e-3, solution.current("m1"));
+        assert_close(-0.5e-3, solution.current("vdd"));
+    }
+
+    #[test]
+    fn missing_device_equation() {
+        let circuit = LCircuit::from_elements([
+            element("v1", ElementKind::VoltageSource, &["1", "0"], "5"),
+            ElementHNode {
+                value: None,
+                ..element("d1", ElementKind::Diode, &["1", "0"], "")
+            },
+        ]);
+        assert_eq!(
+            Err(AnalysisError::MissingDeviceEquation("d1".to_owned())),
+            dc_operating_point(&circuit, &DcConfig::default())
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use std::f64::consts::PI;
+
+use crate::circuit::graph::nodes::ElementHNode;
+use crate::circuit::values::parse_spice_value;
+
+/// Time-dependent value of an independent source, with SPICE defaults resolved against the
+/// `.tran` step and stop time.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SourceWaveform {
+    Dc(f64),
+    Pulse {
+        v1: f64,
+        v2: f64,
+        delay: f64,
+        rise: f64,
+        fall: f64,
+        width: f64,
+        period: f64,
+    },
+    Sin {
+        offset: f64,
+        amplitude: f64,
+        frequency: f64,
+        delay: f64,
+        damping: f64,
+    },
+    /// `(time, value)` corners, in increasing time.
+    Pwl(Vec<(f64, f64)>),
+}
+
+impl SourceWaveform {
+    /// Waveform of a source `ElementHNode` from its `pulse`, `sin` or `pwl` parameter, falling back
+    /// to `dc_value`.
+    pub fn from_element(element: &ElementHNode, dc_value: f64, tstep: f64, tstop: f64) -> Self {
+        let numbers = |param_name: &str| {
+            element.param(param_name).map(|values| {
+                values
+                    .split_whitespace()
+                    .filter_map(parse_spice_value)
+                    .collect::<Vec<_>>()
+            })
+        };
+        let positive_or = |value: Option<&f64>, default: f64| {
+            value
+                .copied()
+                .filter(|value| *value > 0.0)
+                .unwrap_or(default)
+        };
+        if let Some(pulse) = numbers("pulse") {
+            Self::Pulse {
+                v1: pulse.first().copied().unwrap_or(0.0),
+                v2: pulse.get(1).copied().unwrap_or(0.0),
+                delay: pulse.get(2).copied().unwrap_or(0.0),
+                rise: positive_or(pulse.get(3), tstep),
+                fall: positive_or(pulse.get(4), tstep),
+                width: positive_or(pulse.get(5), tstop),
+                period: positive_or(pulse.get(6), tstop),
+            }
+        } else if let Some(sin) = numbers("sin") {
+            Self::Sin {
+                offset: sin.first().copied().unwrap_or(0.0),
+                amplitude: sin.get(1).copied().unwrap_or(0.0),
+                frequency: positive_or(sin.get(2), 1.0 / tstop),
+                delay: sin.get(3).copied().unwrap_or(0.0),
+                damping: sin.get(4).copied().unwrap_or(0.0),
+            }
+        } else if let Some(pwl) = numbers("pwl") {
+            Self::Pwl(
+                pwl.chunks_exact(2)
+                    .map(|corner| (corner[0], corner[1]))
+                    .collect(),
+            )
+        } else {
+            Self::Dc(dc_value)
+        }
+    }
+
+    pub fn value_at(&self, time: f64) -> f64 {
+        match self {
+            Self::Dc(value) => *value,
+            Self::Pulse {
+                v1,
+                v2,
+                delay,
+                rise,
+                fall,
+                width,
+                period,
+            } => {
+                if time < *delay {
+                    return *v1;
+                }
+                let phase = (time - delay) % period;
+                if phase < *rise {
+                    v1 + (v2 - v1) * phase / rise
+                } else if phase < rise + width {
+                    *v2
+                } else if phase < rise + width + fall {
+                    v2 + (v1 - v2) * (phase - rise - width) / fall
+                } else {
+                    *v1
+                }
+            }
+            Self::Sin {
+                offset,
+                amplitude,
+                frequency,
+                delay,
+                damping,
+            } => {
+                if time < *delay {
+                    *offset
+                } else {
+                    let elapsed = time - delay;
+                    offset
+                        + amplitude
+                            * (-elapsed * damping).exp()
+                            * (2.0 * PI * frequency * elapsed).sin()
+                }
+            }
+            Self::Pwl(corners) => {
+                let after = corners.partition_point(|(corner_time, _value)| *corner_time <= time);
+                match (
+                    after.checked_sub(1).map(|idx| corners[idx]),
+                    corners.get(after),
+                ) {
+                    (Some((t0, v0)), Some((t1, v1))) => v0 + (v1 - v0) * (time - t0) / (t1 - t0),
+                    (Some((_t0, v0)), None) => v0,
+                    (None, Some((_t1, v1))) => *v1,
+                    (None, None) => 0.0,
+                }
+            }
+        }
+    }
+
+    /// First corner of the waveform after `time`, the transient analysis steps onto it exactly.
+    ///
+    /// Pulse corners are computed from the cycle at `time`, so a short period does not enumerate
+    /// the cycles up to `tstop`.
+    pub fn next_breakpoint(&self, time: f64) -> Option<f64> {
+        match self {
+            Self::Dc(_value) => None,
+            Self::Pulse {
+                delay,
+                rise,
+                fall,
+                width,
+                period,
+                ..
+            } => {
+                let cycle = ((time - delay) / period).floor().max(0.0);
+                [cycle, cycle + 1.0]
+                    .into_iter()
+                    .flat_map(|cycle| {
+                        let start = delay + cycle * period;
+                        [
+                            start,
+                            start + rise,
+                            start + rise + width,
+                            start + rise + width + fall,
+                        ]
+                    })
+                    .filter(|corner| *corner > time)
+                    .min_by(f64::total_cmp)
+            }
+            Self::Sin { delay, .. } => Some(*delay).filter(|delay| *delay > time),
+            Self::Pwl(corners) => corners
+                .iter()
+                .map(|(corner_time, _value)| *corner_time)
+                .find(|corner_time| *corner_time > time),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::circuit::graph::nodes::ElementKind;
+
+    fn breakpoints(waveform: &SourceWaveform, tstop: f64) -> Vec<f64> {
+        std::iter::successors(waveform.next_breakpoint(0.0), |time| {
+            waveform.next_breakpoint(*time)
+        })
+        .take_while(|time| *time < tstop)
+        .collect()
+    }
+
+    fn source(param_name: &str, values: &str) -> ElementHNode {
+        ElementHNode::builder()
+            .name("v1")
+            .kind(ElementKind::VoltageSource)
+            .params(BTreeMap::from([(param_name.to_owned(), values.to_owned())]))
+            .build()
+    }
+
+    #[test]
+    fn pulse_waveform() {
+        let pulse = SourceWaveform::from_element(
+            &source("pulse", "0 5 1ns 1ns 1ns 5us 10us"),
+            0.0,
+            0.1e-6,
+            10e-6,
+        );
+        assert_eq!(0.0, pulse.value_at(0.5e-9));
+        assert!((pulse.value_at(1.5e-9) - 2.5).abs() < 1e-9);
+        assert_eq!(5.0, pulse.value_at(3e-6));
+        assert_eq!(0.0, pulse.value_at(8e-6));
+        assert_eq!(5.0, pulse.value_at(13e-6));
+        assert_eq!(4, breakpoints(&pulse, 10e-6).len());
+    }
+
+    #[test]
+    fn pulse_breakpoints_short_period() {
+        let pulse =
+            SourceWaveform::from_element(&source("pulse", "0 1 0 1f 1f 1f 1p"), 0.0, 1e-15, 1.0);
+        let breakpoint = pulse.next_breakpoint(0.5).unwrap();
+        assert!(breakpoint > 0.5 && breakpoint - 0.5 <= 1e-12);
+    }
+
+    #[test]
+    fn pulse_waveform_defaults() {
+        let pulse = SourceWaveform::from_element(&source("pulse", "0 1"), 0.0, 0.1, 7.0);
+        assert_eq!(
+            SourceWaveform::Pulse {
+                v1: 0.0,
+                v2: 1.0,
+                delay: 0.0,
+                rise: 0.1,
+                fall: 0.1,
+                width: 7.0,
+                period: 7.0,
+            },
+            pulse
+        );
+    }
+
+    #[test]
+    fn sin_and_pwl_waveforms() {
+        let sin = SourceWaveform::from_element(&source("sin", "1 2 1k"), 0.0, 1e-5, 1e-3);
+        assert!((sin.value_at(0.25e-3) - 3.0).abs() < 1e-9);
+        let pwl = SourceWaveform::from_element(&source("pwl", "0 0 1m 1 2m 1"), 0.0, 1e-5, 3e-3);
+        assert!((pwl.value_at(0.5e-3) - 0.5).abs() < 1e-9);
+        assert_eq!(1.0, pwl.value_at(5e-3));
+        assert_eq!(vec![1e-3, 2e-3], breakpoints(&pwl, 3e-3));
+        let dc = SourceWaveform::from_element(&source("ac", "1"), 2.5, 1e-5, 3e-3);
+        assert_eq!(2.5, dc.value_at(1e-3));
+    }
+}
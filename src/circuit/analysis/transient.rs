@@ -0,0 +1,609 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use derive_getters::Getters;
+use typed_builder::TypedBuilder;
+
+use super::dc::{solve_operating_point, DcConfig};
+use super::sources::SourceWaveform;
+use super::{AnalysisError, MnaElement, MnaMode, MnaSystem};
+use crate::circuit::graph::nodes::ElementKind;
+use crate::circuit::graph::LCircuit;
+use crate::circuit::nodes::CircuitNode;
+use crate::circuit::spice::SPICENetlist;
+use crate::circuit::values::parse_spice_value;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IntegrationMethod {
+    BackwardEuler,
+    #[default]
+    Trapezoidal,
+}
+
+#[derive(Debug, Clone, TypedBuilder, Getters)]
+pub struct TransientConfig {
+    /// Output increment, also the default rise/fall time of `pulse` sources.
+    tstep: f64,
+    tstop: f64,
+    /// Waveforms are recorded from `tstart` on.
+    #[builder(default)]
+    tstart: f64,
+    /// Largest internal timestep, `min(tstep, (tstop - tstart) / 50)` by default.
+    #[builder(default, setter(strip_option))]
+    max_step: Option<f64>,
+    #[builder(default)]
+    method: IntegrationMethod,
+    /// Skip the operating point and start from the `ic=` values of capacitors and inductors.
+    #[builder(default)]
+    uic: bool,
+    /// Truncation error overestimation factor of the timestep control.
+    #[builder(default = 7.0)]
+    trtol: f64,
+    #[builder(default)]
+    newton: DcConfig,
+}
+
+impl TransientConfig {
+    /// `.tran tstep tstop [tstart [tmax]] [uic]` of the top netlist scope, `None` without one.
+    pub fn from_netlist(netlist: &SPICENetlist) -> Result<Option<Self>, AnalysisError> {
+        let Some(tran) = netlist
+            .netlist_scope
+            .statements
+            .iter()
+            .find_map(|statement| statement.tran.as_ref())
+        else {
+            return Ok(None);
+        };
+        let timestep = |idx: usize, name: &str| {
+            tran.timesteps
+                .get(idx)
+                .map(|timestep| {
+                    parse_spice_value(timestep)
+                        .ok_or_else(|| AnalysisError::InvalidValue(name.to_owned()))
+                })
+                .transpose()
+        };
+        let required = |idx: usize, name: &str| {
+            timestep(idx, name)?.ok_or_else(|| AnalysisError::InvalidValue(name.to_owned()))
+        };
+        let config = Self {
+            tstep: required(0, "tstep")?,
+            tstop: required(1, "tstop")?,
+            tstart: timestep(2, "tstart")?.unwrap_or(0.0),
+            max_step: timestep(3, "tmax")?,
+            method: IntegrationMethod::default(),
+            uic: tran.uic.is_some(),
+            trtol: 7.0,
+            newton: DcConfig::default(),
+        };
+        config.validate()?;
+        Ok(Some(config))
+    }
+
+    /// A positive `tstep` and `max_step` and `0 <= tstart < tstop`, otherwise the timestep
+    /// control never advances.
+    fn validate(&self) -> Result<(), AnalysisError> {
+        let invalid = |name: &str| Err(AnalysisError::InvalidValue(name.to_owned()));
+        if !self.tstep.is_finite() || self.tstep <= 0.0 {
+            return invalid("tstep");
+        }
+        if !self.tstart.is_finite() || self.tstart < 0.0 {
+            return invalid("tstart");
+        }
+        if !self.tstop.is_finite() || self.tstop <= self.tstart {
+            return invalid("tstop");
+        }
+        if self
+            .max_step
+            .is_some_and(|max_step| !max_step.is_finite() || max_step <= 0.0)
+        {
+            return invalid("tmax");
+        }
+        Ok(())
+    }
+
+    fn resolved_max_step(&self) -> f64 {
+        self.max_step
+            .unwrap_or_else(|| self.tstep.min((self.tstop - self.tstart) / 50.0))
+            .min(self.tstop)
+    }
+}
+
+/// Previous timepoint state and source values of one integration step.
+#[derive(Debug, Clone)]
+pub(crate) struct TransientStep {
+    step: f64,
+    method: IntegrationMethod,
+    /// Element voltage(`v(p) - v(n)`) and current at the previous timepoint, per element.
+    voltages: Vec<f64>,
+    currents: Vec<f64>,
+    pub(crate) source_values: Vec<f64>,
+}
+
+impl TransientStep {
+    /// Companion conductance and current of a capacitor at `x`.
+    pub(crate) fn capacitor_companion(
+        &self,
+        element_idx: usize,
+        element: &MnaElement,
+        x: &[f64],
+    ) -> (f64, f64) {
+        let voltage = element.terminal_voltage(0, x) - element.terminal_voltage(1, x);
+        let voltage_change = voltage - self.voltages[element_idx];
+        match self.method {
+            IntegrationMethod::BackwardEuler => {
+                let conductance = element.value / self.step;
+                (conductance, conductance * voltage_change)
+            }
+            IntegrationMethod::Trapezoidal => {
+                let conductance = 2.0 * element.value / self.step;
+                (
+                    conductance,
+                    conductance * voltage_change - self.currents[element_idx],
+                )
+            }
+        }
+    }
+
+    /// Companion resistance and voltage of an inductor, `v = resistance * i + voltage`.
+    pub(crate) fn inductor_companion(
+        &self,
+        element_idx: usize,
+        element: &MnaElement,
+    ) -> (f64, f64) {
+        let current = self.currents[element_idx];
+        match self.method {
+            IntegrationMethod::BackwardEuler => {
+                let resistance = element.value / self.step;
+                (resistance, -resistance * current)
+            }
+            IntegrationMethod::Trapezoidal => {
+                let resistance = 2.0 * element.value / self.step;
+                (
+                    resistance,
+                    -resistance * current - self.voltages[element_idx],
+                )
+            }
+        }
+    }
+}
+
+/// Node voltage and element current waveforms, sampled at the accepted timepoints.
+#[derive(Debug, Clone, PartialEq, Getters)]
+pub struct TransientSolution {
+    times: Vec<f64>,
+    node_voltages: BTreeMap<CircuitNode, Vec<f64>>,
+    branch_currents: BTreeMap<String, Vec<f64>>,
+}
+
+impl TransientSolution {
+    pub fn voltage(&self, node_name: &str) -> Option<Vec<f64>> {
+        let node = CircuitNode::from_str(node_name).ok()?;
+        if node.is_ground() {
+            Some(vec![0.0; self.times.len()])
+        } else {
+            self.node_voltages.get(&node).cloned()
+        }
+    }
+
+    /// Node voltage at `time`, linearly interpolated between timepoints.
+    pub fn voltage_at(&self, node_name: &str, time: f64) -> Option<f64> {
+        interpolate(&self.times, &self.voltage(node_name)?, time)
+    }
+
+    pub fn current(&self, element_name: &str) -> Option<&Vec<f64>> {
+        self.branch_currents.get(element_name)
+    }
+
+    pub fn current_at(&self, element_name: &str, time: f64) -> Option<f64> {
+        interpolate(&self.times, self.current(element_name)?, time)
+    }
+
+    fn record(&mut self, system: &MnaSystem, time: f64, x: &[f64], currents: &[f64]) {
+        self.times.push(time);
+        system.nodes.iter().for_each(|(node, idx)| {
+            self.node_voltages
+                .entry(node.clone())
+                .or_default()
+                .push(x[*idx]);
+        });
+        system
+            .elements
+            .iter()
+            .zip(currents)
+            .for_each(|(element, current)| {
+                self.branch_currents
+                    .entry(element.name().to_owned())
+                    .or_default()
+                    .push(*current);
+            });
+    }
+}
+
+fn interpolate(times: &[f64], values: &[f64], time: f64) -> Option<f64> {
+    let after = times.partition_point(|timepoint| *timepoint <= time);
+    match (after.checked_sub(1), times.get(after)) {
+        (Some(before), Some(t1)) => {
+            let t0 = times[before];
+            Some(values[before] + (values[after] - values[before]) * (time - t0) / (t1 - t0))
+        }
+        (Some(before), None) if time <= times[before] => Some(values[before]),
+        _ => None,
+    }
+}
+
+/// Transient analysis of an `LCircuit` from `0` to `tstop`.
+///
+/// Starts from the DC operating point(or the `ic=` values with `uic`), integrates capacitors and
+/// inductors with `method` and adapts the timestep to the predictor-corrector difference of the
+/// node voltages. Source corners are stepped onto exactly and restart integration with backward
+/// Euler.
+pub fn transient_analysis(
+    circuit: &LCircuit,
+    config: &TransientConfig,
+) -> Result<TransientSolution, AnalysisError> {
+    config.validate()?;
+    let newton = config.newton();
+    let system = MnaSystem::new(circuit, *newton.temperature())?;
+    let waveforms = system
+        .elements
+        .iter()
+        .map(|element| {
+            SourceWaveform::from_element(
+                &element.element,
+                element.value,
+                config.tstep,
+                config.tstop,
+            )
+        })
+        .collect::<Vec<_>>();
+    let source_values = |time: f64| {
+        system
+            .elements
+            .iter()
+            .zip(&waveforms)
+            .map(|(element, waveform)| match element.kind() {
+                ElementKind::VoltageSource | ElementKind::CurrentSource => waveform.value_at(time),
+                _ => element.value,
+            })
+            .collect::<Vec<_>>()
+    };
+    let max_step = config.resolved_max_step();
+    let min_step = max_step * 1e-9;
+    let (mut x, mut voltages, mut currents) = initial_state(&system, config)?;
+    let mut solution = TransientSolution {
+        times: Vec::new(),
+        node_voltages: BTreeMap::new(),
+        branch_currents: BTreeMap::new(),
+    };
+    if config.tstart <= 0.0 {
+        solution.record(&system, 0.0, &x, &currents);
+    }
+
+    let mut time = 0.0;
+    let mut step = max_step * 0.1;
+    let mut history: Option<(Vec<f64>, f64)> = None;
+    while config.tstop - time > min_step {
+        let breakpoint = waveforms
+            .iter()
+            .filter_map(|waveform| waveform.next_breakpoint(time + min_step))
+            .fold(config.tstop, f64::min);
+        let step_size = step.min(max_step).min(breakpoint - time);
+        let transient_step = TransientStep {
+            step: step_size,
+            method: if history.is_some() {
+                config.method
+            } else {
+                IntegrationMethod::BackwardEuler
+            },
+            voltages: voltages.clone(),
+            currents: currents.clone(),
+            source_values: source_values(time + step_size),
+        };
+        let x_new = match system.newton(x.clone(), &MnaMode::Transient(&transient_step), newton) {
+            Ok((x_new, _iterations)) => x_new,
+            Err(AnalysisError::NoConvergence { .. }) => {
+                step = step_size / 8.0;
+                if step < min_step {
+                    return Err(AnalysisError::TimestepTooSmall { time });
+                }
+                continue;
+            }
+            Err(error) => return Err(error),
+        };
+
+        let error_ratio = history.as_ref().map_or(0.0, |(x_old, old_step_size)| {
+            system
+                .nodes
+                .values()
+                .map(|idx| {
+                    let predicted = x[*idx] + (x[*idx] - x_old[*idx]) * step_size / old_step_size;
+                    let tolerance = config.trtol
+                        * (newton.vntol() + newton.reltol() * x_new[*idx].abs().max(x[*idx].abs()));
+                    (x_new[*idx] - predicted).abs() / tolerance
+                })
+                .fold(0.0, f64::max)
+        });
+        if error_ratio > 1.0 && step_size > min_step {
+            step = step_size * (0.9 / error_ratio.sqrt()).max(0.25);
+            if step < min_step {
+                return Err(AnalysisError::TimestepTooSmall { time });
+            }
+            continue;
+        }
+
+        let (voltages_new, currents_new) = element_state(&system, &transient_step, &x_new)?;
+        time += step_size;
+        history = Some((std::mem::replace(&mut x, x_new), step_size));
+        voltages = voltages_new;
+        currents = currents_new;
+        if time >= config.tstart {
+            solution.record(&system, time, &x, &currents);
+        }
+        if (breakpoint - time).abs() <= min_step {
+            history = None;
+            step = step_size.min(max_step * 0.1);
+        } else {
+            step = step_size * (0.9 / error_ratio.sqrt()).min(2.0);
+        }
+    }
+    Ok(solution)
+}
+
+/// Unknowns, element voltages and element currents at `t = 0`.
+fn initial_state(
+    system: &MnaSystem,
+    config: &TransientConfig,
+) -> Result<(Vec<f64>, Vec<f64>, Vec<f64>), AnalysisError> {
+    let initial_condition = |element: &MnaElement| {
+        element
+            .element
+            .param("ic")
+            .and_then(parse_spice_value)
+            .unwrap_or(0.0)
+    };
+    if config.uic {
+        let mut x = vec![0.0; system.size()];
+        let (voltages, currents) = system
+            .elements
+            .iter()
+            .map(|element| match (element.kind(), element.branch) {
+                (ElementKind::Capacitor, _) => (initial_condition(element), 0.0),
+                (ElementKind::Inductor, Some(branch)) => {
+                    x[branch] = initial_condition(element);
+                    (0.0, x[branch])
+                }
+                (ElementKind::VoltageSource, _) => (element.value, 0.0),
+                _ => (0.0, 0.0),
+            })
+            .unzip();
+        Ok((x, voltages, currents))
+    } else {
+        let (x, _iterations) = solve_operating_point(system, config.newton())?;
+        let (voltages, currents) = system
+            .elements
+            .iter()
+            .map(|element| {
+                Ok((
+                    element.terminal_voltage(0, &x) - element.terminal_voltage(1, &x),
+                    system.element_current(element, &x, element.value, 0.0)?,
+                ))
+            })
+            .collect::<Result<Vec<_>, AnalysisError>>()?
+            .into_iter()
+            .unzip();
+        Ok((x, voltages, currents))
+    }
+}
+
+/// Element voltages and currents at an accepted timepoint.
+fn element_state(
+    system: &MnaSystem,
+    transient_step: &TransientStep,
+    x: &[f64],
+) -> Result<(Vec<f64>, Vec<f64>), AnalysisError> {
+    Ok(system
+        .elements
+        .iter()
+        .enumerate()
+        .map(|(element_idx, element)| {
+            let capacitor_current = match element.kind() {
+                ElementKind::Capacitor => {
+                    transient_step
+                        .capacitor_companion(element_idx, element, x)
+                        .1
+                }
+                _ => 0.0,
+            };
+            Ok((
+                element.terminal_voltage(0, x) - element.terminal_voltage(1, x),
+                system.element_current(
+                    element,
+                    x,
+                    transient_step.source_values[element_idx],
+                    capacitor_current,
+                )?,
+            ))
+        })
+        .collect::<Result<Vec<_>, AnalysisError>>()?
+        .into_iter()
+        .unzip())
+}
+
+#[cfg(test)]
+mod tests {
+    use peginator::PegParser;
+
+    use super::*;
+    use crate::circuit::equations::DeviceEquationMap;
+    use crate::circuit::graph::nodes::ElementHNode;
+
+    fn element(name: &str, kind: ElementKind, terminals: &[&str], value: &str) -> ElementHNode {
+        ElementHNode::builder()
+            .name(name)
+            .kind(kind)
+            .terminals(
+                terminals
+                    .iter()
+                    .map(|terminal| CircuitNode::from_str(terminal).unwrap())
+                    .collect(),
+            )
+            .value(value)
+            .build()
+    }
+
+    fn step_source(name: &str, terminals: &[&str]) -> ElementHNode {
+        ElementHNode {
+            value: None,
+            params: BTreeMap::from([("pulse".to_owned(), "0 1 0 1n 1n 1 2".to_owned())]),
+            ..element(name, ElementKind::VoltageSource, terminals, "")
+        }
+    }
+
+    fn assert_close(expected: f64, value: Option<f64>, tolerance: f64) {
+        let value = value.unwrap();
+        assert!(
+            (expected - value).abs() < tolerance,
+            "Expected {}, got {}.",
+            expected,
+            value
+        );
+    }
+
+    fn rc_step_circuit() -> LCircuit {
+        LCircuit::from_elements([
+            step_source("v1", &["1", "0"]),
+            element("r1", ElementKind::Resistor, &["1", "2"], "1k"),
+            element("c1", ElementKind::Capacitor, &["2", "0"], "1u"),
+        ])
+    }
+
+    #[test]
+    fn rc_step_response() {
+        let config = TransientConfig::builder().tstep(10e-6).tstop(5e-3).build();
+        let solution = transient_analysis(&rc_step_circuit(), &config).unwrap();
+        assert_close(0.0, solution.voltage_at("2", 0.0), 1e-9);
+        assert_close(1.0 - (-1.0f64).exp(), solution.voltage_at("2", 1e-3), 2e-3);
+        assert_close(1.0 - (-5.0f64).exp(), solution.voltage_at("2", 5e-3), 2e-3);
+        assert_close(
+            (-1.0f64).exp() * 1e-3,
+            solution.current_at("c1", 1e-3),
+            1e-5,
+        );
+        assert!((solution.times().last().unwrap() - 5e-3).abs() < 1e-12);
+    }
+
+    #[test]
+    fn rc_step_response_backward_euler() {
+        let config = TransientConfig::builder()
+            .tstep(10e-6)
+            .tstop(2e-3)
+            .method(IntegrationMethod::BackwardEuler)
+            .build();
+        let solution = transient_analysis(&rc_step_circuit(), &config).unwrap();
+        assert_close(1.0 - (-1.0f64).exp(), solution.voltage_at("2", 1e-3), 1e-2);
+    }
+
+    #[test]
+    fn capacitor_initial_condition() {
+        let circuit = LCircuit::from_elements([
+            element("r1", ElementKind::Resistor, &["1", "0"], "1k"),
+            ElementHNode {
+                params: BTreeMap::from([("ic".to_owned(), "1".to_owned())]),
+                ..element("c1", ElementKind::Capacitor, &["1", "0"], "1u")
+            },
+        ]);
+        let config = TransientConfig::builder()
+            .tstep(10e-6)
+            .tstop(2e-3)
+            .uic(true)
+            .build();
+        let solution = transient_analysis(&circuit, &config).unwrap();
+        assert_close((-1.0f64).exp(), solution.voltage_at("1", 1e-3), 1e-2);
+        assert_close((-2.0f64).exp(), solution.voltage_at("1", 2e-3), 1e-2);
+    }
+
+    #[test]
+    fn rl_step_response() {
+        let circuit = LCircuit::from_elements([
+            step_source("v1", &["1", "0"]),
+            element("r1", ElementKind::Resistor, &["1", "2"], "1k"),
+            element("l1", ElementKind::Inductor, &["2", "0"], "1"),
+        ]);
+        let config = TransientConfig::builder().tstep(10e-6).tstop(3e-3).build();
+        let solution = transient_analysis(&circuit, &config).unwrap();
+        assert_close(
+            (1.0 - (-1.0f64).exp()) * 1e-3,
+            solution.current_at("l1", 1e-3),
+            2e-6,
+        );
+        assert_close((-1.0f64).exp(), solution.voltage_at("2", 1e-3), 2e-3);
+    }
+
+    #[test]
+    fn sin_source_into_resistor() {
+        let circuit = LCircuit::from_elements([
+            ElementHNode {
+                value: None,
+                params: BTreeMap::from([("sin".to_owned(), "0 2 1k".to_owned())]),
+                ..element("v1", ElementKind::VoltageSource, &["1", "0"], "")
+            },
+            element("r1", ElementKind::Resistor, &["1", "0"], "1k"),
+        ]);
+        let config = TransientConfig::builder().tstep(1e-6).tstop(1e-3).build();
+        let solution = transient_analysis(&circuit, &config).unwrap();
+        assert_close(2.0, solution.voltage_at("1", 0.25e-3), 1e-3);
+        assert_close(-2.0, solution.voltage_at("1", 0.75e-3), 1e-3);
+        assert_close(-2e-3, solution.current_at("v1", 0.25e-3), 1e-6);
+    }
+
+    #[test]
+    fn tran_statement_drives_analysis() {
+        let netlist_str = indoc::indoc! {"
+            * rc step
+            r1 1 2 1k
+            c1 2 0 1u
+            vin 1 0 pulse(0 1 0 1n 1n 1 2)
+            .tran 10u 5m
+            .end
+        "};
+        let netlist = SPICENetlist::parse(netlist_str).unwrap();
+        let config = TransientConfig::from_netlist(&netlist).unwrap().unwrap();
+        assert!((config.tstep - 10e-6).abs() < 1e-18);
+        assert_eq!((5e-3, false), (config.tstop, config.uic));
+        let circuit = LCircuit::from((&netlist, &DeviceEquationMap::new()));
+        let solution = transient_analysis(&circuit, &config).unwrap();
+        assert_close(1.0 - (-1.0f64).exp(), solution.voltage_at("2", 1e-3), 2e-3);
+    }
+
+    #[test]
+    fn tran_rejects_invalid_timesteps() {
+        let netlist_str = indoc::indoc! {"
+            * zero step
+            r1 1 0 1k
+            v1 1 0 1
+            .tran 0 10n
+            .end
+        "};
+        let netlist = SPICENetlist::parse(netlist_str).unwrap();
+        assert_eq!(
+            Err(AnalysisError::InvalidValue("tstep".to_owned())),
+            TransientConfig::from_netlist(&netlist).map(|config| config.is_some())
+        );
+        let circuit = LCircuit::from((&netlist, &DeviceEquationMap::new()));
+        let stopped = TransientConfig::builder().tstep(1e-9).tstop(0.0).build();
+        assert_eq!(
+            Some(AnalysisError::InvalidValue("tstop".to_owned())),
+            transient_analysis(&circuit, &stopped).err()
+        );
+        let no_step = TransientConfig::builder()
+            .tstep(1e-9)
+            .tstop(1e-8)
+            .max_step(0.0)
+            .build();
+        assert_eq!(
+            Some(AnalysisError::InvalidValue("tmax".to_owned())),
+            transient_analysis(&circuit, &no_step).err()
+        );
+    }
+}
@@ -17,17 +17,7 @@ pub struct Capacitor {
 
 impl Capacitor {
     fn capacitor_node_subst(p_node: &CircuitNode, n_node: &CircuitNode) -> DeviceEquation {
-        let build_node_difference_str = |node1: &str, node2: &str| {
-            let mut node_difference_str = "(".to_owned();
-            node_difference_str.push_str(node1);
-            node_difference_str.push('-');
-            node_difference_str.push_str(node2);
-            node_difference_str.push(')');
-            DeviceEquation::from_str(&node_difference_str).expect("Invalid resulting equation")
-        };
-
-        let vpn = build_node_difference_str(&p_node.to_string(), &n_node.to_string());
-        vpn
+        DeviceEquation::node_difference(p_node, n_node)
     }
     pub(crate) fn capacitor_variable_ctx(
         p_node: &CircuitNode,
@@ -34,21 +34,12 @@ impl Transistor {
         DeviceEquation,
         DeviceEquation,
     ) {
-        let build_node_difference_str = |node1: &str, node2: &str| {
-            let mut node_difference_str = "(".to_owned();
-            node_difference_str.push_str(node1);
-            node_difference_str.push('-');
-            node_difference_str.push_str(node2);
-            node_difference_str.push(')');
-            DeviceEquation::from_str(&node_difference_str).expect("Invalid resulting equation")
-        };
-
-        let vgs = build_node_difference_str(&gate.to_string(), &source.to_string());
-        let vgd = build_node_difference_str(&gate.to_string(), &drain.to_string());
-        let vgb = build_node_difference_str(&gate.to_string(), &body.to_string());
-        let vds = build_node_difference_str(&drain.to_string(), &source.to_string());
-        let vdb = build_node_difference_str(&drain.to_string(), &body.to_string());
-        let vsb = build_node_difference_str(&source.to_string(), &body.to_string());
+        let vgs = DeviceEquation::node_difference(gate, source);
+        let vgd = DeviceEquation::node_difference(gate, drain);
+        let vgb = DeviceEquation::node_difference(gate, body);
+        let vds = DeviceEquation::node_difference(drain, source);
+        let vdb = DeviceEquation::node_difference(drain, body);
+        let vsb = DeviceEquation::node_difference(source, body);
         (vgs, vgd, vgb, vds, vdb, vsb)
     }
 
@@ -82,16 +73,16 @@ impl Transistor {
 impl From<(MosTransistor, DeviceEquationMap)> for Transistor {
     fn from(tech: (MosTransistor, DeviceEquationMap)) -> Self {
         let ast = tech.0;
-        let id = CircuitElement(ast.id);
         let source = CircuitNode::from_str(&ast.source)
             .expect("Failure to convert `SPICENetlist` `Node` to `CircuitNode`");
         let drain = CircuitNode::from_str(&ast.drain)
             .expect("Failure to convert `SPICENetlist` `Node` to `CircuitNode`");
         let gate = CircuitNode::from_str(&ast.gate)
             .expect("Failure to convert `SPICENetlist` `Node` to `CircuitNode`");
-        let body = CircuitNode::from_str(&ast.body)
+        let body = CircuitNode::from_str(ast.body_node())
             .expect("Failure to convert `SPICENetlist` `Node` to `CircuitNode`");
         let model = ast.model;
+        let id = CircuitElement(ast.id);
         let device_equation = tech.1[&model].clone();
         let ctx: VariableContextMap = Self::transistor_variable_ctx(&source, &drain, &gate, &body)
             .into_iter()
@@ -111,16 +102,12 @@ impl From<(MosTransistor, DeviceEquationMap)> for Transistor {
 impl From<(Instance, DeviceEquationMap)> for Transistor {
     fn from(tech: (Instance, DeviceEquationMap)) -> Self {
         let ast = tech.0;
+        let [source, drain, gate, body] = [0, 1, 2, 3].map(|port| {
+            CircuitNode::from_str(&ast.ports()[port])
+                .expect("Failure to convert `SPICENetlist` `Node` to `CircuitNode`")
+        });
+        let model = ast.model().to_owned();
         let id = CircuitElement(ast.id);
-        let source = CircuitNode::from_str(&ast.source)
-            .expect("Failure to convert `SPICENetlist` `Node` to `CircuitNode`");
-        let drain = CircuitNode::from_str(&ast.drain)
-            .expect("Failure to convert `SPICENetlist` `Node` to `CircuitNode`");
-        let gate = CircuitNode::from_str(&ast.gate)
-            .expect("Failure to convert `SPICENetlist` `Node` to `CircuitNode`");
-        let body = CircuitNode::from_str(&ast.body)
-            .expect("Failure to convert `SPICENetlist` `Node` to `CircuitNode`");
-        let model = ast.model;
         let device_equation = tech.1[&model].clone();
         let ctx: VariableContextMap = Self::transistor_variable_ctx(&source, &drain, &gate, &body)
             .into_iter()
@@ -177,9 +164,7 @@ mod tests {
             .body(n4)
             .build();
         let transistor_eq = transistor.equations().to_string();
-        let eval_eq = indoc::indoc! {"
-            I = Is*(e^((n1 - n2)/(eta*Vt)) - 1)
-        "};
+        let eval_eq = "I = Is * (e ^ ((n1 - n2) / (eta * Vt)) - 1.0)";
         assert!(
             transistor_eq.to_string().contains(eval_eq),
             "Evaluated equation is incorrect."
@@ -198,12 +183,12 @@ mod tests {
             .expect("Failure to convert `SPICENetlist` `Node` to `CircuitNode`");
         let (vgs, vgd, vgb, vds, vdb, vsb) =
             Transistor::transistor_node_subst(&source, &drain, &gate, &body);
-        assert_eq!("(n3-n1)", vgs.to_string());
-        assert_eq!("(n3-n2)", vgd.to_string());
-        assert_eq!("(n3-n4)", vgb.to_string());
-        assert_eq!("(n2-n1)", vds.to_string());
-        assert_eq!("(n2-n4)", vdb.to_string());
-        assert_eq!("(n1-n4)", vsb.to_string());
+        assert_eq!("n3 - n1", vgs.to_string());
+        assert_eq!("n3 - n2", vgd.to_string());
+        assert_eq!("n3 - n4", vgb.to_string());
+        assert_eq!("n2 - n1", vds.to_string());
+        assert_eq!("n2 - n4", vdb.to_string());
+        assert_eq!("n1 - n4", vsb.to_string());
     }
 
     #[test]
@@ -225,13 +210,12 @@ mod tests {
 
         let device_eq_map = DeviceEquationMap::from([(m4_xtor.model.clone(), dev_eq)]);
         let xtor = Transistor::from((m4_xtor, device_eq_map));
-        let eq_expected = indoc::indoc! {"
+        let eq_expected = indoc::indoc! {r#"
             e = 2.718281828459045;
             Is = 1e-12;
             eta = 1.5;
-            Vt = T/11586;
-            I = Is*(e^((5-15)/(eta*Vt)) - 1)
-        "};
+            Vt = T / 11586.0;
+            I = Is * (e ^ ((v("5") - v("15")) / (eta * Vt)) - 1.0)"#};
         assert_eq!(eq_expected, xtor.equations.to_string());
     }
 
@@ -257,15 +241,14 @@ mod tests {
         "};
         let dev_eq = DeviceEquation::from_str(eq).unwrap();
 
-        let device_eq_map = DeviceEquationMap::from([(x4_xtor.model.clone(), dev_eq)]);
+        let device_eq_map = DeviceEquationMap::from([(x4_xtor.model().to_owned(), dev_eq)]);
         let xtor = Transistor::from((x4_xtor, device_eq_map));
         let eq_expected = indoc::indoc! {"
             e = 2.718281828459045;
             Is = 1e-12;
             eta = 1.5;
-            Vt = T/11586;
-            I = Is*(e^((A2-a_317_392#)/(eta*Vt)) - 1)
-        "};
+            Vt = T / 11586.0;
+            I = Is * (e ^ ((A2 - a_317_392#) / (eta * Vt)) - 1.0)"};
         assert_eq!(eq_expected, xtor.equations.to_string());
     }
 }
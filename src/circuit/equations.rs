@@ -1,9 +1,10 @@
-use std::collections::HashMap;
+pub(crate) mod expression;
+
+use std::collections::{BTreeSet, HashMap};
 use std::fmt;
 use std::str::FromStr;
 
-use evalexpr::{build_operator_tree, EvalexprError};
-
+use self::expression::{BinaryOperator, EquationError, Expression};
 use super::nodes::CircuitNode;
 
 pub type ModelName = String;
@@ -12,21 +13,91 @@ pub type ModelNameDeclaration<'model_name_str> = &'model_name_str str;
 pub(super) const RESISTORMODELNAME: ModelNameDeclaration = "r";
 pub(super) const CAPACITORMODELNAME: ModelNameDeclaration = "c";
 
+/// Variable holding the current of an equation, the value of its last expression is the current
+/// when it is never assigned.
+pub const CURRENT_VARIABLE: &str = "I";
+
 pub(crate) type VariableContext = (CircuitNode, DeviceEquation);
 pub(crate) type VariableContextMap = HashMap<CircuitNode, DeviceEquation>;
 pub type DeviceEquationMap = HashMap<ModelName, DeviceEquation>;
 
+/// Current of `expression`, `I` when assigned.
+fn evaluate_current(
+    expression: &Expression,
+    variables: &HashMap<String, f64>,
+) -> Result<f64, EquationError> {
+    let mut variables = variables.to_owned();
+    variables.remove(CURRENT_VARIABLE);
+    let value = expression.evaluate(&mut variables)?;
+    Ok(variables.get(CURRENT_VARIABLE).copied().unwrap_or(value))
+}
+
+/// Derivative of the current of `expression`, reading `I` at the end of the chain when assigned.
+fn current_derivative(
+    expression: &Expression,
+    variable: &str,
+) -> Result<Expression, EquationError> {
+    let assigns_current = |statement: &Expression| matches!(statement, Expression::Assign(name, _value) if name == CURRENT_VARIABLE);
+    let current = Expression::Variable(CURRENT_VARIABLE.to_owned());
+    match expression {
+        Expression::Chain(statements) if statements.iter().any(assigns_current) => {
+            Expression::Chain(statements.iter().cloned().chain([current]).collect())
+                .partial_derivative(variable)
+        }
+        statement if assigns_current(statement) => {
+            Expression::Chain(vec![statement.to_owned(), current]).partial_derivative(variable)
+        }
+        expression => expression.partial_derivative(variable),
+    }
+}
+
+/// Model equation of a device, in terms of its controlling voltages(`vgs`, `vpn`, ...),
+/// parameters and `T`.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
-pub struct DeviceEquation(String);
+pub struct DeviceEquation(Expression);
+
+impl DeviceEquation {
+    /// Voltage between two nodes, `p - n`, bound to a controlling voltage of a device.
+    pub fn node_difference(p_node: &CircuitNode, n_node: &CircuitNode) -> Self {
+        Self(Expression::Binary(
+            BinaryOperator::Sub,
+            Box::new(Expression::Variable(p_node.to_string())),
+            Box::new(Expression::Variable(n_node.to_string())),
+        ))
+    }
+
+    pub fn expression(&self) -> &Expression {
+        &self.0
+    }
+
+    /// Variables the equation reads without assigning them.
+    pub fn variables(&self) -> BTreeSet<String> {
+        self.0.free_variables()
+    }
+
+    /// Device current with `variables` bound.
+    pub fn evaluate(&self, variables: &HashMap<String, f64>) -> Result<f64, EquationError> {
+        evaluate_current(&self.0, variables)
+    }
+
+    /// Partial derivative of the device current by `variable`, evaluated with
+    /// `Expression::evaluate` and the same bindings as the equation.
+    pub fn partial_derivative(&self, variable: &str) -> Result<Expression, EquationError> {
+        current_derivative(&self.0, variable)
+    }
+}
+
+impl From<Expression> for DeviceEquation {
+    fn from(expression: Expression) -> Self {
+        Self(expression)
+    }
+}
 
 impl FromStr for DeviceEquation {
-    type Err = EvalexprError;
+    type Err = EquationError;
 
     fn from_str(eq_str: &str) -> Result<Self, Self::Err> {
-        match build_operator_tree(eq_str) {
-            Ok(_) => Ok(Self(eq_str.to_owned())),
-            Err(error) => Err(error),
-        }
+        Expression::from_str(eq_str).map(Self)
     }
 }
 
@@ -36,27 +107,41 @@ impl fmt::Display for DeviceEquation {
     }
 }
 
+/// `DeviceEquation` of a circuit element in terms of its node voltages.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
-pub struct CircuitEquation(String);
+pub struct CircuitEquation(Expression);
 
 impl CircuitEquation {
+    /// Binds the controlling voltages of `dev_eq` to the node expressions of `ctx`, variables are
+    /// replaced by name so node names containing one another(`A1`, `A11`) stay distinct.
     pub fn new(dev_eq: DeviceEquation, ctx: &VariableContextMap) -> Self {
-        let mut cir_eq = dev_eq.to_string();
-        ctx.iter().for_each(|(var, eq)| {
-            cir_eq = cir_eq.replace(&var.to_string(), &eq.to_string());
-        });
-        Self(cir_eq)
+        let bindings = ctx
+            .iter()
+            .map(|(var, eq)| (var.to_string(), eq.0.to_owned()))
+            .collect();
+        Self(dev_eq.0.substitute(&bindings))
+    }
+
+    pub fn expression(&self) -> &Expression {
+        &self.0
+    }
+
+    /// Element current with the node voltages and parameters in `variables` bound.
+    pub fn evaluate(&self, variables: &HashMap<String, f64>) -> Result<f64, EquationError> {
+        evaluate_current(&self.0, variables)
+    }
+
+    /// Partial derivative of the element current by the voltage of `node`.
+    pub fn partial_derivative(&self, node: &CircuitNode) -> Result<Expression, EquationError> {
+        current_derivative(&self.0, &node.to_string())
     }
 }
 
 impl FromStr for CircuitEquation {
-    type Err = String;
+    type Err = EquationError;
 
     fn from_str(eq_str: &str) -> Result<Self, Self::Err> {
-        match build_operator_tree(eq_str) {
-            Ok(_) => Ok(Self(eq_str.to_owned())),
-            Err(error) => Err(error.to_string()),
-        }
+        Expression::from_str(eq_str).map(Self)
     }
 }
 
@@ -107,8 +192,8 @@ mod tests {
             I = Is*(e^((n1 - n2)/(eta*Vt)) - 1)
         "};
         assert_eq!(
-            dev_eq_with_nodes,
-            cir_eq.to_string(),
+            CircuitEquation::from_str(dev_eq_with_nodes).unwrap(),
+            cir_eq,
             "Substituted equation should contain: `n1 - n2`, in-place of `vd`."
         );
     }
@@ -136,4 +221,58 @@ mod tests {
         let dev_eq = DeviceEquation::from_str(eq).unwrap();
         let _device_eq_map = DeviceEquationMap::from([("m1".to_owned(), dev_eq)]);
     }
+
+    #[test]
+    fn substitute_colliding_node_names() {
+        let dev_eq = DeviceEquation::from_str("I = 1e-3 * vgs * vgs2 + vds").unwrap();
+        let a1 = CircuitNode::from_str("A1").unwrap();
+        let ctx = VariableContextMap::from([
+            (
+                CircuitNode::from_str("vgs").unwrap(),
+                DeviceEquation::node_difference(&a1, &CircuitNode::from_str("VGND").unwrap()),
+            ),
+            (
+                CircuitNode::from_str("vds").unwrap(),
+                DeviceEquation::node_difference(&CircuitNode::from_str("A11").unwrap(), &a1),
+            ),
+        ]);
+        let cir_eq = CircuitEquation::new(dev_eq, &ctx);
+        assert_eq!(
+            "I = 0.001 * (A1 - VGND) * vgs2 + (A11 - A1)",
+            cir_eq.to_string()
+        );
+        let voltages = HashMap::from([
+            ("A1".to_owned(), 1.0),
+            ("A11".to_owned(), 3.0),
+            ("VGND".to_owned(), 0.0),
+            ("vgs2".to_owned(), 2.0),
+        ]);
+        assert!((cir_eq.evaluate(&voltages).unwrap() - 2.002).abs() < 1e-12);
+        let a1_derivative = cir_eq.partial_derivative(&a1).unwrap();
+        assert_eq!(
+            Ok(1e-3 * 2.0 - 1.0),
+            a1_derivative.evaluate(&mut voltages.to_owned())
+        );
+    }
+
+    #[test]
+    fn device_equation_derivative() {
+        let dev_eq = DeviceEquation::from_str(indoc::indoc! {"
+            Is = 1e-12;
+            Vt = T/11586;
+            I = Is*(math::exp(vd/Vt) - 1);
+            Vt * 2
+        "})
+        .unwrap();
+        let variables = HashMap::from([("T".to_owned(), 300.0), ("vd".to_owned(), 0.6)]);
+        let vt: f64 = 300.0 / 11586.0;
+        let current = 1e-12 * ((0.6 / vt).exp() - 1.0);
+        assert!((dev_eq.evaluate(&variables).unwrap() - current).abs() < 1e-15);
+        let conductance = dev_eq
+            .partial_derivative("vd")
+            .unwrap()
+            .evaluate(&mut variables.to_owned())
+            .unwrap();
+        assert!((conductance - 1e-12 * (0.6 / vt).exp() / vt).abs() < 1e-12);
+    }
 }
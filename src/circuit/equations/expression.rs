@@ -0,0 +1,878 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+use evalexpr::{build_operator_tree, Node, Operator, Value};
+use itertools::Itertools;
+
+/// Functions an `Expression` can evaluate and differentiate.
+const FUNCTIONS: [&str; 7] = [
+    "if",
+    "min",
+    "max",
+    "math::exp",
+    "math::ln",
+    "math::sqrt",
+    "math::abs",
+];
+
+/// Node voltage of a variable that evalexpr would not read back as an identifier, `v("15")`.
+const NODE_FUNCTION: &str = "v";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EquationError {
+    Parse(String),
+    /// Operator or literal without a numeric meaning, strings, tuples or compound assignments,
+    /// also a `%` derived by a variable of its divisor.
+    Unsupported(String),
+    UnboundVariable(String),
+    UnknownFunction(String),
+    /// Function called with an unexpected number of arguments.
+    Arguments {
+        function: String,
+        found: usize,
+    },
+}
+
+impl fmt::Display for EquationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Parse(message) => write!(f, "Invalid equation: {}", message),
+            Self::Unsupported(operator) => write!(f, "Unsupported operator {}", operator),
+            Self::UnboundVariable(variable) => write!(f, "Variable {} is not bound", variable),
+            Self::UnknownFunction(function) => write!(f, "Unknown function {}", function),
+            Self::Arguments { function, found } => {
+                write!(f, "Function {} called with {} arguments", function, found)
+            }
+        }
+    }
+}
+
+impl std::error::Error for EquationError {}
+
+/// `f64` literal, compared and hashed by its bits.
+#[derive(Debug, Clone, Copy)]
+pub struct Number(pub f64);
+
+impl PartialEq for Number {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_bits() == other.0.to_bits()
+    }
+}
+
+impl Eq for Number {}
+
+impl Hash for Number {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.to_bits().hash(state);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnaryOperator {
+    Neg,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinaryOperator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Pow,
+    Eq,
+    Neq,
+    Gt,
+    Lt,
+    Geq,
+    Leq,
+    And,
+    Or,
+}
+
+impl BinaryOperator {
+    fn symbol(self) -> &'static str {
+        match self {
+            Self::Add => "+",
+            Self::Sub => "-",
+            Self::Mul => "*",
+            Self::Div => "/",
+            Self::Mod => "%",
+            Self::Pow => "^",
+            Self::Eq => "==",
+            Self::Neq => "!=",
+            Self::Gt => ">",
+            Self::Lt => "<",
+            Self::Geq => ">=",
+            Self::Leq => "<=",
+            Self::And => "&&",
+            Self::Or => "||",
+        }
+    }
+
+    /// evalexpr operator precedence.
+    fn precedence(self) -> u8 {
+        match self {
+            Self::Or => 70,
+            Self::And => 75,
+            Self::Eq | Self::Neq | Self::Gt | Self::Lt | Self::Geq | Self::Leq => 80,
+            Self::Add | Self::Sub => 95,
+            Self::Mul | Self::Div | Self::Mod => 100,
+            Self::Pow => 120,
+        }
+    }
+
+    /// Booleans are `1.0` and `0.0`, any nonzero operand is true.
+    fn apply(self, lhs: f64, rhs: f64) -> f64 {
+        let truth = |value: bool| if value { 1.0 } else { 0.0 };
+        match self {
+            Self::Add => lhs + rhs,
+            Self::Sub => lhs - rhs,
+            Self::Mul => lhs * rhs,
+            Self::Div => lhs / rhs,
+            Self::Mod => lhs % rhs,
+            Self::Pow => lhs.powf(rhs),
+            Self::Eq => truth(lhs == rhs),
+            Self::Neq => truth(lhs != rhs),
+            Self::Gt => truth(lhs > rhs),
+            Self::Lt => truth(lhs < rhs),
+            Self::Geq => truth(lhs >= rhs),
+            Self::Leq => truth(lhs <= rhs),
+            Self::And => truth(lhs != 0.0 && rhs != 0.0),
+            Self::Or => truth(lhs != 0.0 || rhs != 0.0),
+        }
+    }
+}
+
+/// Syntax tree of an evalexpr equation with every literal as `f64` and node names as variables.
+///
+/// Variables are bound by name, never by text, so substituting `a` leaves `a1` and `vgs`
+/// untouched. Node names evalexpr would read as literals are displayed as `v("15")`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Expression {
+    Number(Number),
+    Variable(String),
+    Unary(UnaryOperator, Box<Expression>),
+    Binary(BinaryOperator, Box<Expression>, Box<Expression>),
+    Function(String, Vec<Expression>),
+    Assign(String, Box<Expression>),
+    /// Statements evaluated in order, the value of the last one is the value of the chain.
+    Chain(Vec<Expression>),
+}
+
+impl Default for Expression {
+    fn default() -> Self {
+        Self::Chain(Vec::new())
+    }
+}
+
+impl FromStr for Expression {
+    type Err = EquationError;
+
+    fn from_str(eq_str: &str) -> Result<Self, Self::Err> {
+        let tree =
+            build_operator_tree(eq_str).map_err(|error| EquationError::Parse(error.to_string()))?;
+        Self::from_node(&tree)
+    }
+}
+
+impl Expression {
+    fn from_node(node: &Node) -> Result<Self, EquationError> {
+        let unsupported = || EquationError::Unsupported(node.operator().to_string());
+        let children = || {
+            node.children()
+                .iter()
+                .map(Self::from_node)
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let binary = |operator: BinaryOperator| match children()?.as_slice() {
+            [lhs, rhs] => Ok(Self::Binary(
+                operator,
+                Box::new(lhs.to_owned()),
+                Box::new(rhs.to_owned()),
+            )),
+            _ => Err(unsupported()),
+        };
+        let unary = || match children()?.as_slice() {
+            [operand] => Ok(operand.to_owned()),
+            _ => Err(unsupported()),
+        };
+        match node.operator() {
+            Operator::RootNode if node.children().is_empty() => Ok(Self::default()),
+            Operator::RootNode => unary(),
+            Operator::Chain => Ok(Self::Chain(
+                children()?
+                    .into_iter()
+                    .flat_map(|statement| match statement {
+                        Self::Chain(statements) => statements,
+                        statement => vec![statement],
+                    })
+                    .collect(),
+            )),
+            Operator::Add => binary(BinaryOperator::Add),
+            Operator::Sub => binary(BinaryOperator::Sub),
+            Operator::Mul => binary(BinaryOperator::Mul),
+            Operator::Div => binary(BinaryOperator::Div),
+            Operator::Mod => binary(BinaryOperator::Mod),
+            Operator::Exp => binary(BinaryOperator::Pow),
+            Operator::Eq => binary(BinaryOperator::Eq),
+            Operator::Neq => binary(BinaryOperator::Neq),
+            Operator::Gt => binary(BinaryOperator::Gt),
+            Operator::Lt => binary(BinaryOperator::Lt),
+            Operator::Geq => binary(BinaryOperator::Geq),
+            Operator::Leq => binary(BinaryOperator::Leq),
+            Operator::And => binary(BinaryOperator::And),
+            Operator::Or => binary(BinaryOperator::Or),
+            Operator::Neg => Ok(negation(unary()?)),
+            Operator::Not => Ok(Self::Unary(UnaryOperator::Not, Box::new(unary()?))),
+            Operator::Assign => match children()?.as_slice() {
+                [Self::Variable(name), value] => {
+                    Ok(Self::Assign(name.to_owned(), Box::new(value.to_owned())))
+                }
+                _ => Err(unsupported()),
+            },
+            Operator::Const { value } => match value {
+                Value::Float(number) => Ok(Self::Number(Number(*number))),
+                Value::Int(number) => Ok(Self::Number(Number(*number as f64))),
+                Value::Boolean(truth) => Ok(Self::Number(Number(if *truth { 1.0 } else { 0.0 }))),
+                Value::Empty => Ok(Self::default()),
+                _ => Err(unsupported()),
+            },
+            Operator::VariableIdentifierRead { identifier }
+            | Operator::VariableIdentifierWrite { identifier } => {
+                Ok(Self::Variable(identifier.to_owned()))
+            }
+            Operator::FunctionIdentifier { identifier } => match node.children() {
+                [argument] => match Self::string_argument(argument) {
+                    Some(name) if identifier == NODE_FUNCTION => Ok(Self::Variable(name)),
+                    _ => Ok(Self::Function(
+                        identifier.to_owned(),
+                        Self::function_arguments(argument)?,
+                    )),
+                },
+                _ => Err(unsupported()),
+            },
+            _ => Err(unsupported()),
+        }
+    }
+
+    /// Arguments of a function call, a tuple for `max(a, b)` or a single operand.
+    fn function_arguments(node: &Node) -> Result<Vec<Self>, EquationError> {
+        match (node.operator(), node.children()) {
+            (Operator::RootNode, [argument]) => Self::function_arguments(argument),
+            (Operator::Tuple, arguments) => arguments.iter().map(Self::from_node).collect(),
+            _ => Ok(vec![Self::from_node(node)?]),
+        }
+    }
+
+    fn string_argument(node: &Node) -> Option<String> {
+        match (node.operator(), node.children()) {
+            (Operator::RootNode, [argument]) => Self::string_argument(argument),
+            (
+                Operator::Const {
+                    value: Value::String(name),
+                },
+                _,
+            ) => Some(name.to_owned()),
+            _ => None,
+        }
+    }
+
+    /// Variables read before being assigned, bound by the caller.
+    pub fn free_variables(&self) -> BTreeSet<String> {
+        let mut free = BTreeSet::new();
+        self.collect_free_variables(&mut BTreeSet::new(), &mut free);
+        free
+    }
+
+    fn collect_free_variables(&self, assigned: &mut BTreeSet<String>, free: &mut BTreeSet<String>) {
+        match self {
+            Self::Number(_) => {}
+            Self::Variable(name) => {
+                if !assigned.contains(name) {
+                    free.insert(name.to_owned());
+                }
+            }
+            Self::Unary(_operator, operand) => operand.collect_free_variables(assigned, free),
+            Self::Binary(_operator, lhs, rhs) => {
+                lhs.collect_free_variables(assigned, free);
+                rhs.collect_free_variables(assigned, free);
+            }
+            Self::Function(_, expressions) | Self::Chain(expressions) => expressions
+                .iter()
+                .for_each(|expression| expression.collect_free_variables(assigned, free)),
+            Self::Assign(name, value) => {
+                value.collect_free_variables(assigned, free);
+                assigned.insert(name.to_owned());
+            }
+        }
+    }
+
+    /// Expression with the free variables in `bindings` replaced, variables assigned by the
+    /// expression itself are kept.
+    pub fn substitute(&self, bindings: &HashMap<String, Expression>) -> Self {
+        self.substitute_scoped(bindings, &mut BTreeSet::new())
+    }
+
+    fn substitute_scoped(
+        &self,
+        bindings: &HashMap<String, Expression>,
+        assigned: &mut BTreeSet<String>,
+    ) -> Self {
+        match self {
+            Self::Variable(name) if !assigned.contains(name) => bindings
+                .get(name)
+                .cloned()
+                .unwrap_or_else(|| self.to_owned()),
+            Self::Number(_) | Self::Variable(_) => self.to_owned(),
+            Self::Unary(operator, operand) => Self::Unary(
+                *operator,
+                Box::new(operand.substitute_scoped(bindings, assigned)),
+            ),
+            Self::Binary(operator, lhs, rhs) => Self::Binary(
+                *operator,
+                Box::new(lhs.substitute_scoped(bindings, assigned)),
+                Box::new(rhs.substitute_scoped(bindings, assigned)),
+            ),
+            Self::Function(name, arguments) => Self::Function(
+                name.to_owned(),
+                arguments
+                    .iter()
+                    .map(|argument| argument.substitute_scoped(bindings, assigned))
+                    .collect(),
+            ),
+            Self::Assign(name, value) => {
+                let value = value.substitute_scoped(bindings, assigned);
+                assigned.insert(name.to_owned());
+                Self::Assign(name.to_owned(), Box::new(value))
+            }
+            Self::Chain(statements) => Self::Chain(
+                statements
+                    .iter()
+                    .map(|statement| statement.substitute_scoped(bindings, assigned))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Value of the expression, `variables` receives its assignments.
+    pub fn evaluate(&self, variables: &mut HashMap<String, f64>) -> Result<f64, EquationError> {
+        match self {
+            Self::Number(number) => Ok(number.0),
+            Self::Variable(name) => variables
+                .get(name)
+                .copied()
+                .ok_or_else(|| EquationError::UnboundVariable(name.to_owned())),
+            Self::Unary(UnaryOperator::Neg, operand) => Ok(-operand.evaluate(variables)?),
+            Self::Unary(UnaryOperator::Not, operand) => {
+                Ok(if operand.evaluate(variables)? == 0.0 {
+                    1.0
+                } else {
+                    0.0
+                })
+            }
+            Self::Binary(operator, lhs, rhs) => {
+                let lhs = lhs.evaluate(variables)?;
+                Ok(operator.apply(lhs, rhs.evaluate(variables)?))
+            }
+            Self::Function(name, arguments) => Self::call(name, arguments, variables),
+            Self::Assign(name, value) => {
+                let value = value.evaluate(variables)?;
+                variables.insert(name.to_owned(), value);
+                Ok(value)
+            }
+            Self::Chain(statements) => statements
+                .iter()
+                .try_fold(0.0, |_value, statement| statement.evaluate(variables)),
+        }
+    }
+
+    /// `if` only evaluates the selected branch.
+    fn call(
+        name: &str,
+        arguments: &[Self],
+        variables: &mut HashMap<String, f64>,
+    ) -> Result<f64, EquationError> {
+        match (name, arguments) {
+            ("if", [condition, then, otherwise]) => {
+                if condition.evaluate(variables)? != 0.0 {
+                    then.evaluate(variables)
+                } else {
+                    otherwise.evaluate(variables)
+                }
+            }
+            ("min" | "max", [first, rest @ ..]) => {
+                rest.iter()
+                    .try_fold(first.evaluate(variables)?, |extremum, argument| {
+                        let value = argument.evaluate(variables)?;
+                        Ok(if name == "min" {
+                            extremum.min(value)
+                        } else {
+                            extremum.max(value)
+                        })
+                    })
+            }
+            ("math::exp", [operand]) => Ok(operand.evaluate(variables)?.exp()),
+            ("math::ln", [operand]) => Ok(operand.evaluate(variables)?.ln()),
+            ("math::sqrt", [operand]) => Ok(operand.evaluate(variables)?.sqrt()),
+            ("math::abs", [operand]) => Ok(operand.evaluate(variables)?.abs()),
+            _ => Err(Self::call_error(name, arguments)),
+        }
+    }
+
+    fn call_error(name: &str, arguments: &[Self]) -> EquationError {
+        if FUNCTIONS.contains(&name) {
+            EquationError::Arguments {
+                function: name.to_owned(),
+                found: arguments.len(),
+            }
+        } else {
+            EquationError::UnknownFunction(name.to_owned())
+        }
+    }
+
+    /// Symbolic partial derivative of the value of the expression by `variable`.
+    ///
+    /// Every assignment `x = f` of a chain is preceded by `dx_d<variable> = f'`, so the
+    /// derivative is evaluated with the same variables as the expression. Comparisons are
+    /// piecewise constant, `min`, `max` and `if` differentiate the selected branch.
+    pub fn partial_derivative(&self, variable: &str) -> Result<Self, EquationError> {
+        match self {
+            Self::Chain(statements) => {
+                Self::derive_statements(statements, variable, &mut HashMap::new())
+            }
+            Self::Assign(..) => {
+                Self::derive_statements(std::slice::from_ref(self), variable, &mut HashMap::new())
+            }
+            expression => expression.derive(variable, &HashMap::new()),
+        }
+    }
+
+    fn derive_statements(
+        statements: &[Self],
+        variable: &str,
+        derived: &mut HashMap<String, String>,
+    ) -> Result<Self, EquationError> {
+        let mut chain = Vec::new();
+        let mut derivative = zero();
+        for statement in statements {
+            match statement {
+                Self::Assign(name, value) => {
+                    let derivative_name = format!("d{}_d{}", name, variable);
+                    chain.push(Self::Assign(
+                        derivative_name.to_owned(),
+                        Box::new(value.derive(variable, derived)?),
+                    ));
+                    chain.push(statement.to_owned());
+                    derived.insert(name.to_owned(), derivative_name.to_owned());
+                    derivative = Self::Variable(derivative_name);
+                }
+                statement => derivative = statement.derive(variable, derived)?,
+            }
+        }
+        chain.push(derivative);
+        Ok(Self::Chain(chain))
+    }
+
+    /// `derived` names the variable holding the derivative of each variable assigned so far.
+    fn derive(
+        &self,
+        variable: &str,
+        derived: &HashMap<String, String>,
+    ) -> Result<Self, EquationError> {
+        Ok(match self {
+            Self::Number(_) => zero(),
+            Self::Variable(name) => match derived.get(name) {
+                Some(derivative_name) => Self::Variable(derivative_name.to_owned()),
+                None if name == variable => one(),
+                None => zero(),
+            },
+            Self::Unary(UnaryOperator::Neg, operand) => {
+                negation(operand.derive(variable, derived)?)
+            }
+            Self::Unary(UnaryOperator::Not, _operand) => zero(),
+            Self::Binary(operator, lhs, rhs) => {
+                let (dlhs, drhs) = (
+                    lhs.derive(variable, derived)?,
+                    rhs.derive(variable, derived)?,
+                );
+                let (lhs, rhs) = (lhs.as_ref().to_owned(), rhs.as_ref().to_owned());
+                match operator {
+                    BinaryOperator::Add => sum(dlhs, drhs),
+                    BinaryOperator::Sub => difference(dlhs, drhs),
+                    BinaryOperator::Mul => sum(product(dlhs, rhs.to_owned()), product(lhs, drhs)),
+                    BinaryOperator::Div => quotient(
+                        difference(product(dlhs, rhs.to_owned()), product(lhs, drhs)),
+                        product(rhs.to_owned(), rhs),
+                    ),
+                    // d(a % b) = da - trunc(a / b) * db, only kept for a constant divisor
+                    BinaryOperator::Mod if drhs == zero() => dlhs,
+                    BinaryOperator::Mod => {
+                        return Err(EquationError::Unsupported(
+                            "% with a variable divisor".to_owned(),
+                        ));
+                    }
+                    BinaryOperator::Pow if drhs == zero() => product(
+                        product(rhs.to_owned(), power(lhs, difference(rhs, one()))),
+                        dlhs,
+                    ),
+                    BinaryOperator::Pow => product(
+                        self.to_owned(),
+                        sum(
+                            product(drhs, function("math::ln", vec![lhs.to_owned()])),
+                            quotient(product(rhs, dlhs), lhs),
+                        ),
+                    ),
+                    _ => zero(),
+                }
+            }
+            Self::Function(name, arguments) => {
+                Self::derive_call(name, arguments, variable, derived)?
+            }
+            Self::Assign(_name, value) => value.derive(variable, derived)?,
+            Self::Chain(statements) => {
+                Self::derive_statements(statements, variable, &mut derived.to_owned())?
+            }
+        })
+    }
+
+    fn derive_call(
+        name: &str,
+        arguments: &[Self],
+        variable: &str,
+        derived: &HashMap<String, String>,
+    ) -> Result<Self, EquationError> {
+        let derive = |argument: &Self| argument.derive(variable, derived);
+        Ok(match (name, arguments) {
+            ("if", [condition, then, otherwise]) => {
+                branch(condition.to_owned(), derive(then)?, derive(otherwise)?)
+            }
+            ("min" | "max", [operand]) => derive(operand)?,
+            ("min" | "max", [first, rest @ ..]) => {
+                let comparison = if name == "min" {
+                    BinaryOperator::Leq
+                } else {
+                    BinaryOperator::Geq
+                };
+                let rest_value = match rest {
+                    [operand] => operand.to_owned(),
+                    _ => function(name, rest.to_vec()),
+                };
+                branch(
+                    Self::Binary(comparison, Box::new(first.to_owned()), Box::new(rest_value)),
+                    derive(first)?,
+                    Self::derive_call(name, rest, variable, derived)?,
+                )
+            }
+            ("math::exp", [operand]) => {
+                product(function(name, arguments.to_vec()), derive(operand)?)
+            }
+            ("math::ln", [operand]) => quotient(derive(operand)?, operand.to_owned()),
+            ("math::sqrt", [operand]) => quotient(
+                derive(operand)?,
+                product(
+                    Self::Number(Number(2.0)),
+                    function(name, arguments.to_vec()),
+                ),
+            ),
+            ("math::abs", [operand]) => {
+                let doperand = derive(operand)?;
+                branch(
+                    Self::Binary(
+                        BinaryOperator::Geq,
+                        Box::new(operand.to_owned()),
+                        Box::new(zero()),
+                    ),
+                    doperand.to_owned(),
+                    negation(doperand),
+                )
+            }
+            _ => return Err(Self::call_error(name, arguments)),
+        })
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Self::Chain(_) => 0,
+            Self::Assign(..) => 50,
+            Self::Binary(operator, ..) => operator.precedence(),
+            Self::Unary(..) => 110,
+            Self::Number(number) if number.0.is_sign_negative() => 110,
+            _ => u8::MAX,
+        }
+    }
+
+    /// Writes the expression, parenthesized when it binds looser than `precedence`.
+    fn fmt_operand(&self, f: &mut fmt::Formatter, precedence: u8) -> fmt::Result {
+        if self.precedence() < precedence {
+            write!(f, "({})", self)
+        } else {
+            write!(f, "{}", self)
+        }
+    }
+}
+
+fn zero() -> Expression {
+    Expression::Number(Number(0.0))
+}
+
+fn one() -> Expression {
+    Expression::Number(Number(1.0))
+}
+
+fn number(expression: &Expression) -> Option<f64> {
+    match expression {
+        Expression::Number(number) => Some(number.0),
+        _ => None,
+    }
+}
+
+fn function(name: &str, arguments: Vec<Expression>) -> Expression {
+    Expression::Function(name.to_owned(), arguments)
+}
+
+fn branch(condition: Expression, then: Expression, otherwise: Expression) -> Expression {
+    if then == otherwise {
+        then
+    } else {
+        function("if", vec![condition, then, otherwise])
+    }
+}
+
+fn negation(operand: Expression) -> Expression {
+    match number(&operand) {
+        Some(value) => Expression::Number(Number(-value)),
+        None => Expression::Unary(UnaryOperator::Neg, Box::new(operand)),
+    }
+}
+
+fn sum(lhs: Expression, rhs: Expression) -> Expression {
+    match (number(&lhs), number(&rhs)) {
+        (Some(lhs), Some(rhs)) => Expression::Number(Number(lhs + rhs)),
+        (Some(value), _) if value == 0.0 => rhs,
+        (_, Some(value)) if value == 0.0 => lhs,
+        _ => Expression::Binary(BinaryOperator::Add, Box::new(lhs), Box::new(rhs)),
+    }
+}
+
+fn difference(lhs: Expression, rhs: Expression) -> Expression {
+    match (number(&lhs), number(&rhs)) {
+        (Some(lhs), Some(rhs)) => Expression::Number(Number(lhs - rhs)),
+        (Some(value), _) if value == 0.0 => negation(rhs),
+        (_, Some(value)) if value == 0.0 => lhs,
+        _ => Expression::Binary(BinaryOperator::Sub, Box::new(lhs), Box::new(rhs)),
+    }
+}
+
+fn product(lhs: Expression, rhs: Expression) -> Expression {
+    match (number(&lhs), number(&rhs)) {
+        (Some(lhs), Some(rhs)) => Expression::Number(Number(lhs * rhs)),
+        (Some(value), _) | (_, Some(value)) if value == 0.0 => zero(),
+        (Some(value), _) if value == 1.0 => rhs,
+        (_, Some(value)) if value == 1.0 => lhs,
+        _ => Expression::Binary(BinaryOperator::Mul, Box::new(lhs), Box::new(rhs)),
+    }
+}
+
+fn quotient(lhs: Expression, rhs: Expression) -> Expression {
+    match (number(&lhs), number(&rhs)) {
+        (Some(value), _) if value == 0.0 => zero(),
+        (_, Some(value)) if value == 1.0 => lhs,
+        _ => Expression::Binary(BinaryOperator::Div, Box::new(lhs), Box::new(rhs)),
+    }
+}
+
+fn power(lhs: Expression, rhs: Expression) -> Expression {
+    match number(&rhs) {
+        Some(value) if value == 0.0 => one(),
+        Some(value) if value == 1.0 => lhs,
+        _ => Expression::Binary(BinaryOperator::Pow, Box::new(lhs), Box::new(rhs)),
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Number(number) => write!(f, "{:?}", number.0),
+            Self::Variable(name) => match Self::from_str(name) {
+                Ok(Self::Variable(parsed)) if parsed == *name => write!(f, "{}", name),
+                _ => write!(f, "{}({:?})", NODE_FUNCTION, name),
+            },
+            Self::Unary(operator, operand) => {
+                let symbol = match operator {
+                    UnaryOperator::Neg => "-",
+                    UnaryOperator::Not => "!",
+                };
+                write!(f, "{}", symbol)?;
+                operand.fmt_operand(f, 111)
+            }
+            Self::Binary(operator, lhs, rhs) => {
+                let precedence = operator.precedence();
+                match operator {
+                    BinaryOperator::Pow => lhs.fmt_operand(f, precedence + 1)?,
+                    _ => lhs.fmt_operand(f, precedence)?,
+                }
+                write!(f, " {} ", operator.symbol())?;
+                rhs.fmt_operand(f, precedence + 1)
+            }
+            Self::Function(name, arguments) => {
+                write!(f, "{}({})", name, arguments.iter().join(", "))
+            }
+            Self::Assign(name, value) => {
+                write!(f, "{} = ", name)?;
+                value.fmt_operand(f, 51)
+            }
+            Self::Chain(statements) => {
+                for (idx, statement) in statements.iter().enumerate() {
+                    if idx > 0 {
+                        writeln!(f, ";")?;
+                    }
+                    statement.fmt_operand(f, 1)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(expression: &Expression, variables: &[(&str, f64)]) -> f64 {
+        let mut variables = variables
+            .iter()
+            .map(|(name, value)| (name.to_string(), *value))
+            .collect();
+        expression.evaluate(&mut variables).unwrap()
+    }
+
+    #[test]
+    fn parse_and_display() {
+        let expression = Expression::from_str(indoc::indoc! {"
+            Vt = T/11586;
+            I = 1e-12*(math::exp(-vd/Vt) - 1) + max(vd, 0, a - b);
+        "})
+        .unwrap();
+        let display = indoc::indoc! {"
+            Vt = T / 11586.0;
+            I = 1e-12 * (math::exp(-vd / Vt) - 1.0) + max(vd, 0.0, a - b)"};
+        assert_eq!(display, expression.to_string());
+        assert_eq!(expression, Expression::from_str(display).unwrap());
+        assert_eq!(
+            BTreeSet::from(["T", "a", "b", "vd"].map(String::from)),
+            expression.free_variables()
+        );
+        assert_eq!(Expression::default(), Expression::from_str("").unwrap());
+    }
+
+    #[test]
+    fn display_node_variables() {
+        let expression = Expression::Binary(
+            BinaryOperator::Sub,
+            Box::new(Expression::Variable("1".to_owned())),
+            Box::new(Expression::Variable("x1.a_399_74#".to_owned())),
+        );
+        assert_eq!(r#"v("1") - x1.a_399_74#"#, expression.to_string());
+        assert_eq!(
+            expression,
+            Expression::from_str(&expression.to_string()).unwrap()
+        );
+        assert_eq!(
+            Expression::Function("v".to_owned(), vec![Expression::Variable("a".to_owned())]),
+            Expression::from_str("v(a)").unwrap(),
+            "Only string arguments name node variables."
+        );
+    }
+
+    #[test]
+    fn evaluate_float_semantics() {
+        let expression = Expression::from_str("x = 1 / 2; if(x > 0.25, x * 4, y)").unwrap();
+        assert_eq!(2.0, evaluate(&expression, &[]));
+        assert_eq!(
+            Err(EquationError::UnboundVariable("y".to_owned())),
+            Expression::from_str("if(0, 1, y)")
+                .unwrap()
+                .evaluate(&mut HashMap::new())
+        );
+        assert_eq!(
+            Err(EquationError::UnknownFunction("erf".to_owned())),
+            Expression::from_str("erf(1)")
+                .unwrap()
+                .evaluate(&mut HashMap::new())
+        );
+    }
+
+    #[test]
+    fn substitute_whole_variables() {
+        let expression = Expression::from_str("a1 = a * 2; I = a1 + vgs + vgs2 + a").unwrap();
+        let bindings = HashMap::from([
+            ("a".to_owned(), Expression::from_str("A1 - B1").unwrap()),
+            ("a1".to_owned(), Expression::Variable("A2".to_owned())),
+            ("vgs".to_owned(), Expression::from_str("A1 - VGND").unwrap()),
+        ]);
+        assert_eq!(
+            "a1 = (A1 - B1) * 2.0;\nI = a1 + (A1 - VGND) + vgs2 + (A1 - B1)",
+            expression.substitute(&bindings).to_string()
+        );
+    }
+
+    #[test]
+    fn partial_derivatives() {
+        let expression = Expression::from_str(indoc::indoc! {"
+            vt = 0.025;
+            ids = if(vgs > 0.5, 2e-4 * (vgs - 0.5)^2 * (1 + 0.1 * vds), 0.0);
+            I = ids + 1e-12 * (math::exp(vds / vt) - 1) + math::sqrt(vds * vds + 1) / max(vgs, 1)
+        "})
+        .unwrap();
+        let point = [("vgs", 1.2), ("vds", 0.3)];
+        ["vgs", "vds"].iter().for_each(|variable| {
+            let derivative = expression.partial_derivative(variable).unwrap();
+            let delta = 1e-6;
+            let shifted = |step: f64| {
+                point.map(|(name, value)| {
+                    (
+                        name,
+                        if name == *variable {
+                            value + step
+                        } else {
+                            value
+                        },
+                    )
+                })
+            };
+            let expected = (evaluate(&expression, &shifted(delta))
+                - evaluate(&expression, &shifted(-delta)))
+                / (2.0 * delta);
+            let found = evaluate(&derivative, &point);
+            assert!(
+                (expected - found).abs() < 1e-6 * expected.abs().max(1e-9),
+                "d/d{}: expected {}, found {}.",
+                variable,
+                expected,
+                found
+            );
+        });
+        assert_eq!(
+            Expression::from_str("2.0 * x").unwrap(),
+            Expression::from_str("x^2 + y")
+                .unwrap()
+                .partial_derivative("x")
+                .unwrap()
+        );
+        let modulo = Expression::from_str("x % y").unwrap();
+        assert_eq!(
+            Expression::from_str("1").unwrap(),
+            modulo.partial_derivative("x").unwrap()
+        );
+        assert_eq!(
+            Err(EquationError::Unsupported(
+                "% with a variable divisor".to_owned()
+            )),
+            modulo.partial_derivative("y")
+        );
+    }
+}
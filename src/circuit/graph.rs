@@ -1,41 +1,168 @@
-pub(super) mod edges;
-pub(super) mod nodes;
+pub(crate) mod edges;
+pub(crate) mod hierarchy;
+pub(crate) mod nodes;
 pub(super) mod spice;
 
+use std::collections::{BTreeMap, BTreeSet};
 use std::ops::{Deref, DerefMut};
 
 use bevy_ecs::component::Component;
 use mhgl::HGraph;
 
 use super::equations::DeviceEquationMap;
+use super::models::{model_equations, ModelError};
+use super::nodes::CircuitNode;
 use super::spice::SPICENetlist;
 use crate::circuit::graph::edges::VoltageHEdge;
-use crate::circuit::graph::nodes::ElementHNode;
+use crate::circuit::graph::hierarchy::{SubcircuitTemplate, SubcircuitTemplateMap};
+use crate::circuit::graph::nodes::{ElementHNode, ElementKind};
+use crate::circuit::graph::spice::netlist_scope_circuit;
 
 pub type LCircuitNodeID = u64;
 pub type LCircuitEdgeID = u32;
 
 pub(crate) type LHGraph = HGraph<ElementHNode, VoltageHEdge, LCircuitNodeID, LCircuitEdgeID>;
 
+/// Hypergraph of `ElementHNode`s connected by `VoltageHEdge` nets, indexed by element and net.
+///
+/// `.subckt` definitions are kept as `SubcircuitTemplate`s of the scope defining them, see
+/// `LCircuit::flatten` for the circuit with every instance expanded.
 #[derive(Debug, Clone, Component)]
-pub struct LCircuit(LHGraph);
+pub struct LCircuit {
+    graph: LHGraph,
+    elements: BTreeMap<LCircuitNodeID, ElementHNode>,
+    nets: BTreeMap<CircuitNode, LCircuitEdgeID>,
+    subcircuits: SubcircuitTemplateMap,
+    globals: BTreeSet<CircuitNode>,
+}
+
+impl LCircuit {
+    /// Adds every element, then one net per distinct terminal node.
+    pub fn from_elements(elements: impl IntoIterator<Item = ElementHNode>) -> Self {
+        let mut circuit = Self::default();
+        let mut net_elements: BTreeMap<CircuitNode, Vec<LCircuitNodeID>> = BTreeMap::new();
+        elements.into_iter().for_each(|element| {
+            let terminals = element.terminals().to_owned();
+            let element_id = circuit.add_element(element);
+            terminals.into_iter().for_each(|terminal| {
+                let terminal_elements = net_elements.entry(terminal).or_default();
+                if !terminal_elements.contains(&element_id) {
+                    terminal_elements.push(element_id);
+                }
+            });
+        });
+        net_elements.into_iter().for_each(|(node, element_ids)| {
+            circuit.add_net(node, element_ids);
+        });
+        circuit
+    }
+
+    pub fn add_element(&mut self, element: ElementHNode) -> LCircuitNodeID {
+        let element_id = self.graph.add_node(element.clone());
+        self.elements.insert(element_id, element);
+        element_id
+    }
+
+    pub fn add_net(
+        &mut self,
+        node: CircuitNode,
+        element_ids: Vec<LCircuitNodeID>,
+    ) -> Option<LCircuitEdgeID> {
+        let net_id = self
+            .graph
+            .add_edge(element_ids, VoltageHEdge::new(node.clone()))
+            .ok()?;
+        self.nets.insert(node, net_id);
+        Some(net_id)
+    }
+
+    pub fn elements(&self) -> impl Iterator<Item = (&LCircuitNodeID, &ElementHNode)> {
+        self.elements.iter()
+    }
+
+    pub fn element(&self, element_name: &str) -> Option<&ElementHNode> {
+        self.elements
+            .values()
+            .find(|element| element.name() == element_name)
+    }
+
+    pub fn nets(&self) -> impl Iterator<Item = (&CircuitNode, &LCircuitEdgeID)> {
+        self.nets.iter()
+    }
+
+    pub fn net(&self, node: &CircuitNode) -> Option<LCircuitEdgeID> {
+        self.nets.get(node).copied()
+    }
+
+    /// Adds a template to this scope, returning the template it replaces.
+    pub fn add_subcircuit(&mut self, template: SubcircuitTemplate) -> Option<SubcircuitTemplate> {
+        self.subcircuits
+            .insert(template.name().to_owned(), template)
+    }
+
+    pub fn subcircuits(&self) -> impl Iterator<Item = (&String, &SubcircuitTemplate)> {
+        self.subcircuits.iter()
+    }
+
+    pub fn subcircuit(&self, subcircuit_name: &str) -> Option<&SubcircuitTemplate> {
+        self.subcircuits.get(subcircuit_name)
+    }
+
+    /// Elements instantiating a template of this scope.
+    pub fn instances(&self) -> impl Iterator<Item = (&LCircuitNodeID, &ElementHNode)> {
+        self.elements.iter().filter(|(_element_id, element)| {
+            *element.kind() == ElementKind::Subcircuit
+                && element
+                    .value()
+                    .as_ref()
+                    .is_some_and(|template| self.subcircuits.contains_key(template))
+        })
+    }
+
+    /// Marks a node shared by every instance(`.global`), it keeps its name when flattening.
+    pub fn add_global(&mut self, node: CircuitNode) -> bool {
+        self.globals.insert(node)
+    }
+
+    pub fn globals(&self) -> &BTreeSet<CircuitNode> {
+        &self.globals
+    }
+}
 
+/// Elements of the top scope in netlist order, every `.subckt` becomes a `SubcircuitTemplate`
+/// and `X` instances bind its ports positionally.
+///
+/// Elements without an `LCircuit` representation(mutual inductors, switches and controlled
+/// sources) are skipped.
 impl From<(&SPICENetlist, &DeviceEquationMap)> for LCircuit {
     fn from(spice_netlist_and_map: (&SPICENetlist, &DeviceEquationMap)) -> Self {
-        let _spice_netlist = spice_netlist_and_map.0;
-        let _device_equation_map = spice_netlist_and_map.1;
-        todo!()
+        let (spice_netlist, device_equation_map) = spice_netlist_and_map;
+        netlist_scope_circuit(&spice_netlist.netlist_scope, device_equation_map)
+    }
+}
+
+/// `LCircuit` with the `DeviceEquation`s of the netlist's own `.model` cards, see
+/// `model_equations`.
+impl TryFrom<&SPICENetlist> for LCircuit {
+    type Error = ModelError;
+
+    fn try_from(spice_netlist: &SPICENetlist) -> Result<Self, Self::Error> {
+        Ok(Self::from((
+            spice_netlist,
+            &model_equations(spice_netlist)?,
+        )))
     }
 }
 
 impl Default for LCircuit {
     fn default() -> Self {
-        Self(HGraph::<
-            ElementHNode,
-            VoltageHEdge,
-            LCircuitNodeID,
-            LCircuitEdgeID,
-        >::new())
+        Self {
+            graph: HGraph::<ElementHNode, VoltageHEdge, LCircuitNodeID, LCircuitEdgeID>::new(),
+            elements: BTreeMap::new(),
+            nets: BTreeMap::new(),
+            subcircuits: SubcircuitTemplateMap::new(),
+            globals: BTreeSet::new(),
+        }
     }
 }
 
@@ -43,13 +170,13 @@ impl Deref for LCircuit {
     type Target = LHGraph;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.graph
     }
 }
 
 impl DerefMut for LCircuit {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+        &mut self.graph
     }
 }
 
@@ -82,6 +209,7 @@ mod tests {
     use super::*;
     // use crate::circuit::elements::*;
     use crate::circuit::equations::*;
+    use crate::circuit::graph::nodes::ElementKind;
     use crate::circuit::nodes::CircuitNode;
 
     #[test]
@@ -191,7 +319,6 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "not yet implemented")]
     fn from_spice_netlist() {
         let mut spice_netlist_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
         spice_netlist_path.push("resources/spice3f5_examples/mosamp2.cir");
@@ -208,8 +335,27 @@ mod tests {
         let dev_eq = DeviceEquation::from_str(eq).unwrap();
         let device_eq_map = DeviceEquationMap::from([("m".to_string(), dev_eq)]);
 
-        // let num_elements: usize = 33;
-        // let graph_element_indices = (0..32).collect_vec();
-        let _graph = LCircuit::from((&ast, &device_eq_map));
+        let graph = LCircuit::from((&ast, &device_eq_map));
+        assert_eq!(
+            33,
+            graph.elements().count(),
+            "Every element should be added."
+        );
+        assert_eq!(21, graph.nets().count(), "One net per netlist node.");
+        let m1 = graph.element("m1").unwrap();
+        assert_eq!(ElementKind::MosTransistor, *m1.kind());
+        assert_eq!(
+            ["15", "15", "1", "32"]
+                .map(|node| CircuitNode::from_str(node).unwrap())
+                .to_vec(),
+            *m1.terminals(),
+            "MOS terminals should be in `d g s b` order."
+        );
+        assert_eq!(Some("88.9u"), m1.param("w"));
+        let vccp = graph.element("vccp").unwrap();
+        assert_eq!(ElementKind::VoltageSource, *vccp.kind());
+        assert_eq!(&Some("+15".to_owned()), vccp.value());
+        let vin = graph.element("vin").unwrap();
+        assert_eq!(Some("0 5 1ns 1ns 1ns 5us 10us"), vin.param("pulse"));
     }
 }
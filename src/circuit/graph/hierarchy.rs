@@ -0,0 +1,375 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::str::FromStr;
+
+use derive_getters::Getters;
+use itertools::Itertools;
+
+use super::nodes::{ElementHNode, ElementKind};
+use super::LCircuit;
+use crate::circuit::nodes::CircuitNode;
+
+/// Separator between instance names of a flattened element or internal node, `m.x1.x2.m1`.
+pub const HIERARCHY_SEPARATOR: char = '.';
+
+pub(crate) type SubcircuitTemplateMap = BTreeMap<String, SubcircuitTemplate>;
+
+/// `.subckt` definition, instantiated by `ElementKind::Subcircuit` elements whose `value` is the
+/// template name and whose `terminals` bind its `ports` in order.
+#[derive(Debug, Clone, Getters)]
+pub struct SubcircuitTemplate {
+    name: String,
+    ports: Vec<CircuitNode>,
+    circuit: LCircuit,
+}
+
+impl SubcircuitTemplate {
+    pub fn new(name: impl Into<String>, ports: Vec<CircuitNode>, circuit: LCircuit) -> Self {
+        Self {
+            name: name.into(),
+            ports,
+            circuit,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FlattenError {
+    UnknownSubcircuit(String),
+    /// Instance binds a different number of nodes than its template has ports.
+    PortCount {
+        instance: String,
+        subcircuit: String,
+        expected: usize,
+        found: usize,
+    },
+    /// Template instantiating itself, directly or through other templates.
+    RecursiveSubcircuit(String),
+}
+
+impl fmt::Display for FlattenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownSubcircuit(subcircuit) => write!(f, "No subcircuit {}", subcircuit),
+            Self::PortCount {
+                instance,
+                subcircuit,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Instance {} binds {} nodes, subcircuit {} has {} ports",
+                instance, found, subcircuit, expected
+            ),
+            Self::RecursiveSubcircuit(subcircuit) => {
+                write!(f, "Subcircuit {} instantiates itself", subcircuit)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FlattenError {}
+
+/// Visible templates of a scope, innermost first, and the templates being expanded.
+struct Flattener<'circuit> {
+    scopes: Vec<&'circuit SubcircuitTemplateMap>,
+    expanding: Vec<&'circuit str>,
+    globals: &'circuit BTreeSet<CircuitNode>,
+    elements: Vec<ElementHNode>,
+}
+
+impl<'circuit> Flattener<'circuit> {
+    fn template(&self, name: &str) -> Option<&'circuit SubcircuitTemplate> {
+        self.scopes.iter().rev().copied().find_map(|scope| {
+            scope.get(name).or_else(|| {
+                scope
+                    .iter()
+                    .find(|(template_name, _template)| template_name.eq_ignore_ascii_case(name))
+                    .map(|(_template_name, template)| template)
+            })
+        })
+    }
+
+    fn instance_template(&self, element: &ElementHNode) -> Option<&'circuit SubcircuitTemplate> {
+        match (element.kind(), element.value()) {
+            (ElementKind::Subcircuit, Some(name)) => self.template(name),
+            _ => None,
+        }
+    }
+
+    /// Ground and `.global` nodes are shared, ports are bound to the nets of the instance and the
+    /// remaining nodes are prefixed with the instance path.
+    fn node(
+        &self,
+        node: &CircuitNode,
+        prefix: &str,
+        ports: &BTreeMap<CircuitNode, CircuitNode>,
+    ) -> CircuitNode {
+        if node.is_ground() || self.globals.contains(node) {
+            node.to_owned()
+        } else if let Some(net) = ports.get(node) {
+            net.to_owned()
+        } else {
+            CircuitNode::from_str(&format!("{}{}", prefix, node))
+                .expect("Invalid CircuitNode Name.")
+        }
+    }
+
+    fn expand(
+        &mut self,
+        circuit: &'circuit LCircuit,
+        prefix: &str,
+        ports: &BTreeMap<CircuitNode, CircuitNode>,
+    ) -> Result<(), FlattenError> {
+        self.scopes.push(&circuit.subcircuits);
+        for (_element_id, element) in circuit.elements() {
+            let terminals = element
+                .terminals()
+                .iter()
+                .map(|terminal| self.node(terminal, prefix, ports))
+                .collect_vec();
+            match self.instance_template(element) {
+                Some(template) => {
+                    if self.expanding.contains(&template.name.as_str()) {
+                        return Err(FlattenError::RecursiveSubcircuit(template.name.to_owned()));
+                    }
+                    if template.ports.len() != terminals.len() {
+                        return Err(FlattenError::PortCount {
+                            instance: format!("{}{}", prefix, element.name()),
+                            subcircuit: template.name.to_owned(),
+                            expected: template.ports.len(),
+                            found: terminals.len(),
+                        });
+                    }
+                    let instance_ports = template.ports.iter().cloned().zip(terminals).collect();
+                    let instance_prefix =
+                        format!("{}{}{}", prefix, element.name(), HIERARCHY_SEPARATOR);
+                    self.expanding.push(&template.name);
+                    self.expand(&template.circuit, &instance_prefix, &instance_ports)?;
+                    self.expanding.pop();
+                }
+                None => {
+                    let equations = match element.device() {
+                        Some(device) => ElementHNode::terminal_equations(
+                            *element.kind(),
+                            &terminals,
+                            Some(device),
+                        ),
+                        None => element.equations().to_owned(),
+                    };
+                    self.elements.push(ElementHNode {
+                        name: flattened_name(*element.kind(), prefix, element.name()),
+                        terminals,
+                        equations,
+                        ..element.to_owned()
+                    });
+                }
+            }
+        }
+        self.scopes.pop();
+        Ok(())
+    }
+}
+
+/// SPICE-style name of a flattened element, its kind letter before the instance path(`m.x1.m1`).
+fn flattened_name(kind: ElementKind, prefix: &str, name: &str) -> String {
+    match kind.spice_letter() {
+        Some(letter) if !prefix.is_empty() => {
+            format!("{}{}{}{}", letter, HIERARCHY_SEPARATOR, prefix, name)
+        }
+        _ => format!("{}{}", prefix, name),
+    }
+}
+
+impl LCircuit {
+    /// Circuit without subcircuit instances, every instance replaced by the elements of its
+    /// template.
+    ///
+    /// Flattened elements are named by their kind letter and instance path(`m.x1.x2.m1`), internal
+    /// nodes by their instance path(`x1.x2.net`). Template ports take the net of the instance
+    /// terminal bound to them.
+    pub fn flatten(&self) -> Result<Self, FlattenError> {
+        let mut flattener = Flattener {
+            scopes: Vec::new(),
+            expanding: Vec::new(),
+            globals: &self.globals,
+            elements: Vec::new(),
+        };
+        flattener.expand(self, "", &BTreeMap::new())?;
+        let mut circuit = Self::from_elements(flattener.elements);
+        circuit.globals = self.globals.to_owned();
+        Ok(circuit)
+    }
+
+    /// Flattened body of the template `name`, its ports keep their names.
+    pub fn flatten_subcircuit(&self, name: &str) -> Result<Self, FlattenError> {
+        let mut flattener = Flattener {
+            scopes: vec![&self.subcircuits],
+            expanding: Vec::new(),
+            globals: &self.globals,
+            elements: Vec::new(),
+        };
+        let template = flattener
+            .template(name)
+            .ok_or_else(|| FlattenError::UnknownSubcircuit(name.to_owned()))?;
+        let ports = template
+            .ports
+            .iter()
+            .map(|port| (port.to_owned(), port.to_owned()))
+            .collect();
+        flattener.expanding.push(&template.name);
+        flattener.expand(&template.circuit, "", &ports)?;
+        let mut circuit = Self::from_elements(flattener.elements);
+        circuit.globals = self.globals.to_owned();
+        Ok(circuit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::equations::DeviceEquationMap;
+    use crate::circuit::spice::SPICENetlist;
+
+    fn node(name: &str) -> CircuitNode {
+        CircuitNode::from_str(name).unwrap()
+    }
+
+    fn buffer_circuit() -> LCircuit {
+        let deck = indoc::indoc! {"
+            * buffer of two inverters
+            .global vdd
+            .subckt inv a y
+            mp y a vdd vdd pmos w=2u l=1u
+            mn y a 0 0 nmos w=1u l=1u
+            .ends inv
+            .subckt buf a y
+            x1 a mid inv
+            x2 mid y inv
+            cmid mid 0 1f
+            .ends buf
+            vdd vdd 0 1.8
+            xbuf in out buf
+            rload out 0 10k
+            .end
+        "};
+        let netlist = SPICENetlist::parse_deck(deck).unwrap();
+        LCircuit::from((&netlist, &DeviceEquationMap::new()))
+    }
+
+    #[test]
+    fn subcircuit_templates() {
+        let circuit = buffer_circuit();
+        assert_eq!(
+            vec!["buf", "inv"],
+            circuit
+                .subcircuits()
+                .map(|(name, _template)| name)
+                .collect_vec()
+        );
+        let buf = circuit.subcircuit("buf").unwrap();
+        assert_eq!(&vec![node("a"), node("y")], buf.ports());
+        assert_eq!(3, buf.circuit().elements().count());
+        let xbuf = circuit.element("xbuf").unwrap();
+        assert_eq!(ElementKind::Subcircuit, *xbuf.kind());
+        assert_eq!(&vec![node("in"), node("out")], xbuf.terminals());
+        assert_eq!(
+            vec!["xbuf"],
+            circuit
+                .instances()
+                .map(|(_element_id, element)| element.name().as_str())
+                .collect_vec()
+        );
+        assert!(circuit.globals().contains(&node("vdd")));
+    }
+
+    #[test]
+    fn flatten_hierarchy() {
+        let flat = buffer_circuit().flatten().unwrap();
+        assert_eq!(
+            vec![
+                "vdd",
+                "m.xbuf.x1.mp",
+                "m.xbuf.x1.mn",
+                "m.xbuf.x2.mp",
+                "m.xbuf.x2.mn",
+                "c.xbuf.cmid",
+                "rload"
+            ],
+            flat.elements()
+                .map(|(_element_id, element)| element.name().as_str())
+                .collect_vec()
+        );
+        assert_eq!(
+            &["xbuf.mid", "in", "vdd", "vdd"].map(node).to_vec(),
+            flat.element("m.xbuf.x1.mp").unwrap().terminals()
+        );
+        assert_eq!(
+            &["out", "xbuf.mid", "0", "0"].map(node).to_vec(),
+            flat.element("m.xbuf.x2.mn").unwrap().terminals()
+        );
+        assert_eq!(
+            vec!["0", "in", "out", "vdd", "xbuf.mid"],
+            flat.nets()
+                .map(|(net, _net_id)| net.to_string())
+                .collect_vec()
+        );
+        assert_eq!(0, flat.instances().count());
+    }
+
+    #[test]
+    fn flatten_subcircuit_keeps_ports() {
+        let buf = buffer_circuit().flatten_subcircuit("buf").unwrap();
+        assert_eq!(5, buf.elements().count());
+        assert!(buf.net(&node("a")).is_some() && buf.net(&node("y")).is_some());
+        assert!(buf.net(&node("x1.y")).is_none());
+        assert_eq!(
+            &["mid", "a", "vdd", "vdd"].map(node).to_vec(),
+            buf.element("m.x1.mp").unwrap().terminals()
+        );
+        assert_eq!(
+            Err(FlattenError::UnknownSubcircuit("nand".to_owned())),
+            buffer_circuit()
+                .flatten_subcircuit("nand")
+                .map(|_circuit| ())
+        );
+    }
+
+    #[test]
+    fn flatten_errors() {
+        let mut circuit = buffer_circuit();
+        circuit.add_element(
+            ElementHNode::builder()
+                .name("xbad")
+                .kind(ElementKind::Subcircuit)
+                .terminals(vec![node("in")])
+                .value("inv")
+                .build(),
+        );
+        assert_eq!(
+            Err(FlattenError::PortCount {
+                instance: "xbad".to_owned(),
+                subcircuit: "inv".to_owned(),
+                expected: 2,
+                found: 1,
+            }),
+            circuit.flatten().map(|_circuit| ())
+        );
+
+        let mut looped = LCircuit::default();
+        looped.add_element(
+            ElementHNode::builder()
+                .name("xself")
+                .kind(ElementKind::Subcircuit)
+                .terminals(vec![node("a")])
+                .value("loop")
+                .build(),
+        );
+        let mut circuit = LCircuit::default();
+        circuit.add_subcircuit(SubcircuitTemplate::new("loop", vec![node("a")], looped));
+        assert_eq!(
+            Err(FlattenError::RecursiveSubcircuit("loop".to_owned())),
+            circuit.flatten_subcircuit("loop").map(|_circuit| ())
+        );
+    }
+}
@@ -1,15 +1,104 @@
+use std::collections::BTreeMap;
 use std::fmt::{Display, Formatter, Result};
 
-use crate::circuit::equations::CircuitEquation;
+use derive_getters::Getters;
+use typed_builder::TypedBuilder;
 
-#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+use crate::circuit::elements::resistor::Resistor;
+use crate::circuit::elements::transistor::Transistor;
+use crate::circuit::equations::{CircuitEquation, DeviceEquation, VariableContextMap};
+use crate::circuit::nodes::CircuitNode;
+
+/// SPICE Element Type of an `ElementHNode`
+#[derive(Debug, Clone, Copy, Default, Hash, Eq, PartialEq)]
+pub enum ElementKind {
+    /// Connection point without a device, e.g. a manually added port.
+    #[default]
+    Port,
+    Resistor,
+    Capacitor,
+    Inductor,
+    VoltageSource,
+    CurrentSource,
+    Diode,
+    MosTransistor,
+    Subcircuit,
+}
+
+impl ElementKind {
+    /// Leading letter of the SPICE element line, none for ports.
+    pub const fn spice_letter(&self) -> Option<char> {
+        match self {
+            Self::Port => None,
+            Self::Resistor => Some('r'),
+            Self::Capacitor => Some('c'),
+            Self::Inductor => Some('l'),
+            Self::VoltageSource => Some('v'),
+            Self::CurrentSource => Some('i'),
+            Self::Diode => Some('d'),
+            Self::MosTransistor => Some('m'),
+            Self::Subcircuit => Some('x'),
+        }
+    }
+}
+
+/// Circuit Element, hypergraph node of an `LCircuit`
+///
+/// `terminals` follow SPICE positional order: `p n` for two-terminal elements and `d g s b` for
+/// transistors. `device` is the model's `DeviceEquation` before node substitution, evaluated by
+/// the analyses with its controlling voltages(`vpn`, `vgs`, ...) bound numerically.
+#[derive(Debug, Clone, Default, Hash, Eq, PartialEq, TypedBuilder, Getters)]
 pub struct ElementHNode {
-    equations: CircuitEquation,
+    #[builder(default, setter(into))]
+    pub(crate) name: String,
+    #[builder(default)]
+    pub(crate) kind: ElementKind,
+    #[builder(default)]
+    pub(crate) terminals: Vec<CircuitNode>,
+    #[builder(default, setter(strip_option, into))]
+    pub(crate) value: Option<String>,
+    #[builder(default)]
+    pub(crate) params: BTreeMap<String, String>,
+    #[builder(default, setter(strip_option))]
+    pub(crate) device: Option<DeviceEquation>,
+    #[builder(default)]
+    pub(crate) equations: CircuitEquation,
 }
 
 impl ElementHNode {
-    pub const fn new(equations: CircuitEquation) -> Self {
-        Self { equations }
+    pub fn new(equations: CircuitEquation) -> Self {
+        Self {
+            equations,
+            ..Default::default()
+        }
+    }
+
+    /// `device` with the voltages of `terminals` substituted, `d g s b` for four-terminal
+    /// transistors and `p n` for two-terminal elements.
+    pub(crate) fn terminal_equations(
+        kind: ElementKind,
+        terminals: &[CircuitNode],
+        device: Option<&DeviceEquation>,
+    ) -> CircuitEquation {
+        let ctx: VariableContextMap = match (kind, terminals) {
+            (ElementKind::MosTransistor | ElementKind::Subcircuit, [drain, gate, source, body]) => {
+                Transistor::transistor_variable_ctx(source, drain, gate, body)
+            }
+            (_, [p, n]) => Resistor::resistor_variable_ctx(p, n),
+            _ => Vec::default(),
+        }
+        .into_iter()
+        .collect();
+        device.map_or_else(CircuitEquation::default, |device| {
+            CircuitEquation::new(device.to_owned(), &ctx)
+        })
+    }
+
+    pub fn param(&self, param_name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(name, _value)| name.eq_ignore_ascii_case(param_name))
+            .map(|(_name, value)| value.as_str())
     }
 }
 
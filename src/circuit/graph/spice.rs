@@ -1,10 +1,12 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::str::FromStr;
 
 use itertools::Itertools;
-use peginator::{ParseError, PegParser};
+use peginator::ParseError;
 
-use super::LCircuitNodeID;
+use super::hierarchy::SubcircuitTemplate;
+use super::nodes::{ElementHNode, ElementKind};
+use super::{LCircuit, LCircuitNodeID};
 use crate::circuit::elements::capacitor::Capacitor as LCapacitor;
 use crate::circuit::elements::resistor::Resistor as LResistor;
 use crate::circuit::elements::transistor::Transistor;
@@ -16,8 +18,8 @@ use crate::circuit::nodes::CircuitNode;
 use crate::circuit::spice::NetlistScope;
 #[allow(unused_imports)]
 use crate::circuit::spice::{
-    Capacitor, CurrentSource, Diode, Element, Inductor, Instance, MosTransistor, Node as SPICENode,
-    Resistor, SPICENetlist, VoltageSource,
+    Capacitor, CurrentSource, Diode, Element, Inductor, Instance, KeyValue, MosTransistor,
+    Node as SPICENode, Resistor, SPICENetlist, SourceValues, VoltageSource,
 };
 
 fn resistor_nodes(resistor: &Resistor) -> Vec<SPICENode> {
@@ -49,7 +51,7 @@ fn mos_transistor_nodes(mos_transistor: &MosTransistor) -> Vec<SPICENode> {
         mos_transistor.source.to_owned(),
         mos_transistor.drain.to_owned(),
         mos_transistor.gate.to_owned(),
-        mos_transistor.body.to_owned(),
+        mos_transistor.body_node().to_owned(),
     ];
     mos_transistor_nodes
         .iter()
@@ -58,19 +60,12 @@ fn mos_transistor_nodes(mos_transistor: &MosTransistor) -> Vec<SPICENode> {
 }
 
 fn instance_nodes(instance: &Instance) -> Vec<SPICENode> {
-    let instance_nodes = vec![
-        instance.source.to_owned(),
-        instance.drain.to_owned(),
-        instance.gate.to_owned(),
-        instance.body.to_owned(),
-    ];
-    instance_nodes
-        .iter()
-        .map(|spice_string| SPICENode::from(spice_string))
-        .collect_vec()
+    instance.ports().to_vec()
 }
 
-fn netlist_scope_element_iter(netlist: &SPICENetlist) -> impl Iterator<Item = &Element> + '_ {
+pub(super) fn netlist_scope_element_iter(
+    netlist: &SPICENetlist,
+) -> impl Iterator<Item = &Element> + '_ {
     let subcircuits = &netlist.netlist_scope.subcircuits;
     let top_scope = &netlist.netlist_scope;
     top_scope.elements.iter().chain(
@@ -85,15 +80,18 @@ fn get_element_model_name(element: &Element) -> ModelName {
         Element {
             subcircuit: Some(subcircuit),
             ..
-        } => subcircuit.model.clone(),
+        } => subcircuit.model().to_owned(),
         Element {
             mostransistor: Some(mostransistor),
             ..
         } => mostransistor.model.clone(),
         Element {
-            resistor: Some(_resistor),
+            resistor: Some(resistor),
             ..
-        } => ModelName::from(RESISTORMODELNAME),
+        } => resistor
+            .model
+            .clone()
+            .unwrap_or_else(|| ModelName::from(RESISTORMODELNAME)),
         Element {
             capacitor: Some(_capacitor),
             ..
@@ -108,12 +106,12 @@ fn get_element_nodes_eqs(element: &Element) -> Vec<VariableContext> {
         Element {
             subcircuit: Some(subcircuit),
             ..
-        } => Transistor::transistor_variable_ctx(
-            &CircuitNode::from_str(&subcircuit.source).expect("Invalid CircuitNode Name."),
-            &CircuitNode::from_str(&subcircuit.drain).expect("Invalid CircuitNode Name."),
-            &CircuitNode::from_str(&subcircuit.gate).expect("Invalid CircuitNode Name."),
-            &CircuitNode::from_str(&subcircuit.body).expect("Invalid CircuitNode Name."),
-        ),
+        } => match circuit_nodes(&subcircuit.ports().iter().collect_vec()).as_slice() {
+            [source, drain, gate, body] => {
+                Transistor::transistor_variable_ctx(source, drain, gate, body)
+            }
+            _ => Vec::default(),
+        },
         Element {
             mostransistor: Some(mostransistor),
             ..
@@ -121,7 +119,7 @@ fn get_element_nodes_eqs(element: &Element) -> Vec<VariableContext> {
             &CircuitNode::from_str(&mostransistor.source).expect("Invalid CircuitNode Name."),
             &CircuitNode::from_str(&mostransistor.drain).expect("Invalid CircuitNode Name."),
             &CircuitNode::from_str(&mostransistor.gate).expect("Invalid CircuitNode Name."),
-            &CircuitNode::from_str(&mostransistor.body).expect("Invalid CircuitNode Name."),
+            &CircuitNode::from_str(mostransistor.body_node()).expect("Invalid CircuitNode Name."),
         ),
         Element {
             resistor: Some(resistor),
@@ -152,6 +150,225 @@ fn get_element_circuit_equation(
     CircuitEquation::new(dev_eq, &ctx)
 }
 
+fn circuit_nodes(spice_nodes: &[&SPICENode]) -> Vec<CircuitNode> {
+    spice_nodes
+        .iter()
+        .map(|spice_node| CircuitNode::from_str(spice_node).expect("Invalid CircuitNode Name."))
+        .collect()
+}
+
+fn key_value_params(options: &[KeyValue]) -> BTreeMap<String, String> {
+    options
+        .iter()
+        .map(|option| (option.id.to_owned(), option.value.to_owned()))
+        .collect()
+}
+
+/// DC value and time-dependent functions(`pulse(...)`, `pwl(...)`, ...) of an independent source.
+fn source_value_params(values: &[SourceValues]) -> (Option<String>, BTreeMap<String, String>) {
+    let mut dc_value = None;
+    let mut params = BTreeMap::new();
+    values.iter().for_each(|source_value| {
+        if let Some(value) = &source_value.value {
+            dc_value.get_or_insert_with(|| value.to_owned());
+        }
+        if let Some(option) = &source_value.options {
+            params.insert(option.id.to_owned(), option.value.to_owned());
+        }
+        if let Some(param) = &source_value.params {
+            params.insert(param.id.to_ascii_lowercase(), param.value.join(" "));
+        }
+    });
+    (dc_value, params)
+}
+
+/// Resistors, capacitors, inductors and independent sources are modeled natively by the
+/// analyses, their `equations` are only substituted when `dev_eq_map` has an entry for the
+/// element type(`r`, `c`).
+fn two_terminal_hnode(
+    kind: ElementKind,
+    id: &str,
+    nodes: [&SPICENode; 2],
+    value: Option<String>,
+    params: BTreeMap<String, String>,
+    dev_eq: Option<&DeviceEquation>,
+) -> ElementHNode {
+    let terminals = circuit_nodes(&nodes);
+    let equations = ElementHNode::terminal_equations(kind, &terminals, dev_eq);
+    ElementHNode {
+        name: id.to_owned(),
+        kind,
+        terminals,
+        value,
+        params,
+        device: dev_eq.cloned(),
+        equations,
+    }
+}
+
+/// `ElementHNode` of a SPICE element, `None` for elements without an `LCircuit` representation.
+///
+/// Transistor terminals are taken positionally(`d g s b`), and the `DeviceEquation` of its model
+/// is looked up in `dev_eq_map`.
+pub(super) fn spice_element_hnode(
+    element: &Element,
+    dev_eq_map: &DeviceEquationMap,
+) -> Option<ElementHNode> {
+    let transistor_hnode = |kind: ElementKind,
+                            id: &str,
+                            terminals: &[&SPICENode],
+                            model: &str,
+                            options: &[KeyValue]| {
+        let terminals = circuit_nodes(terminals);
+        let device = dev_eq_map.get(model).cloned();
+        let equations = ElementHNode::terminal_equations(kind, &terminals, device.as_ref());
+        ElementHNode {
+            name: id.to_owned(),
+            kind,
+            terminals,
+            value: Some(model.to_owned()),
+            params: key_value_params(options),
+            device,
+            equations,
+        }
+    };
+    let model_dev_eq = dev_eq_map.get(&get_element_model_name(element));
+    match element {
+        Element {
+            mostransistor: Some(mos),
+            ..
+        } => Some(transistor_hnode(
+            ElementKind::MosTransistor,
+            &mos.id,
+            &[&mos.source, &mos.drain, &mos.gate, mos.body_node()],
+            &mos.model,
+            &mos.options,
+        )),
+        Element {
+            subcircuit: Some(instance),
+            ..
+        } => Some(transistor_hnode(
+            ElementKind::Subcircuit,
+            &instance.id,
+            &instance.ports().iter().collect_vec(),
+            instance.model(),
+            &instance.options,
+        )),
+        Element {
+            resistor: Some(resistor),
+            ..
+        } => Some(two_terminal_hnode(
+            ElementKind::Resistor,
+            &resistor.id,
+            [&resistor.p, &resistor.n],
+            resistor
+                .value
+                .to_owned()
+                .or_else(|| resistor.model.to_owned()),
+            key_value_params(&resistor.options),
+            model_dev_eq,
+        )),
+        Element {
+            capacitor: Some(capacitor),
+            ..
+        } => Some(two_terminal_hnode(
+            ElementKind::Capacitor,
+            &capacitor.id,
+            [&capacitor.p, &capacitor.n],
+            Some(capacitor.value.to_owned()),
+            key_value_params(&capacitor.options),
+            model_dev_eq,
+        )),
+        Element {
+            inductor: Some(inductor),
+            ..
+        } => Some(two_terminal_hnode(
+            ElementKind::Inductor,
+            &inductor.id,
+            [&inductor.p, &inductor.n],
+            Some(inductor.value.to_owned()),
+            key_value_params(&inductor.options),
+            model_dev_eq,
+        )),
+        Element {
+            voltagesource: Some(vsource),
+            ..
+        } => {
+            let (dc_value, params) = source_value_params(&vsource.values);
+            Some(two_terminal_hnode(
+                ElementKind::VoltageSource,
+                &vsource.id,
+                [&vsource.p, &vsource.n],
+                dc_value,
+                params,
+                None,
+            ))
+        }
+        Element {
+            currentsource: Some(isource),
+            ..
+        } => {
+            let (dc_value, params) = source_value_params(&isource.values);
+            Some(two_terminal_hnode(
+                ElementKind::CurrentSource,
+                &isource.id,
+                [&isource.p, &isource.n],
+                dc_value,
+                params,
+                None,
+            ))
+        }
+        Element {
+            diode: Some(diode), ..
+        } => {
+            let terminals = circuit_nodes(&[&diode.p, &diode.n]);
+            let device = dev_eq_map.get(&diode.model).cloned();
+            let equations =
+                ElementHNode::terminal_equations(ElementKind::Diode, &terminals, device.as_ref());
+            Some(ElementHNode {
+                name: diode.id.to_owned(),
+                kind: ElementKind::Diode,
+                terminals,
+                value: Some(diode.model.to_owned()),
+                params: BTreeMap::new(),
+                device,
+                equations,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// `LCircuit` of a netlist scope with its `.subckt` definitions as templates and its `.global`
+/// nodes.
+pub(super) fn netlist_scope_circuit(
+    netlist_scope: &NetlistScope,
+    dev_eq_map: &DeviceEquationMap,
+) -> LCircuit {
+    let mut circuit = LCircuit::from_elements(
+        netlist_scope
+            .elements
+            .iter()
+            .filter_map(|element| spice_element_hnode(element, dev_eq_map)),
+    );
+    netlist_scope.subcircuits.iter().for_each(|subcircuit| {
+        circuit.add_subcircuit(SubcircuitTemplate::new(
+            subcircuit.id.to_owned(),
+            circuit_nodes(&subcircuit.ports.port.iter().collect_vec()),
+            netlist_scope_circuit(&subcircuit.netlist_scope, dev_eq_map),
+        ));
+    });
+    netlist_scope
+        .statements
+        .iter()
+        .filter_map(|statement| statement.globals.as_ref())
+        .flat_map(|global| circuit_nodes(&global.nodes.iter().collect_vec()))
+        .for_each(|node| {
+            circuit.add_global(node);
+        });
+    circuit
+}
+
 pub(super) type SPICENodeMap = HashMap<SPICENode, LCircuitNodeID>;
 
 #[derive(Debug, Clone, Default)]
@@ -201,7 +418,7 @@ impl FromStr for SPICENodeSet {
     type Err = ParseError;
 
     fn from_str(spice_netlist_str: &str) -> Result<Self, Self::Err> {
-        match SPICENetlist::parse(spice_netlist_str) {
+        match SPICENetlist::parse_deck(spice_netlist_str) {
             Ok(ast) => {
                 let mut node_map = HashSet::<SPICENode>::default();
                 Self::collect_netlist_nodes(&ast)
@@ -241,6 +458,8 @@ mod tests {
     use std::fs;
     use std::path::PathBuf;
 
+    use peginator::PegParser;
+
     use super::*;
 
     #[test]
@@ -338,15 +557,18 @@ mod tests {
         let device_eq_map = DeviceEquationMap::from([("m".to_owned(), dev_eq)]);
 
         let m1_dev_eq = get_element_circuit_equation(m1_instance, &device_eq_map);
-        let eq_expected_str = indoc::indoc! {"
+        let eq_expected = indoc::indoc! {r#"
             e = 2.718281828459045;
             Is = 1e-12;
             eta = 1.5;
-            Vt = T/11586;
-            I = Is*(e^((1-15)/(eta*Vt)) - 1)
-        "};
-        let eq_expected = CircuitEquation::from_str(eq_expected_str).unwrap();
-        assert_eq!(eq_expected, m1_dev_eq);
+            Vt = T / 11586.0;
+            I = Is * (e ^ ((v("1") - v("15")) / (eta * Vt)) - 1.0)"#};
+        assert_eq!(eq_expected, m1_dev_eq.to_string());
+        assert_eq!(m1_dev_eq, CircuitEquation::from_str(eq_expected).unwrap());
+        assert!(
+            m1_dev_eq.expression().free_variables().contains("15"),
+            "Numeric node names should stay node voltages."
+        );
     }
 
     #[test]
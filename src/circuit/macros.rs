@@ -12,46 +12,6 @@ macro_rules! circuit {
     }};
 }
 
-struct Netlist {
-    components: Vec<Component>,
-}
-
-impl Netlist {
-    fn new() -> Self {
-        Netlist {
-            components: Vec::new(),
-        }
-    }
-
-    fn add_component(&mut self, name: &str, parameters: Vec<(String, String)>) {
-        self.components.push(Component::new(name, parameters));
-    }
-}
-
-impl std::fmt::Debug for Netlist {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for component in &self.components {
-            writeln!(f, "{:?}", component)?;
-        }
-        Ok(())
-    }
-}
-
-#[derive(Debug)]
-struct Component {
-    name: String,
-    parameters: Vec<(String, String)>,
-}
-
-impl Component {
-    fn new(name: &str, parameters: Vec<(String, String)>) -> Self {
-        Component {
-            name: name.to_string(),
-            parameters,
-        }
-    }
-}
-
 #[cfg(test)]
 mod tests {
     // use super::*;
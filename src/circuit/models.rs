@@ -0,0 +1,483 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use derive_getters::Getters;
+
+use super::equations::{DeviceEquation, DeviceEquationMap, ModelName};
+use super::spice::{ModelStatement, NetlistScope, SPICENetlist};
+use super::values::parse_spice_value;
+
+/// Permittivity of silicon dioxide, in F/m.
+const EPS_OX: f64 = 3.9 * 8.854187817e-12;
+/// Permittivity of silicon, in F/m.
+const EPS_SI: f64 = 11.7 * 8.854187817e-12;
+/// Elementary charge, in C.
+const CHARGE: f64 = 1.602176634e-19;
+/// Intrinsic carrier concentration of silicon at 300K, in cm^-3.
+const NI: f64 = 1.45e10;
+/// Thermal voltage at 300.15K, in V.
+const VT_NOMINAL: f64 = 8.617333262e-5 * 300.15;
+
+/// Drain current of a level 1(Shichman-Hodges) MOSFET, with `polarity`, `vton`, `kp`, `gamma`,
+/// `phi`, `lambda` and `ld` bound by the card and `w`, `l` by the element.
+///
+/// Terminal voltages are normalized to an NMOS in forward mode, drain and source swap when
+/// `vds` is reversed.
+const MOS_LEVEL1: &str = indoc::indoc! {"
+    fwd = polarity * vds >= 0.0;
+    ngs = if(fwd, polarity * vgs, polarity * vgd);
+    nds = if(fwd, polarity * vds, -polarity * vds);
+    nbs = if(fwd, -polarity * vsb, -polarity * vdb);
+    vth = vton + gamma * (math::sqrt(max(phi - nbs, 0.0)) - math::sqrt(phi));
+    vov = max(ngs - vth, 0.0);
+    deff = min(nds, vov);
+    ids = kp * w / (l - 2.0 * ld) * (vov - deff / 2.0) * deff * (1.0 + lambda * nds);
+    I = if(fwd, polarity * ids, -polarity * ids)
+"};
+
+/// Drain current of a level 3(semi-empirical) MOSFET, the level 1 normalization with
+/// drain-induced barrier lowering(`eta`), narrow width(`delta`), mobility degradation(`theta`),
+/// velocity saturation(`vmax`) and channel length modulation(`kappa`).
+const MOS_LEVEL3: &str = indoc::indoc! {"
+    fwd = polarity * vds >= 0.0;
+    ngs = if(fwd, polarity * vgs, polarity * vgd);
+    nds = if(fwd, polarity * vds, -polarity * vds);
+    nbs = if(fwd, -polarity * vsb, -polarity * vdb);
+    leff = l - 2.0 * ld;
+    sigma = eta * 8.15e-22 / (cox * leff ^ 3.0);
+    fnarrow = delta * 3.141592653589793 * epssi / (2.0 * cox * w);
+    sqrtphibs = math::sqrt(max(phi - nbs, 0.0));
+    vth = vton + gamma * (sqrtphibs - math::sqrt(phi)) - sigma * nds - fnarrow * nbs;
+    vov = max(ngs - vth, 0.0);
+    fb = gamma / (4.0 * max(sqrtphibs, 0.001)) + fnarrow;
+    mobility = 1.0 / (1.0 + theta * vov);
+    ueff = kp / cox * mobility;
+    va = vov / (1.0 + fb);
+    vb = if(vmax > 0.0, vmax * leff / ueff, va);
+    dsat = if(vmax > 0.0, va + vb - math::sqrt(va * va + vb * vb), va);
+    deff = min(nds, dsat);
+    fvmax = if(vmax > 0.0, 1.0 + ueff * deff / (vmax * leff), 1.0);
+    ids0 = kp * mobility * w / leff * (vov - (1.0 + fb) / 2.0 * deff) * deff / fvmax;
+    dl = if(nds > dsat, xd * math::sqrt(kappa * (nds - dsat)), 0.0);
+    ids = ids0 / (1.0 - min(dl / leff, 0.5));
+    I = if(fwd, polarity * ids, -polarity * ids)
+"};
+
+/// Junction current with reverse breakdown, `bv` of 0 disables breakdown.
+const DIODE: &str = indoc::indoc! {"
+    vt = 8.617333262e-5 * T;
+    forward = is * (math::exp(min(vd / (n * vt), 80.0)) - 1.0);
+    breakdown = if(bv > 0.0, ibv * math::exp(min(-(bv + vd) / (n * vt), 80.0)), 0.0);
+    I = forward - breakdown
+"};
+
+/// Device kinds of a `.model` card with an `LCircuit` representation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModelKind {
+    Nmos,
+    Pmos,
+    Diode,
+    Resistor,
+    /// Bipolar, switch, transmission line and other cards without a `DeviceEquation`.
+    Other(String),
+}
+
+impl From<&str> for ModelKind {
+    fn from(kind: &str) -> Self {
+        match kind.to_ascii_lowercase().as_str() {
+            "nmos" => Self::Nmos,
+            "pmos" => Self::Pmos,
+            "d" => Self::Diode,
+            "r" | "res" => Self::Resistor,
+            other => Self::Other(other.to_owned()),
+        }
+    }
+}
+
+impl ModelKind {
+    /// MOSFET polarity guessed from a model or subcircuit name, e.g. `sky130_fd_pr__nfet_01v8`.
+    pub fn transistor(model_name: &str) -> Option<Self> {
+        let model_name = model_name.to_ascii_lowercase();
+        if model_name.contains("nfet") || model_name.contains("nmos") {
+            Some(Self::Nmos)
+        } else if model_name.contains("pfet") || model_name.contains("pmos") {
+            Some(Self::Pmos)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModelError {
+    /// Card parameter which is not a SPICE number.
+    InvalidParam {
+        model: ModelName,
+        param: String,
+        value: String,
+    },
+    MissingParam {
+        model: ModelName,
+        param: String,
+    },
+    /// MOSFET level other than 1 and 3.
+    UnsupportedLevel {
+        model: ModelName,
+        level: usize,
+    },
+    UnsupportedKind {
+        model: ModelName,
+        kind: String,
+    },
+}
+
+impl fmt::Display for ModelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidParam {
+                model,
+                param,
+                value,
+            } => write!(f, "Invalid value {} of {} in model {}", value, param, model),
+            Self::MissingParam { model, param } => {
+                write!(f, "Model {} is missing parameter {}", model, param)
+            }
+            Self::UnsupportedLevel { model, level } => {
+                write!(f, "Unsupported level {} of model {}", level, model)
+            }
+            Self::UnsupportedKind { model, kind } => {
+                write!(f, "Unsupported kind {} of model {}", kind, model)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ModelError {}
+
+/// `.model` card with lowercased parameter names and numeric values, parameters without a
+/// value(flags like `steplimit`) are dropped.
+#[derive(Debug, Clone, PartialEq, Getters)]
+pub struct ModelCard {
+    name: ModelName,
+    kind: ModelKind,
+    params: BTreeMap<String, f64>,
+}
+
+impl TryFrom<&ModelStatement> for ModelCard {
+    type Error = ModelError;
+
+    fn try_from(model: &ModelStatement) -> Result<Self, Self::Error> {
+        let params = model
+            .params
+            .iter()
+            .filter_map(|param| param.value.as_ref().map(|value| (&param.id, value)))
+            .map(|(id, value)| {
+                parse_spice_value(value)
+                    .map(|number| (id.to_ascii_lowercase(), number))
+                    .ok_or_else(|| ModelError::InvalidParam {
+                        model: model.name.to_owned(),
+                        param: id.to_owned(),
+                        value: value.to_owned(),
+                    })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            name: model.name.to_owned(),
+            kind: ModelKind::from(model.kind.as_str()),
+            params,
+        })
+    }
+}
+
+impl ModelCard {
+    pub fn param(&self, name: &str) -> Option<f64> {
+        self.params.get(name).copied()
+    }
+
+    /// `DeviceEquation` of the card, its parameters bound ahead of the model equations with SPICE
+    /// defaults for the missing ones.
+    pub fn device_equation(&self) -> Result<DeviceEquation, ModelError> {
+        let (constants, equations) = match &self.kind {
+            ModelKind::Nmos | ModelKind::Pmos => self.mos_equation()?,
+            ModelKind::Diode => (
+                vec![
+                    ("is", self.param("is").unwrap_or(1e-14)),
+                    ("n", self.param("n").unwrap_or(1.0)),
+                    ("bv", self.param("bv").unwrap_or(0.0)),
+                    ("ibv", self.param("ibv").unwrap_or(1e-3)),
+                ],
+                DIODE.to_owned(),
+            ),
+            ModelKind::Resistor => self.resistor_equation()?,
+            ModelKind::Other(kind) => {
+                return Err(ModelError::UnsupportedKind {
+                    model: self.name.to_owned(),
+                    kind: kind.to_owned(),
+                })
+            }
+        };
+        let equation = constants
+            .iter()
+            .map(|(name, value)| format!("{} = {:?};\n", name, value))
+            .chain(std::iter::once(equations))
+            .collect::<String>();
+        Ok(DeviceEquation::from_str(&equation).expect("Invalid model DeviceEquation."))
+    }
+
+    /// Level 1 and level 3 parameters, `kp` derived from `uo` and `tox`, `gamma` and `phi` from
+    /// `nsub` when not given.
+    fn mos_equation(&self) -> Result<(Vec<(&'static str, f64)>, String), ModelError> {
+        let level = self.param("level").unwrap_or(1.0).round() as usize;
+        let polarity = match self.kind {
+            ModelKind::Pmos => -1.0,
+            _ => 1.0,
+        };
+        let vto = self
+            .param("vto")
+            .or_else(|| self.param("vt0"))
+            .unwrap_or(0.0);
+        let tox = self.param("tox");
+        let nsub = self.param("nsub").filter(|nsub| *nsub > 0.0);
+        let cox = EPS_OX / tox.unwrap_or(1e-7);
+        let uo = self.param("uo").unwrap_or(600.0);
+        let kp = self.param("kp").unwrap_or(match (level, tox) {
+            (1, None) => 2e-5,
+            _ => uo * 1e-4 * cox,
+        });
+        let phi = self.param("phi").unwrap_or(match nsub {
+            Some(nsub) => 2.0 * VT_NOMINAL * (nsub / NI).ln(),
+            None => 0.6,
+        });
+        let gamma = self.param("gamma").unwrap_or(match (nsub, tox) {
+            (Some(nsub), Some(_tox)) => (2.0 * CHARGE * EPS_SI * nsub * 1e6).sqrt() / cox,
+            _ => 0.0,
+        });
+        let mut constants = vec![
+            ("polarity", polarity),
+            ("vton", polarity * vto),
+            ("kp", kp),
+            ("gamma", gamma),
+            ("phi", phi),
+            ("ld", self.param("ld").unwrap_or(0.0)),
+        ];
+        match level {
+            1 => {
+                constants.push(("lambda", self.param("lambda").unwrap_or(0.0)));
+                Ok((constants, MOS_LEVEL1.to_owned()))
+            }
+            3 => {
+                let xd = nsub.map_or(0.0, |nsub| (2.0 * EPS_SI / (CHARGE * nsub * 1e6)).sqrt());
+                constants.extend([
+                    ("cox", cox),
+                    ("epssi", EPS_SI),
+                    ("eta", self.param("eta").unwrap_or(0.0)),
+                    ("delta", self.param("delta").unwrap_or(0.0)),
+                    ("theta", self.param("theta").unwrap_or(0.0)),
+                    ("vmax", self.param("vmax").unwrap_or(0.0)),
+                    ("kappa", self.param("kappa").unwrap_or(0.2)),
+                    ("xd", xd),
+                ]);
+                Ok((constants, MOS_LEVEL3.to_owned()))
+            }
+            level => Err(ModelError::UnsupportedLevel {
+                model: self.name.to_owned(),
+                level,
+            }),
+        }
+    }
+
+    /// Sheet resistance scaled by the element `w` and `l`, or the card resistance `r`.
+    fn resistor_equation(&self) -> Result<(Vec<(&'static str, f64)>, String), ModelError> {
+        match (
+            self.param("rsh"),
+            self.param("r").or_else(|| self.param("res")),
+        ) {
+            (Some(rsh), _) => Ok((
+                vec![
+                    ("rsh", rsh),
+                    ("narrow", self.param("narrow").unwrap_or(0.0)),
+                ],
+                "R = rsh * (l - narrow) / (w - narrow);\nI = vpn / R".to_owned(),
+            )),
+            (None, Some(r)) => Ok((vec![("R", r)], "I = vpn / R".to_owned())),
+            (None, None) => Err(ModelError::MissingParam {
+                model: self.name.to_owned(),
+                param: "rsh".to_owned(),
+            }),
+        }
+    }
+}
+
+fn scope_model_statements(netlist_scope: &NetlistScope) -> Vec<&ModelStatement> {
+    netlist_scope
+        .statements
+        .iter()
+        .filter_map(|statement| statement.model.as_ref())
+        .chain(
+            netlist_scope
+                .subcircuits
+                .iter()
+                .flat_map(|subcircuit| scope_model_statements(&subcircuit.netlist_scope)),
+        )
+        .collect()
+}
+
+/// `DeviceEquation`s of the MOSFET, diode and resistor `.model` cards of every scope, keyed by
+/// model name. Cards of other kinds are skipped.
+pub fn model_equations(netlist: &SPICENetlist) -> Result<DeviceEquationMap, ModelError> {
+    scope_model_statements(&netlist.netlist_scope)
+        .into_iter()
+        .filter(|model| !matches!(ModelKind::from(model.kind.as_str()), ModelKind::Other(_)))
+        .map(|model| {
+            let card = ModelCard::try_from(model)?;
+            Ok((card.name.to_owned(), card.device_equation()?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::circuit::analysis::dc::{dc_operating_point, DcConfig};
+    use crate::circuit::graph::LCircuit;
+
+    const LEVEL1_CARDS: &str = indoc::indoc! {"
+        .MODEL N10L1 NMOS
+        + LEVEL=1 TPG=1
+        + KP=2.33082E-05
+        + LAMBDA=0.013333 VT0=0.69486 GAMMA=0.60309 PHI=1
+        + TOX=1.9800000E-08  XJ=0.2U            LD=0.1U           NSUB=4.9999999E+16
+        .MODEL P12L1 PMOS
+        + LEVEL=1 TPG=-1
+        + KP=7.69968E-06
+        + LAMBDA=0.018966 VT0=-0.60865 GAMMA=0.89213 PHI=1
+        + TOX=1.9800000E-08  XJ=0.4U            LD=0.28U           NSUB=4.9999999E+17
+    "};
+
+    const LEVEL3_CARDS: &str = indoc::indoc! {"
+        .MODEL  N10L3  NMOS
+        + LEVEL=3            TPG=+1
+        + GAMMA=1.140501     THETA=0.8109787    KAPPA=0.1579183    ETA=5.0622310E-02
+        + DELTA=0.000000E+00 UO=812.5126        VMAX=1186662.      VTO=0.8
+        + TOX=1.9800000E-08  XJ=0.2U            LD=0.1U            NSUB=4.9999999E+16
+        .MODEL  P12L3  PMOS
+        + LEVEL=3            TPG=-1
+        + GAMMA=1.211640     THETA=0.1184638    KAPPA=0.2162577    ETA=2.7580135E-02
+        + DELTA=0.000000E+00 UO=89.16160        VMAX=5.9000000E+07 VTO=-0.8
+        + TOX=1.9800000E-08  XJ=0.4U            LD=0.28U           NSUB=4.9999999E+17
+    "};
+
+    fn model_cards(deck: &str) -> Vec<ModelCard> {
+        let netlist = SPICENetlist::parse_deck(deck).unwrap();
+        scope_model_statements(&netlist.netlist_scope)
+            .into_iter()
+            .map(|model| ModelCard::try_from(model).unwrap())
+            .collect()
+    }
+
+    fn inverter_output(cards: &str, nmos: &str, pmos: &str, vin: f64) -> f64 {
+        let deck = format!(
+            "* inverter\nvdd vdd 0 5\nvin in 0 {}\nmp out in vdd vdd {} w=10u l=1.2u\nmn out in 0 \
+             0 {} w=5u l=1u\n{}.end\n",
+            vin, pmos, nmos, cards
+        );
+        let netlist = SPICENetlist::parse_deck(&deck).unwrap();
+        let circuit = LCircuit::try_from(&netlist).unwrap();
+        dc_operating_point(&circuit, &DcConfig::default())
+            .unwrap()
+            .voltage("out")
+            .unwrap()
+    }
+
+    #[test]
+    fn parse_model_cards() {
+        let cards = model_cards(&format!("* cards\n{}{}", LEVEL1_CARDS, LEVEL3_CARDS));
+        assert_eq!(
+            vec!["N10L1", "P12L1", "N10L3", "P12L3"],
+            cards
+                .iter()
+                .map(|card| card.name().as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(&ModelKind::Pmos, cards[1].kind());
+        assert_eq!(Some(-0.60865), cards[1].param("vt0"));
+        assert_eq!(Some(1.0), cards[2].param("tpg"));
+        assert!((cards[2].param("xj").unwrap() - 0.2e-6).abs() < 1e-15);
+        assert!(cards.iter().all(|card| card.device_equation().is_ok()));
+
+        let examples =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources/spice3f5_examples");
+        let mosmem = std::fs::read_to_string(examples.join("mosmem.cir")).unwrap();
+        let mosmem = model_equations(&SPICENetlist::parse_deck(&mosmem).unwrap()).unwrap();
+        assert!(mosmem.contains_key("mod"));
+
+        let mos6inv = std::fs::read_to_string(examples.join("mos6inv.cir")).unwrap();
+        assert_eq!(
+            Err(ModelError::UnsupportedLevel {
+                model: "N10L5".to_owned(),
+                level: 6,
+            }),
+            model_equations(&SPICENetlist::parse_deck(&mos6inv).unwrap())
+        );
+    }
+
+    #[test]
+    fn level1_common_source() {
+        let deck = indoc::indoc! {"
+            * common source stage
+            vdd vdd 0 5
+            vg g 0 2
+            rd vdd d 10k
+            m1 d g 0 0 nch w=10u l=1u
+            .model nch nmos(level=1 vto=1 kp=2e-5)
+            .end
+        "};
+        let circuit = LCircuit::try_from(&SPICENetlist::parse_deck(deck).unwrap()).unwrap();
+        let solution = dc_operating_point(&circuit, &DcConfig::default()).unwrap();
+        // Saturated, Id = kp/2 * w/l * (vgs - vto)^2 = 100uA.
+        assert!((solution.voltage("d").unwrap() - 4.0).abs() < 1e-6);
+        assert!((solution.current("m1").unwrap() - 1e-4).abs() < 1e-9);
+    }
+
+    #[test]
+    fn mos_inverters() {
+        [
+            (LEVEL1_CARDS, "N10L1", "P12L1"),
+            (LEVEL3_CARDS, "N10L3", "P12L3"),
+        ]
+        .iter()
+        .for_each(|(cards, nmos, pmos)| {
+            assert!(inverter_output(cards, nmos, pmos, 0.0) > 4.99);
+            assert!(inverter_output(cards, nmos, pmos, 5.0) < 0.01);
+            let switching = inverter_output(cards, nmos, pmos, 2.5);
+            assert!(switching > 0.0 && switching < 5.0);
+        });
+    }
+
+    #[test]
+    fn diode_and_resistor_models() {
+        let deck = indoc::indoc! {"
+            * diode with a poly resistor
+            vs in 0 5
+            r1 in a rpoly w=1u l=10u
+            d1 a 0 dmod1
+            .model rpoly r(rsh=100)
+            .model dmod1 d(n=2.25 is=1.6399e-4 bv=10)
+            .end
+        "};
+        let netlist = SPICENetlist::parse_deck(deck).unwrap();
+        let equations = model_equations(&netlist).unwrap();
+        assert!(equations.contains_key("dmod1") && equations.contains_key("rpoly"));
+        let circuit = LCircuit::try_from(&netlist).unwrap();
+        let config = DcConfig::default();
+        let solution = dc_operating_point(&circuit, &config).unwrap();
+        let va = solution.voltage("a").unwrap();
+        let vt = 8.617333262e-5 * config.temperature();
+        let diode_current = 1.6399e-4 * ((va / (2.25 * vt)).exp() - 1.0);
+        assert!(((5.0 - va) / 1e3 - diode_current).abs() < 1e-8);
+        assert!((solution.current("d1").unwrap() - diode_current).abs() < 1e-8);
+    }
+}
@@ -1,9 +1,16 @@
 use std::fmt;
 use std::str::FromStr;
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 pub struct CircuitNode(String);
 
+impl CircuitNode {
+    /// SPICE reference node, `0`(or `gnd`).
+    pub fn is_ground(&self) -> bool {
+        self.0 == "0" || self.0.eq_ignore_ascii_case("gnd")
+    }
+}
+
 impl FromStr for CircuitNode {
     type Err = String;
 
@@ -40,6 +47,13 @@ mod tests {
         assert_eq!("f_1", node.to_string(), "String should match.");
     }
 
+    #[test]
+    fn ground_node() {
+        assert!(CircuitNode::from_str("0").unwrap().is_ground());
+        assert!(CircuitNode::from_str("GND").unwrap().is_ground());
+        assert!(!CircuitNode::from_str("10").unwrap().is_ground());
+    }
+
     #[test]
     fn invalid_equation_whitespace() {
         let node = "f _1";
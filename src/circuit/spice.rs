@@ -1,3 +1,6 @@
+pub(crate) mod writer;
+
+use peginator::{ParseError, PegParser};
 use peginator_macro::peginate;
 
 // SPICE EBNF(Extended Backus Naur Form)
@@ -14,15 +17,19 @@ use peginator_macro::peginate;
 //                | voltage_controlled_current_source
 //                | current_controlled_current_source
 //                | diode
-//                | mos_transistor;
+//                | mos_transistor
+//                | bjt
+//                | transmission_line
+//                | instance;
 // resistor        = "R", identifier, node, node, value;
 // capacitor       = "C", identifier, node, node, value, [ "ic=", value ];
 // inductor        = "L", identifier, node, node, value, [ "ic=", float ];
 // mutual_inductor = "K", identifier, identifier, identifier, ( value | "k=", value );
 // voltage_controlled_switch
 //                = "S", identifier, node, node, node, node, model_id;
-// voltage_source  = "v", identifier, node, node, { type_value };
-// current_source  = "i", identifier, node, node, { type_value };
+// voltage_source  = "v", identifier, node, node, { source_value };
+// current_source  = "i", identifier, node, node, { source_value };
+// source_value    = "dc" | value | "ac", [ value, [ value ] ] | function, "(", { value }, ")";
 // voltage_controlled_voltage_source
 //                = "E", identifier, node, node, node, node, value;
 // voltage_controlled_current_source
@@ -31,9 +38,13 @@ use peginator_macro::peginate;
 //                = "F", identifier, node, node, identifier, value;
 // diode           = "D", identifier, node, node, model_id, { diode_param };
 // mos_transistor  = "M", identifier, node, node, node, node, model_id, "w=", float, "l=", float;
+// bjt             = "Q", identifier, node, node, node, [ node ], model_id, [ "off" ];
+// transmission_line
+//                = ( "O" | "T" ), identifier, node, node, node, node, [ model_id ], { param };
+// instance        = "X", identifier, { node }, subcircuit_id, { param };
 // identifier      = letter, { letter | digit };
-// node            = letter, { letter | digit };
-// value           = float;
+// node            = ( letter | digit | "_" ), { letter | digit | "_" | "." | "#" };
+// value           = float | "{", expression, "}";
 // type_value      = "type=", type_identifier, type_identifier, "=", float;
 // type_identifier = "vdc" | "vac" | "idc" | "iac" | ... ;
 // diode_param     = ( "AREA=", float | "T=", float | "IC=", float | "OFF=", boolean );
@@ -42,13 +53,17 @@ use peginator_macro::peginate;
 // digit           = "0" | "1" | "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9";
 // float           = digit, { digit }, [ ".", { digit } ];
 // boolean         = "true" | "false";
+//
+// Continuation lines(`+`) and the title line are resolved by `SPICENetlist::parse_deck` before
+// parsing.
 
 peginate!(
     "
 @export
-SPICENetlist = netlist_scope:NetlistScope;
+SPICENetlist = netlist_scope:NetlistScope $;
 
-SubcircuitScope = i'.subckt' id:Identifier ports:SubcircuitPorts netlist_scope:NetlistScope Ends;
+SubcircuitScope = i'.subckt' id:Identifier ports:SubcircuitPorts netlist_scope:NetlistScope \
+     ends:Ends;
 
 @no_skip_ws
 SubcircuitPorts = { port:Node NWhitespace } EOL;
@@ -56,15 +71,19 @@ SubcircuitPorts = { port:Node NWhitespace } EOL;
 @no_skip_ws
 NWhitespace = { '\t' | '\x0C' | ' ' };
 
-NetlistScope = { elements:Element | statements:Statement | comments:Comment  | \
-     subcircuits:SubcircuitScope};
+@no_skip_ws
+Separator = { '\t' | '\x0C' | ' ' }+;
+
+NetlistScope = { subcircuits:SubcircuitScope | elements:Element | statements:Statement | \
+     comments:Comment };
 
 @no_skip_ws
 SubcircuitInnerScope = { elements:Element | statements:Statement | comments:Comment };
 
 @no_skip_ws
-Comment = '*' {!'\n' char} EOL;
+Comment = '*' text:CommentLine EOL;
 
+@string
 @no_skip_ws
 CommentLine = {!'\n' char};
 
@@ -82,132 +101,228 @@ Element = ( subcircuit:Instance
           | vcurrentsource:VCurrentSource
           | ccurrentsource:CCurrentSource
           | diode:Diode
-          | mostransistor:MosTransistor ) EOL;
+          | mostransistor:MosTransistor
+          | bjt:Bjt
+          | transmissionline:TransmissionLine ) EOL;
 
 @no_skip_ws
 Statement = ( model:ModelStatement
+          | option:OptionStatement
           | op:OpAnalysis
           | dc:DcAnalysis
           | tran:TransientAnalysis
           | ac:AcAnalysis
-          | option:OptionStatement
-          | option:PlotStatement
           | print:PrintStatement
-          | End ) EOL;
+          | plot:PlotStatement
+          | param:ParamStatement
+          | include:IncludeStatement
+          | lib:LibStatement
+          | endl:EndLibStatement
+          | globals:GlobalStatement
+          | end:End
+          | control:ControlStatement ) EOL;
 
-Instance = id:InstanceIdentifier source:Node drain:Node gate:Node body:Node model:Node { \
-     options:KeyValue };
+@no_skip_ws
+Instance = id:InstanceIdentifier { Separator !KeyValue nodes:Node } { Separator options:KeyValue };
 
 @string
-InstanceIdentifier = i'x' Node;
+@no_skip_ws
+InstanceIdentifier = i'x' [ '.' ] [ Node ];
 
-Resistor = id:ResistorIdentifier p:Node n:Node value:Value { options:KeyValue };
+Resistor = id:ResistorIdentifier p:Node n:Node ( value:Value | model:Node ) { options:KeyValue };
 
 @string
-ResistorIdentifier = i'r' Node;
+@no_skip_ws
+ResistorIdentifier = i'r' [ '.' ] [ Node ];
 
 Capacitor = id:CapacitorIdentifier p:Node n:Node value:Value { options:KeyValue };
 
 @string
-CapacitorIdentifier = i'c' Node;
+@no_skip_ws
+CapacitorIdentifier = i'c' [ '.' ] [ Node ];
 
 Inductor = id:InductorIdentifier p:Node n:Node value:Value { options:KeyValue };
 
 @string
-InductorIdentifier = i'l' Node;
+@no_skip_ws
+InductorIdentifier = i'l' [ '.' ] [ Node ];
 
-MutualInductor1 = id:MutualInductorIdentifier Identifier Identifier Value;
+MutualInductor1 = id:MutualInductorIdentifier l1:Identifier l2:Identifier value:Value;
 
-MutualInductor2 = id:MutualInductorIdentifier Identifier Identifier i'k=' Value;
+MutualInductor2 = id:MutualInductorIdentifier l1:Identifier l2:Identifier i'k=' value:Value;
 
 @string
-MutualInductorIdentifier = i'k' Node;
+@no_skip_ws
+MutualInductorIdentifier = i'k' [ '.' ] [ Node ];
 
-VSwitch = id:VSwitchIdentifier Node Node Node Node Identifier;
+VSwitch = id:VSwitchIdentifier p:Node n:Node cp:Node cn:Node model:Identifier;
 
 @string
-VSwitchIdentifier = i's' Node;
+@no_skip_ws
+VSwitchIdentifier = i's' [ '.' ] [ Node ];
 
-VoltageSource = id:VoltageSourceIdentifier p:Node n:Node { type:VoltageSourceType } { \
-     values:SourceValues }+;
+@no_skip_ws
+VoltageSource = id:VoltageSourceIdentifier Separator p:Node Separator n:Node { Separator \
+     values:SourceValues };
 
 @string
-VoltageSourceIdentifier = i'v' Node;
+@no_skip_ws
+VoltageSourceIdentifier = i'v' [ '.' ] [ Node ];
 
-VoltageSourceType = 'dc' | 'ac';
+@no_skip_ws
+CurrentSource = id:CurrentSourceIdentifier Separator p:Node Separator n:Node { Separator \
+     values:SourceValues };
+
+@string
+@no_skip_ws
+CurrentSourceIdentifier = i'i' [ '.' ] [ Node ];
 
-CurrentSource = id:CurrentSourceIdentifier Node Node CurrentType;
+VVoltageSource = id:VVoltageSourceIdentifier p:Node n:Node cp:Node cn:Node value:Value;
 
 @string
-CurrentSourceIdentifier = i'i' Node;
+@no_skip_ws
+VVoltageSourceIdentifier = i'e' [ '.' ] [ Node ];
 
-VVoltageSource = id:VVoltageSourceIdentifier Node Node Node Node Value;
+VCurrentSource = id:VCurrentSourceIdentifier p:Node n:Node cp:Node cn:Node value:Value;
 
 @string
-VVoltageSourceIdentifier = i'e' Node;
+@no_skip_ws
+VCurrentSourceIdentifier = i'g' [ '.' ] [ Node ];
 
-VCurrentSource = id:VCurrentSourceIdentifier Node Node Node Node Value;
+CCurrentSource = id:CCurrentSourceIdentifier p:Node n:Node control:Identifier value:Value;
 
 @string
-VCurrentSourceIdentifier = i'g' Node;
+@no_skip_ws
+CCurrentSourceIdentifier = i'f' [ '.' ] [ Node ];
 
-CCurrentSource = id:CCurrentSourceIdentifier Node Node Identifier Value;
+Diode = id:DiodeIdentifier p:Node n:Node model:Node { options:KeyValue };
 
 @string
-CCurrentSourceIdentifier = i'f' Node;
+@no_skip_ws
+DiodeIdentifier = i'd' [ '.' ] [ Node ];
 
-Diode = id:DiodeIdentifier p:Node n:Node model:Identifier { DiodeParams };
+MosTransistor = id:MosTransistorIdentifier source:Node drain:Node gate:Node ( body:Node model:Node \
+     !'=' | model:Node ) { options:KeyValue };
 
 @string
-DiodeIdentifier = i'd' Node;
+@no_skip_ws
+MosTransistorIdentifier = i'm' [ '.' ] [ Node ];
+
+@no_skip_ws
+Bjt = id:BjtIdentifier { Separator !Off !KeyValue nodes:Node } { Separator ( options:KeyValue | \
+     off:Off ) };
 
-MosTransistor = id:MosTransistorIdentifier source:Node drain:Node gate:Node body:Node model:Node { \
+@string
+@no_skip_ws
+BjtIdentifier = i'q' [ '.' ] [ Node ];
+
+@no_skip_ws
+TransmissionLine = id:TransmissionLineIdentifier { Separator !KeyValue nodes:Node } { Separator \
      options:KeyValue };
 
 @string
-MosTransistorIdentifier = i'm' Node;
+@no_skip_ws
+TransmissionLineIdentifier = ( i'o' | i't' ) [ '.' ] [ Node ];
+
+@no_skip_ws
+ModelStatement = i'.model' Separator name:Node Separator kind:Node [ Separator ] [ '(' ] { [ \
+     Separator ] !')' params:ModelParam } [ Separator ] [ ')' ];
+
+@no_skip_ws
+ModelParam = id:Node [ [ Separator ] '=' [ Separator ] value:Operand | Separator !Letter \
+     value:Operand ];
+
+@no_skip_ws
+OpAnalysis = i'.op' { Separator ( params:ParamValue | options:KeyValue ) };
+
+@no_skip_ws
+DcAnalysis = i'.dc' Separator source:Node Separator start:Value Separator stop:Value Separator \
+     step:Value [ Separator source2:Node Separator start2:Value Separator stop2:Value Separator \
+     step2:Value ];
+
+TransientAnalysis = i'.tran' { timesteps:Value }+ [ uic:Uic ] { params:ParamValue | \
+     options:KeyValue };
+
+@string
+Uic = i'uic';
+
+@no_skip_ws
+AcAnalysis = i'.ac' Separator sweep:Sweep Separator points:Value Separator start:Value Separator \
+     stop:Value;
+
+@string
+@no_skip_ws
+Sweep = i'dec' | i'oct' | i'lin';
+
+@no_skip_ws
+OptionStatement = ( i'.options' | i'.option' | i'.opt' ) { Separator ( params:ParamValue | \
+     options:KeyValue | flags:Node ) };
+
+@no_skip_ws
+PrintStatement = i'.print' Separator kind:Node { Separator args:Argument };
+
+@no_skip_ws
+PlotStatement = i'.plot' Separator kind:Node { Separator args:Argument };
 
-ModelStatement = i'.model' Identifier Identifier ModelParams;
+@no_skip_ws
+ParamStatement = ( i'.params' | i'.param' ) { Separator params:KeyValue };
 
-OpAnalysis = i'.op' { params:ParamValue | options:KeyValue }+;
+@no_skip_ws
+IncludeStatement = ( i'.include' | i'.inc' ) Separator [ '\"' ] path:FilePath [ '\"' ];
 
-DcAnalysis = i'.dc' 'src=' Identifier Value Value Value 'type=' ( 'lin' | 'log' );
+@no_skip_ws
+LibStatement = i'.lib' Separator [ '\"' ] path:FilePath [ '\"' ] [ Separator section:Node ];
 
-TransientAnalysis = i'.tran' { timesteps:Value }+ { params:ParamValue | options:KeyValue };
+@no_skip_ws
+EndLibStatement = i'.endl' [ Separator section:Node ];
 
-AcAnalysis = i'.ac' ( 'lin' | 'log' ) Value Value Value;
+@no_skip_ws
+GlobalStatement = i'.global' { Separator nodes:Node };
 
-OptionStatement = i'.options' id:Identifier { params:ParamValue | options:KeyValue }+;
+@no_skip_ws
+ControlStatement = '.' !( i'ends' | i'subckt' ) command:Identifier { Separator args:Argument };
 
-PrintStatement = i'.print' id:Identifier { params:ParamValue | options:KeyValue }+;
+SourceValues = params:ParamValue | ac:AcValue | distortion:DistortionValue | dc:DcKeyword | \
+     options:KeyValue | value:Value;
 
-PlotStatement = i'.plot' id:Identifier { params:ParamValue | options:KeyValue }+;
+@string
+@no_skip_ws
+DcKeyword = i'dc';
 
-VoltageType = 'type=' ( 'vdc' 'vdc=' Value | 'vac' 'vac=' Value );
+AcValue = i'ac' [ magnitude:Value [ phase:Value ] ];
 
-CurrentType = 'type=' ( 'idc' 'idc=' Value | 'iac' 'iac=' Value );
+DistortionValue = kind:DistortionKind [ magnitude:Value [ phase:Value ] ];
 
-DiodeParams = { i'AREA=' Value } { i'T=' Value } { i'IC=' Value } { i'OFF=' Boolean };
+@string
+@no_skip_ws
+DistortionKind = i'distof1' | i'distof2';
 
-MosParams = i'w=' Value i'l=' Value;
+Argument = params:ParamValue | options:KeyValue | '\"' quoted:QuotedText '\"' | value:Operand;
 
-ModelParams = 'TYPE=' ( 'n' | 'p' ) { ModelParam };
+KeyValue = id:Node '=' value:Operand;
 
-ModelParam = 'TNOM=' Value | 'COX=' Value | 'GAMMA=' Value | 'NSUB=' Value | 'PHI=' Value | 'VTO=' \
-     Value | 'KP=' Value | 'TOX=' Value | 'VFB=' Value | 'U0=' Value | 'TCV=' Value | 'BEX=' \
-     Value;
+ParamValue = id:Node '(' { value:Operand [ ',' ] }+ ')';
 
-SourceType = Identifier;
+@string
+@no_skip_ws
+Off = i'off';
 
-SourceValues = params:ParamValue | options:KeyValue | value:Value;
+@string
+@no_skip_ws
+QuotedText = { !'\"' !'\n' char };
 
-KeyValue = id:Node '=' value:Value;
+@string
+@no_skip_ws
+FilePath = { !'\"' !'\t' !' ' !'\r' !'\n' char }+;
 
-ParamValue = id:Node '(' { value:Value }+ ')';
+@string
+@no_skip_ws
+Operand = Value | Node;
 
 @string
 @no_skip_ws
-Node = { 'a'..'z' | 'A'..'Z' | '_' | '0'..'9' }+ { '#' };
+Node = { 'a'..'z' | 'A'..'Z' | '_' | '0'..'9' }+ { 'a'..'z' | 'A'..'Z' | '_' | '0'..'9' | '.' | \
+     '#' };
 
 @string
 @no_skip_ws
@@ -215,7 +330,8 @@ Identifier = Letter {'a'..'z' | 'A'..'Z' | '_' | '0'..'9'}+;
 
 @string
 @no_skip_ws
-Value = { Digit | '+' | '-' }+ { Digit | '.' | 'e' | 'E' | '-' | '+' | Letter };
+Value = '{' { !'}' !'\n' char } '}' | { Digit | '+' | '-' }+ { Digit | '.' | 'e' | 'E' | '-' | '+' \
+     | Letter } | '.' Digit { Digit | '.' | 'e' | 'E' | '-' | '+' | Letter };
 
 @string
 @no_skip_ws
@@ -237,19 +353,77 @@ Boolean = 'true' | 'false';
 @no_skip_ws
 End = i'.end';
 
-@string
 @no_skip_ws
-Ends = i'.ends';
+Ends = i'.ends' [ Separator name:Node ];
 
 @no_skip_ws
-EOL = '\n' | ( '\r' '\n' );
+EOL = { '\t' | '\x0C' | ' ' } ( '\n' | ( '\r' '\n' ) );
 "
 );
 
+impl SPICENetlist {
+    /// Parses a SPICE deck, or a library/cell file included by one.
+    ///
+    /// A first line that is neither a comment nor a control line is the deck title and is kept as
+    /// a comment. Continuation lines(`+`) are joined onto the line they continue, skipping
+    /// comments in between, and trailing whitespace is dropped.
+    pub fn parse_deck(deck: &str) -> Result<Self, ParseError> {
+        Self::parse(&logical_lines(deck))
+    }
+}
+
+fn logical_lines(deck: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    deck.lines().enumerate().for_each(|(idx, line)| {
+        let line = line.trim_end();
+        let statement = line.trim_start();
+        if idx == 0 && !statement.is_empty() && !statement.starts_with(['*', '.']) {
+            lines.push(format!("* {}", statement));
+        } else if let Some(continuation) = statement.strip_prefix('+') {
+            match lines.iter_mut().rev().find(|previous| {
+                let previous = previous.trim_start();
+                !previous.is_empty() && !previous.starts_with('*')
+            }) {
+                Some(previous) => {
+                    previous.push(' ');
+                    previous.push_str(continuation.trim_start());
+                }
+                None => lines.push(line.to_owned()),
+            }
+        } else {
+            lines.push(line.to_owned());
+        }
+    });
+    let mut logical_lines = lines.join("\n");
+    logical_lines.push('\n');
+    logical_lines
+}
+
+impl MosTransistor {
+    /// Body node, three terminal devices(CDL) tie the body to their third terminal.
+    pub fn body_node(&self) -> &Node {
+        self.body.as_ref().unwrap_or(&self.gate)
+    }
+}
+
+impl Instance {
+    /// Nodes connected to the subcircuit, every positional token but the last.
+    pub fn ports(&self) -> &[Node] {
+        self.nodes
+            .split_last()
+            .map_or(&[], |(_subcircuit, ports)| ports)
+    }
+
+    /// Subcircuit or device model name, the last positional token.
+    pub fn model(&self) -> &str {
+        self.nodes.last().map_or("", String::as_str)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::fs;
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
 
     use peginator::PegParser;
 
@@ -344,9 +518,10 @@ mod tests {
             "There should be 12 Instances in Subcircuit."
         );
         let x0_instance = &subcircuit_scope.elements[0].clone().subcircuit.unwrap();
-        assert_eq!("VGND", x0_instance.source, "Source name for X0 is VGND");
+        assert_eq!("VGND", x0_instance.ports()[0], "Source name for X0 is VGND");
         assert_eq!(
-            "sky130_fd_pr__nfet_01v8", x0_instance.model,
+            "sky130_fd_pr__nfet_01v8",
+            x0_instance.model(),
             "Model name for X0 is sky130_fd_pr__nfet_01v8"
         );
         assert_eq!(
@@ -415,9 +590,10 @@ mod tests {
             "There should be 12 Instances in Subcircuit."
         );
         let x0_instance = &subcircuit_scope.elements[0].clone().subcircuit.unwrap();
-        assert_eq!("VGND", x0_instance.source, "Source name for X0 is VGND");
+        assert_eq!("VGND", x0_instance.ports()[0], "Source name for X0 is VGND");
         assert_eq!(
-            "sky130_fd_pr__nfet_01v8", x0_instance.model,
+            "sky130_fd_pr__nfet_01v8",
+            x0_instance.model(),
             "Model name for X0 is sky130_fd_pr__nfet_01v8"
         );
         assert_eq!(
@@ -429,4 +605,108 @@ mod tests {
             "First Parameter value for X0 is 740000u"
         );
     }
+
+    fn dir_deck_paths(dir: &Path, extensions: &[&str]) -> Vec<PathBuf> {
+        let mut deck_paths = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .flat_map(|path| {
+                if path.is_dir() {
+                    dir_deck_paths(&path, extensions)
+                } else if path
+                    .extension()
+                    .and_then(|extension| extension.to_str())
+                    .is_some_and(|extension| extensions.contains(&extension))
+                {
+                    vec![path]
+                } else {
+                    vec![]
+                }
+            })
+            .collect::<Vec<_>>();
+        deck_paths.sort();
+        deck_paths
+    }
+
+    /// `spice3f5_examples` decks and models, and every sky130 cell netlist.
+    pub(super) fn deck_paths() -> Vec<PathBuf> {
+        let resources = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources");
+        let spice3f5_decks = dir_deck_paths(&resources.join("spice3f5_examples"), &["cir", "mod"]);
+        let sky130_decks = dir_deck_paths(
+            &resources.join("libraries_no_liberty/sky130_fd_sc_ls/latest/cells"),
+            &["spice", "cdl"],
+        );
+        assert!(!spice3f5_decks.is_empty() && !sky130_decks.is_empty());
+        spice3f5_decks.into_iter().chain(sky130_decks).collect()
+    }
+
+    #[test]
+    fn spice_parse_all_decks() {
+        deck_paths().iter().for_each(|deck_path| {
+            let deck = fs::read_to_string(deck_path).unwrap();
+            if let Err(error) = SPICENetlist::parse_deck(&deck) {
+                panic!("Failed to parse {}: {}", deck_path.display(), error);
+            }
+        });
+    }
+
+    #[test]
+    fn spice_parse_deck_continuations() {
+        let deck = indoc::indoc! {"
+            title line
+            .subckt cell A B
+            + Y VDD
+            * comment between continuation lines
+            + VSS
+            X0 Y A VSS VSS sky130_fd_pr__nfet_01v8
+            + w=650000u l=150000u
+            .ends cell
+            .end
+        "};
+        let ast = SPICENetlist::parse_deck(deck).unwrap();
+        let subcircuit = &ast.netlist_scope.subcircuits[0];
+        assert_eq!(
+            vec!["A", "B", "Y", "VDD", "VSS"],
+            subcircuit.ports.port,
+            "Continued subcircuit ports."
+        );
+        let x0_instance = subcircuit.netlist_scope.elements[0]
+            .subcircuit
+            .clone()
+            .unwrap();
+        assert_eq!(vec!["Y", "A", "VSS", "VSS"], x0_instance.ports());
+        assert_eq!("sky130_fd_pr__nfet_01v8", x0_instance.model());
+        assert_eq!(2, x0_instance.options.len(), "Continued instance options.");
+    }
+
+    #[test]
+    fn spice_parse_mos_optional_body() {
+        let deck = indoc::indoc! {"
+            * mos terminals
+            MI1 Y A VGND VNB nshort m=1 w=0.74
+            MI2 VPWR VGND VPB pshort m=1 w=1.255
+            .end
+        "};
+        let ast = SPICENetlist::parse_deck(deck).unwrap();
+        let [mi1, mi2] = [0, 1].map(|idx| {
+            ast.netlist_scope.elements[idx]
+                .mostransistor
+                .clone()
+                .unwrap()
+        });
+        assert_eq!(
+            ("VNB", "nshort"),
+            (mi1.body_node().as_str(), mi1.model.as_str())
+        );
+        assert_eq!(
+            (None, "VPB", "pshort"),
+            (
+                mi2.body.as_deref(),
+                mi2.body_node().as_str(),
+                mi2.model.as_str()
+            ),
+            "A three terminal MOS ties its body to the third terminal."
+        );
+        assert_eq!(2, mi2.options.len());
+    }
 }
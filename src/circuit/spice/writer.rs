@@ -0,0 +1,750 @@
+use std::fmt::{self, Display, Formatter};
+
+use itertools::Itertools;
+use typed_builder::TypedBuilder;
+
+use super::{
+    AcAnalysis, AcValue, Argument, Bjt, CCurrentSource, Capacitor, Comment, ControlStatement,
+    CurrentSource, DcAnalysis, Diode, DistortionValue, Element, EndLibStatement, Ends,
+    GlobalStatement, IncludeStatement, Inductor, Instance, KeyValue, LibStatement, ModelParam,
+    ModelStatement, MosTransistor, MutualInductor1, MutualInductor2, NetlistScope, OpAnalysis,
+    OptionStatement, ParamStatement, ParamValue, PlotStatement, PrintStatement, Resistor,
+    SPICENetlist, SourceValues, Statement, SubcircuitScope, TransientAnalysis, TransmissionLine,
+    VCurrentSource, VSwitch, VVoltageSource, VoltageSource,
+};
+use crate::circuit::graph::nodes::{ElementHNode, ElementKind};
+use crate::circuit::graph::LCircuit;
+
+/// Independent source functions, written as `name(values)` rather than `name=value`.
+const SOURCE_FUNCTIONS: [&str; 6] = ["pulse", "sin", "exp", "pwl", "sffm", "am"];
+
+/// Writes every item preceded by a space, the separator of SPICE fields.
+fn write_fields<T: Display>(
+    f: &mut Formatter<'_>,
+    fields: impl IntoIterator<Item = T>,
+) -> fmt::Result {
+    fields
+        .into_iter()
+        .try_for_each(|field| write!(f, " {}", field))
+}
+
+/// Deck with one line per comment, subcircuit, element and statement of each scope, continuation
+/// lines are never written. The first comment is the title, an empty title line is written for
+/// netlists without comments.
+impl Display for SPICENetlist {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.netlist_scope.comments.is_empty() {
+            writeln!(f)?;
+        }
+        write!(f, "{}", self.netlist_scope)
+    }
+}
+
+impl Display for NetlistScope {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.comments
+            .iter()
+            .try_for_each(|comment| writeln!(f, "{}", comment))?;
+        self.subcircuits
+            .iter()
+            .try_for_each(|subcircuit| writeln!(f, "{}", subcircuit))?;
+        self.elements
+            .iter()
+            .try_for_each(|element| writeln!(f, "{}", element))?;
+        self.statements
+            .iter()
+            .try_for_each(|statement| writeln!(f, "{}", statement))
+    }
+}
+
+impl Display for SubcircuitScope {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, ".subckt {}", self.id)?;
+        write_fields(f, &self.ports.port)?;
+        writeln!(f)?;
+        write!(f, "{}{}", self.netlist_scope, self.ends)
+    }
+}
+
+impl Display for Ends {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, ".ends")?;
+        write_fields(f, &self.name)
+    }
+}
+
+impl Display for Comment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "*{}", self.text)
+    }
+}
+
+impl Display for Element {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self {
+                subcircuit: Some(instance),
+                ..
+            } => write!(f, "{}", instance),
+            Self {
+                resistor: Some(resistor),
+                ..
+            } => write!(f, "{}", resistor),
+            Self {
+                capacitor: Some(capacitor),
+                ..
+            } => write!(f, "{}", capacitor),
+            Self {
+                inductor: Some(inductor),
+                ..
+            } => write!(f, "{}", inductor),
+            Self {
+                mutualinductor1: Some(mutual_inductor),
+                ..
+            } => write!(f, "{}", mutual_inductor),
+            Self {
+                mutualinductor2: Some(mutual_inductor),
+                ..
+            } => write!(f, "{}", mutual_inductor),
+            Self {
+                vswitch: Some(vswitch),
+                ..
+            } => write!(f, "{}", vswitch),
+            Self {
+                voltagesource: Some(vsource),
+                ..
+            } => write!(f, "{}", vsource),
+            Self {
+                currentsource: Some(isource),
+                ..
+            } => write!(f, "{}", isource),
+            Self {
+                vvoltagesource: Some(vcvs),
+                ..
+            } => write!(f, "{}", vcvs),
+            Self {
+                vcurrentsource: Some(vccs),
+                ..
+            } => write!(f, "{}", vccs),
+            Self {
+                ccurrentsource: Some(cccs),
+                ..
+            } => write!(f, "{}", cccs),
+            Self {
+                diode: Some(diode), ..
+            } => write!(f, "{}", diode),
+            Self {
+                mostransistor: Some(mos),
+                ..
+            } => write!(f, "{}", mos),
+            Self { bjt: Some(bjt), .. } => write!(f, "{}", bjt),
+            Self {
+                transmissionline: Some(transmission_line),
+                ..
+            } => write!(f, "{}", transmission_line),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Display for Instance {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.id)?;
+        write_fields(f, &self.nodes)?;
+        write_fields(f, &self.options)
+    }
+}
+
+impl Display for Resistor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.id, self.p, self.n)?;
+        write_fields(f, self.value.iter().chain(&self.model))?;
+        write_fields(f, &self.options)
+    }
+}
+
+impl Display for Capacitor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {} {}", self.id, self.p, self.n, self.value)?;
+        write_fields(f, &self.options)
+    }
+}
+
+impl Display for Inductor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {} {}", self.id, self.p, self.n, self.value)?;
+        write_fields(f, &self.options)
+    }
+}
+
+impl Display for MutualInductor1 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {} {}", self.id, self.l1, self.l2, self.value)
+    }
+}
+
+impl Display for MutualInductor2 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {} k={}", self.id, self.l1, self.l2, self.value)
+    }
+}
+
+impl Display for VSwitch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {} {} {}",
+            self.id, self.p, self.n, self.cp, self.cn, self.model
+        )
+    }
+}
+
+impl Display for VoltageSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.id, self.p, self.n)?;
+        write_fields(f, &self.values)
+    }
+}
+
+impl Display for CurrentSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.id, self.p, self.n)?;
+        write_fields(f, &self.values)
+    }
+}
+
+impl Display for VVoltageSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {} {} {}",
+            self.id, self.p, self.n, self.cp, self.cn, self.value
+        )
+    }
+}
+
+impl Display for VCurrentSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {} {} {}",
+            self.id, self.p, self.n, self.cp, self.cn, self.value
+        )
+    }
+}
+
+impl Display for CCurrentSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {} {}",
+            self.id, self.p, self.n, self.control, self.value
+        )
+    }
+}
+
+impl Display for Diode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {} {}", self.id, self.p, self.n, self.model)?;
+        write_fields(f, &self.options)
+    }
+}
+
+impl Display for MosTransistor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}",
+            self.id, self.source, self.drain, self.gate
+        )?;
+        write_fields(f, &self.body)?;
+        write!(f, " {}", self.model)?;
+        write_fields(f, &self.options)
+    }
+}
+
+impl Display for Bjt {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.id)?;
+        write_fields(f, &self.nodes)?;
+        write_fields(f, &self.options)?;
+        write_fields(f, &self.off)
+    }
+}
+
+impl Display for TransmissionLine {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.id)?;
+        write_fields(f, &self.nodes)?;
+        write_fields(f, &self.options)
+    }
+}
+
+impl Display for Statement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self {
+                model: Some(model), ..
+            } => write!(f, "{}", model),
+            Self {
+                option: Some(option),
+                ..
+            } => write!(f, "{}", option),
+            Self { op: Some(op), .. } => write!(f, "{}", op),
+            Self { dc: Some(dc), .. } => write!(f, "{}", dc),
+            Self {
+                tran: Some(tran), ..
+            } => write!(f, "{}", tran),
+            Self { ac: Some(ac), .. } => write!(f, "{}", ac),
+            Self {
+                print: Some(print), ..
+            } => write!(f, "{}", print),
+            Self {
+                plot: Some(plot), ..
+            } => write!(f, "{}", plot),
+            Self {
+                param: Some(param), ..
+            } => write!(f, "{}", param),
+            Self {
+                include: Some(include),
+                ..
+            } => write!(f, "{}", include),
+            Self { lib: Some(lib), .. } => write!(f, "{}", lib),
+            Self {
+                endl: Some(endl), ..
+            } => write!(f, "{}", endl),
+            Self {
+                globals: Some(globals),
+                ..
+            } => write!(f, "{}", globals),
+            Self { end: Some(end), .. } => write!(f, "{}", end),
+            Self {
+                control: Some(control),
+                ..
+            } => write!(f, "{}", control),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Display for ModelStatement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            ".model {} {} ({})",
+            self.name,
+            self.kind,
+            self.params.iter().join(" ")
+        )
+    }
+}
+
+impl Display for ModelParam {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.value {
+            Some(value) => write!(f, "{}={}", self.id, value),
+            None => write!(f, "{}", self.id),
+        }
+    }
+}
+
+impl Display for OpAnalysis {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, ".op")?;
+        write_fields(f, &self.params)?;
+        write_fields(f, &self.options)
+    }
+}
+
+impl Display for DcAnalysis {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            ".dc {} {} {} {}",
+            self.source, self.start, self.stop, self.step
+        )?;
+        write_fields(
+            f,
+            self.source2
+                .iter()
+                .chain(&self.start2)
+                .chain(&self.stop2)
+                .chain(&self.step2),
+        )
+    }
+}
+
+impl Display for TransientAnalysis {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, ".tran")?;
+        write_fields(f, self.timesteps.iter().chain(&self.uic))?;
+        write_fields(f, &self.params)?;
+        write_fields(f, &self.options)
+    }
+}
+
+impl Display for AcAnalysis {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            ".ac {} {} {} {}",
+            self.sweep, self.points, self.start, self.stop
+        )
+    }
+}
+
+impl Display for OptionStatement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, ".options")?;
+        write_fields(f, &self.params)?;
+        write_fields(f, &self.options)?;
+        write_fields(f, &self.flags)
+    }
+}
+
+impl Display for PrintStatement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, ".print {}", self.kind)?;
+        write_fields(f, &self.args)
+    }
+}
+
+impl Display for PlotStatement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, ".plot {}", self.kind)?;
+        write_fields(f, &self.args)
+    }
+}
+
+impl Display for ParamStatement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, ".param")?;
+        write_fields(f, &self.params)
+    }
+}
+
+impl Display for IncludeStatement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, ".include \"{}\"", self.path)
+    }
+}
+
+impl Display for LibStatement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, ".lib \"{}\"", self.path)?;
+        write_fields(f, &self.section)
+    }
+}
+
+impl Display for EndLibStatement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, ".endl")?;
+        write_fields(f, &self.section)
+    }
+}
+
+impl Display for GlobalStatement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, ".global")?;
+        write_fields(f, &self.nodes)
+    }
+}
+
+impl Display for ControlStatement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, ".{}", self.command)?;
+        write_fields(f, &self.args)
+    }
+}
+
+impl Display for SourceValues {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self {
+                params: Some(params),
+                ..
+            } => write!(f, "{}", params),
+            Self { ac: Some(ac), .. } => write!(f, "{}", ac),
+            Self {
+                distortion: Some(distortion),
+                ..
+            } => write!(f, "{}", distortion),
+            Self { dc: Some(dc), .. } => write!(f, "{}", dc),
+            Self {
+                options: Some(options),
+                ..
+            } => write!(f, "{}", options),
+            Self {
+                value: Some(value), ..
+            } => write!(f, "{}", value),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Display for AcValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "ac")?;
+        write_fields(f, self.magnitude.iter().chain(&self.phase))
+    }
+}
+
+impl Display for DistortionValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        write_fields(f, self.magnitude.iter().chain(&self.phase))
+    }
+}
+
+impl Display for Argument {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self {
+                params: Some(params),
+                ..
+            } => write!(f, "{}", params),
+            Self {
+                options: Some(options),
+                ..
+            } => write!(f, "{}", options),
+            Self {
+                quoted: Some(quoted),
+                ..
+            } => write!(f, "\"{}\"", quoted),
+            Self {
+                value: Some(value), ..
+            } => write!(f, "{}", value),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Display for KeyValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.id, self.value)
+    }
+}
+
+impl Display for ParamValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}({})", self.id, self.value.join(" "))
+    }
+}
+
+/// SPICE deck of an `LCircuit`, its `.global` nodes, `.subckt` templates and elements, followed
+/// by `statements`(`.model` cards, analyses, `.options`, ...) of the netlist it was read from.
+///
+/// Elements are named with the letter of their `ElementKind` prepended when missing, `Port`
+/// elements have no SPICE representation and are skipped.
+#[derive(Debug, Clone, TypedBuilder)]
+pub struct SPICEDeck<'deck> {
+    #[builder(default, setter(into))]
+    title: String,
+    circuit: &'deck LCircuit,
+    #[builder(default)]
+    statements: &'deck [Statement],
+}
+
+impl<'deck> SPICEDeck<'deck> {
+    /// Deck of `circuit` with the statements of the top scope of `netlist`.
+    pub fn from_netlist(circuit: &'deck LCircuit, netlist: &'deck SPICENetlist) -> Self {
+        Self::builder()
+            .circuit(circuit)
+            .statements(&netlist.netlist_scope.statements)
+            .build()
+    }
+}
+
+impl Display for SPICEDeck<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "* {}", self.title)?;
+        write_circuit(f, self.circuit)?;
+        self.statements
+            .iter()
+            .filter(|statement| statement.end.is_none())
+            .try_for_each(|statement| writeln!(f, "{}", statement))?;
+        writeln!(f, ".end")
+    }
+}
+
+fn write_circuit(f: &mut Formatter<'_>, circuit: &LCircuit) -> fmt::Result {
+    if !circuit.globals().is_empty() {
+        write!(f, ".global")?;
+        write_fields(f, circuit.globals())?;
+        writeln!(f)?;
+    }
+    circuit
+        .subcircuits()
+        .try_for_each(|(subcircuit_name, template)| {
+            write!(f, ".subckt {}", subcircuit_name)?;
+            write_fields(f, template.ports())?;
+            writeln!(f)?;
+            write_circuit(f, template.circuit())?;
+            writeln!(f, ".ends {}", subcircuit_name)
+        })?;
+    circuit
+        .elements()
+        .try_for_each(|(_element_id, element)| write_element(f, element))
+}
+
+fn write_element(f: &mut Formatter<'_>, element: &ElementHNode) -> fmt::Result {
+    let Some(prefix) = element.kind().spice_letter() else {
+        return Ok(());
+    };
+    if !element
+        .name()
+        .starts_with([prefix, prefix.to_ascii_uppercase()])
+    {
+        write!(f, "{}", prefix)?;
+    }
+    write!(f, "{}", element.name())?;
+    write_fields(f, element.terminals())?;
+    write_fields(f, element.value())?;
+    element.params().iter().try_for_each(|(param, value)| {
+        let source_function = matches!(
+            element.kind(),
+            ElementKind::VoltageSource | ElementKind::CurrentSource
+        ) && (SOURCE_FUNCTIONS
+            .iter()
+            .any(|function| param.eq_ignore_ascii_case(function))
+            || value.contains(char::is_whitespace));
+        if source_function {
+            write!(f, " {}({})", param, value)
+        } else {
+            write!(f, " {}={}", param, value)
+        }
+    })?;
+    writeln!(f)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::circuit::equations::DeviceEquationMap;
+    use crate::circuit::nodes::CircuitNode;
+    use crate::circuit::spice::tests::deck_paths;
+
+    #[test]
+    fn netlist_round_trip() {
+        deck_paths().iter().for_each(|deck_path| {
+            let deck = fs::read_to_string(deck_path).unwrap();
+            let netlist = SPICENetlist::parse_deck(&deck).unwrap();
+            let written = netlist.to_string();
+            let reparsed = SPICENetlist::parse_deck(&written).unwrap_or_else(|error| {
+                panic!("Failed to parse written {}: {}", deck_path.display(), error)
+            });
+            assert_eq!(
+                format!("{:?}", netlist),
+                format!("{:?}", reparsed),
+                "Written {} should parse to the same netlist.",
+                deck_path.display()
+            );
+        });
+    }
+
+    #[test]
+    fn circuit_round_trip() {
+        let device_eq_map = DeviceEquationMap::new();
+        deck_paths().iter().for_each(|deck_path| {
+            let deck = fs::read_to_string(deck_path).unwrap();
+            let netlist = SPICENetlist::parse_deck(&deck).unwrap();
+            let circuit = LCircuit::from((&netlist, &device_eq_map));
+            let written = SPICEDeck::from_netlist(&circuit, &netlist).to_string();
+            let renetlist = SPICENetlist::parse_deck(&written).unwrap_or_else(|error| {
+                panic!("Failed to parse written {}: {}", deck_path.display(), error)
+            });
+            let recircuit = LCircuit::from((&renetlist, &device_eq_map));
+            assert_eq!(
+                format!("{:?}", circuit_elements(&circuit)),
+                format!("{:?}", circuit_elements(&recircuit)),
+                "Written {} should read back to the same circuit.",
+                deck_path.display()
+            );
+            let statements = |netlist: &SPICENetlist| {
+                netlist
+                    .netlist_scope
+                    .statements
+                    .iter()
+                    .filter(|statement| statement.end.is_none())
+                    .map(|statement| format!("{:?}", statement))
+                    .collect_vec()
+            };
+            assert_eq!(statements(&netlist), statements(&renetlist));
+        });
+    }
+
+    /// Elements of every scope, with the templates and ports of subcircuits.
+    fn circuit_elements(circuit: &LCircuit) -> Vec<String> {
+        circuit
+            .subcircuits()
+            .flat_map(|(name, template)| {
+                let mut template_elements = circuit_elements(template.circuit());
+                template_elements.insert(0, format!("{} {:?}", name, template.ports()));
+                template_elements
+            })
+            .chain(
+                circuit
+                    .elements()
+                    .map(|(_element_id, element)| format!("{:?}", element)),
+            )
+            .chain(circuit.globals().iter().map(|global| global.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn flattened_circuit_deck() {
+        let deck = indoc::indoc! {"
+            * inverter chain
+            .subckt inv a y vdd
+            mp y a vdd vdd pmos w=2u l=1u
+            mn y a 0 0 nmos w=1u l=1u
+            .ends inv
+            vdd vdd 0 1.8
+            vin in 0 pulse(0 1.8 1n 100p 100p 2n 4n)
+            x1 in mid vdd inv
+            x2 mid out vdd inv
+            cload out 0 10f
+            .tran 10p 8n
+            .end
+        "};
+        let netlist = SPICENetlist::parse_deck(deck).unwrap();
+        let flat = LCircuit::from((&netlist, &DeviceEquationMap::new()))
+            .flatten()
+            .unwrap();
+        let written = SPICEDeck::builder()
+            .title("flattened inverter chain")
+            .circuit(&flat)
+            .statements(&netlist.netlist_scope.statements)
+            .build()
+            .to_string();
+        assert_eq!(
+            indoc::indoc! {"
+                * flattened inverter chain
+                vdd vdd 0 1.8
+                vin in 0 pulse(0 1.8 1n 100p 100p 2n 4n)
+                m.x1.mp mid in vdd vdd pmos l=1u w=2u
+                m.x1.mn mid in 0 0 nmos l=1u w=1u
+                m.x2.mp out mid vdd vdd pmos l=1u w=2u
+                m.x2.mn out mid 0 0 nmos l=1u w=1u
+                cload out 0 10f
+                .tran 10p 8n
+                .end
+            "},
+            written
+        );
+        let reread = LCircuit::from((
+            &SPICENetlist::parse_deck(&written).unwrap(),
+            &DeviceEquationMap::new(),
+        ));
+        assert_eq!(7, reread.elements().count());
+        assert_eq!(
+            &["out", "mid", "0", "0"]
+                .map(|node| CircuitNode::from_str(node).unwrap())
+                .to_vec(),
+            reread.element("m.x2.mn").unwrap().terminals()
+        );
+    }
+}
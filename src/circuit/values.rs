@@ -0,0 +1,80 @@
+/// Parse a SPICE number with an optional scale suffix, e.g. `1k`, `740000u`, `4.7meg`, `70pf`.
+///
+/// Suffixes are case-insensitive and any trailing unit letters after the scale(`pf`, `uA`, `ns`)
+/// are ignored.
+pub fn parse_spice_value(value_str: &str) -> Option<f64> {
+    let value_str = value_str.trim();
+    let numeric_end = value_str
+        .char_indices()
+        .find(|(idx, ch)| {
+            !(ch.is_ascii_digit()
+                || *ch == '.'
+                || ((*ch == '+' || *ch == '-')
+                    && (*idx == 0 || value_str[..*idx].ends_with(['e', 'E'])))
+                || ((*ch == 'e' || *ch == 'E')
+                    && value_str[idx + 1..]
+                        .chars()
+                        .next()
+                        .is_some_and(|next| next.is_ascii_digit() || next == '+' || next == '-')))
+        })
+        .map_or(value_str.len(), |(idx, _ch)| idx);
+    let mantissa = value_str[..numeric_end].parse::<f64>().ok()?;
+    let suffix = value_str[numeric_end..].to_ascii_lowercase();
+    let scale = if suffix.starts_with("meg") {
+        1e6
+    } else if suffix.starts_with("mil") {
+        25.4e-6
+    } else {
+        match suffix.chars().next() {
+            Some('t') => 1e12,
+            Some('g') => 1e9,
+            Some('k') => 1e3,
+            Some('m') => 1e-3,
+            Some('u') => 1e-6,
+            Some('n') => 1e-9,
+            Some('p') => 1e-12,
+            Some('f') => 1e-15,
+            Some('a') => 1e-18,
+            _ => 1.0,
+        }
+    };
+    Some(mantissa * scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_value(expected: f64, value_str: &str) {
+        let value = parse_spice_value(value_str).unwrap();
+        assert!(
+            ((value - expected) / expected).abs() < 1e-12,
+            "`{}` should parse as {}, got {}.",
+            value_str,
+            expected,
+            value
+        );
+    }
+
+    #[test]
+    fn spice_values_with_suffixes() {
+        assert_value(1.0, "1.0");
+        assert_value(-15.0, "-15");
+        assert_value(15.0, "+15");
+        assert_value(1e3, "1k");
+        assert_value(4.7e6, "4.7meg");
+        assert_value(4.7e6, "4.7MEG");
+        assert_value(0.74, "740000u");
+        assert_value(70e-12, "70pf");
+        assert_value(100e-6, "100uA");
+        assert_value(1e-9, "1ns");
+        assert_value(2.5e-3, "2.5e-3");
+        assert_value(2.5e3, "2.5E+3");
+    }
+
+    #[test]
+    fn invalid_spice_values() {
+        assert!(parse_spice_value("k1").is_none());
+        assert!(parse_spice_value("").is_none());
+    }
+}
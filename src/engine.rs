@@ -1,6 +1,6 @@
 use ascent::ascent;
 
-mod lattice;
+pub(crate) mod lattice;
 mod program_builder;
 
 ascent! {
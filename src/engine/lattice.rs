@@ -103,21 +103,21 @@ mod clause_ascent_doc_example {
     }
 }
 
-mod chatterjee_brayton_physical_synthesis_example {
+pub(crate) mod chatterjee_brayton_physical_synthesis_example {
     use ascent::ascent;
 
-    type SortedRectEdgeList = Vec<(i32, TopBottom)>;
-    type OptimumRegionCost = (i32, i32, i32);
+    pub(crate) type SortedRectEdgeList = Vec<(i32, TopBottom)>;
+    pub(crate) type OptimumRegionCost = (i32, i32, i32);
 
-    #[allow(dead_code)]
-    #[derive(Clone, Copy, Hash, Eq, PartialEq)]
-    enum TopBottom {
+    #[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+    pub(crate) enum TopBottom {
         Top,
         Bottom,
     }
 
-    #[allow(dead_code)]
-    fn calculate_optimum_region(sorted_regions: &[(i32, TopBottom)]) -> OptimumRegionCost {
+    pub(crate) fn calculate_optimum_region(
+        sorted_regions: &[(i32, TopBottom)],
+    ) -> OptimumRegionCost {
         let middle = sorted_regions.len() / 2;
         let mut optimum_region: OptimumRegionCost = (0, 0, 0);
         for (ii, region_node) in sorted_regions.iter().enumerate() {
@@ -147,6 +147,22 @@ mod chatterjee_brayton_physical_synthesis_example {
             let (y1, y2, cost) = calculate_optimum_region(region_nodes);
     }
 
+    /// Run the Optimum Region Program over a Single Sorted Edge List
+    ///
+    /// Each bounding interval `[lo, hi]` contributes `(lo, TopBottom::Top)` and
+    /// `(hi, TopBottom::Bottom)`, sorted by coordinate.
+    pub(crate) fn optimum_region(sorted_regions: SortedRectEdgeList) -> Option<OptimumRegionCost> {
+        if sorted_regions.is_empty() {
+            return None;
+        }
+        let mut prog = AscentProgram {
+            sorted_rect_edge_list: vec![(sorted_regions,)],
+            ..Default::default()
+        };
+        prog.run();
+        prog.optimum_region.into_iter().next()
+    }
+
     #[cfg(test)]
     mod tests {
         use super::*;
@@ -187,5 +203,27 @@ mod chatterjee_brayton_physical_synthesis_example {
                  (12+18) = 22."
             );
         }
+
+        #[test]
+        fn optimum_region_of_interval_list() {
+            let edges: SortedRectEdgeList = vec![
+                (0, TopBottom::Top),
+                (2, TopBottom::Top),
+                (4, TopBottom::Bottom),
+                (10, TopBottom::Top),
+                (12, TopBottom::Bottom),
+                (14, TopBottom::Bottom),
+            ];
+            assert_eq!(
+                Some((4, 10, 6)),
+                optimum_region(edges),
+                "Optimum region should lie between the median edges 4 and 10."
+            );
+            assert_eq!(
+                None,
+                optimum_region(vec![]),
+                "An empty edge list has no optimum region."
+            );
+        }
     }
 }
@@ -48,6 +48,7 @@ mod tests {
         let unit_id = UnitId::new(0);
         let unit_program_and_inst: Vec<(Value, Value, Value, u64)> = llhd_world
             .unit_program_inst::<LLHDInstComponent>(unit_id)
+            .unwrap()
             .filter(|((_unit_id, _inst_id), inst_component, _inst_data)| {
                 Opcode::And == inst_component.data.opcode()
             })
@@ -61,6 +62,7 @@ mod tests {
             .collect();
         let unit_program_or_inst: Vec<(Value, Value, Value, u64)> = llhd_world
             .unit_program_inst::<LLHDInstComponent>(unit_id)
+            .unwrap()
             .filter(|((_unit_id, _inst_id), inst_component, _inst_data)| {
                 Opcode::Or == inst_component.data.opcode()
             })
@@ -139,6 +141,7 @@ mod tests {
         let unit_id = UnitId::new(0);
         let unit_program_and_inst: Vec<(Value, Value, Value, u64)> = llhd_world
             .unit_program_inst::<LLHDInstComponent>(unit_id)
+            .unwrap()
             .filter(|((_unit_id, _inst_id), inst_component, _inst_data)| {
                 Opcode::And == inst_component.data.opcode()
             })
@@ -152,6 +155,7 @@ mod tests {
             .collect();
         let unit_program_or_inst: Vec<(Value, Value, Value, u64)> = llhd_world
             .unit_program_inst::<LLHDInstComponent>(unit_id)
+            .unwrap()
             .filter(|((_unit_id, _inst_id), inst_component, _inst_data)| {
                 Opcode::Or == inst_component.data.opcode()
             })
@@ -230,6 +234,7 @@ mod tests {
         let unit_id = UnitId::new(0);
         let unit_program_and_inst: Vec<(Value, Value, Value, u64)> = llhd_world
             .unit_program_inst::<LLHDInstComponent>(unit_id)
+            .unwrap()
             .filter(|((_unit_id, _inst_id), inst_component, _inst_data)| {
                 Opcode::And == inst_component.data.opcode()
             })
@@ -243,6 +248,7 @@ mod tests {
             .collect();
         let unit_program_or_inst: Vec<(Value, Value, Value, u64)> = llhd_world
             .unit_program_inst::<LLHDInstComponent>(unit_id)
+            .unwrap()
             .filter(|((_unit_id, _inst_id), inst_component, _inst_data)| {
                 Opcode::Or == inst_component.data.opcode()
             })
@@ -321,6 +327,7 @@ mod tests {
         let unit_id = UnitId::new(0);
         let unit_program_and_inst: Vec<(Value, Value, Value, u64)> = llhd_world
             .unit_program_inst::<LLHDInstComponent>(unit_id)
+            .unwrap()
             .filter(|((_unit_id, _inst_id), inst_component, _inst_data)| {
                 Opcode::And == inst_component.data.opcode()
             })
@@ -334,6 +341,7 @@ mod tests {
             .collect();
         let unit_program_or_inst: Vec<(Value, Value, Value, u64)> = llhd_world
             .unit_program_inst::<LLHDInstComponent>(unit_id)
+            .unwrap()
             .filter(|((_unit_id, _inst_id), inst_component, _inst_data)| {
                 Opcode::Or == inst_component.data.opcode()
             })
@@ -3,7 +3,7 @@ use bevy_ecs::prelude::Component;
 use itertools::Itertools;
 use llhd::ir::prelude::*;
 
-use crate::llhd_world::world::LLHDWorld;
+use crate::llhd_world::world::{LLHDWorld, LLHDWorldError};
 
 #[derive(Clone, PartialEq, Eq, Hash, Component)]
 struct LLHDInstLocation(usize, usize);
@@ -11,9 +11,12 @@ struct LLHDInstLocation(usize, usize);
 type LLHDProgramWithInstLocation = Vec<(Value, Value, Value, LLHDInstLocation)>;
 type LLHDProgramWithInstOpAndLocation = Vec<(Opcode, Value, Value, Value, LLHDInstLocation)>;
 
-fn get_all_llhd_insts(llhd_world: &LLHDWorld, unit_id: UnitId) -> LLHDProgramWithInstOpAndLocation {
-    llhd_world
-        .unit_program_inst::<LLHDInstLocation>(unit_id)
+fn get_all_llhd_insts(
+    llhd_world: &LLHDWorld,
+    unit_id: UnitId,
+) -> Result<LLHDProgramWithInstOpAndLocation, LLHDWorldError> {
+    Ok(llhd_world
+        .unit_program_inst::<LLHDInstLocation>(unit_id)?
         .map(|(_inst_idx, inst_component, inst_data)| {
             let opcode = inst_component.data.opcode();
             let inst_value = inst_component.value.unwrap();
@@ -22,16 +25,16 @@ fn get_all_llhd_insts(llhd_world: &LLHDWorld, unit_id: UnitId) -> LLHDProgramWit
             let input2 = args[1];
             (opcode, inst_value, input1, input2, inst_data)
         })
-        .collect()
+        .collect())
 }
 
 fn get_llhd_insts(
     llhd_world: &LLHDWorld,
     unit_id: UnitId,
     opcode: Opcode,
-) -> LLHDProgramWithInstLocation {
-    llhd_world
-        .unit_program_inst::<LLHDInstLocation>(unit_id)
+) -> Result<LLHDProgramWithInstLocation, LLHDWorldError> {
+    Ok(llhd_world
+        .unit_program_inst::<LLHDInstLocation>(unit_id)?
         .filter(|((_unit_id, _inst_id), inst_component, _inst_data)| {
             opcode == inst_component.data.opcode()
         })
@@ -42,20 +45,23 @@ fn get_llhd_insts(
             let input2 = args[1];
             (inst_value, input1, input2, inst_data)
         })
-        .collect()
+        .collect())
 }
 
 fn run_divisor_extraction(
     llhd_world: &LLHDWorld,
     unit_id: UnitId,
-) -> (
-    LLHDProgramWithInstLocation,
-    LLHDProgramWithInstOpAndLocation,
-) {
-    let unit_program_and_inst = get_llhd_insts(llhd_world, unit_id, Opcode::And);
-    let unit_program_or_inst = get_llhd_insts(llhd_world, unit_id, Opcode::Or);
-    let unit_program_not_inst = get_llhd_insts(llhd_world, unit_id, Opcode::Not);
-    let unit_program_all_inst = get_all_llhd_insts(llhd_world, unit_id);
+) -> Result<
+    (
+        LLHDProgramWithInstLocation,
+        LLHDProgramWithInstOpAndLocation,
+    ),
+    LLHDWorldError,
+> {
+    let unit_program_and_inst = get_llhd_insts(llhd_world, unit_id, Opcode::And)?;
+    let unit_program_or_inst = get_llhd_insts(llhd_world, unit_id, Opcode::Or)?;
+    let unit_program_not_inst = get_llhd_insts(llhd_world, unit_id, Opcode::Not)?;
+    let unit_program_all_inst = get_all_llhd_insts(llhd_world, unit_id)?;
 
     // Replace a*b + a*c
     // with    a*(b + c)
@@ -82,7 +88,7 @@ fn run_divisor_extraction(
             llhd_inst(Opcode::And, and2_idx, a, c, and2_loc);
     };
 
-    (
+    Ok((
         ascent_program
             .div_noti
             .into_iter()
@@ -90,13 +96,14 @@ fn run_divisor_extraction(
             .chain(ascent_program.div_ori)
             .collect_vec(),
         ascent_program.div_llhd_inst.into_iter().collect_vec(),
-    )
+    ))
 }
 
 #[cfg(test)]
 mod tests {
     use std::usize;
 
+    use llhd::table::TableKey;
     use pretty_assertions::assert_eq;
 
     use super::*;
@@ -216,7 +223,9 @@ mod tests {
         let positions = space_to_grid(bb.0, bb.1, unit_insts.len());
         unit_insts.iter().enumerate().for_each(|(ii, inst)| {
             let position = LLHDInstLocation(positions[ii].0, positions[ii].1);
-            llhd_world.set_inst::<LLHDInstLocation>(unit_id, *inst, position)
+            llhd_world
+                .set_inst::<LLHDInstLocation>(unit_id, *inst, position)
+                .unwrap();
         });
     }
 
@@ -293,7 +302,7 @@ mod tests {
             "There should be 6 Instructions in original Unit."
         );
 
-        let extracted_divisor_program_all = run_divisor_extraction(&llhd_world, unit_id);
+        let extracted_divisor_program_all = run_divisor_extraction(&llhd_world, unit_id).unwrap();
         let extracted_divisor_program_opcode_types = extracted_divisor_program_all.0;
         assert_eq!(
             2,
@@ -309,5 +318,11 @@ mod tests {
             "There should be 2 instructions remaining in the extracted program(`a*b + a*c` -> \
              `a*(b + c)`)."
         );
+        let stale_unit_id = UnitId::new(1);
+        assert_eq!(
+            Some(LLHDWorldError::MissingUnit(stale_unit_id)),
+            run_divisor_extraction(&llhd_world, stale_unit_id).err(),
+            "A stale unit should be reported, not extracted as empty."
+        );
     }
 }
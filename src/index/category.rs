@@ -1,9 +1,21 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
 use derive_getters::Getters;
+use euclid::default::Box2D;
+use itertools::Itertools;
+use llhd::ir::{Inst, UnitId, Value};
+use llhd::table::TableKey;
 use typed_builder::TypedBuilder;
 
+use crate::circuit::graph::LCircuitEdgeID;
 use crate::index::unit::{
-    DesignGateIndex, DesignUnitIndex, DesignValueDefIndex, DesignValueRefIndex,
+    AnalogNetComponent, CircuitComponent, DesignGateIndex, DesignUnitIndex, DesignValueDefIndex,
+    DesignValueRefIndex,
 };
+use crate::llhd_world::placement::LLHDInstPlacement;
+use crate::llhd_world::world::edit::LLHDEditError;
+use crate::llhd_world::world::{LLHDWorld, LLHDWorldError};
 
 pub type DesignUnitSet = Vec<DesignUnitIndex>;
 pub type DesignGateSet = Vec<DesignGateIndex>;
@@ -23,14 +35,196 @@ pub trait DICagetoryMorphism {
     fn arrow(self, domain_object: &DICategoryObject) -> DICategoryObject;
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DICategoryError {
+    Edit(LLHDEditError),
+    World(LLHDWorldError),
+}
+
+impl fmt::Display for DICategoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Edit(edit_error) => write!(f, "{}", edit_error),
+            Self::World(world_error) => write!(f, "{}", world_error),
+        }
+    }
+}
+
+impl std::error::Error for DICategoryError {}
+
+impl From<LLHDEditError> for DICategoryError {
+    fn from(edit_error: LLHDEditError) -> Self {
+        Self::Edit(edit_error)
+    }
+}
+
+impl From<LLHDWorldError> for DICategoryError {
+    fn from(world_error: LLHDWorldError) -> Self {
+        Self::World(world_error)
+    }
+}
+
+fn inst_bb(llhd_world: &LLHDWorld, unit_id: UnitId, inst: Inst) -> Vec<Box2D<usize>> {
+    llhd_world
+        .get_inst::<LLHDInstPlacement>(unit_id, inst)
+        .map(|placement| *placement.bb())
+        .into_iter()
+        .collect()
+}
+
+fn circuit_nets(circuit: Result<&CircuitComponent, LLHDWorldError>) -> BTreeSet<LCircuitEdgeID> {
+    circuit
+        .into_iter()
+        .flat_map(|circuit| circuit.circuit().nets().map(|(_node, net)| *net))
+        .collect()
+}
+
+fn analog_nets(nets: Result<&AnalogNetComponent, LLHDWorldError>) -> BTreeSet<LCircuitEdgeID> {
+    nets.map(|nets| nets.nets().to_owned()).unwrap_or_default()
+}
+
+/// Index sets of every unit, inst, value def and value ref of the world, in module order.
+///
+/// `nets` are those of the circuits bound by `LLHDWorld::bind_circuits`, `bb` the
+/// `LLHDInstPlacement` of the inst, every placed inst of a unit. Gates of insts without a result
+/// have an invalid `value`.
+impl From<&LLHDWorld> for DICategoryObject {
+    fn from(llhd_world: &LLHDWorld) -> Self {
+        let mut object = Self::default();
+        for unit in llhd_world.module().units() {
+            let unit_id = unit.id();
+            let insts = unit
+                .blocks()
+                .flat_map(|block| unit.insts(block))
+                .collect::<Vec<_>>();
+            object.units.push(
+                DesignUnitIndex::builder()
+                    .unit(unit_id)
+                    .nets(circuit_nets(llhd_world.get_unit(unit_id)))
+                    .bb(insts
+                        .iter()
+                        .flat_map(|inst| inst_bb(llhd_world, unit_id, *inst))
+                        .collect())
+                    .build(),
+            );
+            for arg in unit.args() {
+                object.value_defs.push(
+                    DesignValueDefIndex::builder()
+                        .unit(unit_id)
+                        .value(arg)
+                        .nets(analog_nets(llhd_world.get_value_def(unit_id, arg)))
+                        .bb(Vec::new())
+                        .build(),
+                );
+            }
+            for inst in insts {
+                let result = unit.get_inst_result(inst);
+                object.gates.push(
+                    DesignGateIndex::builder()
+                        .unit(unit_id)
+                        .id(inst)
+                        .value(result.unwrap_or_else(Value::invalid))
+                        .data(unit[inst].clone())
+                        .nets(circuit_nets(llhd_world.get_inst(unit_id, inst)))
+                        .bb(inst_bb(llhd_world, unit_id, inst))
+                        .build(),
+                );
+                if let Some(result) = result {
+                    object.value_defs.push(
+                        DesignValueDefIndex::builder()
+                            .unit(unit_id)
+                            .value(result)
+                            .nets(analog_nets(llhd_world.get_value_def(unit_id, result)))
+                            .bb(inst_bb(llhd_world, unit_id, inst))
+                            .build(),
+                    );
+                }
+                for value in unit[inst].args().iter().unique() {
+                    object.value_refs.push(
+                        DesignValueRefIndex::builder()
+                            .unit(unit_id)
+                            .id(inst)
+                            .value(*value)
+                            .nets(analog_nets(llhd_world.get_value_ref(unit_id, inst, *value)))
+                            .bb(inst_bb(llhd_world, unit_id, inst))
+                            .build(),
+                    );
+                }
+            }
+        }
+        object
+    }
+}
+
+impl DICategoryObject {
+    /// Applies the gates back to `llhd_world`, the other index sets follow from the module and
+    /// are left as they are.
+    ///
+    /// A gate whose `data` differs has its inst rewritten, a gate whose valid `value` differs
+    /// from its result takes over the uses of that value, and a gate with a single `bb` is
+    /// placed there. Insts without a gate are kept.
+    ///
+    /// The rewrites run in one transaction and the placements are set once it commits. The unit
+    /// and inst of every gate are checked first, so a stale gate leaves `llhd_world` unchanged.
+    pub fn apply(&self, llhd_world: &mut LLHDWorld) -> Result<(), DICategoryError> {
+        for gate in &self.gates {
+            llhd_world.unit_entity(*gate.unit())?;
+            llhd_world.inst_entity(*gate.unit(), *gate.id())?;
+        }
+        llhd_world.transaction(|transaction| {
+            for gate in &self.gates {
+                let unit = transaction.module().unit(*gate.unit());
+                let data = unit[*gate.id()].clone();
+                let result = unit.get_inst_result(*gate.id());
+                if data != *gate.data() {
+                    transaction.replace_inst(*gate.unit(), *gate.id(), gate.data().to_owned())?;
+                }
+                if let Some(result) =
+                    result.filter(|result| !gate.value().is_invalid() && result != gate.value())
+                {
+                    transaction.replace_uses(*gate.unit(), *gate.value(), result)?;
+                }
+            }
+            Ok(())
+        })?;
+        for gate in &self.gates {
+            let [bb] = gate.bb().as_slice() else {
+                continue;
+            };
+            if inst_bb(llhd_world, *gate.unit(), *gate.id()) != [*bb] {
+                llhd_world.set_inst(*gate.unit(), *gate.id(), LLHDInstPlacement::new(*bb))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Runs `morphism` on the index sets of `llhd_world` and applies its codomain object back.
+pub fn apply_morphism(
+    llhd_world: &mut LLHDWorld,
+    morphism: impl DICagetoryMorphism,
+) -> Result<DICategoryObject, DICategoryError> {
+    let codomain_object = morphism.arrow(&DICategoryObject::from(&*llhd_world));
+    codomain_object.apply(llhd_world)?;
+    Ok(codomain_object)
+}
+
 #[cfg(test)]
 mod tests {
 
     use ascent::ascent_run;
+    use euclid::default::Point2D;
     use itertools::Itertools;
     use llhd::ir::prelude::*;
+    use llhd::ir::InstData;
+    use pretty_assertions::assert_eq;
 
     use super::*;
+    use crate::circuit::equations::DeviceEquationMap;
+    use crate::circuit::spice::SPICENetlist;
+    use crate::llhd::module::LLHDModule;
+    use crate::llhd_library::circuit_library::LCircuitLibrary;
+    use crate::llhd_world::components::value::LLHDValueDefComponent;
 
     struct ExampleCategory;
 
@@ -150,4 +344,220 @@ mod tests {
     fn default_value() {
         let _ = DICategoryObject::default();
     }
+
+    const INV_DECK: &str = indoc::indoc! {"
+        * inverter cell
+        .subckt inv a y vdd vss
+        mp y a vdd vdd pmos w=2u l=1u
+        mn y a vss vss nmos w=1u l=1u
+        .ends inv
+    "};
+
+    fn category_world() -> (LLHDWorld, UnitId, UnitId) {
+        let module = llhd::assembly::parse_module(indoc::indoc! {"
+            entity @inv (i1$ %a) -> (i1$ %y) {
+                %epsilon = const time 0s 1e
+                %a_prb = prb i1$ %a
+                %y_not = not i1 %a_prb
+                drv i1$ %y, %y_not, %epsilon
+            }
+
+            entity @top (i1$ %in1, i1 %b, i1 %c) -> (i1$ %out1) {
+                %and1 = and i1 %b, %c
+                inst @inv (i1$ %in1) -> (i1$ %out1)
+            }
+        "})
+        .unwrap();
+        let mut llhd_world = LLHDWorld::new(LLHDModule::from(module));
+        let library = LCircuitLibrary::from_spice(
+            &SPICENetlist::parse_deck(INV_DECK).unwrap(),
+            &DeviceEquationMap::new(),
+        )
+        .unwrap();
+        llhd_world.bind_circuits(&library).unwrap();
+        let unit_id = |name: &str| {
+            llhd_world
+                .module()
+                .units()
+                .find(|unit| unit.name().get_name() == Some(name))
+                .unwrap()
+                .id()
+        };
+        let (inv_id, top_id) = (unit_id("inv"), unit_id("top"));
+        (llhd_world, inv_id, top_id)
+    }
+
+    fn opcode_inst(llhd_world: &LLHDWorld, unit_id: UnitId, opcode: Opcode) -> Inst {
+        let unit = llhd_world.module().unit(unit_id);
+        unit.all_insts()
+            .find(|inst| unit[*inst].opcode() == opcode)
+            .unwrap()
+    }
+
+    #[test]
+    fn di_category_object_from_llhd_world() {
+        let (mut llhd_world, inv_id, top_id) = category_world();
+        let and_inst = opcode_inst(&llhd_world, top_id, Opcode::And);
+        let bb = Box2D::new(Point2D::new(0, 0), Point2D::new(460, 3330));
+        llhd_world
+            .set_inst(top_id, and_inst, LLHDInstPlacement::new(bb))
+            .unwrap();
+        let object = DICategoryObject::from(&llhd_world);
+
+        let inv_unit = object
+            .units()
+            .iter()
+            .find(|unit| *unit.unit() == inv_id)
+            .unwrap();
+        assert_eq!(
+            llhd_world.unit_circuit(inv_id).unwrap().nets().count(),
+            inv_unit.nets().len()
+        );
+        let top_unit = object
+            .units()
+            .iter()
+            .find(|unit| *unit.unit() == top_id)
+            .unwrap();
+        assert_eq!(vec![bb], *top_unit.bb());
+
+        let and_gate = object
+            .gates()
+            .iter()
+            .find(|gate| *gate.unit() == top_id && *gate.id() == and_inst)
+            .unwrap();
+        assert_eq!(vec![bb], *and_gate.bb());
+        assert!(and_gate.nets().is_empty());
+        let instance = opcode_inst(&llhd_world, top_id, Opcode::Inst);
+        let instance_gate = object
+            .gates()
+            .iter()
+            .find(|gate| *gate.unit() == top_id && *gate.id() == instance)
+            .unwrap();
+        assert!(instance_gate.value().is_invalid());
+        assert_eq!(inv_unit.nets(), instance_gate.nets());
+
+        let out1 = llhd_world.module().unit(top_id).output_arg(0);
+        let out1_ref = object
+            .value_refs()
+            .iter()
+            .find(|value_ref| *value_ref.id() == instance && *value_ref.value() == out1)
+            .unwrap();
+        let inv_y = llhd_world.module().unit(inv_id).output_arg(0);
+        assert_eq!(
+            llhd_world.value_nets(inv_id, inv_y).unwrap(),
+            out1_ref.nets()
+        );
+        let mut value_defs = llhd_world.query::<&LLHDValueDefComponent>();
+        assert_eq!(
+            value_defs.iter(llhd_world.world()).count(),
+            object.value_defs().len(),
+            "Every value def entity should be indexed."
+        );
+    }
+
+    /// Turns every `and` gate into an `or`, moving its placement right by `offset`.
+    struct AndToOr {
+        offset: usize,
+    }
+
+    impl DICagetoryMorphism for AndToOr {
+        fn arrow(self, domain_object: &DICategoryObject) -> DICategoryObject {
+            let gates = domain_object
+                .gates()
+                .iter()
+                .filter(|gate| gate.data().opcode() == Opcode::And)
+                .map(|gate| {
+                    let (unit, id, value, data, nets, bb) = gate.to_owned().dissolve();
+                    let InstData::Binary { args, .. } = data else {
+                        panic!("And should be a binary inst.");
+                    };
+                    DesignGateIndex::builder()
+                        .unit(unit)
+                        .id(id)
+                        .value(value)
+                        .data(InstData::Binary {
+                            opcode: Opcode::Or,
+                            args,
+                        })
+                        .nets(nets)
+                        .bb(bb
+                            .into_iter()
+                            .map(|bb| bb.translate(euclid::default::Vector2D::new(self.offset, 0)))
+                            .collect())
+                        .build()
+                })
+                .collect_vec();
+            DICategoryObject::builder()
+                .units(domain_object.units().to_owned())
+                .gates(gates)
+                .value_defs(domain_object.value_defs().to_owned())
+                .value_refs(domain_object.value_refs().to_owned())
+                .build()
+        }
+    }
+
+    #[test]
+    fn apply_di_category_morphism() {
+        let (mut llhd_world, _inv_id, top_id) = category_world();
+        let and_inst = opcode_inst(&llhd_world, top_id, Opcode::And);
+        let bb = Box2D::new(Point2D::new(0, 0), Point2D::new(460, 3330));
+        llhd_world
+            .set_inst(top_id, and_inst, LLHDInstPlacement::new(bb))
+            .unwrap();
+        let codomain_object = apply_morphism(&mut llhd_world, AndToOr { offset: 460 }).unwrap();
+        assert_eq!(1, codomain_object.gates().len());
+
+        let top = llhd_world.module().unit(top_id);
+        assert_eq!(Opcode::Or, top[and_inst].opcode());
+        assert!(top
+            .all_insts()
+            .all(|inst| top[inst].opcode() != Opcode::And));
+        assert_eq!(
+            Box2D::new(Point2D::new(460, 0), Point2D::new(920, 3330)),
+            *llhd_world
+                .get_inst::<LLHDInstPlacement>(top_id, and_inst)
+                .unwrap()
+                .bb()
+        );
+        assert_eq!(
+            codomain_object.gates(),
+            DICategoryObject::from(&llhd_world)
+                .gates()
+                .iter()
+                .filter(|gate| *gate.unit() == top_id && *gate.id() == and_inst)
+                .cloned()
+                .collect_vec()
+                .as_slice()
+        );
+    }
+
+    #[test]
+    fn apply_stale_gate() {
+        let (mut llhd_world, _inv_id, top_id) = category_world();
+        let and_inst = opcode_inst(&llhd_world, top_id, Opcode::And);
+        let module_dump = llhd_world.module().dump().to_string();
+        let codomain_object = AndToOr { offset: 460 }.arrow(&DICategoryObject::from(&llhd_world));
+        let stale_inst = Inst::new(and_inst.index() + 100);
+        let stale_gate = DesignGateIndex::builder()
+            .unit(top_id)
+            .id(stale_inst)
+            .value(Value::invalid())
+            .data(codomain_object.gates()[0].data().to_owned())
+            .nets(BTreeSet::new())
+            .bb(Vec::new())
+            .build();
+        let stale_object = DICategoryObject::builder()
+            .units(codomain_object.units().to_owned())
+            .gates([codomain_object.gates().to_owned(), vec![stale_gate]].concat())
+            .value_defs(codomain_object.value_defs().to_owned())
+            .value_refs(codomain_object.value_refs().to_owned())
+            .build();
+        assert_eq!(
+            Err(DICategoryError::World(LLHDWorldError::MissingInst(
+                top_id, stale_inst
+            ))),
+            stale_object.apply(&mut llhd_world)
+        );
+        assert_eq!(module_dump, llhd_world.module().dump().to_string());
+    }
 }
@@ -3,7 +3,6 @@ use std::collections::BTreeSet;
 use std::hash::Hash;
 
 use ascent::Lattice;
-use bevy_ecs::bundle::Bundle;
 use bevy_ecs::component::Component;
 use derive_getters::{Dissolve, Getters};
 use euclid::default::Box2D;
@@ -12,22 +11,33 @@ use llhd::ir::InstData;
 use typed_builder::TypedBuilder;
 
 use crate::circuit::graph::{LCircuit, LCircuitEdgeID};
-use crate::llhd::components::unit::UnitBundle;
+use crate::llhd_world::world::AnalogCircuitIndex;
 
 /// Type Constraint for Use in a Datalog Relation Column
 pub trait FlatIndex: Clone + PartialEq + Eq + Hash {}
 
 /// Design Unit's Circuit Component
-#[derive(Debug, Clone, Component)]
+#[derive(Debug, Clone, Component, Getters)]
 pub struct CircuitComponent {
     circuit: LCircuit,
 }
 
-/// Design Unit Component
-#[derive(Debug, Clone, Bundle)]
-pub struct SynthesisUnitBundle {
-    unit: UnitBundle,
-    circuit: CircuitComponent,
+impl CircuitComponent {
+    pub const fn new(circuit: LCircuit) -> Self {
+        Self { circuit }
+    }
+}
+
+/// Nets of a Unit's Circuit Bound to a Port Value
+#[derive(Debug, Clone, Default, PartialEq, Eq, Component, Getters)]
+pub struct AnalogNetComponent {
+    nets: AnalogCircuitIndex,
+}
+
+impl AnalogNetComponent {
+    pub const fn new(nets: AnalogCircuitIndex) -> Self {
+        Self { nets }
+    }
 }
 
 /// `FlatIndex` for Design Units
@@ -317,8 +327,8 @@ mod tests {
         let inst_or1_result1 = test_unit.inst_result(inst_or1_id);
 
         let unit_id_component = UnitIdComponent { id: test_unit_id };
-        let test_unit_bundle = SynthesisUnitBundle {
-            unit: UnitBundle {
+        let test_unit_bundle = (
+            UnitBundle {
                 unit: unit_id_component.clone(),
                 name: UnitNameComponent {
                     name: test_unit_name.clone(),
@@ -326,8 +336,8 @@ mod tests {
                     signature: test_unit_sig.clone(),
                 },
             },
-            circuit: CircuitComponent { circuit },
-        };
+            CircuitComponent { circuit },
+        );
         let _unit_entity_id = ecs.spawn(test_unit_bundle);
 
         let test_unit_arg1_bundle = ValueDefBundle {
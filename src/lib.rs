@@ -67,10 +67,11 @@ mod config;
 ///
 /// 1) Start with a Digital Design(LLHD Module)
 /// 2) Constrain the Synthesis Flow to a Technology
-/// 3) Apply Synthesis Rules to Design
+/// 3) Apply Synthesis Rules to Design, the passes of `SYNTHESIS_SCHEDULE`
 ///
 /// ```rust
-/// # use syndex::{Flow, DesignState, TechnologyState, SynthesisState};
+/// # use syndex::{Flow, DesignState, FailedState, Synthesized, TechnologyState, SynthesisState};
+/// # use syndex::{LLHDChangedUnits, LLHDPassKind, SYNTHESIS_SCHEDULE};
 /// # let input = indoc::indoc! {"
 /// #         entity @test_entity (i1 %in1, i1 %in2, i1 %in3, i1 %in4) -> (i1$ %out1) {
 /// #             %null = const time 0s 1e
@@ -82,10 +83,32 @@ mod config;
 /// #     "};
 ///
 /// # let module = llhd::assembly::parse_module(input).unwrap();
-/// let _technology_flow = Flow::load(module.into());
+/// let technology_flow = Flow::load(module.into());
+/// let mut synthesis_flow = technology_flow.constrain();
+/// synthesis_flow
+///     .passes_mut()
+///     .add_pass(SYNTHESIS_SCHEDULE, "count_units", LLHDPassKind::Analysis, &[], |units: LLHDChangedUnits| {
+///         assert_eq!(1, units.iter().count());
+///     })
+///     .unwrap();
+/// let _design_flow = match synthesis_flow.synthesize() {
+///     Synthesized::Design(design_flow) => design_flow,
+///     Synthesized::Failed(failed_flow) => panic!("{}", failed_flow.error()),
+/// };
 /// ```
 pub mod synthesis_state;
-pub use synthesis_state::builder::{DesignState, Flow, SynthesisState, TechnologyState};
+pub use llhd_world::world::passes::{
+    LLHDChangedUnits, LLHDPassError, LLHDPassKind, LLHDPassManager, LLHDPassTiming, LLHDUnitEdits,
+    SYNTHESIS_SCHEDULE,
+};
+pub use llhd_world::world::snapshot::{
+    LLHDComponentRegistry, LLHDSnapshotError, LLHDWorldSnapshot,
+};
+pub use llhd_world::world::update::UnitUpdate;
+pub use llhd_world::world::LLHDWorld;
+pub use synthesis_state::builder::{
+    DesignState, FailedState, Flow, SynthesisState, Synthesized, TechnologyState,
+};
 
 /// Monadic Type for LLHD Module Synthesis
 pub mod synthesis;
@@ -1,5 +1,5 @@
 use llhd::ir::prelude::*;
-use llhd::ir::InstData;
+use llhd::ir::{ExtUnit, InstData};
 
 use crate::llhd::{LLHDInst, LLHDUtils, LLHDValueRef};
 
@@ -37,6 +37,104 @@ impl LLHDUtils {
         }
     }
 
+    /// Copy of `inst_data` with its arguments, blocks and external unit renamed, `None` when one
+    /// of them has no counterpart.
+    pub(crate) fn rename_inst_data(
+        inst_data: &InstData,
+        mut value: impl FnMut(Value) -> Option<Value>,
+        mut block: impl FnMut(Block) -> Option<Block>,
+        mut ext_unit: impl FnMut(ExtUnit) -> Option<ExtUnit>,
+    ) -> Option<InstData> {
+        let mut values = |args: &[Value]| {
+            args.iter()
+                .map(|arg| value(*arg))
+                .collect::<Option<Vec<_>>>()
+        };
+        let mut blocks =
+            |bbs: &[Block]| bbs.iter().map(|bb| block(*bb)).collect::<Option<Vec<_>>>();
+        let renamed = match inst_data {
+            InstData::ConstInt { .. } | InstData::ConstTime { .. } | InstData::Nullary { .. } => {
+                inst_data.clone()
+            }
+            InstData::Array { opcode, imms, args } => InstData::Array {
+                opcode: *opcode,
+                imms: *imms,
+                args: values(args)?.try_into().ok()?,
+            },
+            InstData::Aggregate { opcode, args } => InstData::Aggregate {
+                opcode: *opcode,
+                args: values(args)?,
+            },
+            InstData::Unary { opcode, args } => InstData::Unary {
+                opcode: *opcode,
+                args: values(args)?.try_into().ok()?,
+            },
+            InstData::Binary { opcode, args } => InstData::Binary {
+                opcode: *opcode,
+                args: values(args)?.try_into().ok()?,
+            },
+            InstData::Ternary { opcode, args } => InstData::Ternary {
+                opcode: *opcode,
+                args: values(args)?.try_into().ok()?,
+            },
+            InstData::Quaternary { opcode, args } => InstData::Quaternary {
+                opcode: *opcode,
+                args: values(args)?.try_into().ok()?,
+            },
+            InstData::Jump { opcode, bbs } => InstData::Jump {
+                opcode: *opcode,
+                bbs: blocks(bbs)?.try_into().ok()?,
+            },
+            InstData::Phi { opcode, args, bbs } => InstData::Phi {
+                opcode: *opcode,
+                args: values(args)?,
+                bbs: blocks(bbs)?,
+            },
+            InstData::Branch { opcode, args, bbs } => InstData::Branch {
+                opcode: *opcode,
+                args: values(args)?.try_into().ok()?,
+                bbs: blocks(bbs)?.try_into().ok()?,
+            },
+            InstData::Wait { opcode, bbs, args } => InstData::Wait {
+                opcode: *opcode,
+                bbs: blocks(bbs)?.try_into().ok()?,
+                args: values(args)?,
+            },
+            InstData::Call {
+                opcode,
+                unit,
+                ins,
+                args,
+            } => InstData::Call {
+                opcode: *opcode,
+                unit: ext_unit(*unit)?,
+                ins: *ins,
+                args: values(args)?,
+            },
+            InstData::InsExt { opcode, args, imms } => {
+                // Extractions only use their first argument, the second slot is left as is.
+                let used = inst_data.args().len();
+                let mut renamed_args = *args;
+                renamed_args[..used].copy_from_slice(&values(&args[..used])?);
+                InstData::InsExt {
+                    opcode: *opcode,
+                    args: renamed_args,
+                    imms: *imms,
+                }
+            }
+            InstData::Reg {
+                opcode,
+                args,
+                modes,
+            } => InstData::Reg {
+                opcode: *opcode,
+                args: values(args)?,
+                modes: modes.clone(),
+            },
+        };
+        Some(renamed)
+    }
+
     pub(crate) fn iterate_unit_value_defs<'unit>(
         unit: &'unit Unit,
     ) -> impl Iterator<Item = LLHDValueRef> + 'unit {
@@ -82,6 +180,33 @@ mod tests {
         );
     }
 
+    #[test]
+    fn rename_llhd_inst_data() {
+        let unit_data = utilities::build_entity_alpha(UnitName::anonymous(0));
+        let unit = Unit::new(UnitId::new(0), &unit_data);
+        let add2_inst = LLHDUtils::last_unit_inst(&unit).1;
+        let shifted = LLHDUtils::rename_inst_data(
+            &unit[add2_inst],
+            |value| Some(Value::new(value.index() + 100)),
+            Some,
+            Some,
+        )
+        .unwrap();
+        assert_eq!(unit[add2_inst].opcode(), shifted.opcode());
+        assert_eq!(
+            unit[add2_inst]
+                .args()
+                .iter()
+                .map(|arg| arg.index() + 100)
+                .collect_vec(),
+            shifted.args().iter().map(|arg| arg.index()).collect_vec()
+        );
+        assert!(
+            LLHDUtils::rename_inst_data(&unit[add2_inst], |_value| None, Some, Some).is_none(),
+            "Unmapped argument should fail the rename."
+        );
+    }
+
     #[test]
     fn get_last_llhd_unit_inst() {
         let unit_data = utilities::build_entity_alpha(UnitName::anonymous(0));
@@ -3,6 +3,7 @@ pub(crate) mod utils;
 #[cfg(test)]
 mod tests {
     use itertools::Itertools;
+    use llhd::ir::Unit;
 
     use crate::llhd::LLHDUtils;
 
@@ -28,4 +29,38 @@ mod tests {
             "There should be 3 args present in second unit."
         );
     }
+
+    #[test]
+    fn clone_and_rebuild_unit_data() {
+        let module = utilities::load_llhd_module("testbench_example1.llhd");
+        let unit = module.units().collect_vec()[0];
+        let unit_data = LLHDUtils::clone_unit_data(unit.data());
+        let copy = Unit::new(unit.id(), &unit_data);
+        assert_eq!(unit.to_string(), copy.to_string());
+        assert_eq!(
+            unit.all_insts().collect_vec(),
+            copy.all_insts().collect_vec(),
+            "Cloned unit should keep its inst ids."
+        );
+
+        let mut sig = unit.sig().to_owned();
+        let arg = sig.add_input(llhd::int_ty(1));
+        let unit_data = LLHDUtils::rebuild_unit_data(&unit, sig);
+        let rebuilt = Unit::new(unit.id(), &unit_data);
+        assert_eq!(
+            Some(rebuilt.arg_value(arg)),
+            rebuilt.input_args().last(),
+            "Added input should be the last input."
+        );
+        assert_eq!(unit.all_insts().count(), rebuilt.all_insts().count());
+        assert_eq!(
+            unit.all_insts()
+                .map(|inst| unit[inst].opcode())
+                .collect_vec(),
+            rebuilt
+                .all_insts()
+                .map(|inst| rebuilt[inst].opcode())
+                .collect_vec()
+        );
+    }
 }
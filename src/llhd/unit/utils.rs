@@ -1,3 +1,5 @@
+use std::collections::{HashMap, HashSet};
+
 use llhd::ir::prelude::*;
 
 use crate::llhd::{LLHDDef, LLHDUtils};
@@ -21,6 +23,101 @@ impl LLHDUtils {
             .chain(unit.output_args())
             .map(|arg| (unit.id(), arg))
     }
+
+    /// Exact copy of `unit_data`, ids and free slots included, which `UnitData` only offers
+    /// through serde.
+    pub(crate) fn clone_unit_data(unit_data: &UnitData) -> UnitData {
+        let mut bytes = Vec::new();
+        ciborium::into_writer(unit_data, &mut bytes).expect("UnitData should serialize.");
+        ciborium::from_reader(bytes.as_slice()).expect("Serialized UnitData should deserialize.")
+    }
+
+    /// Rebuilds `unit` with the signature `sig`, which extends the signature of `unit` by
+    /// trailing inputs and outputs, e.g. to add a port.
+    ///
+    /// Insts, blocks and values are numbered anew in layout order, names and hints are kept.
+    pub(crate) fn rebuild_unit_data(unit: &Unit, sig: Signature) -> UnitData {
+        let mut unit_data = UnitData::new(unit.kind(), unit.name().to_owned(), sig);
+        let mut builder = UnitBuilder::new_anonymous(&mut unit_data);
+        if let Some(entry) = builder.first_block() {
+            builder.delete_block(entry);
+        }
+        let mut values = unit
+            .input_args()
+            .zip(builder.input_args())
+            .chain(unit.output_args().zip(builder.output_args()))
+            .collect::<HashMap<_, _>>();
+        let ext_units = unit
+            .extern_units()
+            .map(|(ext_unit, ext_unit_data)| {
+                let new_ext_unit =
+                    builder.add_extern(ext_unit_data.name.to_owned(), ext_unit_data.sig.to_owned());
+                (ext_unit, new_ext_unit)
+            })
+            .collect::<HashMap<_, _>>();
+        let blocks = unit
+            .blocks()
+            .map(|block| {
+                let new_block = builder.block();
+                if let Some(name) = unit.get_block_name(block) {
+                    builder.set_block_name(new_block, name.to_owned());
+                }
+                if let Some(hint) = unit.get_anonymous_block_hint(block) {
+                    builder.set_anonymous_block_hint(new_block, hint);
+                }
+                (block, new_block)
+            })
+            .collect::<HashMap<_, _>>();
+
+        // Results read before their definition in layout order, e.g. by a `phi`, start out as
+        // placeholders.
+        let mut defined = values.keys().copied().collect::<HashSet<_>>();
+        let mut placeholders = Vec::new();
+        for inst in unit.all_insts() {
+            for arg in unit[inst].args() {
+                if defined.insert(*arg) {
+                    let placeholder = builder.add_placeholder(unit.value_type(*arg));
+                    values.insert(*arg, placeholder);
+                    placeholders.push((*arg, placeholder));
+                }
+            }
+            if let Some(result) = unit.get_inst_result(inst) {
+                defined.insert(result);
+            }
+        }
+        for block in unit.blocks() {
+            builder.append_to(blocks[&block]);
+            for inst in unit.insts(block) {
+                let inst_data = Self::rename_inst_data(
+                    &unit[inst],
+                    |value| values.get(&value).copied(),
+                    |bb| blocks.get(&bb).copied(),
+                    |ext_unit| ext_units.get(&ext_unit).copied(),
+                )
+                .expect("Operands of a unit should be defined within it.");
+                let new_inst = builder.build_inst(inst_data, unit.inst_type(inst));
+                if let Some(location) = unit.location_hint(inst) {
+                    builder.set_location_hint(new_inst, location);
+                }
+                if let Some(result) = unit.get_inst_result(inst) {
+                    values.insert(result, builder.inst_result(new_inst));
+                }
+            }
+        }
+        for (value, placeholder) in placeholders {
+            builder.replace_use(placeholder, values[&value]);
+            builder.remove_placeholder(placeholder);
+        }
+        for (value, new_value) in values {
+            if let Some(name) = unit.get_name(value) {
+                builder.set_name(new_value, name.to_owned());
+            }
+            if let Some(hint) = unit.get_anonymous_hint(value) {
+                builder.set_anonymous_hint(new_value, hint);
+            }
+        }
+        unit_data
+    }
 }
 
 // TODO: Test fixture
@@ -4,6 +4,7 @@ mod inst;
 pub(crate) mod unit;
 pub use unit::LLHDEgglogFacts;
 pub mod llhd;
+pub mod placement;
 pub mod rules;
 pub mod schedules;
 
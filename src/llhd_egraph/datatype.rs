@@ -1,10 +1,12 @@
-use egglog::ast::{Symbol, Variant, DUMMY_SPAN};
+use std::collections::HashMap;
+
+use egglog::ast::{Command, Symbol, Variant, DUMMY_SPAN};
 use itertools::Itertools;
 use llhd::ir::Opcode;
 
 use super::egglog_names::{LLHD_UNIT_FIELD, LLHD_VALUE_DATATYPE, LLHD_VALUE_REF_FIELD};
 use super::inst::opcode::opcode_symbol;
-use super::{inst, unit};
+use super::{inst, placement, unit};
 use crate::egraph::sorts::EgglogSorts;
 use crate::egraph::EgglogCommandList;
 
@@ -20,6 +22,30 @@ impl LLHDEgglogSorts {
         unit_type_sorts.append(&mut unit_sorts);
         Self(unit_type_sorts)
     }
+
+    /// LLHD DFG Sorts, plus `Point`/`BBox` Datatypes for Inst Placement Facts
+    pub fn llhd_dfg_with_placement() -> Self {
+        let mut llhd_dfg_sorts = Self::llhd_dfg().0;
+        llhd_dfg_sorts.append(&mut placement::placement_sorts());
+        Self(llhd_dfg_sorts)
+    }
+
+    /// Sets the extraction cost of each opcode variant found in `opcode_costs`.
+    pub fn with_costs(mut self, opcode_costs: &HashMap<Opcode, usize>) -> Self {
+        self.0.iter_mut().for_each(|command| {
+            if let Command::Datatype { variants, .. } = command {
+                variants.iter_mut().for_each(|variant| {
+                    if let Some((_opcode, cost)) = opcode_costs
+                        .iter()
+                        .find(|(opcode, _cost)| opcode_symbol(**opcode) == variant.name)
+                    {
+                        variant.cost = Some(*cost);
+                    }
+                });
+            }
+        });
+        self
+    }
 }
 
 impl Default for LLHDEgglogSorts {
@@ -94,4 +120,40 @@ mod tests {
             egraph_msgs.err().unwrap()
         );
     }
+
+    #[test]
+    fn valid_dfg_with_placement_llhd_egglog_datatypes() {
+        let llhd_dfg_sort = LLHDEgglogSorts::llhd_dfg_with_placement();
+        let mut egraph = EGraph::default();
+        let egraph_msgs = egraph.run_program(llhd_dfg_sort.into());
+        assert!(
+            egraph_msgs.is_ok(),
+            "Error loading LLHD DFG Datatype with Placement. Error: {:?}",
+            egraph_msgs.err().unwrap()
+        );
+    }
+
+    #[test]
+    fn llhd_egglog_datatypes_with_opcode_costs() {
+        let opcode_costs = HashMap::from([(Opcode::And, 7), (Opcode::Or, 3)]);
+        let llhd_dfg_sort = LLHDEgglogSorts::llhd_dfg().with_costs(&opcode_costs);
+        let variant_costs: HashMap<Symbol, Option<usize>> =
+            EgglogCommandList::from(llhd_dfg_sort.clone())
+                .into_iter()
+                .filter_map(|command| match command {
+                    Command::Datatype { variants, .. } => Some(variants),
+                    _ => None,
+                })
+                .flatten()
+                .map(|variant| (variant.name, variant.cost))
+                .collect();
+        assert_eq!(Some(7), variant_costs[&opcode_symbol(Opcode::And)]);
+        assert_eq!(Some(3), variant_costs[&opcode_symbol(Opcode::Or)]);
+        assert_eq!(None, variant_costs[&opcode_symbol(Opcode::Xor)]);
+        let mut egraph = EGraph::default();
+        assert!(
+            egraph.run_program(llhd_dfg_sort.into()).is_ok(),
+            "Costed LLHD DFG Datatype should load."
+        );
+    }
 }
@@ -40,3 +40,10 @@ pub(super) const LLHD_EXT_UNIT_FIELD: &str = "ExtUnit";
 pub(super) const LLHD_EXT_UNIT_DATATYPE: &str = "LLHDExtUnit";
 pub(super) const LLHD_DFG_DATATYPE: &str = "LLHDDFG";
 pub(super) const LLHD_CFG_DATATYPE: &str = "LLHDCFG";
+pub(super) const LLHD_POINT_DATATYPE: &str = "Point";
+pub(super) const LLHD_POINT_FIELD: &str = "P";
+pub(super) const LLHD_BBOX_DATATYPE: &str = "BBox";
+pub(super) const LLHD_BBOX_FIELD: &str = "Box";
+pub(super) const LLHD_INST_PLACEMENT_DATATYPE: &str = "LLHDInstPlacement";
+pub(super) const LLHD_INST_BBOX_FIELD: &str = "InstBBox";
+pub(super) const LLHD_PLACEMENT_DIV_EXTRACT_RULESET: &str = "div-ext-placement";
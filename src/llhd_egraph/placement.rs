@@ -0,0 +1,238 @@
+use std::str::FromStr;
+
+use egglog::ast::{
+    Action, Command, Expr, GenericCommand, GenericExpr, Literal, Symbol, Variant, DUMMY_SPAN,
+};
+use euclid::default::{Box2D, Point2D};
+use llhd::ir::{Inst, UnitId};
+use llhd::table::TableKey;
+
+use crate::egraph::egglog_names::{EGGLOG_I64_SORT, EGGLOG_U64_SORT};
+use crate::egraph::EgglogCommandList;
+use crate::llhd_egraph::egglog_names::*;
+use crate::llhd_egraph::rules::LLHDEgglogRules;
+use crate::llhd_egraph::unit::unit_symbol;
+use crate::llhd_world::placement::LLHDInstPlacement;
+use crate::llhd_world::world::{LLHDWorld, LLHDWorldError};
+
+fn datatype(name: &str, variant_name: &str, types: &[&str]) -> Command {
+    Command::Datatype {
+        span: DUMMY_SPAN.clone(),
+        name: Symbol::new(name),
+        variants: vec![Variant {
+            span: DUMMY_SPAN.clone(),
+            name: Symbol::new(variant_name),
+            types: types.iter().map(|ty| Symbol::new(*ty)).collect(),
+            cost: None,
+        }],
+    }
+}
+
+/// ```text
+/// (datatype Point (P i64 i64))
+/// (datatype BBox (Box Point Point))
+/// (datatype LLHDInstPlacement (InstBBox u64 BBox))
+/// ```
+pub(in crate::llhd_egraph) fn placement_sorts() -> EgglogCommandList {
+    vec![
+        datatype(
+            LLHD_POINT_DATATYPE,
+            LLHD_POINT_FIELD,
+            &[EGGLOG_I64_SORT, EGGLOG_I64_SORT],
+        ),
+        datatype(
+            LLHD_BBOX_DATATYPE,
+            LLHD_BBOX_FIELD,
+            &[LLHD_POINT_DATATYPE, LLHD_POINT_DATATYPE],
+        ),
+        datatype(
+            LLHD_INST_PLACEMENT_DATATYPE,
+            LLHD_INST_BBOX_FIELD,
+            &[EGGLOG_U64_SORT, LLHD_BBOX_DATATYPE],
+        ),
+    ]
+}
+
+fn coordinate_expr(coordinate: usize) -> Expr {
+    GenericExpr::Lit(
+        DUMMY_SPAN.clone(),
+        Literal::Int(
+            i64::try_from(coordinate).expect("Out-of-bound value for usize -> i64 conversion."),
+        ),
+    )
+}
+
+fn point_expr(point: &Point2D<usize>) -> Expr {
+    GenericExpr::Call(
+        DUMMY_SPAN.clone(),
+        Symbol::new(LLHD_POINT_FIELD),
+        vec![coordinate_expr(point.x), coordinate_expr(point.y)],
+    )
+}
+
+pub(in crate::llhd_egraph) fn inst_bbox_expr(inst_id: Inst, bb: &Box2D<usize>) -> Expr {
+    let inst_id_literal = GenericExpr::Lit(
+        DUMMY_SPAN.clone(),
+        Literal::UInt(
+            u64::try_from(inst_id.index())
+                .expect("Out-of-bound value for usize -> u64 conversion."),
+        ),
+    );
+    let bbox_expr = GenericExpr::Call(
+        DUMMY_SPAN.clone(),
+        Symbol::new(LLHD_BBOX_FIELD),
+        vec![point_expr(&bb.min), point_expr(&bb.max)],
+    );
+    GenericExpr::Call(
+        DUMMY_SPAN.clone(),
+        Symbol::new(LLHD_INST_BBOX_FIELD),
+        vec![inst_id_literal, bbox_expr],
+    )
+}
+
+/// One `(let unit_<name>_<inst>_bbox (InstBBox <inst> (Box (P x1 y1) (P x2 y2))))` per placed inst.
+///
+/// `InstBBox` is keyed by the same inst id as the gate variants of the unit's DFG.
+pub(in crate::llhd_egraph) fn placement_facts(
+    llhd_world: &LLHDWorld,
+    unit_id: UnitId,
+) -> Result<EgglogCommandList, LLHDWorldError> {
+    let placements = llhd_world.unit_program_inst::<LLHDInstPlacement>(unit_id)?;
+    let unit_prefix = unit_symbol(llhd_world.module().unit(unit_id)).to_string();
+    Ok(placements
+        .map(|((_unit_id, inst_id), _inst_component, placement)| {
+            let bbox_symbol = Symbol::new(format!("{}_{}_bbox", unit_prefix, inst_id));
+            GenericCommand::Action(Action::Let(
+                DUMMY_SPAN.clone(),
+                bbox_symbol,
+                inst_bbox_expr(inst_id, placement.bb()),
+            ))
+        })
+        .collect())
+}
+
+/// Divisor Extraction Gated by Placement
+///
+/// `a*c + b*c -> (a + b)*c` only fires when the half-perimeter of the bounding box enclosing both
+/// `And` gates, which get merged into a single `Or`, is at most `max_merged_hpwl`.
+pub fn placement_div_extract_rules(max_merged_hpwl: usize) -> LLHDEgglogRules {
+    let rules = format!(
+        "(ruleset {ruleset})
+        (rule (
+            (= or_gate (Or or_id or_ty (And and_id1 and_ty a c) (And and_id2 and_ty b c)))
+            ({inst_bbox} and_id1 ({bbox} ({point} x1 y1) ({point} x2 y2)))
+            ({inst_bbox} and_id2 ({bbox} ({point} x3 y3) ({point} x4 y4)))
+            (<= (+ (- (max x2 x4) (min x1 x3)) (- (max y2 y4) (min y1 y3))) {max_merged_hpwl})
+        )(
+            (union or_gate (And or_id and_ty (Or and_id1 or_ty a b) c))
+        ) :ruleset {ruleset})",
+        ruleset = LLHD_PLACEMENT_DIV_EXTRACT_RULESET,
+        inst_bbox = LLHD_INST_BBOX_FIELD,
+        bbox = LLHD_BBOX_FIELD,
+        point = LLHD_POINT_FIELD,
+        max_merged_hpwl = max_merged_hpwl,
+    );
+    LLHDEgglogRules::from_str(&rules).expect("Placement divisor extraction rules should parse.")
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+    use llhd::ir::Opcode;
+
+    use super::*;
+    use crate::llhd::module::LLHDModule;
+    use crate::llhd_egraph::datatype::LLHDEgglogSorts;
+    use crate::llhd_egraph::llhd::{LLHDEGraph, LLHDEgglogProgram};
+    use crate::llhd_egraph::unit::LLHDEgglogFacts;
+
+    fn placed_llhd_world() -> (LLHDWorld, UnitId, Vec<Inst>) {
+        let test_module = utilities::load_llhd_module("2and_1or_common.llhd");
+        let mut llhd_world = LLHDWorld::new(LLHDModule::from(test_module));
+        let unit_id = llhd_world.module().units().next().unwrap().id();
+        let and_insts = llhd_world
+            .module()
+            .unit(unit_id)
+            .all_insts()
+            .filter(|inst| llhd_world.module().unit(unit_id)[*inst].opcode() == Opcode::And)
+            .collect_vec();
+        and_insts.iter().enumerate().for_each(|(ii, inst)| {
+            let bb = Box2D::new(
+                Point2D::new(ii * 2400, 0),
+                Point2D::new((ii + 1) * 2400, 3330),
+            );
+            llhd_world
+                .set_inst(unit_id, *inst, LLHDInstPlacement::new(bb))
+                .unwrap();
+        });
+        (llhd_world, unit_id, and_insts)
+    }
+
+    fn divisor_extracted(max_merged_hpwl: usize) -> bool {
+        let (llhd_world, unit_id, _and_insts) = placed_llhd_world();
+        let facts = LLHDEgglogFacts::from_unit(&llhd_world.module().unit(unit_id))
+            .with_placement(&llhd_world, unit_id)
+            .unwrap();
+        let program = LLHDEgglogProgram::builder()
+            .sorts(LLHDEgglogSorts::llhd_dfg_with_placement())
+            .rules(placement_div_extract_rules(max_merged_hpwl))
+            .facts(facts)
+            .build();
+        let mut egraph = LLHDEGraph::try_from(program).unwrap();
+        let check_cmds = egraph
+            .parse_program(
+                None,
+                "(run-schedule (saturate div-ext-placement))
+                (check (And or_id and_ty (Or and_id or_ty a b) c))",
+            )
+            .unwrap();
+        egraph.run_program(check_cmds).is_ok()
+    }
+
+    #[test]
+    fn placement_facts_per_placed_inst() {
+        let (llhd_world, unit_id, and_insts) = placed_llhd_world();
+        let facts = placement_facts(&llhd_world, unit_id)
+            .unwrap()
+            .into_iter()
+            .map(|fact| fact.to_string())
+            .collect_vec();
+        assert_eq!(2, facts.len(), "Both and gates should have a bbox fact.");
+        let first_and_bbox = format!(
+            "(InstBBox {} (Box (P 0 0) (P 2400 3330)))",
+            and_insts[0].index()
+        );
+        assert!(
+            facts.iter().any(|fact| fact.contains(&first_and_bbox)),
+            "First and gate should be placed at the origin."
+        );
+    }
+
+    #[test]
+    fn placement_div_extract_within_threshold() {
+        assert!(
+            divisor_extracted(10_000),
+            "Merged bbox HPWL(4800 + 3330) is within the threshold, divisor should be extracted."
+        );
+    }
+
+    #[test]
+    fn placement_div_extract_outside_threshold() {
+        assert!(
+            !divisor_extracted(5_000),
+            "Merged bbox HPWL(4800 + 3330) exceeds the threshold, divisor should not be extracted."
+        );
+    }
+
+    #[test]
+    fn placement_facts_stale_unit() {
+        let (llhd_world, unit_id, _and_insts) = placed_llhd_world();
+        let stale_unit_id = UnitId::new(unit_id.index() + 1);
+        assert_eq!(
+            Some(LLHDWorldError::MissingUnit(stale_unit_id)),
+            LLHDEgglogFacts::default()
+                .with_placement(&llhd_world, stale_unit_id)
+                .err()
+        );
+    }
+}
@@ -20,6 +20,8 @@ use crate::llhd::LLHDUtils;
 use crate::llhd_egraph::datatype::unit_root_variant_symbol;
 use crate::llhd_egraph::egglog_names::*;
 use crate::llhd_egraph::inst::*;
+use crate::llhd_egraph::placement;
+use crate::llhd_world::world::{LLHDWorld, LLHDWorldError};
 
 #[derive(Debug, Clone, Default)]
 pub struct LLHDEgglogFacts(EgglogCommandList);
@@ -37,6 +39,19 @@ impl LLHDEgglogFacts {
     pub fn from_unit(unit: &Unit) -> Self {
         Self(vec![GenericCommand::Action(from_unit(unit))])
     }
+
+    /// Append `InstBBox` facts for every placed inst of a unit.
+    ///
+    /// Requires the sorts of `LLHDEgglogSorts::llhd_dfg_with_placement`.
+    pub fn with_placement(
+        mut self,
+        llhd_world: &LLHDWorld,
+        unit_id: UnitId,
+    ) -> Result<Self, LLHDWorldError> {
+        self.0
+            .append(&mut placement::placement_facts(llhd_world, unit_id)?);
+        Ok(self)
+    }
 }
 
 impl From<LLHDEgglogFacts> for EgglogCommandList {
@@ -1,6 +1,10 @@
+pub mod cell_function;
+pub(crate) mod characterization;
 pub mod circuit_library;
 pub mod gds_library;
 pub mod lef_library;
+pub mod liberty_library;
+pub mod lvs;
 
 pub use builder::*;
 use typestate::typestate;
@@ -9,6 +13,7 @@ use typestate::typestate;
 pub mod builder {
     use super::gds_library::LGdsLibrary;
     use super::lef_library::LLefLibrary;
+    use super::liberty_library::LLibertyLibrary;
     use crate::circuit::netlist::{AnalogCircuit, NetlistFlow};
     use crate::llhd::module::LLHDModule;
 
@@ -16,6 +21,7 @@ pub mod builder {
     #[automaton]
     pub struct TechnologyFlow {
         lef: LLefLibrary,
+        liberty: LLibertyLibrary,
         netlist: NetlistFlow<AnalogCircuit>,
         gds: LGdsLibrary,
         module: LLHDModule,
@@ -43,6 +49,7 @@ pub mod builder {
     }
 
     pub trait Analog {
+        fn load_liberty(self, library_liberty: LLibertyLibrary) -> Analog;
         fn construct_circuit(self, netlist: NetlistFlow<AnalogCircuit>) -> Physical;
     }
 
@@ -65,6 +72,7 @@ pub mod builder {
         fn load_lef(self, lef: LLefLibrary) -> TechnologyFlow<Analog> {
             TechnologyFlow::<Analog> {
                 lef,
+                liberty: self.liberty,
                 netlist: self.netlist,
                 gds: self.gds,
                 module: self.module,
@@ -74,6 +82,10 @@ pub mod builder {
     }
 
     impl AnalogState for TechnologyFlow<Analog> {
+        fn load_liberty(self, liberty: LLibertyLibrary) -> TechnologyFlow<Analog> {
+            TechnologyFlow::<Analog> { liberty, ..self }
+        }
+
         fn construct_circuit(
             self,
             _netlist: NetlistFlow<AnalogCircuit>,
@@ -99,6 +111,7 @@ pub mod builder {
 mod tests {
     use super::gds_library::LGdsLibrary;
     use super::lef_library::LLefLibrary;
+    use super::liberty_library::LLibertyLibrary;
     use super::*;
     use crate::circuit::netlist::*;
 
@@ -106,10 +119,12 @@ mod tests {
     #[should_panic(expected = "not yet implemented")]
     fn build_technology_flow() {
         let lef = LLefLibrary::default();
+        let liberty = LLibertyLibrary::default();
         let netlist = NetlistFlow::default();
         let gds = LGdsLibrary::default();
         TechnologyFlow::unbound_library()
             .load_lef(lef)
+            .load_liberty(liberty)
             .construct_circuit(netlist)
             .load_gds(gds)
             .bind_units();
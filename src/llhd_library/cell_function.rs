@@ -0,0 +1,626 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use derive_getters::Getters;
+use llhd::ir::prelude::*;
+use llhd::TimeValue;
+use typed_builder::TypedBuilder;
+
+use super::characterization::CellNetlist;
+use crate::circuit::graph::nodes::{ElementHNode, ElementKind};
+use crate::circuit::graph::LCircuit;
+use crate::circuit::models::ModelKind;
+use crate::llhd::common::build_unit_name;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CellFunctionError {
+    /// Pull network of a stage output that does not reduce to series and parallel transistors.
+    NotSeriesParallel(String),
+    /// Pull-up and pull-down networks of a stage output conducting at the same time, or neither.
+    NotComplementary(String),
+    /// Stage outputs feeding back into their own gates, e.g. a latch.
+    CombinationalLoop(String),
+    NoOutputs(String),
+}
+
+impl fmt::Display for CellFunctionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotSeriesParallel(net) => {
+                write!(f, "Pull network of {} is not series-parallel", net)
+            }
+            Self::NotComplementary(net) => {
+                write!(f, "Pull-up and pull-down of {} are not complementary", net)
+            }
+            Self::CombinationalLoop(net) => write!(f, "Combinational loop through {}", net),
+            Self::NoOutputs(cell) => write!(f, "No CMOS stage drives a port of {}", cell),
+        }
+    }
+}
+
+impl std::error::Error for CellFunctionError {}
+
+/// Boolean function over net names, written with the Liberty operators(`!`, `&`, `|`).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LogicFunction {
+    Input(String),
+    Not(Box<LogicFunction>),
+    And(Vec<LogicFunction>),
+    Or(Vec<LogicFunction>),
+}
+
+impl LogicFunction {
+    pub fn evaluate(&self, levels: &BTreeMap<String, bool>) -> Option<bool> {
+        match self {
+            Self::Input(net) => levels.get(net).copied(),
+            Self::Not(operand) => operand.evaluate(levels).map(|level| !level),
+            Self::And(operands) => operands.iter().try_fold(true, |level, operand| {
+                operand.evaluate(levels).map(|operand| level && operand)
+            }),
+            Self::Or(operands) => operands.iter().try_fold(false, |level, operand| {
+                operand.evaluate(levels).map(|operand| level || operand)
+            }),
+        }
+    }
+
+    pub fn inputs(&self) -> BTreeSet<&str> {
+        match self {
+            Self::Input(net) => BTreeSet::from([net.as_str()]),
+            Self::Not(operand) => operand.inputs(),
+            Self::And(operands) | Self::Or(operands) => operands
+                .iter()
+                .flat_map(|operand| operand.inputs())
+                .collect(),
+        }
+    }
+
+    /// `self` with every `Input` found in `functions` replaced by its function.
+    pub fn substitute(&self, functions: &BTreeMap<String, LogicFunction>) -> Self {
+        match self {
+            Self::Input(net) => functions
+                .get(net)
+                .cloned()
+                .unwrap_or_else(|| self.to_owned()),
+            Self::Not(operand) => Self::Not(Box::new(operand.substitute(functions))),
+            Self::And(operands) => Self::And(
+                operands
+                    .iter()
+                    .map(|operand| operand.substitute(functions))
+                    .collect(),
+            ),
+            Self::Or(operands) => Self::Or(
+                operands
+                    .iter()
+                    .map(|operand| operand.substitute(functions))
+                    .collect(),
+            ),
+        }
+    }
+
+    fn series(self, other: Self) -> Self {
+        Self::And(
+            self.operands_of(false)
+                .chain(other.operands_of(false))
+                .collect(),
+        )
+    }
+
+    fn parallel(self, other: Self) -> Self {
+        Self::Or(
+            self.operands_of(true)
+                .chain(other.operands_of(true))
+                .collect(),
+        )
+    }
+
+    /// Operands of a nested `Or`(`or` set) or `And`, or `self` alone.
+    fn operands_of(self, or: bool) -> Box<dyn Iterator<Item = Self>> {
+        match self {
+            Self::Or(operands) if or => Box::new(operands.into_iter()),
+            Self::And(operands) if !or => Box::new(operands.into_iter()),
+            function => Box::new(std::iter::once(function)),
+        }
+    }
+
+    fn build(&self, builder: &mut UnitBuilder, levels: &BTreeMap<String, Value>) -> Value {
+        match self {
+            Self::Input(net) => levels[net],
+            Self::Not(operand) => {
+                let operand = operand.build(builder, levels);
+                builder.ins().not(operand)
+            }
+            Self::And(operands) | Self::Or(operands) => {
+                let values = operands
+                    .iter()
+                    .map(|operand| operand.build(builder, levels))
+                    .collect::<Vec<_>>();
+                values[1..].iter().fold(values[0], |value, operand| {
+                    if matches!(self, Self::And(_)) {
+                        builder.ins().and(value, *operand)
+                    } else {
+                        builder.ins().or(value, *operand)
+                    }
+                })
+            }
+        }
+    }
+}
+
+impl fmt::Display for LogicFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (operands, operator) = match self {
+            Self::Input(net) => return write!(f, "{}", net),
+            Self::Not(operand) => return write!(f, "!{}", operand),
+            Self::And(operands) => (operands, "&"),
+            Self::Or(operands) => (operands, "|"),
+        };
+        write!(f, "(")?;
+        for (index, operand) in operands.iter().enumerate() {
+            if index > 0 {
+                write!(f, "{}", operator)?;
+            }
+            write!(f, "{}", operand)?;
+        }
+        write!(f, ")")
+    }
+}
+
+#[derive(Debug, Clone, TypedBuilder, Getters)]
+pub struct CellFunctionConfig {
+    #[builder(default = vec!["VPWR".to_owned(), "VPB".to_owned()])]
+    power_pins: Vec<String>,
+    #[builder(default = vec!["VGND".to_owned(), "VNB".to_owned()])]
+    ground_pins: Vec<String>,
+}
+
+impl Default for CellFunctionConfig {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+/// Boolean function of a static CMOS cell, recovered from its transistor netlist.
+///
+/// Every net joining n-channel and p-channel drains is a stage output, the complement of its
+/// pull-down network. `stages` are over input pins and other stage outputs, and `outputs` over
+/// input pins only.
+#[derive(Debug, Clone, PartialEq, Eq, Getters)]
+pub struct CellFunction {
+    name: String,
+    inputs: Vec<String>,
+    stages: BTreeMap<String, LogicFunction>,
+    outputs: BTreeMap<String, LogicFunction>,
+    /// `stages` ordered from the inputs towards the outputs.
+    stage_order: Vec<String>,
+}
+
+impl CellFunction {
+    pub fn from_cell(
+        cell: &CellNetlist,
+        config: &CellFunctionConfig,
+    ) -> Result<Self, CellFunctionError> {
+        Self::extract(cell.name(), cell.ports(), cell.elements().iter(), config)
+    }
+
+    /// Function of a flat `circuit`, `ports` being its pins in order.
+    pub fn from_circuit(
+        name: &str,
+        ports: &[String],
+        circuit: &LCircuit,
+        config: &CellFunctionConfig,
+    ) -> Result<Self, CellFunctionError> {
+        let elements = circuit.elements().map(|(_element_id, element)| element);
+        Self::extract(name, ports, elements, config)
+    }
+
+    fn extract<'e>(
+        name: &str,
+        ports: &[String],
+        elements: impl Iterator<Item = &'e ElementHNode>,
+        config: &CellFunctionConfig,
+    ) -> Result<Self, CellFunctionError> {
+        let (mut pull_up_edges, mut pull_down_edges) = (Vec::new(), Vec::new());
+        for element in elements {
+            let (
+                ElementKind::MosTransistor | ElementKind::Subcircuit,
+                [drain, gate, source, _body],
+            ) = (element.kind, element.terminals.as_slice())
+            else {
+                continue;
+            };
+            let Some(kind) = element.value.as_deref().and_then(ModelKind::transistor) else {
+                continue;
+            };
+            let gate = LogicFunction::Input(gate.to_string());
+            let ends = [drain.to_string(), source.to_string()];
+            match kind {
+                ModelKind::Pmos => pull_up_edges.push(PullEdge {
+                    ends,
+                    conducts: LogicFunction::Not(Box::new(gate)),
+                }),
+                _ => pull_down_edges.push(PullEdge {
+                    ends,
+                    conducts: gate,
+                }),
+            }
+        }
+        let channel_nets = |edges: &[PullEdge]| {
+            edges
+                .iter()
+                .flat_map(|edge| edge.ends.iter().cloned())
+                .collect::<BTreeSet<_>>()
+        };
+        let rails = config
+            .power_pins
+            .iter()
+            .chain(&config.ground_pins)
+            .cloned()
+            .collect::<BTreeSet<_>>();
+        let stage_nets = channel_nets(&pull_up_edges)
+            .intersection(&channel_nets(&pull_down_edges))
+            .filter(|net| !rails.contains(*net))
+            .cloned()
+            .collect::<BTreeSet<_>>();
+
+        let mut stages = BTreeMap::new();
+        for net in &stage_nets {
+            let pull_down = pull_network(&pull_down_edges, net, &config.ground_pins, &stage_nets)?;
+            let pull_up = pull_network(&pull_up_edges, net, &config.power_pins, &stage_nets)?;
+            let complementary = truth_table(&[&pull_down, &pull_up])
+                .into_iter()
+                .all(|levels| pull_down.evaluate(&levels) != pull_up.evaluate(&levels));
+            if !complementary {
+                return Err(CellFunctionError::NotComplementary(net.to_owned()));
+            }
+            stages.insert(net.to_owned(), LogicFunction::Not(Box::new(pull_down)));
+        }
+
+        let stage_order = stage_order(&stages)?;
+        let mut flat_stages: BTreeMap<String, LogicFunction> = BTreeMap::new();
+        for stage in &stage_order {
+            let flat = stages[stage].substitute(&flat_stages);
+            flat_stages.insert(stage.to_owned(), flat);
+        }
+        let outputs = ports
+            .iter()
+            .filter_map(|port| {
+                flat_stages
+                    .get(port)
+                    .map(|function| (port.to_owned(), function.to_owned()))
+            })
+            .collect::<BTreeMap<_, _>>();
+        if outputs.is_empty() {
+            return Err(CellFunctionError::NoOutputs(name.to_owned()));
+        }
+        let inputs = ports
+            .iter()
+            .filter(|port| {
+                outputs
+                    .values()
+                    .any(|function| function.inputs().contains(port.as_str()))
+            })
+            .cloned()
+            .collect();
+        Ok(Self {
+            name: name.to_owned(),
+            inputs,
+            stages,
+            outputs,
+            stage_order,
+        })
+    }
+
+    /// Levels of every output for the input pin `levels`.
+    pub fn evaluate(&self, levels: &BTreeMap<String, bool>) -> BTreeMap<String, bool> {
+        self.outputs
+            .iter()
+            .filter_map(|(pin, function)| {
+                function
+                    .evaluate(levels)
+                    .map(|level| (pin.to_owned(), level))
+            })
+            .collect()
+    }
+
+    /// LLHD entity with an `i1$` signal per input and output pin, one gate per stage operator.
+    pub fn entity(&self) -> UnitData {
+        let mut sig = Signature::new();
+        let input_args = self
+            .inputs
+            .iter()
+            .map(|_pin| sig.add_input(llhd::signal_ty(llhd::int_ty(1))))
+            .collect::<Vec<_>>();
+        let output_args = self
+            .outputs
+            .keys()
+            .map(|_pin| sig.add_output(llhd::signal_ty(llhd::int_ty(1))))
+            .collect::<Vec<_>>();
+        let mut entity = UnitData::new(UnitKind::Entity, build_unit_name(&self.name), sig);
+        {
+            let mut builder = UnitBuilder::new_anonymous(&mut entity);
+            let mut levels = BTreeMap::new();
+            for (pin, arg) in self.inputs.iter().zip(input_args) {
+                let signal = builder.unit().arg_value(arg);
+                builder.set_name(signal, pin.to_owned());
+                let level = builder.ins().prb(signal);
+                levels.insert(pin.to_owned(), level);
+            }
+            for stage in &self.stage_order {
+                let level = self.stages[stage].build(&mut builder, &levels);
+                levels.insert(stage.to_owned(), level);
+            }
+            let delay = builder.ins().const_time(TimeValue::zero());
+            for (pin, arg) in self.outputs.keys().zip(output_args) {
+                let signal = builder.unit().arg_value(arg);
+                builder.set_name(signal, pin.to_owned());
+                builder.ins().drv(signal, levels[pin], delay);
+            }
+        }
+        Unit::new_anonymous(&entity).verify();
+        entity
+    }
+}
+
+/// Transistor channel between two nets, conducting when `conducts` is true.
+#[derive(Debug, Clone)]
+struct PullEdge {
+    ends: [String; 2],
+    conducts: LogicFunction,
+}
+
+/// Conduction function between `output` and any of `rails`, by series and parallel reduction of
+/// the channels reachable from `output` without crossing a rail or another stage output.
+fn pull_network(
+    edges: &[PullEdge],
+    output: &str,
+    rails: &[String],
+    stage_nets: &BTreeSet<String>,
+) -> Result<LogicFunction, CellFunctionError> {
+    const RAIL: &str = "";
+    let is_rail = |net: &String| rails.contains(net);
+    let mut included = BTreeSet::new();
+    let mut reached = BTreeSet::from([output.to_owned()]);
+    let mut frontier = vec![output.to_owned()];
+    while let Some(net) = frontier.pop() {
+        for (index, edge) in edges.iter().enumerate() {
+            if !edge.ends.contains(&net) || !included.insert(index) {
+                continue;
+            }
+            for end in edge
+                .ends
+                .iter()
+                .filter(|end| **end != net && !is_rail(*end))
+            {
+                if stage_nets.contains(end) && end != output {
+                    return Err(CellFunctionError::NotSeriesParallel(output.to_owned()));
+                }
+                if reached.insert(end.to_owned()) {
+                    frontier.push(end.to_owned());
+                }
+            }
+        }
+    }
+    let mut network = included
+        .into_iter()
+        .map(|index| PullEdge {
+            ends: edges[index].ends.to_owned().map(|end| {
+                if is_rail(&end) {
+                    RAIL.to_owned()
+                } else {
+                    end
+                }
+            }),
+            conducts: edges[index].conducts.to_owned(),
+        })
+        .filter(|edge| edge.ends[0] != edge.ends[1])
+        .collect::<Vec<_>>();
+
+    loop {
+        let mut reduced = false;
+        // Parallel channels between the same nets.
+        for index in 0..network.len() {
+            let ends = sorted_ends(&network[index]);
+            if let Some(other) =
+                (index + 1..network.len()).find(|other| sorted_ends(&network[*other]) == ends)
+            {
+                let parallel = network.remove(other);
+                let edge = &mut network[index];
+                edge.conducts = edge.conducts.to_owned().parallel(parallel.conducts);
+                reduced = true;
+                break;
+            }
+        }
+        if reduced {
+            continue;
+        }
+        // Series channels through an internal net, and dangling internal nets.
+        let mut degrees: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+        network.iter().enumerate().for_each(|(index, edge)| {
+            edge.ends
+                .iter()
+                .for_each(|net| degrees.entry(net.as_str()).or_default().push(index))
+        });
+        let internal = degrees
+            .iter()
+            .find(|(net, incident)| **net != output && **net != RAIL && incident.len() <= 2)
+            .map(|(net, incident)| (net.to_string(), incident.to_owned()));
+        match internal {
+            Some((_net, incident)) if incident.len() == 1 => {
+                network.remove(incident[0]);
+            }
+            Some((net, incident)) => {
+                let second = network.remove(incident[1]);
+                let first = network.remove(incident[0]);
+                let far_end = |edge: &PullEdge| {
+                    edge.ends
+                        .iter()
+                        .find(|end| **end != net)
+                        .cloned()
+                        .unwrap_or_default()
+                };
+                network.push(PullEdge {
+                    ends: [far_end(&first), far_end(&second)],
+                    conducts: first.conducts.series(second.conducts),
+                });
+            }
+            None => break,
+        }
+    }
+    match network.as_slice() {
+        [edge] if sorted_ends(edge) == [RAIL, output] => Ok(edge.conducts.to_owned()),
+        _ => Err(CellFunctionError::NotSeriesParallel(output.to_owned())),
+    }
+}
+
+fn sorted_ends(edge: &PullEdge) -> [&str; 2] {
+    let [a, b] = &edge.ends;
+    if a <= b {
+        [a, b]
+    } else {
+        [b, a]
+    }
+}
+
+/// Every assignment of the inputs of `functions`.
+fn truth_table(functions: &[&LogicFunction]) -> Vec<BTreeMap<String, bool>> {
+    let inputs = functions
+        .iter()
+        .flat_map(|function| function.inputs())
+        .collect::<BTreeSet<_>>();
+    (0..1_usize << inputs.len())
+        .map(|assignment| {
+            inputs
+                .iter()
+                .enumerate()
+                .map(|(bit, input)| (input.to_string(), assignment & (1 << bit) != 0))
+                .collect()
+        })
+        .collect()
+}
+
+/// Stages ordered so every stage follows the stages driving its gates.
+fn stage_order(stages: &BTreeMap<String, LogicFunction>) -> Result<Vec<String>, CellFunctionError> {
+    fn visit<'s>(
+        stage: &'s str,
+        stages: &'s BTreeMap<String, LogicFunction>,
+        visiting: &mut BTreeSet<&'s str>,
+        order: &mut Vec<String>,
+    ) -> Result<(), CellFunctionError> {
+        if order.iter().any(|ordered| ordered == stage) {
+            return Ok(());
+        }
+        if !visiting.insert(stage) {
+            return Err(CellFunctionError::CombinationalLoop(stage.to_owned()));
+        }
+        for input in stages[stage].inputs() {
+            if let Some((driver, _function)) = stages.get_key_value(input) {
+                visit(driver, stages, visiting, order)?;
+            }
+        }
+        visiting.remove(stage);
+        order.push(stage.to_owned());
+        Ok(())
+    }
+
+    let mut order = Vec::new();
+    for stage in stages.keys() {
+        visit(stage, stages, &mut BTreeSet::new(), &mut order)?;
+    }
+    Ok(order)
+}
+
+#[cfg(test)]
+mod tests {
+    use llhd::ir::Opcode;
+
+    use super::*;
+    use crate::llhd_library::characterization::tests::sky130_cell;
+
+    fn levels(pins: &[(&str, bool)]) -> BTreeMap<String, bool> {
+        pins.iter()
+            .map(|(pin, level)| (pin.to_string(), *level))
+            .collect()
+    }
+
+    #[test]
+    fn nand2_function() {
+        let nand2 = sky130_cell("nand2", "sky130_fd_sc_ls__nand2_1");
+        let function = CellFunction::from_cell(&nand2, &CellFunctionConfig::default()).unwrap();
+        assert_eq!(vec!["A", "B"], *function.inputs());
+        assert_eq!(
+            vec!["Y"],
+            function.outputs().keys().cloned().collect::<Vec<_>>()
+        );
+        for (a, b) in [(false, false), (false, true), (true, false), (true, true)] {
+            assert_eq!(
+                Some(&!(a && b)),
+                function.evaluate(&levels(&[("A", a), ("B", b)])).get("Y")
+            );
+        }
+    }
+
+    #[test]
+    fn a211o_two_stage_function() {
+        let a211o = sky130_cell("a211o", "sky130_fd_sc_ls__a211o_2");
+        let function = CellFunction::from_cell(&a211o, &CellFunctionConfig::default()).unwrap();
+        assert_eq!(vec!["A1", "A2", "B1", "C1"], *function.inputs());
+        assert_eq!(vec!["a_85_270#", "X"], *function.stage_order());
+        for assignment in 0..16 {
+            let [a1, a2, b1, c1] = [0, 1, 2, 3].map(|bit| assignment & (1 << bit) != 0);
+            let pins = levels(&[("A1", a1), ("A2", a2), ("B1", b1), ("C1", c1)]);
+            assert_eq!(
+                Some(&((a1 && a2) || b1 || c1)),
+                function.evaluate(&pins).get("X"),
+                "{:?}",
+                pins
+            );
+        }
+    }
+
+    #[test]
+    fn xor2_function() {
+        let xor2 = sky130_cell("xor2", "sky130_fd_sc_ls__xor2_1");
+        let function = CellFunction::from_cell(&xor2, &CellFunctionConfig::default()).unwrap();
+        assert_eq!("!(A|B)", function.stages()["a_194_125#"].to_string());
+        for (a, b) in [(false, false), (false, true), (true, false), (true, true)] {
+            assert_eq!(
+                Some(&(a != b)),
+                function.evaluate(&levels(&[("A", a), ("B", b)])).get("X")
+            );
+        }
+    }
+
+    #[test]
+    fn logic_function_display() {
+        let input = |net: &str| LogicFunction::Input(net.to_owned());
+        let function = LogicFunction::Not(Box::new(
+            input("A1").series(input("A2")).parallel(input("B1")),
+        ));
+        assert_eq!("!((A1&A2)|B1)", function.to_string());
+        assert_eq!(BTreeSet::from(["A1", "A2", "B1"]), function.inputs());
+    }
+
+    #[test]
+    fn latch_is_not_static_cmos() {
+        let dlxtp = sky130_cell("dlxtp", "sky130_fd_sc_ls__dlxtp_1");
+        assert!(CellFunction::from_cell(&dlxtp, &CellFunctionConfig::default()).is_err());
+    }
+
+    #[test]
+    fn cell_entity() {
+        let a211o = sky130_cell("a211o", "sky130_fd_sc_ls__a211o_2");
+        let entity = CellFunction::from_cell(&a211o, &CellFunctionConfig::default())
+            .unwrap()
+            .entity();
+        let unit = Unit::new_anonymous(&entity);
+        assert_eq!(UnitKind::Entity, unit.kind());
+        assert_eq!(4, unit.input_args().count());
+        assert_eq!(1, unit.output_args().count());
+        assert_eq!(
+            1,
+            unit.all_insts()
+                .filter(|inst| unit[*inst].opcode() == Opcode::Drv)
+                .count()
+        );
+    }
+}
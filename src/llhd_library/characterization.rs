@@ -0,0 +1,773 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::{fmt, fs};
+
+use derive_getters::Getters;
+use itertools::Itertools;
+use typed_builder::TypedBuilder;
+
+use super::lef_library::LLefLibrary;
+use super::liberty_library::{
+    LLibertyLibrary, LibertyCell, LibertyPin, LibertyPinDirection, LibertyTimingArc, NldmTable,
+};
+use crate::circuit::analysis::dc::{dc_operating_point, DcConfig};
+use crate::circuit::analysis::transient::{transient_analysis, TransientConfig};
+use crate::circuit::analysis::AnalysisError;
+use crate::circuit::equations::{DeviceEquation, DeviceEquationMap};
+use crate::circuit::graph::hierarchy::FlattenError;
+use crate::circuit::graph::nodes::{ElementHNode, ElementKind};
+use crate::circuit::graph::LCircuit;
+use crate::circuit::nodes::CircuitNode;
+use crate::circuit::spice::SPICENetlist;
+use crate::circuit::values::parse_spice_value;
+
+/// Long-channel square-law NMOS, drain to source current, symmetric in drain and source.
+pub const NMOS_SQUARE_LAW: &str = indoc::indoc! {"
+    vt = 0.5;
+    kp = 170e-6;
+    vf = max(vgs - vt, 0.0);
+    vr = max(vgd - vt, 0.0);
+    I = 0.5 * kp * (w / l) * (vf * vf - vr * vr)
+"};
+
+/// Long-channel square-law PMOS, drain to source current, symmetric in drain and source.
+pub const PMOS_SQUARE_LAW: &str = indoc::indoc! {"
+    vt = 0.6;
+    kp = 60e-6;
+    vf = max(-vgs - vt, 0.0);
+    vr = max(-vgd - vt, 0.0);
+    I = -0.5 * kp * (w / l) * (vf * vf - vr * vr)
+"};
+
+/// Square-law `DeviceEquation`s of the sky130 standard cell transistor models.
+pub fn sky130_square_law_equations() -> DeviceEquationMap {
+    let nmos = DeviceEquation::from_str(NMOS_SQUARE_LAW).expect("Invalid NMOS equation.");
+    let pmos = DeviceEquation::from_str(PMOS_SQUARE_LAW).expect("Invalid PMOS equation.");
+    DeviceEquationMap::from([
+        ("sky130_fd_pr__nfet_01v8".to_owned(), nmos),
+        ("sky130_fd_pr__pfet_01v8".to_owned(), pmos.clone()),
+        ("sky130_fd_pr__pfet_01v8_hvt".to_owned(), pmos),
+    ])
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CharacterizationError {
+    Io(String),
+    Parse(String),
+    MissingSubcircuit(String),
+    Hierarchy(FlattenError),
+    Analysis {
+        cell: String,
+        error: AnalysisError,
+    },
+    /// Output never crossed its switching thresholds after the input edge.
+    NoTransition {
+        cell: String,
+        input: String,
+        output: String,
+    },
+}
+
+impl fmt::Display for CharacterizationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(message) => write!(f, "Unable to read cell netlist: {}", message),
+            Self::Parse(message) => write!(f, "Unable to parse cell netlist: {}", message),
+            Self::MissingSubcircuit(cell) => write!(f, "No subcircuit for cell {}", cell),
+            Self::Hierarchy(error) => write!(f, "Unable to flatten cell netlist: {}", error),
+            Self::Analysis { cell, error } => write!(f, "Simulation of {} failed: {}", cell, error),
+            Self::NoTransition {
+                cell,
+                input,
+                output,
+            } => write!(
+                f,
+                "Output {} of {} does not switch with input {}",
+                output, cell, input
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CharacterizationError {}
+
+/// Sweep and testbench knobs of the characterization harness.
+///
+/// Slews are 20%-80% transition times, in s, and loads are in F. The resulting library is in
+/// `ns` and `pF`.
+#[derive(Debug, Clone, TypedBuilder, Getters)]
+pub struct CharacterizationConfig {
+    #[builder(default = 1.8)]
+    vdd: f64,
+    #[builder(default = vec![20e-12, 100e-12, 500e-12])]
+    input_slews: Vec<f64>,
+    #[builder(default = vec![1e-15, 10e-15, 50e-15])]
+    output_loads: Vec<f64>,
+    #[builder(default = vec!["VPWR".to_owned(), "VPB".to_owned()])]
+    power_pins: Vec<String>,
+    #[builder(default = vec!["VGND".to_owned(), "VNB".to_owned()])]
+    ground_pins: Vec<String>,
+    /// Gate capacitance per unit of transistor `w * l`, tied from every gate to ground.
+    #[builder(default = 8.5e-15)]
+    gate_capacitance: f64,
+    /// Simulated time after the input edge, doubled up to three times until the output switches.
+    #[builder(default = 2e-9)]
+    settle_time: f64,
+    #[builder(default)]
+    newton: DcConfig,
+}
+
+impl Default for CharacterizationConfig {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+/// Transistor-level netlist of a standard cell, the first subcircuit of its SPICE file.
+#[derive(Debug, Clone, Getters)]
+pub struct CellNetlist {
+    name: String,
+    ports: Vec<String>,
+    elements: Vec<ElementHNode>,
+}
+
+impl CellNetlist {
+    pub fn from_spice(
+        netlist: &SPICENetlist,
+        device_equations: &DeviceEquationMap,
+    ) -> Result<Self, CharacterizationError> {
+        let subcircuit = netlist
+            .netlist_scope
+            .subcircuits
+            .first()
+            .ok_or_else(|| CharacterizationError::MissingSubcircuit("<netlist>".to_owned()))?;
+        let circuit = LCircuit::from((netlist, device_equations))
+            .flatten_subcircuit(&subcircuit.id)
+            .map_err(CharacterizationError::Hierarchy)?;
+        Ok(Self {
+            name: subcircuit.id.to_owned(),
+            ports: subcircuit.ports.port.to_owned(),
+            elements: circuit
+                .elements()
+                .map(|(_element_id, element)| element.to_owned())
+                .collect(),
+        })
+    }
+
+    pub fn open(
+        path: impl AsRef<Path>,
+        device_equations: &DeviceEquationMap,
+    ) -> Result<Self, CharacterizationError> {
+        let spice_str = fs::read_to_string(path)
+            .map_err(|error| CharacterizationError::Io(error.to_string()))?;
+        let netlist = SPICENetlist::parse_deck(&spice_str)
+            .map_err(|error| CharacterizationError::Parse(error.to_string()))?;
+        Self::from_spice(&netlist, device_equations)
+    }
+
+    fn transistors(&self) -> impl Iterator<Item = &ElementHNode> {
+        self.elements.iter().filter(|element| {
+            matches!(
+                element.kind(),
+                ElementKind::MosTransistor | ElementKind::Subcircuit
+            ) && element.terminals().len() >= 4
+        })
+    }
+
+    /// Signal ports driving only transistor gates.
+    fn input_pins(&self, config: &CharacterizationConfig) -> Vec<String> {
+        let channel_nodes = self
+            .transistors()
+            .flat_map(|transistor| [&transistor.terminals()[0], &transistor.terminals()[2]])
+            .collect::<BTreeSet<_>>();
+        self.signal_ports(config)
+            .filter(|port| {
+                CircuitNode::from_str(port).is_ok_and(|node| !channel_nodes.contains(&node))
+            })
+            .collect()
+    }
+
+    /// Signal ports connected to a transistor channel.
+    fn output_pins(&self, config: &CharacterizationConfig) -> Vec<String> {
+        let inputs = self.input_pins(config);
+        self.signal_ports(config)
+            .filter(|port| !inputs.contains(port))
+            .collect()
+    }
+
+    fn signal_ports<'cell>(
+        &'cell self,
+        config: &'cell CharacterizationConfig,
+    ) -> impl Iterator<Item = String> + 'cell {
+        self.ports
+            .iter()
+            .filter(|port| {
+                !config.power_pins.contains(*port) && !config.ground_pins.contains(*port)
+            })
+            .cloned()
+    }
+
+    /// `(gate node, capacitance)` of every transistor with numeric `w` and `l`.
+    fn gate_capacitances(&self, config: &CharacterizationConfig) -> Vec<(CircuitNode, f64)> {
+        self.transistors()
+            .filter_map(|transistor| {
+                let width = transistor.param("w").and_then(parse_spice_value)?;
+                let length = transistor.param("l").and_then(parse_spice_value)?;
+                Some((
+                    transistor.terminals()[1].to_owned(),
+                    config.gate_capacitance * width * length,
+                ))
+            })
+            .filter(|(_gate, capacitance)| *capacitance > 0.0)
+            .collect()
+    }
+}
+
+/// Cell netlists of every LEF macro with a `<cells_dir>/<family>/<macro>.spice` netlist.
+pub fn lef_cell_netlists(
+    lef: &LLefLibrary,
+    cells_dir: impl AsRef<Path>,
+    device_equations: &DeviceEquationMap,
+) -> Vec<Result<CellNetlist, CharacterizationError>> {
+    let family_dirs = fs::read_dir(cells_dir)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| path.is_dir())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    lef.macros
+        .iter()
+        .filter_map(|lef_macro| {
+            family_dirs
+                .iter()
+                .map(|family_dir| family_dir.join(format!("{}.spice", lef_macro.name)))
+                .find(|spice_path: &PathBuf| spice_path.is_file())
+        })
+        .map(|spice_path| CellNetlist::open(spice_path, device_equations))
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TimingSense {
+    PositiveUnate,
+    NegativeUnate,
+}
+
+impl TimingSense {
+    const fn liberty_name(self) -> &'static str {
+        match self {
+            Self::PositiveUnate => "positive_unate",
+            Self::NegativeUnate => "negative_unate",
+        }
+    }
+}
+
+/// Input drive of a testbench: a DC level or a full-swing ramp starting at `start`.
+#[derive(Debug, Clone, Copy)]
+enum InputDrive {
+    Level(bool),
+    Ramp {
+        rising: bool,
+        start: f64,
+        duration: f64,
+    },
+}
+
+struct Testbench<'cell> {
+    cell: &'cell CellNetlist,
+    config: &'cell CharacterizationConfig,
+}
+
+impl Testbench<'_> {
+    fn voltage_source(name: String, node: &str, value: Option<f64>) -> ElementHNode {
+        ElementHNode {
+            name,
+            kind: ElementKind::VoltageSource,
+            terminals: vec![
+                CircuitNode::from_str(node).expect("Invalid CircuitNode Name."),
+                CircuitNode::from_str("0").expect("Invalid CircuitNode Name."),
+            ],
+            value: value.map(|value| value.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn capacitor(name: String, node: &CircuitNode, capacitance: f64) -> ElementHNode {
+        ElementHNode {
+            name,
+            kind: ElementKind::Capacitor,
+            terminals: vec![
+                node.to_owned(),
+                CircuitNode::from_str("0").expect("Invalid CircuitNode Name."),
+            ],
+            value: Some(capacitance.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn circuit(
+        &self,
+        drives: &BTreeMap<String, InputDrive>,
+        load: Option<(&str, f64)>,
+    ) -> LCircuit {
+        let vdd = self.config.vdd;
+        let level = |high: bool| if high { vdd } else { 0.0 };
+        let supplies = self
+            .config
+            .power_pins
+            .iter()
+            .map(|pin| (pin, vdd))
+            .chain(self.config.ground_pins.iter().map(|pin| (pin, 0.0)))
+            .filter(|(pin, _voltage)| self.cell.ports.contains(*pin))
+            .map(|(pin, voltage)| {
+                Self::voltage_source(format!("vsupply_{}", pin), pin, Some(voltage))
+            });
+        let inputs = drives.iter().map(|(pin, drive)| match drive {
+            InputDrive::Level(high) => {
+                Self::voltage_source(format!("vin_{}", pin), pin, Some(level(*high)))
+            }
+            InputDrive::Ramp {
+                rising,
+                start,
+                duration,
+            } => {
+                let (from, to) = (level(!rising), level(*rising));
+                ElementHNode {
+                    params: BTreeMap::from([(
+                        "pwl".to_owned(),
+                        format!("0 {} {} {} {} {}", from, start, from, start + duration, to),
+                    )]),
+                    ..Self::voltage_source(format!("vin_{}", pin), pin, None)
+                }
+            }
+        });
+        let gate_capacitors = self
+            .cell
+            .gate_capacitances(self.config)
+            .into_iter()
+            .enumerate()
+            .map(|(idx, (gate, capacitance))| {
+                Self::capacitor(format!("cgate_{}", idx), &gate, capacitance)
+            });
+        let load_capacitor = load
+            .filter(|(_output, capacitance)| *capacitance > 0.0)
+            .map(|(output, capacitance)| {
+                Self::capacitor(
+                    "cload".to_owned(),
+                    &CircuitNode::from_str(output).expect("Invalid CircuitNode Name."),
+                    capacitance,
+                )
+            });
+        LCircuit::from_elements(
+            self.cell
+                .elements
+                .iter()
+                .cloned()
+                .chain(supplies)
+                .chain(inputs)
+                .chain(gate_capacitors)
+                .chain(load_capacitor),
+        )
+    }
+
+    fn analysis_error(&self, error: AnalysisError) -> CharacterizationError {
+        CharacterizationError::Analysis {
+            cell: self.cell.name.to_owned(),
+            error,
+        }
+    }
+
+    /// Logic level of `output` for static `inputs`.
+    fn output_level(
+        &self,
+        inputs: &BTreeMap<String, bool>,
+        output: &str,
+    ) -> Result<bool, CharacterizationError> {
+        let drives = inputs
+            .iter()
+            .map(|(pin, high)| (pin.to_owned(), InputDrive::Level(*high)))
+            .collect();
+        let solution = dc_operating_point(&self.circuit(&drives, None), &self.config.newton)
+            .map_err(|error| self.analysis_error(error))?;
+        Ok(solution.voltage(output).unwrap_or_default() > self.config.vdd / 2.0)
+    }
+
+    /// Truth table of `output` over every input assignment, in binary counting order.
+    fn truth_table(
+        &self,
+        inputs: &[String],
+        output: &str,
+    ) -> Result<Vec<(BTreeMap<String, bool>, bool)>, CharacterizationError> {
+        (0..1_usize << inputs.len())
+            .map(|assignment| {
+                let levels = inputs
+                    .iter()
+                    .enumerate()
+                    .map(|(bit, pin)| (pin.to_owned(), assignment & (1 << bit) != 0))
+                    .collect::<BTreeMap<_, _>>();
+                let level = self.output_level(&levels, output)?;
+                Ok((levels, level))
+            })
+            .collect()
+    }
+
+    /// Rising or falling edge on `input` with the other inputs at `side_inputs`, returning the
+    /// 50%-50% delay, the 20%-80% output transition and whether the output rose.
+    fn measure_edge(
+        &self,
+        side_inputs: &BTreeMap<String, bool>,
+        input: &str,
+        output: &str,
+        input_rising: bool,
+        slew: f64,
+        load: f64,
+    ) -> Result<(f64, f64, bool), CharacterizationError> {
+        let vdd = self.config.vdd;
+        let ramp = slew / 0.6;
+        let start = ramp;
+        let mut drives = side_inputs
+            .iter()
+            .map(|(pin, high)| (pin.to_owned(), InputDrive::Level(*high)))
+            .collect::<BTreeMap<_, _>>();
+        drives.insert(
+            input.to_owned(),
+            InputDrive::Ramp {
+                rising: input_rising,
+                start,
+                duration: ramp,
+            },
+        );
+        let circuit = self.circuit(&drives, Some((output, load)));
+        let mut settle_time = self.config.settle_time;
+        for _attempt in 0..4 {
+            let tstop = start + ramp + settle_time;
+            let transient_config = TransientConfig::builder()
+                .tstep((ramp / 10.0).max(tstop / 500.0))
+                .tstop(tstop)
+                .newton(self.config.newton.clone())
+                .build();
+            let solution = transient_analysis(&circuit, &transient_config)
+                .map_err(|error| self.analysis_error(error))?;
+            let waveform = solution.voltage(output).unwrap_or_default();
+            let times = solution.times();
+            if let (Some(initial), Some(last)) = (waveform.first(), waveform.last()) {
+                let output_rising = last > initial;
+                let crossing = |fraction: f64| {
+                    crossing_time(times, &waveform, fraction * vdd, output_rising, start)
+                };
+                if let (Some(t20), Some(t50), Some(t80)) =
+                    (crossing(0.2), crossing(0.5), crossing(0.8))
+                {
+                    let delay = t50 - (start + ramp / 2.0);
+                    return Ok((delay, (t80 - t20).abs(), output_rising));
+                }
+            }
+            settle_time *= 2.0;
+        }
+        Err(CharacterizationError::NoTransition {
+            cell: self.cell.name.to_owned(),
+            input: input.to_owned(),
+            output: output.to_owned(),
+        })
+    }
+}
+
+/// First time after `after` where `values` crosses `level` in the given direction.
+fn crossing_time(
+    times: &[f64],
+    values: &[f64],
+    level: f64,
+    rising: bool,
+    after: f64,
+) -> Option<f64> {
+    times
+        .iter()
+        .zip(values)
+        .tuple_windows()
+        .filter(|(_sample, (end_time, _end_value))| **end_time >= after)
+        .find_map(|((t0, v0), (t1, v1))| {
+            let crosses = if rising {
+                *v0 < level && *v1 >= level
+            } else {
+                *v0 > level && *v1 <= level
+            };
+            crosses.then(|| t0 + (t1 - t0) * (level - v0) / (v1 - v0))
+        })
+}
+
+/// Sum of products over the input assignments where the output is high.
+fn liberty_function(truth_table: &[(BTreeMap<String, bool>, bool)]) -> String {
+    let minterms = truth_table
+        .iter()
+        .filter(|(_levels, high)| *high)
+        .map(|(levels, _high)| {
+            let literals = levels
+                .iter()
+                .map(|(pin, high)| {
+                    if *high {
+                        pin.to_owned()
+                    } else {
+                        format!("!{}", pin)
+                    }
+                })
+                .collect::<Vec<_>>();
+            match literals.len() {
+                1 => literals.join(""),
+                _ => format!("({})", literals.join("&")),
+            }
+        })
+        .collect::<Vec<_>>();
+    match (minterms.len(), truth_table.len()) {
+        (0, _) => "0".to_owned(),
+        (ones, rows) if ones == rows => "1".to_owned(),
+        _ => minterms.join("|"),
+    }
+}
+
+/// Side input levels sensitizing `input -> output`, from the truth table.
+fn sensitization(
+    truth_table: &[(BTreeMap<String, bool>, bool)],
+    input: &str,
+) -> Option<(BTreeMap<String, bool>, TimingSense)> {
+    truth_table
+        .iter()
+        .filter(|(levels, _high)| !levels[input])
+        .find_map(|(levels, low_output)| {
+            let mut toggled = levels.clone();
+            toggled.insert(input.to_owned(), true);
+            let (_toggled, high_output) = truth_table
+                .iter()
+                .find(|(other_levels, _high)| *other_levels == toggled)?;
+            (low_output != high_output).then(|| {
+                let mut side_inputs = levels.clone();
+                side_inputs.remove(input);
+                let sense = if *high_output {
+                    TimingSense::PositiveUnate
+                } else {
+                    TimingSense::NegativeUnate
+                };
+                (side_inputs, sense)
+            })
+        })
+}
+
+/// NLDM tables of one timing arc, indexed `[slew][load]`.
+#[derive(Debug, Clone, Default)]
+struct ArcTables {
+    cell_rise: Vec<Vec<f64>>,
+    cell_fall: Vec<Vec<f64>>,
+    rise_transition: Vec<Vec<f64>>,
+    fall_transition: Vec<Vec<f64>>,
+}
+
+impl ArcTables {
+    /// Appends a measurement, in ns, to the row of the `slew_idx`th input slew.
+    fn record(&mut self, slew_idx: usize, output_rising: bool, delay: f64, transition: f64) {
+        let (delays, transitions) = if output_rising {
+            (&mut self.cell_rise, &mut self.rise_transition)
+        } else {
+            (&mut self.cell_fall, &mut self.fall_transition)
+        };
+        [(delays, delay), (transitions, transition)]
+            .into_iter()
+            .for_each(|(table, value)| {
+                if table.len() <= slew_idx {
+                    table.resize(slew_idx + 1, Vec::new());
+                }
+                table[slew_idx].push(value * 1e9);
+            });
+    }
+}
+
+/// Characterizes every combinational timing arc of a cell.
+///
+/// Pins are classified structurally: signal ports driving only transistor gates are inputs, the
+/// others outputs. Each output's function comes from DC operating points over every input
+/// assignment, and each sensitizable `input -> output` arc is simulated for rising and falling
+/// input edges over the slew/load grid of `config`.
+pub fn characterize_cell(
+    cell: &CellNetlist,
+    config: &CharacterizationConfig,
+) -> Result<LibertyCell, CharacterizationError> {
+    let testbench = Testbench { cell, config };
+    let inputs = cell.input_pins(config);
+    let gate_capacitances = cell.gate_capacitances(config);
+    let mut pins = inputs
+        .iter()
+        .map(|input| {
+            let capacitance = gate_capacitances
+                .iter()
+                .filter(|(gate, _capacitance)| gate.to_string() == *input)
+                .map(|(_gate, capacitance)| capacitance)
+                .sum::<f64>();
+            let pin = LibertyPin::builder()
+                .name(input.to_owned())
+                .direction(LibertyPinDirection::Input)
+                .capacitance(capacitance * 1e12)
+                .build();
+            (input.to_owned(), pin)
+        })
+        .collect::<BTreeMap<_, _>>();
+    let index_1 = config
+        .input_slews
+        .iter()
+        .map(|slew| slew * 1e9)
+        .collect_vec();
+    let index_2 = config
+        .output_loads
+        .iter()
+        .map(|load| load * 1e12)
+        .collect_vec();
+    let table = |values: Vec<Vec<f64>>| {
+        NldmTable::builder()
+            .index_1(index_1.clone())
+            .index_2(index_2.clone())
+            .values(values)
+            .build()
+    };
+    for output in cell.output_pins(config) {
+        let truth_table = testbench.truth_table(&inputs, &output)?;
+        let timing_arcs = inputs
+            .iter()
+            .filter_map(|input| {
+                sensitization(&truth_table, input).map(|(side_inputs, sense)| {
+                    let mut tables = ArcTables::default();
+                    for (slew_idx, slew) in config.input_slews.iter().enumerate() {
+                        for load in &config.output_loads {
+                            for input_rising in [true, false] {
+                                let (delay, transition, output_rising) = testbench.measure_edge(
+                                    &side_inputs,
+                                    input,
+                                    &output,
+                                    input_rising,
+                                    *slew,
+                                    *load,
+                                )?;
+                                tables.record(slew_idx, output_rising, delay, transition);
+                            }
+                        }
+                    }
+                    Ok(LibertyTimingArc::builder()
+                        .related_pin(input.to_owned())
+                        .timing_sense(sense.liberty_name())
+                        .timing_type("combinational")
+                        .cell_rise(table(tables.cell_rise))
+                        .cell_fall(table(tables.cell_fall))
+                        .rise_transition(table(tables.rise_transition))
+                        .fall_transition(table(tables.fall_transition))
+                        .build())
+                })
+            })
+            .collect::<Result<Vec<_>, CharacterizationError>>()?;
+        let pin = LibertyPin::builder()
+            .name(output.to_owned())
+            .direction(LibertyPinDirection::Output)
+            .function(liberty_function(&truth_table))
+            .timing_arcs(timing_arcs)
+            .build();
+        pins.insert(output, pin);
+    }
+    Ok(LibertyCell::builder()
+        .name(cell.name.to_owned())
+        .pins(pins)
+        .build())
+}
+
+/// Liberty library, in `ns` and `pF`, of every characterized cell.
+pub fn characterize_library(
+    library_name: &str,
+    cells: &[CellNetlist],
+    config: &CharacterizationConfig,
+) -> Result<LLibertyLibrary, CharacterizationError> {
+    let cells = cells
+        .iter()
+        .map(|cell| Ok((cell.name.to_owned(), characterize_cell(cell, config)?)))
+        .collect::<Result<BTreeMap<_, _>, CharacterizationError>>()?;
+    Ok(LLibertyLibrary::builder()
+        .name(library_name)
+        .time_unit("1ns")
+        .capacitive_load_unit((1.0, "pf".to_owned()))
+        .cells(cells)
+        .build())
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+
+    /// Path of a sky130 cell without extension, its `.spice` and `.gds` views sit side by side.
+    pub(crate) fn sky130_cell_path(family: &str, cell_name: &str) -> PathBuf {
+        let mut cell_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        cell_path.push("resources/libraries_no_liberty/sky130_fd_sc_ls/latest/cells");
+        cell_path.push(family);
+        cell_path.push(cell_name);
+        cell_path
+    }
+
+    pub(crate) fn sky130_cell(family: &str, cell_name: &str) -> CellNetlist {
+        CellNetlist::open(
+            sky130_cell_path(family, cell_name).with_extension("spice"),
+            &sky130_square_law_equations(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn cell_pin_classification() {
+        let nand2 = sky130_cell("nand2", "sky130_fd_sc_ls__nand2_1");
+        let config = CharacterizationConfig::default();
+        assert_eq!(vec!["A", "B"], nand2.input_pins(&config));
+        assert_eq!(vec!["Y"], nand2.output_pins(&config));
+    }
+
+    #[test]
+    fn liberty_functions() {
+        let nand2 = sky130_cell("nand2", "sky130_fd_sc_ls__nand2_1");
+        let config = CharacterizationConfig::default();
+        let testbench = Testbench {
+            cell: &nand2,
+            config: &config,
+        };
+        let truth_table = testbench
+            .truth_table(&["A".to_owned(), "B".to_owned()], "Y")
+            .unwrap();
+        assert_eq!("(!A&!B)|(A&!B)|(!A&B)", liberty_function(&truth_table));
+        let (side_inputs, sense) = sensitization(&truth_table, "A").unwrap();
+        assert_eq!(BTreeMap::from([("B".to_owned(), true)]), side_inputs);
+        assert_eq!(TimingSense::NegativeUnate, sense);
+    }
+
+    #[test]
+    fn characterize_sky130_inverter() {
+        let inv = sky130_cell("inv", "sky130_fd_sc_ls__inv_1");
+        let config = CharacterizationConfig::builder()
+            .input_slews(vec![20e-12, 200e-12])
+            .output_loads(vec![5e-15, 50e-15])
+            .settle_time(1e-9)
+            .build();
+        let liberty = characterize_library("sky130_characterized", &[inv], &config).unwrap();
+        let cell = liberty.cell("sky130_fd_sc_ls__inv_1").unwrap();
+        assert_eq!(Some("!A"), cell.pin("Y").unwrap().function().as_deref());
+        assert!(cell.pin_capacitance("A").unwrap() > 0.0);
+        let arc = cell.pin("Y").unwrap().timing_arc("A").unwrap();
+        assert_eq!(Some("negative_unate"), arc.timing_sense().as_deref());
+        let cell_fall = arc.cell_fall().as_ref().unwrap();
+        let cell_rise = arc.cell_rise().as_ref().unwrap();
+        [cell_fall, cell_rise].iter().for_each(|table| {
+            assert!(
+                table.values()[0][1] > table.values()[0][0],
+                "Delay should grow with the output load."
+            );
+        });
+        assert!(
+            cell_rise.values()[0][1] > cell_fall.values()[0][1],
+            "The weaker PMOS should rise slower than the NMOS falls."
+        );
+        let written = liberty.to_string();
+        assert_eq!(liberty, written.parse::<LLibertyLibrary>().unwrap());
+    }
+}
@@ -1 +1,91 @@
+use std::collections::BTreeMap;
 
+use crate::circuit::equations::DeviceEquationMap;
+use crate::circuit::graph::hierarchy::{FlattenError, SubcircuitTemplate};
+use crate::circuit::graph::LCircuit;
+use crate::circuit::spice::SPICENetlist;
+
+/// Flat transistor-level circuits of the cells of a technology, keyed by cell name.
+#[derive(Debug, Clone, Default)]
+pub struct LCircuitLibrary {
+    cells: BTreeMap<String, SubcircuitTemplate>,
+}
+
+impl LCircuitLibrary {
+    /// Every `.subckt` of `spice_netlist` as a cell, its instances flattened.
+    pub fn from_spice(
+        spice_netlist: &SPICENetlist,
+        device_equation_map: &DeviceEquationMap,
+    ) -> Result<Self, FlattenError> {
+        let circuit = LCircuit::from((spice_netlist, device_equation_map));
+        let mut library = Self::default();
+        for (name, template) in circuit.subcircuits() {
+            let cell_circuit = circuit.flatten_subcircuit(name)?;
+            library.insert(SubcircuitTemplate::new(
+                name.to_owned(),
+                template.ports().to_owned(),
+                cell_circuit,
+            ));
+        }
+        Ok(library)
+    }
+
+    /// Adds a cell, returning the cell of the same name it replaces.
+    pub fn insert(&mut self, cell: SubcircuitTemplate) -> Option<SubcircuitTemplate> {
+        self.cells.insert(cell.name().to_owned(), cell)
+    }
+
+    pub fn cells(&self) -> impl Iterator<Item = (&String, &SubcircuitTemplate)> {
+        self.cells.iter()
+    }
+
+    pub fn cell(&self, cell_name: &str) -> Option<&SubcircuitTemplate> {
+        self.cells.get(cell_name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::circuit::nodes::CircuitNode;
+
+    #[test]
+    fn circuit_library_from_spice() {
+        let deck = indoc::indoc! {"
+            * inverter and buffer cells
+            .subckt inv a y vdd vss
+            mp y a vdd vdd pmos w=2u l=1u
+            mn y a vss vss nmos w=1u l=1u
+            .ends inv
+            .subckt buf a y vdd vss
+            x1 a mid vdd vss inv
+            x2 mid y vdd vss inv
+            .ends buf
+        "};
+        let spice_netlist = SPICENetlist::parse_deck(deck).unwrap();
+        let library =
+            LCircuitLibrary::from_spice(&spice_netlist, &DeviceEquationMap::new()).unwrap();
+        assert_eq!(
+            vec!["buf", "inv"],
+            library
+                .cells()
+                .map(|(name, _cell)| name)
+                .collect::<Vec<_>>()
+        );
+        let buf = library.cell("buf").unwrap();
+        assert_eq!(
+            4,
+            buf.circuit().elements().count(),
+            "Two inverters of two transistors."
+        );
+        assert_eq!(0, buf.circuit().instances().count());
+        assert!(buf
+            .circuit()
+            .net(&CircuitNode::from_str("mid").unwrap())
+            .is_some());
+    }
+}
@@ -0,0 +1,1243 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use derive_getters::Getters;
+use itertools::Itertools;
+use typed_builder::TypedBuilder;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LibertyError {
+    Io(String),
+    Syntax {
+        line: usize,
+        message: String,
+    },
+    MissingLibrary,
+    /// NLDM table whose `values` rows or columns don't match its `index_1` and `index_2`.
+    TableDimensions {
+        table: String,
+        rows: usize,
+        columns: usize,
+    },
+}
+
+impl fmt::Display for LibertyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(message) => write!(f, "Unable to read Liberty file: {}", message),
+            Self::Syntax { line, message } => {
+                write!(f, "Liberty syntax error on line {}: {}", line, message)
+            }
+            Self::MissingLibrary => write!(f, "Liberty source has no `library` group."),
+            Self::TableDimensions {
+                table,
+                rows,
+                columns,
+            } => write!(
+                f,
+                "Liberty table {} should have {} rows of {} values",
+                table, rows, columns
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LibertyError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum LibertyToken {
+    Word(String),
+    Str(String),
+    LParen,
+    RParen,
+    LBrace,
+    RBrace,
+    Colon,
+    Semi,
+    Comma,
+}
+
+fn tokenize(source: &str) -> Result<Vec<(usize, LibertyToken)>, LibertyError> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    let mut line = 1;
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\n' => line += 1,
+            '\\' => {
+                // Line continuation
+                if chars.peek() == Some(&'\r') {
+                    chars.next();
+                }
+                if chars.peek() == Some(&'\n') {
+                    chars.next();
+                    line += 1;
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = ' ';
+                loop {
+                    match chars.next() {
+                        Some('/') if prev == '*' => break,
+                        Some(comment_ch) => {
+                            if comment_ch == '\n' {
+                                line += 1;
+                            }
+                            prev = comment_ch;
+                        }
+                        None => {
+                            return Err(LibertyError::Syntax {
+                                line,
+                                message: "Unterminated comment.".to_owned(),
+                            })
+                        }
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.peek().is_some_and(|comment_ch| *comment_ch != '\n') {
+                    chars.next();
+                }
+            }
+            '"' => {
+                let start_line = line;
+                let mut quoted = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') if chars.peek() == Some(&'\n') => {
+                            chars.next();
+                            line += 1;
+                        }
+                        Some(quoted_ch) => {
+                            if quoted_ch == '\n' {
+                                line += 1;
+                            }
+                            quoted.push(quoted_ch);
+                        }
+                        None => {
+                            return Err(LibertyError::Syntax {
+                                line: start_line,
+                                message: "Unterminated string.".to_owned(),
+                            })
+                        }
+                    }
+                }
+                tokens.push((start_line, LibertyToken::Str(quoted)));
+            }
+            '(' => tokens.push((line, LibertyToken::LParen)),
+            ')' => tokens.push((line, LibertyToken::RParen)),
+            '{' => tokens.push((line, LibertyToken::LBrace)),
+            '}' => tokens.push((line, LibertyToken::RBrace)),
+            ':' => tokens.push((line, LibertyToken::Colon)),
+            ';' => tokens.push((line, LibertyToken::Semi)),
+            ',' => tokens.push((line, LibertyToken::Comma)),
+            ch if ch.is_whitespace() => (),
+            _ => {
+                let mut word = String::from(ch);
+                while let Some(word_ch) = chars.peek() {
+                    if word_ch.is_whitespace() || "(){}:;,\"".contains(*word_ch) {
+                        break;
+                    }
+                    word.push(*word_ch);
+                    chars.next();
+                }
+                tokens.push((line, LibertyToken::Word(word)));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Untyped Liberty Group: `kind (names) { attributes; groups }`
+#[derive(Debug, Clone, Default)]
+struct LibertyGroup {
+    kind: String,
+    names: Vec<String>,
+    simple_attributes: Vec<(String, String)>,
+    complex_attributes: Vec<(String, Vec<String>)>,
+    groups: Vec<LibertyGroup>,
+}
+
+impl LibertyGroup {
+    fn name(&self) -> String {
+        self.names.first().cloned().unwrap_or_default()
+    }
+
+    fn simple(&self, attribute: &str) -> Option<&str> {
+        self.simple_attributes
+            .iter()
+            .find(|(name, _value)| name == attribute)
+            .map(|(_name, value)| value.as_str())
+    }
+
+    fn simple_f64(&self, attribute: &str) -> Option<f64> {
+        self.simple(attribute)
+            .and_then(|value| value.parse::<f64>().ok())
+    }
+
+    fn complex(&self, attribute: &str) -> Option<&[String]> {
+        self.complex_attributes
+            .iter()
+            .find(|(name, _values)| name == attribute)
+            .map(|(_name, values)| values.as_slice())
+    }
+
+    fn groups<'group>(&'group self, kind: &'group str) -> impl Iterator<Item = &Self> + 'group {
+        self.groups.iter().filter(move |group| group.kind == kind)
+    }
+}
+
+struct LibertyParser {
+    tokens: Vec<(usize, LibertyToken)>,
+    position: usize,
+}
+
+impl LibertyParser {
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .or_else(|| self.tokens.last())
+            .map_or(0, |(line, _token)| *line)
+    }
+
+    fn peek(&self) -> Option<&LibertyToken> {
+        self.tokens.get(self.position).map(|(_line, token)| token)
+    }
+
+    fn advance(&mut self) -> Option<LibertyToken> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .map(|(_line, token)| token.clone());
+        self.position += 1;
+        token
+    }
+
+    fn error<T>(&self, message: &str) -> Result<T, LibertyError> {
+        Err(LibertyError::Syntax {
+            line: self.line(),
+            message: message.to_owned(),
+        })
+    }
+
+    fn skip_semi(&mut self) {
+        if self.peek() == Some(&LibertyToken::Semi) {
+            self.position += 1;
+        }
+    }
+
+    fn word(&mut self) -> Result<String, LibertyError> {
+        match self.advance() {
+            Some(LibertyToken::Word(word) | LibertyToken::Str(word)) => Ok(word),
+            _ => {
+                self.position -= 1;
+                self.error("Expected identifier.")
+            }
+        }
+    }
+
+    fn args(&mut self) -> Result<Vec<String>, LibertyError> {
+        let mut args = Vec::new();
+        loop {
+            match self.advance() {
+                Some(LibertyToken::RParen) => return Ok(args),
+                Some(LibertyToken::Comma) => (),
+                Some(LibertyToken::Word(arg) | LibertyToken::Str(arg)) => args.push(arg),
+                _ => {
+                    self.position -= 1;
+                    return self.error("Expected `)` closing argument list.");
+                }
+            }
+        }
+    }
+
+    /// Simple attribute values end at `;`, or at the end of the line when `;` is omitted.
+    fn simple_value(&mut self) -> Result<String, LibertyError> {
+        let value_line = self.line();
+        let mut value_parts = Vec::new();
+        while let Some((token_line, token)) = self.tokens.get(self.position) {
+            match token {
+                LibertyToken::Semi => {
+                    self.position += 1;
+                    break;
+                }
+                LibertyToken::RBrace => break,
+                _ if *token_line != value_line && !value_parts.is_empty() => break,
+                LibertyToken::Word(part) | LibertyToken::Str(part) => {
+                    value_parts.push(part.clone());
+                    self.position += 1;
+                }
+                _ => return self.error("Unexpected token in attribute value."),
+            }
+        }
+        Ok(value_parts.join(" "))
+    }
+
+    fn group_body(&mut self, group: &mut LibertyGroup) -> Result<(), LibertyError> {
+        loop {
+            match self.peek() {
+                Some(LibertyToken::RBrace) => {
+                    self.position += 1;
+                    return Ok(());
+                }
+                Some(LibertyToken::Semi) => self.position += 1,
+                None => return self.error("Expected `}` closing group."),
+                Some(_) => self.statement(group)?,
+            }
+        }
+    }
+
+    fn statement(&mut self, parent: &mut LibertyGroup) -> Result<(), LibertyError> {
+        let name = self.word()?;
+        match self.advance() {
+            Some(LibertyToken::Colon) => {
+                let value = self.simple_value()?;
+                parent.simple_attributes.push((name, value));
+            }
+            Some(LibertyToken::LParen) => {
+                let args = self.args()?;
+                if self.peek() == Some(&LibertyToken::LBrace) {
+                    self.position += 1;
+                    let mut group = LibertyGroup {
+                        kind: name,
+                        names: args,
+                        ..Default::default()
+                    };
+                    self.group_body(&mut group)?;
+                    parent.groups.push(group);
+                } else {
+                    self.skip_semi();
+                    parent.complex_attributes.push((name, args));
+                }
+            }
+            _ => {
+                self.position -= 1;
+                return self.error("Expected `:` or `(` after identifier.");
+            }
+        }
+        Ok(())
+    }
+
+    fn parse(mut self) -> Result<LibertyGroup, LibertyError> {
+        let mut root = LibertyGroup::default();
+        while self.peek().is_some() {
+            self.statement(&mut root)?;
+        }
+        Ok(root)
+    }
+}
+
+fn parse_f64_list(values: &str) -> Vec<f64> {
+    values
+        .split(|ch: char| ch == ',' || ch.is_whitespace())
+        .filter(|value| !value.is_empty())
+        .filter_map(|value| value.parse::<f64>().ok())
+        .collect()
+}
+
+/// Table Axis Variable of an NLDM `lu_table_template`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum NldmVariable {
+    InputTransition,
+    OutputLoad,
+}
+
+impl NldmVariable {
+    fn from_template(variable: &str) -> Self {
+        if variable.contains("capacitance") {
+            Self::OutputLoad
+        } else {
+            Self::InputTransition
+        }
+    }
+
+    const fn liberty_name(self) -> &'static str {
+        match self {
+            Self::InputTransition => "input_net_transition",
+            Self::OutputLoad => "total_output_net_capacitance",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct LibertyTableTemplate {
+    variables: Vec<NldmVariable>,
+    index_1: Vec<f64>,
+    index_2: Vec<f64>,
+}
+
+impl From<&LibertyGroup> for LibertyTableTemplate {
+    fn from(group: &LibertyGroup) -> Self {
+        let variables = ["variable_1", "variable_2"]
+            .iter()
+            .filter_map(|variable| group.simple(variable))
+            .map(NldmVariable::from_template)
+            .collect();
+        Self {
+            variables,
+            index_1: index_values(group, "index_1"),
+            index_2: index_values(group, "index_2"),
+        }
+    }
+}
+
+fn index_values(group: &LibertyGroup, index: &str) -> Vec<f64> {
+    group
+        .complex(index)
+        .map(|values| parse_f64_list(&values.join(",")))
+        .unwrap_or_default()
+}
+
+/// Non-Linear Delay Model Lookup Table
+///
+/// `values[i][j]` is sampled at `index_1[i]`, `index_2[j]`. A scalar table has empty indices and
+/// a single value.
+#[derive(Debug, Clone, PartialEq, TypedBuilder, Getters)]
+pub struct NldmTable {
+    #[builder(default = vec![NldmVariable::InputTransition, NldmVariable::OutputLoad])]
+    variables: Vec<NldmVariable>,
+    #[builder(default)]
+    index_1: Vec<f64>,
+    #[builder(default)]
+    index_2: Vec<f64>,
+    values: Vec<Vec<f64>>,
+}
+
+/// Segment of `index` bracketing `x`, and the interpolation weight of its upper sample. Outside
+/// of the index the end segments are extrapolated.
+fn bracket(index: &[f64], x: f64) -> (usize, usize, f64) {
+    if index.len() < 2 {
+        return (0, 0, 0.0);
+    }
+    let upper = index
+        .iter()
+        .position(|sample| x < *sample)
+        .unwrap_or(index.len() - 1)
+        .clamp(1, index.len() - 1);
+    let lower = upper - 1;
+    let span = index[upper] - index[lower];
+    let weight = if span == 0.0 {
+        0.0
+    } else {
+        (x - index[lower]) / span
+    };
+    (lower, upper, weight)
+}
+
+fn lerp(lower: f64, upper: f64, weight: f64) -> f64 {
+    lower + (upper - lower) * weight
+}
+
+impl NldmTable {
+    fn from_group(
+        group: &LibertyGroup,
+        templates: &HashMap<String, LibertyTableTemplate>,
+    ) -> Result<Self, LibertyError> {
+        let template = templates.get(&group.name());
+        let table_index = |index: &str, template_index: Option<&Vec<f64>>| {
+            let values = index_values(group, index);
+            if values.is_empty() {
+                template_index.cloned().unwrap_or_default()
+            } else {
+                values
+            }
+        };
+        let variables = template.map_or_else(
+            || vec![NldmVariable::InputTransition, NldmVariable::OutputLoad],
+            |template| template.variables.clone(),
+        );
+        let index_1 = table_index("index_1", template.map(|template| &template.index_1));
+        let index_2 = table_index("index_2", template.map(|template| &template.index_2));
+        let values = group
+            .complex("values")
+            .map(|rows| rows.iter().map(|row| parse_f64_list(row)).collect())
+            .unwrap_or_default();
+        let table = Self {
+            variables,
+            index_1,
+            index_2,
+            values,
+        };
+        table.check_dimensions(&group.kind)?;
+        Ok(table)
+    }
+
+    /// One row per `index_1` sample of `index_2.len()` values, or a single row over `index_1`
+    /// for a 1-D table, so `lookup` stays within `values`.
+    fn check_dimensions(&self, table: &str) -> Result<(), LibertyError> {
+        let (rows, columns) = if self.index_2.is_empty() {
+            (1, self.index_1.len().max(1))
+        } else {
+            (self.index_1.len().max(1), self.index_2.len())
+        };
+        let matching = self.values.is_empty()
+            || (self.values.len() == rows && self.values.iter().all(|row| row.len() == columns));
+        if matching {
+            Ok(())
+        } else {
+            Err(LibertyError::TableDimensions {
+                table: table.to_owned(),
+                rows,
+                columns,
+            })
+        }
+    }
+
+    /// Bilinear interpolation of the table at an input transition and output load.
+    pub fn lookup(&self, input_transition: f64, output_load: f64) -> f64 {
+        let axis_value = |axis: usize| match self.variables.get(axis) {
+            Some(NldmVariable::OutputLoad) => output_load,
+            _ => input_transition,
+        };
+        let row_value = |row: &Vec<f64>| {
+            let (lower, upper, weight) = bracket(&self.index_2, axis_value(1));
+            match (row.get(lower), row.get(upper)) {
+                (Some(lower), Some(upper)) => lerp(*lower, *upper, weight),
+                _ => row.first().copied().unwrap_or_default(),
+            }
+        };
+        match self.values.len() {
+            0 => 0.0,
+            1 if self.index_2.is_empty() => {
+                // 1-D table, or scalar, indexed only by `index_1`
+                let (lower, upper, weight) = bracket(&self.index_1, axis_value(0));
+                let row = &self.values[0];
+                match (row.get(lower), row.get(upper)) {
+                    (Some(lower), Some(upper)) => lerp(*lower, *upper, weight),
+                    _ => row.first().copied().unwrap_or_default(),
+                }
+            }
+            1 => row_value(&self.values[0]),
+            _ => {
+                let (lower, upper, weight) = bracket(&self.index_1, axis_value(0));
+                match (self.values.get(lower), self.values.get(upper)) {
+                    (Some(lower), Some(upper)) => lerp(row_value(lower), row_value(upper), weight),
+                    _ => self.values.first().map_or(0.0, row_value),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, TypedBuilder, Getters)]
+pub struct LibertyTimingArc {
+    #[builder(setter(into))]
+    related_pin: String,
+    #[builder(default, setter(strip_option, into))]
+    timing_sense: Option<String>,
+    #[builder(default, setter(strip_option, into))]
+    timing_type: Option<String>,
+    #[builder(default, setter(strip_option))]
+    cell_rise: Option<NldmTable>,
+    #[builder(default, setter(strip_option))]
+    cell_fall: Option<NldmTable>,
+    #[builder(default, setter(strip_option))]
+    rise_transition: Option<NldmTable>,
+    #[builder(default, setter(strip_option))]
+    fall_transition: Option<NldmTable>,
+}
+
+impl LibertyTimingArc {
+    fn from_group(
+        group: &LibertyGroup,
+        templates: &HashMap<String, LibertyTableTemplate>,
+    ) -> Result<Self, LibertyError> {
+        let table = |kind: &str| {
+            group
+                .groups(kind)
+                .next()
+                .map(|table_group| NldmTable::from_group(table_group, templates))
+                .transpose()
+        };
+        Ok(Self {
+            related_pin: group.simple("related_pin").unwrap_or_default().to_owned(),
+            timing_sense: group.simple("timing_sense").map(str::to_owned),
+            timing_type: group.simple("timing_type").map(str::to_owned),
+            cell_rise: table("cell_rise")?,
+            cell_fall: table("cell_fall")?,
+            rise_transition: table("rise_transition")?,
+            fall_transition: table("fall_transition")?,
+        })
+    }
+
+    /// Worst of the rise and fall delays.
+    pub fn delay(&self, input_transition: f64, output_load: f64) -> Option<f64> {
+        worst_lookup(
+            [&self.cell_rise, &self.cell_fall],
+            input_transition,
+            output_load,
+        )
+    }
+
+    /// Worst of the rise and fall output transitions.
+    pub fn transition(&self, input_transition: f64, output_load: f64) -> Option<f64> {
+        worst_lookup(
+            [&self.rise_transition, &self.fall_transition],
+            input_transition,
+            output_load,
+        )
+    }
+}
+
+fn worst_lookup(
+    tables: [&Option<NldmTable>; 2],
+    input_transition: f64,
+    output_load: f64,
+) -> Option<f64> {
+    tables
+        .into_iter()
+        .flatten()
+        .map(|table| table.lookup(input_transition, output_load))
+        .reduce(f64::max)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LibertyPinDirection {
+    Input,
+    Output,
+    Inout,
+    Internal,
+}
+
+impl LibertyPinDirection {
+    const fn liberty_name(self) -> &'static str {
+        match self {
+            Self::Input => "input",
+            Self::Output => "output",
+            Self::Inout => "inout",
+            Self::Internal => "internal",
+        }
+    }
+}
+
+impl FromStr for LibertyPinDirection {
+    type Err = String;
+
+    fn from_str(direction: &str) -> Result<Self, Self::Err> {
+        match direction {
+            "input" => Ok(Self::Input),
+            "output" => Ok(Self::Output),
+            "inout" => Ok(Self::Inout),
+            "internal" => Ok(Self::Internal),
+            _ => Err(format!("Unknown pin direction `{}`.", direction)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, TypedBuilder, Getters)]
+pub struct LibertyPin {
+    #[builder(setter(into))]
+    name: String,
+    #[builder(default, setter(strip_option))]
+    direction: Option<LibertyPinDirection>,
+    #[builder(default)]
+    capacitance: f64,
+    #[builder(default, setter(strip_option, into))]
+    function: Option<String>,
+    #[builder(default)]
+    timing_arcs: Vec<LibertyTimingArc>,
+}
+
+impl LibertyPin {
+    fn from_group(
+        group: &LibertyGroup,
+        templates: &HashMap<String, LibertyTableTemplate>,
+    ) -> Result<Self, LibertyError> {
+        Ok(Self {
+            name: group.name(),
+            direction: group
+                .simple("direction")
+                .and_then(|direction| direction.parse().ok()),
+            capacitance: group.simple_f64("capacitance").unwrap_or_default(),
+            function: group.simple("function").map(str::to_owned),
+            timing_arcs: group
+                .groups("timing")
+                .map(|timing_group| LibertyTimingArc::from_group(timing_group, templates))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    pub fn timing_arc(&self, related_pin: &str) -> Option<&LibertyTimingArc> {
+        self.timing_arcs
+            .iter()
+            .find(|timing_arc| timing_arc.related_pin == related_pin)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, TypedBuilder, Getters)]
+pub struct LibertyCell {
+    #[builder(setter(into))]
+    name: String,
+    #[builder(default)]
+    area: f64,
+    #[builder(default, setter(strip_option))]
+    cell_leakage_power: Option<f64>,
+    /// `(when, value)` of each `leakage_power` group.
+    #[builder(default)]
+    leakage_states: Vec<(Option<String>, f64)>,
+    #[builder(default)]
+    pins: BTreeMap<String, LibertyPin>,
+}
+
+impl LibertyCell {
+    fn from_group(
+        group: &LibertyGroup,
+        templates: &HashMap<String, LibertyTableTemplate>,
+    ) -> Result<Self, LibertyError> {
+        Ok(Self {
+            name: group.name(),
+            area: group.simple_f64("area").unwrap_or_default(),
+            cell_leakage_power: group.simple_f64("cell_leakage_power"),
+            leakage_states: group
+                .groups("leakage_power")
+                .filter_map(|leakage_group| {
+                    let when = leakage_group.simple("when").map(str::to_owned);
+                    leakage_group
+                        .simple_f64("value")
+                        .map(|leakage| (when, leakage))
+                })
+                .collect(),
+            pins: group
+                .groups("pin")
+                .map(|pin_group| {
+                    let pin = LibertyPin::from_group(pin_group, templates)?;
+                    Ok((pin.name.to_owned(), pin))
+                })
+                .collect::<Result<_, LibertyError>>()?,
+        })
+    }
+
+    pub fn pin(&self, pin_name: &str) -> Option<&LibertyPin> {
+        self.pins.get(pin_name)
+    }
+
+    pub fn pin_capacitance(&self, pin_name: &str) -> Option<f64> {
+        self.pin(pin_name).map(|pin| pin.capacitance)
+    }
+
+    /// `cell_leakage_power`, or the state-averaged `leakage_power` groups when it's absent.
+    pub fn leakage_power(&self) -> f64 {
+        self.cell_leakage_power.unwrap_or_else(|| {
+            if self.leakage_states.is_empty() {
+                0.0
+            } else {
+                self.leakage_states
+                    .iter()
+                    .map(|(_when, leakage)| leakage)
+                    .sum::<f64>()
+                    / self.leakage_states.len() as f64
+            }
+        })
+    }
+
+    /// Delay of the `related_pin -> output_pin` arc.
+    pub fn arc_delay(
+        &self,
+        related_pin: &str,
+        output_pin: &str,
+        input_transition: f64,
+        output_load: f64,
+    ) -> Option<f64> {
+        self.pin(output_pin)?
+            .timing_arc(related_pin)?
+            .delay(input_transition, output_load)
+    }
+
+    /// Worst delay over every timing arc of the cell.
+    pub fn worst_delay(&self, input_transition: f64, output_load: f64) -> Option<f64> {
+        self.pins
+            .values()
+            .flat_map(|pin| pin.timing_arcs.iter())
+            .filter_map(|timing_arc| timing_arc.delay(input_transition, output_load))
+            .reduce(f64::max)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, TypedBuilder, Getters)]
+pub struct LLibertyLibrary {
+    #[builder(setter(into))]
+    name: String,
+    #[builder(default, setter(strip_option, into))]
+    time_unit: Option<String>,
+    #[builder(default, setter(strip_option))]
+    capacitive_load_unit: Option<(f64, String)>,
+    #[builder(default, setter(strip_option, into))]
+    leakage_power_unit: Option<String>,
+    #[builder(default)]
+    cells: BTreeMap<String, LibertyCell>,
+}
+
+impl LLibertyLibrary {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, LibertyError> {
+        std::fs::read_to_string(path)
+            .map_err(|err| LibertyError::Io(err.to_string()))?
+            .parse()
+    }
+
+    pub fn cell(&self, cell_name: &str) -> Option<&LibertyCell> {
+        self.cells.get(cell_name)
+    }
+
+    /// Worst arc delay of every cell at a nominal input transition and output load, e.g. for
+    /// the `cell_delays` of a `DelayTable`.
+    pub fn cell_delays(&self, input_transition: f64, output_load: f64) -> HashMap<String, f64> {
+        self.cells
+            .iter()
+            .filter_map(|(cell_name, cell)| {
+                cell.worst_delay(input_transition, output_load)
+                    .map(|delay| (cell_name.to_owned(), delay))
+            })
+            .collect()
+    }
+}
+
+impl FromStr for LLibertyLibrary {
+    type Err = LibertyError;
+
+    fn from_str(liberty_str: &str) -> Result<Self, Self::Err> {
+        let parser = LibertyParser {
+            tokens: tokenize(liberty_str)?,
+            position: 0,
+        };
+        let root = parser.parse()?;
+        let library = root
+            .groups("library")
+            .next()
+            .ok_or(LibertyError::MissingLibrary)?;
+        let templates: HashMap<String, LibertyTableTemplate> = library
+            .groups("lu_table_template")
+            .map(|template_group| {
+                (
+                    template_group.name(),
+                    LibertyTableTemplate::from(template_group),
+                )
+            })
+            .collect();
+        let capacitive_load_unit =
+            library
+                .complex("capacitive_load_unit")
+                .and_then(|unit| match unit {
+                    [scale, unit_name] => scale
+                        .parse::<f64>()
+                        .ok()
+                        .map(|scale| (scale, unit_name.to_owned())),
+                    _ => None,
+                });
+        Ok(Self {
+            name: library.name(),
+            time_unit: library.simple("time_unit").map(str::to_owned),
+            capacitive_load_unit,
+            leakage_power_unit: library.simple("leakage_power_unit").map(str::to_owned),
+            cells: library
+                .groups("cell")
+                .map(|cell_group| {
+                    let cell = LibertyCell::from_group(cell_group, &templates)?;
+                    Ok((cell.name.to_owned(), cell))
+                })
+                .collect::<Result<_, LibertyError>>()?,
+        })
+    }
+}
+
+fn template_name(variables: &[NldmVariable]) -> String {
+    if variables.is_empty() {
+        "scalar".to_owned()
+    } else {
+        variables
+            .iter()
+            .map(|variable| match variable {
+                NldmVariable::InputTransition => "transition",
+                NldmVariable::OutputLoad => "load",
+            })
+            .join("_by_")
+    }
+}
+
+fn write_f64_list(f: &mut fmt::Formatter<'_>, values: &[f64]) -> fmt::Result {
+    write!(f, "\"{}\"", values.iter().join(", "))
+}
+
+fn write_table(
+    f: &mut fmt::Formatter<'_>,
+    kind: &str,
+    table: &Option<NldmTable>,
+    indent: &str,
+) -> fmt::Result {
+    let Some(table) = table else {
+        return Ok(());
+    };
+    writeln!(
+        f,
+        "{}{} ({}) {{",
+        indent,
+        kind,
+        template_name(&table.variables)
+    )?;
+    for (index, index_values) in [("index_1", &table.index_1), ("index_2", &table.index_2)] {
+        if !index_values.is_empty() {
+            write!(f, "{}  {} (", indent, index)?;
+            write_f64_list(f, index_values)?;
+            writeln!(f, ") ;")?;
+        }
+    }
+    write!(f, "{}  values (", indent)?;
+    for (row_idx, row) in table.values.iter().enumerate() {
+        if row_idx > 0 {
+            write!(f, ", \\\n{}          ", indent)?;
+        }
+        write_f64_list(f, row)?;
+    }
+    writeln!(f, ") ;")?;
+    writeln!(f, "{}}}", indent)
+}
+
+/// Liberty source of the library, readable by `LLibertyLibrary::from_str`. One
+/// `lu_table_template` is written per distinct table axis order.
+impl fmt::Display for LLibertyLibrary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "library ({}) {{", self.name)?;
+        writeln!(f, "  delay_model : table_lookup ;")?;
+        if let Some(time_unit) = &self.time_unit {
+            writeln!(f, "  time_unit : \"{}\" ;", time_unit)?;
+        }
+        if let Some(leakage_power_unit) = &self.leakage_power_unit {
+            writeln!(f, "  leakage_power_unit : \"{}\" ;", leakage_power_unit)?;
+        }
+        if let Some((scale, unit)) = &self.capacitive_load_unit {
+            writeln!(f, "  capacitive_load_unit ({}, {}) ;", scale, unit)?;
+        }
+        let templates = self
+            .cells
+            .values()
+            .flat_map(|cell| cell.pins.values())
+            .flat_map(|pin| pin.timing_arcs.iter())
+            .flat_map(|timing_arc| {
+                [
+                    &timing_arc.cell_rise,
+                    &timing_arc.cell_fall,
+                    &timing_arc.rise_transition,
+                    &timing_arc.fall_transition,
+                ]
+            })
+            .flatten()
+            .map(|table| table.variables.clone())
+            .collect::<BTreeSet<_>>();
+        for variables in templates {
+            writeln!(f, "  lu_table_template ({}) {{", template_name(&variables))?;
+            for (variable_idx, variable) in variables.iter().enumerate() {
+                writeln!(
+                    f,
+                    "    variable_{} : {} ;",
+                    variable_idx + 1,
+                    variable.liberty_name()
+                )?;
+            }
+            writeln!(f, "  }}")?;
+        }
+        for cell in self.cells.values() {
+            writeln!(f, "  cell ({}) {{", cell.name)?;
+            writeln!(f, "    area : {} ;", cell.area)?;
+            if let Some(cell_leakage_power) = cell.cell_leakage_power {
+                writeln!(f, "    cell_leakage_power : {} ;", cell_leakage_power)?;
+            }
+            for (when, leakage) in &cell.leakage_states {
+                writeln!(f, "    leakage_power () {{")?;
+                if let Some(when) = when {
+                    writeln!(f, "      when : \"{}\" ;", when)?;
+                }
+                writeln!(f, "      value : {} ;", leakage)?;
+                writeln!(f, "    }}")?;
+            }
+            for pin in cell.pins.values() {
+                writeln!(f, "    pin ({}) {{", pin.name)?;
+                if let Some(direction) = pin.direction {
+                    writeln!(f, "      direction : {} ;", direction.liberty_name())?;
+                }
+                writeln!(f, "      capacitance : {} ;", pin.capacitance)?;
+                if let Some(function) = &pin.function {
+                    writeln!(f, "      function : \"{}\" ;", function)?;
+                }
+                for timing_arc in &pin.timing_arcs {
+                    writeln!(f, "      timing () {{")?;
+                    writeln!(f, "        related_pin : \"{}\" ;", timing_arc.related_pin)?;
+                    if let Some(timing_sense) = &timing_arc.timing_sense {
+                        writeln!(f, "        timing_sense : {} ;", timing_sense)?;
+                    }
+                    if let Some(timing_type) = &timing_arc.timing_type {
+                        writeln!(f, "        timing_type : {} ;", timing_type)?;
+                    }
+                    write_table(f, "cell_rise", &timing_arc.cell_rise, "        ")?;
+                    write_table(f, "cell_fall", &timing_arc.cell_fall, "        ")?;
+                    write_table(
+                        f,
+                        "rise_transition",
+                        &timing_arc.rise_transition,
+                        "        ",
+                    )?;
+                    write_table(
+                        f,
+                        "fall_transition",
+                        &timing_arc.fall_transition,
+                        "        ",
+                    )?;
+                    writeln!(f, "      }}")?;
+                }
+                writeln!(f, "    }}")?;
+            }
+            writeln!(f, "  }}")?;
+        }
+        writeln!(f, "}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    const NAND2_LIBERTY: &str = indoc::indoc! {r#"
+        /* Two Input NAND */
+        library (example_lib) {
+            time_unit : "1ns" ;
+            leakage_power_unit : "1nW" ;
+            capacitive_load_unit (1.0, pf) ;
+            lu_table_template (del_2_2) {
+                variable_1 : input_net_transition ;
+                variable_2 : total_output_net_capacitance ;
+                index_1 ("0.1, 0.5") ;
+                index_2 ("0.01, 0.05") ;
+            }
+            cell (NAND2) {
+                area : 3.75 ;
+                leakage_power () {
+                    when : "!A&!B" ;
+                    value : 1.0 ;
+                }
+                leakage_power () {
+                    when : "A&B" ;
+                    value : 3.0 ;
+                }
+                pin (A) {
+                    direction : input ;
+                    capacitance : 0.0023 ;
+                }
+                pin (B) {
+                    direction : input ;
+                    capacitance : 0.0025 ;
+                }
+                pin (Y) {
+                    direction : output ;
+                    function : "!(A&B)" ;
+                    timing () {
+                        related_pin : "A" ;
+                        timing_sense : negative_unate ;
+                        cell_rise (del_2_2) {
+                            values ("0.10, 0.30", \
+                                    "0.20, 0.40") ;
+                        }
+                        cell_fall (del_2_2) {
+                            values ("0.05, 0.15", "0.10, 0.20") ;
+                        }
+                    }
+                }
+            }
+        }
+    "#};
+
+    #[test]
+    fn liberty_library_attributes() {
+        let liberty = LLibertyLibrary::from_str(NAND2_LIBERTY).unwrap();
+        assert_eq!("example_lib", liberty.name());
+        assert_eq!(&Some("1ns".to_owned()), liberty.time_unit());
+        assert_eq!(
+            &Some((1.0, "pf".to_owned())),
+            liberty.capacitive_load_unit()
+        );
+        let nand2 = liberty.cell("NAND2").unwrap();
+        assert_eq!(3.75, *nand2.area());
+        assert_eq!(Some(0.0025), nand2.pin_capacitance("B"));
+        assert_eq!(
+            Some(LibertyPinDirection::Output),
+            *nand2.pin("Y").unwrap().direction()
+        );
+        assert_eq!(2.0, nand2.leakage_power(), "Average of leakage states.");
+    }
+
+    #[test]
+    fn nldm_table_interpolation() {
+        let liberty = LLibertyLibrary::from_str(NAND2_LIBERTY).unwrap();
+        let nand2 = liberty.cell("NAND2").unwrap();
+        let cell_rise = nand2
+            .pin("Y")
+            .and_then(|pin| pin.timing_arc("A"))
+            .and_then(|timing_arc| timing_arc.cell_rise().as_ref())
+            .unwrap();
+        assert!(
+            (cell_rise.lookup(0.1, 0.01) - 0.10).abs() < 1e-9,
+            "Table corner."
+        );
+        assert!(
+            (cell_rise.lookup(0.3, 0.03) - 0.25).abs() < 1e-9,
+            "Table center."
+        );
+        assert!(
+            (cell_rise.lookup(0.1, 0.07) - 0.40).abs() < 1e-9,
+            "Load beyond the table should extrapolate."
+        );
+        assert_eq!(
+            Some(0.25),
+            nand2
+                .arc_delay("A", "Y", 0.3, 0.03)
+                .map(|delay| (delay * 1e9).round() / 1e9),
+            "Arc delay is the worse of rise and fall."
+        );
+        assert!(nand2.arc_delay("B", "Y", 0.3, 0.03).is_none());
+        assert_eq!(
+            vec!["NAND2"],
+            liberty
+                .cell_delays(0.3, 0.03)
+                .into_keys()
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn scalar_and_untemplated_tables() {
+        let liberty = LLibertyLibrary::from_str(indoc::indoc! {r#"
+            library (scalar_lib) {
+                cell (BUF) {
+                    cell_leakage_power : 0.5 ;
+                    pin (Y) {
+                        direction : output ;
+                        timing () {
+                            related_pin : "A" ;
+                            cell_rise (scalar) { values ("0.07") ; }
+                            cell_fall (lut_1d) {
+                                index_1 ("0.0, 1.0") ;
+                                values ("0.02, 0.12") ;
+                            }
+                        }
+                    }
+                }
+            }
+        "#})
+        .unwrap();
+        let buf = liberty.cell("BUF").unwrap();
+        assert_eq!(0.5, buf.leakage_power());
+        let timing_arc = buf.pin("Y").unwrap().timing_arc("A").unwrap();
+        assert_eq!(
+            Some(0.07),
+            timing_arc
+                .cell_rise()
+                .as_ref()
+                .map(|table| table.lookup(0.5, 0.0))
+        );
+        assert!((timing_arc.delay(0.8, 0.0).unwrap() - 0.10).abs() < 1e-9);
+    }
+
+    #[test]
+    fn liberty_syntax_errors() {
+        assert_eq!(
+            Err(LibertyError::MissingLibrary),
+            LLibertyLibrary::from_str("cell (X) { }")
+        );
+        assert!(matches!(
+            LLibertyLibrary::from_str("library (broken) {\n  cell (X) {\n"),
+            Err(LibertyError::Syntax { .. })
+        ));
+    }
+
+    #[test]
+    fn liberty_malformed_table() {
+        let liberty = LLibertyLibrary::from_str(indoc::indoc! {r#"
+            library (malformed_lib) {
+                cell (BUF) {
+                    pin (Y) {
+                        direction : output ;
+                        timing () {
+                            related_pin : "A" ;
+                            cell_rise (lut_2d) {
+                                index_1 ("0.0, 0.5, 1.0") ;
+                                index_2 ("0.0, 0.1") ;
+                                values ("0.02, 0.03", "0.04, 0.05") ;
+                            }
+                        }
+                    }
+                }
+            }
+        "#});
+        assert_eq!(
+            Err(LibertyError::TableDimensions {
+                table: "cell_rise".to_owned(),
+                rows: 3,
+                columns: 2,
+            }),
+            liberty
+        );
+    }
+
+    #[test]
+    fn liberty_example_file() {
+        let mut liberty_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        liberty_path.push("resources/liberty_examples/example_cells.lib");
+        let liberty = LLibertyLibrary::open(liberty_path).unwrap();
+        assert_eq!(3, liberty.cells().len());
+        let inv = liberty.cell("INV_X1").unwrap();
+        assert_eq!(Some(0.0017), inv.pin_capacitance("A"));
+        assert!(inv.worst_delay(0.05, 0.01).is_some());
+    }
+
+    #[test]
+    fn liberty_write_round_trip() {
+        let mut liberty_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        liberty_path.push("resources/liberty_examples/example_cells.lib");
+        let liberty = LLibertyLibrary::open(liberty_path).unwrap();
+        let written = liberty.to_string();
+        assert_eq!(liberty, written.parse::<LLibertyLibrary>().unwrap());
+    }
+
+    #[test]
+    fn liberty_built_library() {
+        let delay = NldmTable::builder()
+            .index_1(vec![0.01, 0.1])
+            .index_2(vec![0.001, 0.01])
+            .values(vec![vec![0.02, 0.05], vec![0.03, 0.07]])
+            .build();
+        let output = LibertyPin::builder()
+            .name("Y")
+            .direction(LibertyPinDirection::Output)
+            .function("!A")
+            .timing_arcs(vec![LibertyTimingArc::builder()
+                .related_pin("A")
+                .timing_sense("negative_unate")
+                .cell_rise(delay.clone())
+                .cell_fall(delay)
+                .build()])
+            .build();
+        let cell = LibertyCell::builder()
+            .name("INV")
+            .pins(BTreeMap::from([("Y".to_owned(), output)]))
+            .build();
+        let liberty = LLibertyLibrary::builder()
+            .name("characterized")
+            .time_unit("1ns")
+            .capacitive_load_unit((1.0, "pf".to_owned()))
+            .cells(BTreeMap::from([("INV".to_owned(), cell)]))
+            .build();
+        let written = liberty.to_string();
+        assert!(written.contains("lu_table_template (transition_by_load)"));
+        let parsed = written.parse::<LLibertyLibrary>().unwrap();
+        assert_eq!(liberty, parsed);
+        assert_eq!(
+            Some(0.02),
+            parsed.cell("INV").unwrap().arc_delay("A", "Y", 0.01, 0.001)
+        );
+    }
+}
@@ -0,0 +1,883 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::str::FromStr;
+
+use derive_getters::Getters;
+use layout21::gds21::{GdsElement, GdsPoint, GdsStruct};
+use typed_builder::TypedBuilder;
+
+use super::gds_library::LGdsLibrary;
+use crate::circuit::equations::DeviceEquationMap;
+use crate::circuit::graph::hierarchy::FlattenError;
+use crate::circuit::graph::nodes::{ElementHNode, ElementKind};
+use crate::circuit::graph::LCircuit;
+use crate::circuit::models::ModelKind;
+use crate::circuit::nodes::CircuitNode;
+use crate::circuit::spice::SPICENetlist;
+
+/// Device assignments tried before `LayoutNetlist::compare` settles on its best partial match.
+const MATCH_BUDGET: usize = 200_000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LvsError {
+    MissingStructure(String),
+    Hierarchy(FlattenError),
+}
+
+impl fmt::Display for LvsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingStructure(cell) => write!(f, "No GDS structure for cell {}", cell),
+            Self::Hierarchy(error) => write!(f, "Unable to flatten cell netlist: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for LvsError {}
+
+/// GDS `(layer, datatype)` pair, `datatype` doubling as the `texttype` of labels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GdsLayer {
+    pub layer: i16,
+    pub datatype: i16,
+}
+
+impl GdsLayer {
+    pub const fn new(layer: i16, datatype: i16) -> Self {
+        Self { layer, datatype }
+    }
+}
+
+/// Extracted layers carrying nets, `Metal(0)` being the lowest entry of `LvsLayerMap::metals`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LvsConductor {
+    /// Source and drain diffusion, the diffusion layer outside of poly.
+    Diffusion,
+    Poly,
+    Well,
+    Metal(usize),
+}
+
+/// Cut layer joining every conductor shape it overlaps on `layers`.
+#[derive(Debug, Clone, PartialEq, Eq, TypedBuilder, Getters)]
+pub struct LvsContact {
+    cut: GdsLayer,
+    layers: Vec<LvsConductor>,
+}
+
+/// Layers extracted by `LvsLayerMap::extract`.
+///
+/// Transistors are the regions of `poly` over `diffusion`, an n-channel device under `n_implant`
+/// and a p-channel device under `p_implant`. P-channel bodies are the `well` net they sit in, and
+/// n-channel bodies the substrate, named by `substrate_labels`.
+#[derive(Debug, Clone, PartialEq, Eq, TypedBuilder, Getters)]
+pub struct LvsLayerMap {
+    diffusion: GdsLayer,
+    poly: GdsLayer,
+    n_implant: GdsLayer,
+    p_implant: GdsLayer,
+    well: GdsLayer,
+    #[builder(default)]
+    metals: Vec<GdsLayer>,
+    #[builder(default)]
+    contacts: Vec<LvsContact>,
+    #[builder(default)]
+    labels: Vec<(GdsLayer, LvsConductor)>,
+    #[builder(default)]
+    substrate_labels: Vec<GdsLayer>,
+}
+
+impl LvsLayerMap {
+    /// SkyWater 130nm front end, `li1` and `met1`, as drawn in the `sky130_fd_sc_*` cells.
+    pub fn sky130() -> Self {
+        Self::builder()
+            .diffusion(GdsLayer::new(65, 20))
+            .poly(GdsLayer::new(66, 20))
+            .n_implant(GdsLayer::new(93, 44))
+            .p_implant(GdsLayer::new(94, 20))
+            .well(GdsLayer::new(64, 20))
+            .metals(vec![GdsLayer::new(67, 20), GdsLayer::new(68, 20)])
+            .contacts(vec![
+                LvsContact::builder()
+                    .cut(GdsLayer::new(66, 44))
+                    .layers(vec![
+                        LvsConductor::Diffusion,
+                        LvsConductor::Poly,
+                        LvsConductor::Metal(0),
+                    ])
+                    .build(),
+                LvsContact::builder()
+                    .cut(GdsLayer::new(67, 44))
+                    .layers(vec![LvsConductor::Metal(0), LvsConductor::Metal(1)])
+                    .build(),
+            ])
+            .labels(vec![
+                (GdsLayer::new(66, 5), LvsConductor::Poly),
+                (GdsLayer::new(67, 5), LvsConductor::Metal(0)),
+                (GdsLayer::new(68, 5), LvsConductor::Metal(1)),
+                (GdsLayer::new(64, 5), LvsConductor::Well),
+            ])
+            .substrate_labels(vec![GdsLayer::new(64, 59)])
+            .build()
+    }
+
+    /// Transistors and nets of a flat `GdsStruct`, built from its boundaries and text labels.
+    ///
+    /// Only nets reaching a transistor or carrying a label are kept.
+    pub fn extract(&self, structure: &GdsStruct) -> LayoutNetlist {
+        let mut layer_rects: BTreeMap<GdsLayer, Vec<Rect>> = BTreeMap::new();
+        let mut texts = Vec::new();
+        structure.elems.iter().for_each(|element| match element {
+            GdsElement::GdsBoundary(boundary) => layer_rects
+                .entry(GdsLayer::new(boundary.layer, boundary.datatype))
+                .or_default()
+                .extend(polygon_rects(&boundary.xy)),
+            GdsElement::GdsTextElem(text) => texts.push((
+                GdsLayer::new(text.layer, text.texttype),
+                text.string.to_string(),
+                (text.xy.x, text.xy.y),
+            )),
+            _ => (),
+        });
+        let rects = |layer: &GdsLayer| layer_rects.get(layer).cloned().unwrap_or_default();
+
+        let diffusion = rects(&self.diffusion);
+        let poly = rects(&self.poly);
+        let channels = diffusion
+            .iter()
+            .flat_map(|diff| poly.iter().filter_map(|gate| diff.intersection(gate)))
+            .collect::<Vec<_>>();
+        let diffusion_pieces = diffusion
+            .iter()
+            .flat_map(|diff| {
+                poly.iter().fold(vec![*diff], |pieces, gate| {
+                    pieces
+                        .iter()
+                        .flat_map(|piece| piece.subtract(gate))
+                        .collect()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut shapes = diffusion_pieces
+            .into_iter()
+            .map(|rect| (LvsConductor::Diffusion, rect))
+            .chain(poly.into_iter().map(|rect| (LvsConductor::Poly, rect)))
+            .chain(
+                rects(&self.well)
+                    .into_iter()
+                    .map(|rect| (LvsConductor::Well, rect)),
+            )
+            .collect::<Vec<_>>();
+        self.metals.iter().enumerate().for_each(|(level, layer)| {
+            shapes.extend(
+                rects(layer)
+                    .into_iter()
+                    .map(|rect| (LvsConductor::Metal(level), rect)),
+            )
+        });
+        let substrate = shapes.len();
+        let mut connectivity = DisjointSet::new(shapes.len() + 1);
+        for (index, (conductor, rect)) in shapes.iter().enumerate() {
+            for (other_index, (other_conductor, other_rect)) in shapes[..index].iter().enumerate() {
+                if conductor == other_conductor && rect.touches(other_rect) {
+                    connectivity.union(index, other_index);
+                }
+            }
+        }
+        for contact in &self.contacts {
+            for cut in rects(&contact.cut) {
+                let contacted = shapes
+                    .iter()
+                    .enumerate()
+                    .filter(|(_index, (conductor, rect))| {
+                        contact.layers.contains(conductor) && rect.overlaps(&cut)
+                    })
+                    .map(|(index, _shape)| index)
+                    .collect::<Vec<_>>();
+                contacted
+                    .windows(2)
+                    .for_each(|pair| connectivity.union(pair[0], pair[1]));
+            }
+        }
+        let roots = (0..=substrate)
+            .map(|shape| connectivity.find(shape))
+            .collect::<Vec<_>>();
+
+        let mut netlist = LayoutNetlist::default();
+        let mut net_ids: BTreeMap<usize, usize> = BTreeMap::new();
+        let mut net_id = |shape: usize, layout: &mut LayoutNetlist| {
+            *net_ids.entry(roots[shape]).or_insert_with(|| {
+                layout.nets.push(BTreeSet::new());
+                layout.nets.len() - 1
+            })
+        };
+
+        let mut channel_groups = DisjointSet::new(channels.len());
+        for (index, channel) in channels.iter().enumerate() {
+            for (other_index, other_channel) in channels[..index].iter().enumerate() {
+                if channel.touches(other_channel) {
+                    channel_groups.union(index, other_index);
+                }
+            }
+        }
+        let devices = (0..channels.len())
+            .fold(BTreeMap::<usize, Vec<Rect>>::new(), |mut devices, index| {
+                devices
+                    .entry(channel_groups.find(index))
+                    .or_default()
+                    .push(channels[index]);
+                devices
+            })
+            .into_values()
+            .collect::<Vec<_>>();
+        for channel in devices {
+            let in_layer = |layer: &GdsLayer| {
+                rects(layer)
+                    .iter()
+                    .any(|rect| channel.iter().any(|part| part.overlaps(rect)))
+            };
+            let kind = if in_layer(&self.n_implant) {
+                ModelKind::Nmos
+            } else if in_layer(&self.p_implant) {
+                ModelKind::Pmos
+            } else {
+                ModelKind::Other("mos".to_owned())
+            };
+            let Some(gate) = channel
+                .iter()
+                .find_map(|part| overlapping_shape(&shapes, LvsConductor::Poly, part))
+            else {
+                continue;
+            };
+            let source_drain = shapes
+                .iter()
+                .enumerate()
+                .filter(|(_index, (conductor, rect))| {
+                    *conductor == LvsConductor::Diffusion
+                        && channel.iter().any(|part| part.touches(rect))
+                })
+                .map(|(index, _shape)| roots[index])
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect::<Vec<_>>();
+            let body = channel
+                .iter()
+                .find_map(|part| overlapping_shape(&shapes, LvsConductor::Well, part))
+                .unwrap_or(substrate);
+            let device = LayoutDevice {
+                kind,
+                gate: net_id(gate, &mut netlist),
+                source_drain: source_drain
+                    .into_iter()
+                    .map(|shape| net_id(shape, &mut netlist))
+                    .collect(),
+                body: net_id(body, &mut netlist),
+                location: (channel[0].x0, channel[0].y0),
+            };
+            netlist.devices.push(device);
+        }
+
+        for (layer, label, point) in texts {
+            let shape = if self.substrate_labels.contains(&layer) {
+                Some(substrate)
+            } else {
+                self.labels
+                    .iter()
+                    .filter(|(label_layer, _conductor)| *label_layer == layer)
+                    .find_map(|(_layer, conductor)| {
+                        shapes.iter().position(|(shape_conductor, rect)| {
+                            shape_conductor == conductor && rect.contains(point)
+                        })
+                    })
+            };
+            if let Some(shape) = shape {
+                let net = net_id(shape, &mut netlist);
+                netlist.nets[net].insert(label);
+            }
+        }
+        netlist
+    }
+}
+
+/// Transistor extracted from layout, its terminals indexing `LayoutNetlist::nets`.
+#[derive(Debug, Clone, PartialEq, Eq, Getters)]
+pub struct LayoutDevice {
+    kind: ModelKind,
+    gate: usize,
+    /// Distinct diffusion nets beside the channel, two for a well-formed transistor.
+    source_drain: Vec<usize>,
+    body: usize,
+    /// Lower left corner of the channel, in database units.
+    location: (i32, i32),
+}
+
+/// Connectivity extracted by `LvsLayerMap::extract`, nets being the set of labels naming them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Getters)]
+pub struct LayoutNetlist {
+    nets: Vec<BTreeSet<String>>,
+    devices: Vec<LayoutDevice>,
+}
+
+impl LayoutNetlist {
+    /// Matches the transistors of a flat `schematic` against the extracted devices.
+    ///
+    /// Labeled layout nets are bound to the schematic nets of the same name, then devices are
+    /// paired by kind and consistent gate, body and(unordered) source and drain nets, backtracking
+    /// on ambiguity. Elements other than MOS transistors have no layout counterpart and are
+    /// reported unmatched.
+    pub fn compare(&self, schematic: &LCircuit) -> LvsReport {
+        let elements = schematic
+            .elements()
+            .map(|(_element_id, element)| element)
+            .filter(|element| element.kind != ElementKind::Port)
+            .collect::<Vec<_>>();
+        let transistors = elements
+            .iter()
+            .enumerate()
+            .filter_map(|(index, element)| {
+                SchematicDevice::from_element(element).map(|device| (index, device))
+            })
+            .collect::<Vec<_>>();
+
+        let mut seed = Binding::default();
+        for (net, labels) in self.nets.iter().enumerate() {
+            for node in labels
+                .iter()
+                .filter_map(|label| CircuitNode::from_str(label).ok())
+            {
+                if schematic.net(&node).is_some()
+                    && !seed.nets.contains_key(&node)
+                    && !seed.layout_nets.contains_key(&net)
+                {
+                    seed.nets.insert(node.to_owned(), net);
+                    seed.layout_nets.insert(net, node);
+                }
+            }
+        }
+        let mut search = DeviceSearch {
+            layout: self,
+            transistors: &transistors,
+            budget: MATCH_BUDGET,
+            best: seed.to_owned(),
+        };
+        search.run(seed);
+        let binding = search.best;
+
+        let layout_device_nets = self
+            .devices
+            .iter()
+            .flat_map(|device| {
+                [device.gate, device.body]
+                    .into_iter()
+                    .chain(device.source_drain.iter().copied())
+            })
+            .collect::<BTreeSet<_>>();
+        LvsReport {
+            matched_devices: binding
+                .devices
+                .iter()
+                .map(|(transistor, layout)| {
+                    (
+                        elements[transistors[*transistor].0].name.to_owned(),
+                        *layout,
+                    )
+                })
+                .collect(),
+            unmatched_schematic_devices: elements
+                .iter()
+                .enumerate()
+                .filter(|(index, _element)| {
+                    !binding
+                        .devices
+                        .keys()
+                        .any(|transistor| transistors[*transistor].0 == *index)
+                })
+                .map(|(_index, element)| element.name.to_owned())
+                .collect(),
+            unmatched_layout_devices: (0..self.devices.len())
+                .filter(|device| !binding.devices.values().any(|layout| layout == device))
+                .collect(),
+            unmatched_schematic_nets: elements
+                .iter()
+                .flat_map(|element| element.terminals.iter())
+                .filter(|node| !binding.nets.contains_key(*node))
+                .cloned()
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect(),
+            unmatched_layout_nets: layout_device_nets
+                .into_iter()
+                .filter(|net| !binding.layout_nets.contains_key(net))
+                .collect(),
+            matched_nets: binding.nets,
+        }
+    }
+}
+
+/// Result of `LayoutNetlist::compare`, layout devices and nets indexing the `LayoutNetlist`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Getters)]
+pub struct LvsReport {
+    matched_devices: BTreeMap<String, usize>,
+    matched_nets: BTreeMap<CircuitNode, usize>,
+    unmatched_schematic_devices: Vec<String>,
+    unmatched_layout_devices: Vec<usize>,
+    unmatched_schematic_nets: Vec<CircuitNode>,
+    unmatched_layout_nets: Vec<usize>,
+}
+
+impl LvsReport {
+    pub fn is_clean(&self) -> bool {
+        self.unmatched_schematic_devices.is_empty()
+            && self.unmatched_layout_devices.is_empty()
+            && self.unmatched_schematic_nets.is_empty()
+            && self.unmatched_layout_nets.is_empty()
+    }
+}
+
+impl LGdsLibrary {
+    pub fn structure(&self, cell: &str) -> Option<&GdsStruct> {
+        self.structs
+            .iter()
+            .find(|structure| &*structure.name == cell)
+    }
+
+    pub fn layout_netlist(
+        &self,
+        cell: &str,
+        layers: &LvsLayerMap,
+    ) -> Result<LayoutNetlist, LvsError> {
+        self.structure(cell)
+            .map(|structure| layers.extract(structure))
+            .ok_or_else(|| LvsError::MissingStructure(cell.to_owned()))
+    }
+}
+
+/// Compares the `cell` structure of `gds` with its flattened `.subckt` in `netlist`.
+pub fn check_cell(
+    gds: &LGdsLibrary,
+    netlist: &SPICENetlist,
+    cell: &str,
+    layers: &LvsLayerMap,
+) -> Result<LvsReport, LvsError> {
+    let layout = gds.layout_netlist(cell, layers)?;
+    let schematic = LCircuit::from((netlist, &DeviceEquationMap::new()))
+        .flatten_subcircuit(cell)
+        .map_err(LvsError::Hierarchy)?;
+    Ok(layout.compare(&schematic))
+}
+
+/// MOS transistor of a schematic, its kind guessed from the model name(`nfet`, `pmos`, ...).
+#[derive(Debug)]
+struct SchematicDevice<'c> {
+    kind: ModelKind,
+    gate: &'c CircuitNode,
+    source_drain: [&'c CircuitNode; 2],
+    body: &'c CircuitNode,
+}
+
+impl<'c> SchematicDevice<'c> {
+    fn from_element(element: &'c ElementHNode) -> Option<Self> {
+        let [drain, gate, source, body] = element.terminals.as_slice() else {
+            return None;
+        };
+        if !matches!(
+            element.kind,
+            ElementKind::MosTransistor | ElementKind::Subcircuit
+        ) {
+            return None;
+        }
+        let kind = ModelKind::transistor(element.value.as_deref()?)?;
+        Some(Self {
+            kind,
+            gate,
+            source_drain: [drain, source],
+            body,
+        })
+    }
+}
+
+/// Schematic to layout correspondence, `devices` indexing the compared transistors.
+#[derive(Debug, Clone, Default)]
+struct Binding {
+    nets: BTreeMap<CircuitNode, usize>,
+    layout_nets: BTreeMap<usize, CircuitNode>,
+    devices: BTreeMap<usize, usize>,
+}
+
+impl Binding {
+    fn bind<'c>(&self, pairs: impl IntoIterator<Item = (&'c CircuitNode, usize)>) -> Option<Self> {
+        let mut binding = self.to_owned();
+        for (node, net) in pairs {
+            match (binding.nets.get(node), binding.layout_nets.get(&net)) {
+                (Some(bound_net), _) if *bound_net != net => return None,
+                (_, Some(bound_node)) if bound_node != node => return None,
+                _ => {
+                    binding.nets.insert(node.to_owned(), net);
+                    binding.layout_nets.insert(net, node.to_owned());
+                }
+            }
+        }
+        Some(binding)
+    }
+}
+
+/// Depth first device assignment, most constrained schematic transistor first.
+struct DeviceSearch<'l, 'c> {
+    layout: &'l LayoutNetlist,
+    transistors: &'l [(usize, SchematicDevice<'c>)],
+    budget: usize,
+    best: Binding,
+}
+
+impl DeviceSearch<'_, '_> {
+    fn run(&mut self, binding: Binding) -> bool {
+        if binding.devices.len() > self.best.devices.len() {
+            self.best = binding.to_owned();
+        }
+        if binding.devices.len() == self.transistors.len() {
+            return true;
+        }
+        if self.budget == 0 {
+            return false;
+        }
+        self.budget -= 1;
+        let mut choice: Option<Vec<Binding>> = None;
+        for (transistor, (_element, device)) in self.transistors.iter().enumerate() {
+            if binding.devices.contains_key(&transistor) {
+                continue;
+            }
+            let options = self.options(&binding, transistor, device);
+            let most_constrained = options.len() <= 1;
+            if choice
+                .as_ref()
+                .map_or(true, |choice| options.len() < choice.len())
+            {
+                choice = Some(options);
+            }
+            if most_constrained {
+                break;
+            }
+        }
+        choice
+            .unwrap_or_default()
+            .into_iter()
+            .any(|option| self.run(option))
+    }
+
+    fn options(
+        &self,
+        binding: &Binding,
+        transistor: usize,
+        device: &SchematicDevice,
+    ) -> Vec<Binding> {
+        let bound_layout = binding.devices.values().collect::<BTreeSet<_>>();
+        let [first, second] = device.source_drain;
+        self.layout
+            .devices
+            .iter()
+            .enumerate()
+            .filter(|(index, layout)| !bound_layout.contains(index) && layout.kind == device.kind)
+            .flat_map(|(index, layout)| {
+                let (a, b) = match layout.source_drain.as_slice() {
+                    [shorted] => (*shorted, *shorted),
+                    [a, b] => (*a, *b),
+                    _ => return Vec::new(),
+                };
+                [(a, b), (b, a)]
+                    .into_iter()
+                    .filter_map(|(a, b)| {
+                        binding.bind([
+                            (device.gate, layout.gate),
+                            (device.body, layout.body),
+                            (first, a),
+                            (second, b),
+                        ])
+                    })
+                    .map(|mut option| {
+                        option.devices.insert(transistor, index);
+                        option
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Axis aligned rectangle, `(x0, y0)` its lower left and `(x1, y1)` its upper right corner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Rect {
+    x0: i32,
+    y0: i32,
+    x1: i32,
+    y1: i32,
+}
+
+impl Rect {
+    /// Overlapping or sharing an edge or corner.
+    fn touches(&self, other: &Rect) -> bool {
+        self.x0 <= other.x1 && other.x0 <= self.x1 && self.y0 <= other.y1 && other.y0 <= self.y1
+    }
+
+    /// Sharing a non-empty area.
+    fn overlaps(&self, other: &Rect) -> bool {
+        self.x0 < other.x1 && other.x0 < self.x1 && self.y0 < other.y1 && other.y0 < self.y1
+    }
+
+    fn contains(&self, (x, y): (i32, i32)) -> bool {
+        self.x0 <= x && x <= self.x1 && self.y0 <= y && y <= self.y1
+    }
+
+    fn intersection(&self, other: &Rect) -> Option<Rect> {
+        self.overlaps(other).then(|| Rect {
+            x0: self.x0.max(other.x0),
+            y0: self.y0.max(other.y0),
+            x1: self.x1.min(other.x1),
+            y1: self.y1.min(other.y1),
+        })
+    }
+
+    /// Up to four rectangles covering `self` outside of `other`.
+    fn subtract(&self, other: &Rect) -> Vec<Rect> {
+        let Some(cut) = self.intersection(other) else {
+            return vec![*self];
+        };
+        [
+            Rect {
+                y1: cut.y0,
+                ..*self
+            },
+            Rect {
+                y0: cut.y1,
+                ..*self
+            },
+            Rect {
+                y0: cut.y0,
+                x1: cut.x0,
+                y1: cut.y1,
+                ..*self
+            },
+            Rect {
+                x0: cut.x1,
+                y0: cut.y0,
+                y1: cut.y1,
+                ..*self
+            },
+        ]
+        .into_iter()
+        .filter(|rect| rect.x0 < rect.x1 && rect.y0 < rect.y1)
+        .collect()
+    }
+}
+
+/// Horizontal slabs of a polygon between consecutive vertex ordinates, filled even-odd.
+fn polygon_rects(points: &[GdsPoint]) -> Vec<Rect> {
+    let points = match points {
+        [first, .., last] if first == last => &points[..points.len() - 1],
+        _ => points,
+    };
+    let edges = points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .filter(|(start, end)| start.y != end.y)
+        .collect::<Vec<_>>();
+    let ordinates = points
+        .iter()
+        .map(|point| point.y)
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    ordinates
+        .windows(2)
+        .flat_map(|slab| {
+            let middle = (f64::from(slab[0]) + f64::from(slab[1])) / 2.0;
+            let mut crossings = edges
+                .iter()
+                .filter(|(start, end)| {
+                    f64::from(start.y.min(end.y)) <= middle
+                        && middle < f64::from(start.y.max(end.y))
+                })
+                .map(|(start, end)| {
+                    let (x0, y0) = (f64::from(start.x), f64::from(start.y));
+                    let (x1, y1) = (f64::from(end.x), f64::from(end.y));
+                    (x0 + (x1 - x0) * (middle - y0) / (y1 - y0)).round() as i32
+                })
+                .collect::<Vec<_>>();
+            crossings.sort_unstable();
+            crossings
+                .chunks_exact(2)
+                .filter(|span| span[0] < span[1])
+                .map(|span| Rect {
+                    x0: span[0],
+                    y0: slab[0],
+                    x1: span[1],
+                    y1: slab[1],
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+fn overlapping_shape(
+    shapes: &[(LvsConductor, Rect)],
+    conductor: LvsConductor,
+    rect: &Rect,
+) -> Option<usize> {
+    shapes
+        .iter()
+        .position(|(shape_conductor, shape)| *shape_conductor == conductor && shape.overlaps(rect))
+}
+
+/// Union-find over shape indices.
+struct DisjointSet(Vec<usize>);
+
+impl DisjointSet {
+    fn new(size: usize) -> Self {
+        Self((0..size).collect())
+    }
+
+    fn find(&mut self, mut index: usize) -> usize {
+        while self.0[index] != index {
+            self.0[index] = self.0[self.0[index]];
+            index = self.0[index];
+        }
+        index
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        self.0[a] = b;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use layout21::gds21::GdsLibrary;
+
+    use super::*;
+    use crate::llhd_library::characterization::tests::sky130_cell_path;
+
+    fn sky130_cell(family: &str, cell_name: &str) -> (LGdsLibrary, SPICENetlist) {
+        let cell_path = sky130_cell_path(family, cell_name);
+        let gds = GdsLibrary::open(cell_path.with_extension("gds")).unwrap();
+        let spice_str = fs::read_to_string(cell_path.with_extension("spice")).unwrap();
+        (gds.into(), SPICENetlist::parse_deck(&spice_str).unwrap())
+    }
+
+    #[test]
+    fn polygon_decomposition() {
+        let point = |x, y| GdsPoint::new(x, y);
+        let ell = [
+            point(0, 0),
+            point(4, 0),
+            point(4, 2),
+            point(2, 2),
+            point(2, 6),
+            point(0, 6),
+            point(0, 0),
+        ];
+        assert_eq!(
+            vec![
+                Rect {
+                    x0: 0,
+                    y0: 0,
+                    x1: 4,
+                    y1: 2
+                },
+                Rect {
+                    x0: 0,
+                    y0: 2,
+                    x1: 2,
+                    y1: 6
+                },
+            ],
+            polygon_rects(&ell)
+        );
+        let plate = Rect {
+            x0: 0,
+            y0: 0,
+            x1: 4,
+            y1: 2,
+        };
+        let gate = Rect {
+            x0: 1,
+            y0: -1,
+            x1: 2,
+            y1: 3,
+        };
+        assert_eq!(2, plate.subtract(&gate).len());
+        assert!(plate.subtract(&gate).iter().all(|rect| rect.touches(&gate)));
+    }
+
+    #[test]
+    fn inverter_layout_extraction() {
+        let (gds, _netlist) = sky130_cell("inv", "sky130_fd_sc_ls__inv_1");
+        let layout = gds
+            .layout_netlist("sky130_fd_sc_ls__inv_1", &LvsLayerMap::sky130())
+            .unwrap();
+        assert_eq!(2, layout.devices().len());
+        let net = |label: &str| {
+            layout
+                .nets()
+                .iter()
+                .position(|labels| labels.contains(label))
+                .unwrap()
+        };
+        let pmos = layout
+            .devices()
+            .iter()
+            .find(|device| *device.kind() == ModelKind::Pmos)
+            .unwrap();
+        assert_eq!(net("A"), *pmos.gate());
+        assert_eq!(net("VPB"), *pmos.body());
+        assert_eq!(
+            BTreeSet::from([net("VPWR"), net("Y")]),
+            pmos.source_drain().iter().copied().collect()
+        );
+    }
+
+    #[test]
+    fn sky130_cells_lvs_clean() {
+        for (family, cell) in [
+            ("inv", "sky130_fd_sc_ls__inv_1"),
+            ("nand2", "sky130_fd_sc_ls__nand2_1"),
+            ("a2111o", "sky130_fd_sc_ls__a2111o_1"),
+            ("xor3", "sky130_fd_sc_ls__xor3_1"),
+        ] {
+            let (gds, netlist) = sky130_cell(family, cell);
+            let report = check_cell(&gds, &netlist, cell, &LvsLayerMap::sky130()).unwrap();
+            assert!(
+                report.is_clean(),
+                "{} should be LVS clean: {:?}",
+                cell,
+                report
+            );
+        }
+    }
+
+    #[test]
+    fn missing_contact_is_reported() {
+        let cell = "sky130_fd_sc_ls__nand2_1";
+        let (mut gds, netlist) = sky130_cell("nand2", cell);
+        let layers = LvsLayerMap::sky130();
+        let licon = layers.contacts()[0].cut;
+        gds.structs[0].elems.retain(|element| {
+            !matches!(element, GdsElement::GdsBoundary(boundary)
+                if GdsLayer::new(boundary.layer, boundary.datatype) == licon)
+        });
+        let report = check_cell(&gds, &netlist, cell, &layers).unwrap();
+        assert!(!report.is_clean());
+        assert!(!report.unmatched_schematic_devices().is_empty());
+    }
+
+    #[test]
+    fn missing_structure() {
+        let (gds, netlist) = sky130_cell("inv", "sky130_fd_sc_ls__inv_1");
+        assert_eq!(
+            Err(LvsError::MissingStructure("inv".to_owned())),
+            check_cell(&gds, &netlist, "inv", &LvsLayerMap::sky130())
+        );
+    }
+}
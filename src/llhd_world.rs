@@ -2,5 +2,9 @@ mod bevy_example;
 pub(crate) mod components;
 // mod example_hierarchy;
 // mod example_relations;
+pub(crate) mod graph;
 mod initializer;
+pub(crate) mod metrics;
+pub(crate) mod placement;
+pub(crate) mod timing;
 pub(crate) mod world;
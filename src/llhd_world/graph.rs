@@ -0,0 +1,465 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt;
+
+use bevy_ecs::prelude::Entity;
+use llhd::ir::{Inst, UnitId, Value, ValueData};
+
+use crate::llhd_world::components::inst::LLHDInstComponent;
+use crate::llhd_world::components::value::LLHDValueDefComponent;
+use crate::llhd_world::world::{InstIndex, LLHDWorld, LLHDWorldError};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LLHDGraphError {
+    /// Inst on a cycle of the DFG, which has no topological order.
+    Cycle(InstIndex),
+}
+
+impl fmt::Display for LLHDGraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cycle((unit_id, inst)) => {
+                write!(
+                    f,
+                    "Inst `{}` of unit `{}` is part of a cycle.",
+                    inst, unit_id
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for LLHDGraphError {}
+
+/// Inst DFG of a Unit, from the value-ref entities of an `LLHDWorld`.
+///
+/// An inst fans in from the insts defining the values it reads, and fans out to the insts reading
+/// its result. Queries return `(UnitId, Inst)` in inst order, `entities` maps them back to the
+/// world.
+#[derive(Debug, Clone, Default)]
+pub struct LLHDInstGraph {
+    unit_id: Option<UnitId>,
+    insts: BTreeMap<Inst, Entity>,
+    drivers: BTreeMap<Value, Inst>,
+    users: BTreeMap<Value, BTreeSet<Inst>>,
+    fanins: BTreeMap<Inst, BTreeSet<Inst>>,
+    fanouts: BTreeMap<Inst, BTreeSet<Inst>>,
+    input_driven: BTreeSet<Inst>,
+}
+
+impl LLHDInstGraph {
+    pub fn new(llhd_world: &LLHDWorld, unit_id: UnitId) -> Result<Self, LLHDWorldError> {
+        let insts = llhd_world
+            .unit_program_inst::<LLHDInstComponent>(unit_id)?
+            .map(|((_unit_id, inst), _inst_component, _inst_data)| {
+                llhd_world
+                    .inst_entity(unit_id, inst)
+                    .map(|inst_entity| (inst, inst_entity))
+            })
+            .collect::<Result<BTreeMap<_, _>, _>>()?;
+        let mut graph = Self {
+            unit_id: Some(unit_id),
+            insts,
+            ..Self::default()
+        };
+        llhd_world
+            .unit_value_refs(unit_id)
+            .for_each(|value_ref_component| {
+                if let (Some(value), Some(inst)) =
+                    (value_ref_component.id, value_ref_component.inst)
+                {
+                    graph.users.entry(value).or_default().insert(inst);
+                }
+            });
+        let input_args: BTreeSet<Value> = llhd_world.module().unit(unit_id).input_args().collect();
+        for (value, users) in &graph.users {
+            match llhd_world.get_value_def::<LLHDValueDefComponent>(unit_id, *value) {
+                Ok(LLHDValueDefComponent {
+                    data: ValueData::Inst { inst: driver, .. },
+                    ..
+                }) => {
+                    graph.drivers.insert(*value, *driver);
+                }
+                Ok(LLHDValueDefComponent {
+                    data: ValueData::Arg { .. },
+                    ..
+                }) if input_args.contains(value) => {
+                    graph.input_driven.extend(users.iter().copied());
+                }
+                _ => (),
+            }
+        }
+        for (value, users) in &graph.users {
+            let Some(driver) = graph.drivers.get(value) else {
+                continue;
+            };
+            graph
+                .fanouts
+                .entry(*driver)
+                .or_default()
+                .extend(users.iter().copied());
+            users.iter().for_each(|user| {
+                graph.fanins.entry(*user).or_default().insert(*driver);
+            });
+        }
+        Ok(graph)
+    }
+
+    fn index(&self, insts: impl IntoIterator<Item = Inst>) -> Vec<InstIndex> {
+        let unit_id = self.unit_id.expect("Graph should be built from a unit.");
+        insts.into_iter().map(|inst| (unit_id, inst)).collect()
+    }
+
+    /// Entities of `insts`, skipping insts outside of the graph.
+    pub fn entities(&self, insts: &[InstIndex]) -> Vec<Entity> {
+        insts
+            .iter()
+            .filter_map(|(_unit_id, inst)| self.insts.get(inst).copied())
+            .collect()
+    }
+
+    /// Insts reading `value`.
+    pub fn value_users(&self, value: Value) -> Vec<InstIndex> {
+        self.index(self.users.get(&value).into_iter().flatten().copied())
+    }
+
+    /// Inst defining `value`, none for arguments.
+    pub fn value_driver(&self, value: Value) -> Option<InstIndex> {
+        self.index(self.drivers.get(&value).copied()).pop()
+    }
+
+    /// Whether `inst` reads a unit input argument.
+    pub fn is_input_driven(&self, inst: Inst) -> bool {
+        self.input_driven.contains(&inst)
+    }
+
+    pub fn fanins(&self, inst: Inst) -> Vec<InstIndex> {
+        self.index(self.fanins.get(&inst).into_iter().flatten().copied())
+    }
+
+    pub fn fanouts(&self, inst: Inst) -> Vec<InstIndex> {
+        self.index(self.fanouts.get(&inst).into_iter().flatten().copied())
+    }
+
+    /// `roots` and every inst they transitively fan in from.
+    pub fn fanin_cone(&self, roots: &[Inst]) -> Vec<InstIndex> {
+        self.index(Self::reachable(&self.fanins, roots))
+    }
+
+    /// `roots` and every inst they transitively fan out to.
+    pub fn fanout_cone(&self, roots: &[Inst]) -> Vec<InstIndex> {
+        self.index(Self::reachable(&self.fanouts, roots))
+    }
+
+    fn reachable(edges: &BTreeMap<Inst, BTreeSet<Inst>>, roots: &[Inst]) -> BTreeSet<Inst> {
+        let mut visited: BTreeSet<Inst> = roots.iter().copied().collect();
+        let mut pending: Vec<Inst> = roots.to_vec();
+        while let Some(inst) = pending.pop() {
+            for next in edges.get(&inst).into_iter().flatten() {
+                if visited.insert(*next) {
+                    pending.push(*next);
+                }
+            }
+        }
+        visited
+    }
+
+    /// Insts ordered after all of their fanins.
+    pub fn topological_order(&self) -> Result<Vec<InstIndex>, LLHDGraphError> {
+        kahn_order(self.insts.keys().copied(), &self.fanouts)
+            .map(|order| self.index(order))
+            .map_err(|looped_inst| LLHDGraphError::Cycle(self.index([looped_inst])[0]))
+    }
+
+    /// Strongly connected components, Tarjan's algorithm without recursion.
+    ///
+    /// Components come fanouts first, each in inst order, single insts without a self-loop
+    /// included.
+    pub fn strongly_connected_components(&self) -> Vec<Vec<InstIndex>> {
+        let mut tarjan = TarjanState::default();
+        for root in self.insts.keys() {
+            if tarjan.indices.contains_key(root) {
+                continue;
+            }
+            let mut call_stack = vec![tarjan.visit(&self.fanouts, *root)];
+            while let Some((inst, successors)) = call_stack.last_mut() {
+                let inst = *inst;
+                if let Some(successor) = successors.pop() {
+                    if !tarjan.indices.contains_key(&successor) {
+                        call_stack.push(tarjan.visit(&self.fanouts, successor));
+                    } else if tarjan.on_stack.contains(&successor) {
+                        tarjan.lower(inst, tarjan.indices[&successor]);
+                    }
+                    continue;
+                }
+                call_stack.pop();
+                if let Some((parent, _successors)) = call_stack.last() {
+                    tarjan.lower(*parent, tarjan.low_links[&inst]);
+                }
+                if tarjan.low_links[&inst] == tarjan.indices[&inst] {
+                    let component = tarjan.pop_component(inst);
+                    tarjan.components.push(self.index(component));
+                }
+            }
+        }
+        tarjan.components
+    }
+}
+
+/// Kahn's algorithm over `nodes`, ready nodes taken first in node order. Edges to or from nodes
+/// outside of `nodes` are ignored, a node on a cycle is the error.
+pub(crate) fn kahn_order<N: Copy + Ord>(
+    nodes: impl IntoIterator<Item = N>,
+    successors: &BTreeMap<N, BTreeSet<N>>,
+) -> Result<Vec<N>, N> {
+    let mut indegrees: BTreeMap<N, usize> = nodes.into_iter().map(|node| (node, 0)).collect();
+    for (node, node_successors) in successors {
+        if !indegrees.contains_key(node) {
+            continue;
+        }
+        for successor in node_successors {
+            if let Some(indegree) = indegrees.get_mut(successor) {
+                *indegree += 1;
+            }
+        }
+    }
+    let mut ready: VecDeque<N> = indegrees
+        .iter()
+        .filter(|(_node, indegree)| **indegree == 0)
+        .map(|(node, _indegree)| *node)
+        .collect();
+    let mut order = Vec::with_capacity(indegrees.len());
+    while let Some(node) = ready.pop_front() {
+        order.push(node);
+        for successor in successors.get(&node).into_iter().flatten() {
+            if let Some(indegree) = indegrees.get_mut(successor) {
+                *indegree -= 1;
+                if *indegree == 0 {
+                    ready.push_back(*successor);
+                }
+            }
+        }
+    }
+    let unordered: BTreeSet<N> = indegrees
+        .into_iter()
+        .filter(|(_node, indegree)| *indegree > 0)
+        .map(|(node, _indegree)| node)
+        .collect();
+    let Some(mut node) = unordered.first().copied() else {
+        return Ok(order);
+    };
+    // Unordered nodes keep an unordered predecessor, walking them back repeats a node on a cycle
+    // rather than one downstream of it.
+    let mut walked = BTreeSet::new();
+    while walked.insert(node) {
+        node = successors
+            .iter()
+            .find(|(predecessor, node_successors)| {
+                unordered.contains(predecessor) && node_successors.contains(&node)
+            })
+            .map_or(node, |(predecessor, _node_successors)| *predecessor);
+    }
+    Err(node)
+}
+
+#[derive(Debug, Default)]
+struct TarjanState {
+    indices: BTreeMap<Inst, usize>,
+    low_links: BTreeMap<Inst, usize>,
+    stack: Vec<Inst>,
+    on_stack: BTreeSet<Inst>,
+    components: Vec<Vec<InstIndex>>,
+}
+
+impl TarjanState {
+    /// Numbers `inst` and returns it with its successors left to visit.
+    fn visit(&mut self, fanouts: &BTreeMap<Inst, BTreeSet<Inst>>, inst: Inst) -> (Inst, Vec<Inst>) {
+        let index = self.indices.len();
+        self.indices.insert(inst, index);
+        self.low_links.insert(inst, index);
+        self.stack.push(inst);
+        self.on_stack.insert(inst);
+        let successors = fanouts
+            .get(&inst)
+            .into_iter()
+            .flatten()
+            .rev()
+            .copied()
+            .collect();
+        (inst, successors)
+    }
+
+    fn lower(&mut self, inst: Inst, low_link: usize) {
+        if let Some(inst_low_link) = self.low_links.get_mut(&inst) {
+            *inst_low_link = (*inst_low_link).min(low_link);
+        }
+    }
+
+    /// Stack entries down to `root`, the component it is the root of.
+    fn pop_component(&mut self, root: Inst) -> BTreeSet<Inst> {
+        let mut component = BTreeSet::new();
+        while let Some(member) = self.stack.pop() {
+            self.on_stack.remove(&member);
+            component.insert(member);
+            if member == root {
+                break;
+            }
+        }
+        component
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::llhd::module::LLHDModule;
+
+    fn test_graph(input: &str) -> (LLHDWorld, UnitId, LLHDInstGraph) {
+        let module = llhd::assembly::parse_module(input).unwrap();
+        let llhd_world = LLHDWorld::new(LLHDModule::from(module));
+        let unit_id = llhd_world.module().units().next().unwrap().id();
+        let graph = LLHDInstGraph::new(&llhd_world, unit_id).unwrap();
+        (llhd_world, unit_id, graph)
+    }
+
+    fn value_named(llhd_world: &LLHDWorld, unit_id: UnitId, name: &str) -> Value {
+        let unit = llhd_world.module().unit(unit_id);
+        unit.all_insts()
+            .filter_map(|inst| unit.get_inst_result(inst))
+            .chain(unit.args())
+            .find(|value| unit.get_name(*value) == Some(name))
+            .unwrap()
+    }
+
+    fn driver_named(llhd_world: &LLHDWorld, unit_id: UnitId, name: &str) -> Inst {
+        let unit = llhd_world.module().unit(unit_id);
+        unit.value_inst(value_named(llhd_world, unit_id, name))
+    }
+
+    #[test]
+    fn llhd_inst_graph_cones() {
+        let (llhd_world, unit_id, graph) = test_graph(indoc::indoc! {"
+            entity @test_entity (i1 %in1, i1 %in2, i1 %in3) -> (i1$ %out1, i1$ %out2) {
+                %null = const time 0s 1e
+                %and1 = and i1 %in1, %in2
+                %or1 = or i1 %and1, %in3
+                %xor1 = xor i1 %and1, %in3
+                drv i1$ %out1, %or1, %null
+                drv i1$ %out2, %xor1, %null
+            }
+        "});
+        let inst = |name| driver_named(&llhd_world, unit_id, name);
+        let (null, and1, or1, xor1) = (inst("null"), inst("and1"), inst("or1"), inst("xor1"));
+        let unit = llhd_world.module().unit(unit_id);
+        let drives = unit
+            .all_insts()
+            .filter(|inst| unit[*inst].opcode() == llhd::ir::Opcode::Drv)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            vec![(unit_id, or1), (unit_id, xor1)],
+            graph.value_users(value_named(&llhd_world, unit_id, "and1"))
+        );
+        assert_eq!(
+            Some((unit_id, and1)),
+            graph.value_driver(value_named(&llhd_world, unit_id, "and1"))
+        );
+        assert_eq!(
+            None,
+            graph.value_driver(value_named(&llhd_world, unit_id, "in1"))
+        );
+        assert!(graph.is_input_driven(and1) && graph.is_input_driven(or1));
+        assert!(!graph.is_input_driven(null) && !graph.is_input_driven(drives[0]));
+        assert_eq!(vec![(unit_id, and1)], graph.fanins(or1));
+        assert_eq!(vec![(unit_id, or1), (unit_id, xor1)], graph.fanouts(and1));
+        assert_eq!(
+            vec![
+                (unit_id, null),
+                (unit_id, and1),
+                (unit_id, or1),
+                (unit_id, drives[0])
+            ],
+            graph.fanin_cone(&[drives[0]])
+        );
+        assert_eq!(
+            vec![
+                (unit_id, and1),
+                (unit_id, or1),
+                (unit_id, xor1),
+                (unit_id, drives[0]),
+                (unit_id, drives[1])
+            ],
+            graph.fanout_cone(&[and1])
+        );
+        assert_eq!(
+            vec![
+                llhd_world.inst_entity(unit_id, and1).unwrap(),
+                llhd_world.inst_entity(unit_id, or1).unwrap()
+            ],
+            graph.entities(&[(unit_id, and1), (unit_id, or1)])
+        );
+
+        let order = graph.topological_order().unwrap();
+        assert_eq!(unit.all_insts().count(), order.len());
+        let position = |inst| order.iter().position(|index| *index == (unit_id, inst));
+        assert!(position(and1) < position(or1) && position(or1) < position(drives[0]));
+        let components = graph.strongly_connected_components();
+        assert_eq!(unit.all_insts().count(), components.len());
+        assert!(components.iter().all(|component| component.len() == 1));
+    }
+
+    #[test]
+    fn llhd_inst_graph_cycles() {
+        let (llhd_world, unit_id, graph) = test_graph(indoc::indoc! {"
+            proc @test_proc (i1$ %in1) -> (i1$ %out1) {
+            %entry:
+                %null = const time 0s 1e
+                %zero = const i1 0
+                br %loop
+            %loop:
+                %acc = phi i1 [%zero, %entry], [%next, %loop]
+                %in1_prb = prb i1$ %in1
+                %next = xor i1 %acc, %in1_prb
+                %not1 = not i1 %next
+                drv i1$ %out1, %not1, %null
+                br %loop
+            }
+        "});
+        let inst = |name| driver_named(&llhd_world, unit_id, name);
+        let (acc, next, not1) = (inst("acc"), inst("next"), inst("not1"));
+
+        assert!(matches!(
+            graph.topological_order(),
+            Err(LLHDGraphError::Cycle((cycle_unit_id, cycle_inst)))
+                if cycle_unit_id == unit_id && [acc, next].contains(&cycle_inst)
+        ));
+        let components = graph.strongly_connected_components();
+        assert!(components.contains(&vec![(unit_id, acc), (unit_id, next)]));
+        let component_of = |inst| {
+            components
+                .iter()
+                .position(|component| component.contains(&(unit_id, inst)))
+        };
+        assert!(
+            component_of(not1) < component_of(next),
+            "Fanout components come first."
+        );
+        let unit = llhd_world.module().unit(unit_id);
+        assert_eq!(unit.all_insts().count() - 1, components.len());
+    }
+
+    #[test]
+    fn kahn_order_reports_node_on_cycle() {
+        let successors = BTreeMap::from([
+            (1, BTreeSet::from([2])),
+            (2, BTreeSet::from([3])),
+            (3, BTreeSet::from([0, 2])),
+        ]);
+        assert!(
+            matches!(kahn_order(0..4, &successors), Err(2 | 3)),
+            "Node 0 is downstream of the 2 -> 3 cycle, not on it."
+        );
+        assert_eq!(Ok(vec![0, 1, 2]), kahn_order(0..3, &successors));
+    }
+}
@@ -6,11 +6,15 @@ use crate::llhd_world::components::unit::LLHDUnitComponent;
 use crate::llhd_world::components::value::{LLHDValueDefComponent, LLHDValueRefComponent};
 
 pub(crate) fn build_units(module: &Module) -> impl Iterator<Item = LLHDUnitComponent> + '_ {
-    module.units().map(|unit| LLHDUnitComponent {
+    module.units().map(|unit| build_unit(&unit))
+}
+
+pub(crate) fn build_unit(unit: &Unit) -> LLHDUnitComponent {
+    LLHDUnitComponent {
         id: Some(unit.id()),
         name: unit.name().clone(),
         kind: unit.kind(),
-    })
+    }
 }
 
 pub(crate) fn build_value_defs<'unit>(
@@ -0,0 +1,405 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use derive_getters::Getters;
+use euclid::default::{Box2D, Point2D};
+use itertools::Itertools;
+use llhd::ir::{Inst, UnitId, Value, ValueData};
+use typed_builder::TypedBuilder;
+
+use crate::llhd_world::components::value::LLHDValueDefComponent;
+use crate::llhd_world::placement::LLHDInstPlacement;
+use crate::llhd_world::world::LLHDWorld;
+
+/// Net of a Unit: a `Value`, the inst defining it(none for unit arguments), and every inst
+/// referencing it through an `LLHDValueRefComponent`.
+#[derive(Debug, Clone, PartialEq, Eq, Getters)]
+pub struct LLHDNet {
+    value: Value,
+    driver: Option<Inst>,
+    sinks: Vec<Inst>,
+}
+
+impl LLHDNet {
+    pub fn insts(&self) -> impl Iterator<Item = Inst> + '_ {
+        self.driver.iter().chain(self.sinks.iter()).copied()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Getters)]
+pub struct NetWirelength {
+    net: LLHDNet,
+    bb: Option<Box2D<usize>>,
+    hpwl: usize,
+}
+
+/// Bin Grid over a Placement Region, in Database Units
+#[derive(Debug, Clone, TypedBuilder, Getters)]
+pub struct CongestionConfig {
+    region: Box2D<usize>,
+    bin_width: usize,
+    bin_height: usize,
+}
+
+/// RUDY-style congestion estimate: the HPWL of each net is spread over the bins overlapped by its
+/// bounding box, proportional to the overlapped area.
+#[derive(Debug, Clone, PartialEq, Getters)]
+pub struct CongestionMap {
+    region: Box2D<usize>,
+    bin_width: usize,
+    bin_height: usize,
+    columns: usize,
+    rows: usize,
+    demand: Vec<f64>,
+}
+
+impl CongestionMap {
+    fn new(config: &CongestionConfig) -> Self {
+        let bin_width = (*config.bin_width()).max(1);
+        let bin_height = (*config.bin_height()).max(1);
+        let columns = config.region().width().div_ceil(bin_width).max(1);
+        let rows = config.region().height().div_ceil(bin_height).max(1);
+        Self {
+            region: *config.region(),
+            bin_width,
+            bin_height,
+            columns,
+            rows,
+            demand: vec![0.0; columns * rows],
+        }
+    }
+
+    pub fn bin(&self, column: usize, row: usize) -> Option<f64> {
+        if column < self.columns && row < self.rows {
+            self.demand.get(row * self.columns + column).copied()
+        } else {
+            None
+        }
+    }
+
+    pub fn total_demand(&self) -> f64 {
+        self.demand.iter().sum()
+    }
+
+    pub fn max_demand(&self) -> f64 {
+        self.demand.iter().copied().fold(0.0, f64::max)
+    }
+
+    pub fn average_demand(&self) -> f64 {
+        self.total_demand() / self.demand.len() as f64
+    }
+
+    pub fn overflowing_bins(&self, bin_capacity: f64) -> usize {
+        self.demand
+            .iter()
+            .filter(|demand| **demand > bin_capacity)
+            .count()
+    }
+
+    fn add_net(&mut self, bb: &Box2D<usize>, hpwl: usize) {
+        // Degenerate(single row/column) boxes still occupy one database unit of routing area.
+        let net_min = (bb.min.x as f64, bb.min.y as f64);
+        let net_max = (
+            bb.max.x.max(bb.min.x + 1) as f64,
+            bb.max.y.max(bb.min.y + 1) as f64,
+        );
+        let net_area = (net_max.0 - net_min.0) * (net_max.1 - net_min.1);
+        for row in 0..self.rows {
+            for column in 0..self.columns {
+                let bin_min = (
+                    (self.region.min.x + column * self.bin_width) as f64,
+                    (self.region.min.y + row * self.bin_height) as f64,
+                );
+                let bin_max = (
+                    bin_min.0 + self.bin_width as f64,
+                    bin_min.1 + self.bin_height as f64,
+                );
+                let overlap_width = net_max.0.min(bin_max.0) - net_min.0.max(bin_min.0);
+                let overlap_height = net_max.1.min(bin_max.1) - net_min.1.max(bin_min.1);
+                if overlap_width > 0.0 && overlap_height > 0.0 {
+                    self.demand[row * self.columns + column] +=
+                        hpwl as f64 * overlap_width * overlap_height / net_area;
+                }
+            }
+        }
+    }
+}
+
+/// Wirelength & Congestion Summary of a Placed Unit
+#[derive(Debug, Clone, PartialEq, Getters)]
+pub struct WirelengthReport {
+    unit_name: String,
+    nets: Vec<NetWirelength>,
+    total_hpwl: usize,
+    congestion: CongestionMap,
+}
+
+impl fmt::Display for WirelengthReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "unit: {}", self.unit_name)?;
+        writeln!(f, "nets: {}", self.nets.len())?;
+        writeln!(f, "total hpwl: {}", self.total_hpwl)?;
+        for net_wirelength in &self.nets {
+            writeln!(
+                f,
+                "  net {}: pins={} hpwl={}",
+                net_wirelength.net.value,
+                net_wirelength.net.insts().count(),
+                net_wirelength.hpwl
+            )?;
+        }
+        writeln!(
+            f,
+            "congestion: {}x{} bins of {}x{}, max={:.2} avg={:.2}",
+            self.congestion.columns,
+            self.congestion.rows,
+            self.congestion.bin_width,
+            self.congestion.bin_height,
+            self.congestion.max_demand(),
+            self.congestion.average_demand()
+        )?;
+        for row in (0..self.congestion.rows).rev() {
+            let row_demand = (0..self.congestion.columns)
+                .map(|column| {
+                    format!(
+                        "{:.2}",
+                        self.congestion.bin(column, row).unwrap_or_default()
+                    )
+                })
+                .join(" ");
+            writeln!(f, "  {}", row_demand)?;
+        }
+        Ok(())
+    }
+}
+
+/// All nets of a unit with at least one referencing inst, ordered by `Value`.
+pub fn unit_nets(llhd_world: &LLHDWorld, unit_id: UnitId) -> Vec<LLHDNet> {
+    let mut value_sinks: BTreeMap<Value, Vec<Inst>> = BTreeMap::new();
+    llhd_world
+        .unit_value_refs(unit_id)
+        .for_each(|value_ref_component| {
+            if let (Some(value), Some(inst)) = (value_ref_component.id, value_ref_component.inst) {
+                value_sinks.entry(value).or_default().push(inst);
+            }
+        });
+    value_sinks
+        .into_iter()
+        .map(|(value, mut sinks)| {
+            sinks.sort_unstable();
+            sinks.dedup();
+            let driver = llhd_world
+                .get_value_def::<LLHDValueDefComponent>(unit_id, value)
+                .ok()
+                .and_then(|value_def| match value_def.data {
+                    ValueData::Inst { inst, .. } => Some(inst),
+                    _ => None,
+                });
+            LLHDNet {
+                value,
+                driver,
+                sinks,
+            }
+        })
+        .collect()
+}
+
+/// Bounding box of the placed cell centers of a net, if any of its insts are placed.
+///
+/// Unit IO pins have no location in `LLHDWorld`, so unit argument and output nets are bounded by
+/// their placed cells only.
+pub fn net_bounding_box(
+    llhd_world: &LLHDWorld,
+    unit_id: UnitId,
+    net: &LLHDNet,
+) -> Option<Box2D<usize>> {
+    let centers = net
+        .insts()
+        .filter_map(|inst| llhd_world.get_inst::<LLHDInstPlacement>(unit_id, inst).ok())
+        .map(LLHDInstPlacement::center)
+        .collect_vec();
+    let (min_x, max_x) = centers
+        .iter()
+        .map(|center| center.x)
+        .minmax()
+        .into_option()?;
+    let (min_y, max_y) = centers
+        .iter()
+        .map(|center| center.y)
+        .minmax()
+        .into_option()?;
+    Some(Box2D::new(
+        Point2D::new(min_x, min_y),
+        Point2D::new(max_x, max_y),
+    ))
+}
+
+/// HPWL of `net_bounding_box`, `0` for an unplaced net.
+///
+/// Unlike the placement objective(`PlacementReport::hpwl`), IO pins are not counted and each
+/// LLHD value is its own net, so the two only agree on nets between placed cells.
+pub fn net_hpwl(llhd_world: &LLHDWorld, unit_id: UnitId, net: &LLHDNet) -> usize {
+    net_bounding_box(llhd_world, unit_id, net).map_or(0, |bb| bb.width() + bb.height())
+}
+
+pub fn unit_wirelength(llhd_world: &LLHDWorld, unit_id: UnitId) -> Vec<NetWirelength> {
+    unit_nets(llhd_world, unit_id)
+        .into_iter()
+        .map(|net| {
+            let bb = net_bounding_box(llhd_world, unit_id, &net);
+            let hpwl = net_hpwl(llhd_world, unit_id, &net);
+            NetWirelength { net, bb, hpwl }
+        })
+        .collect()
+}
+
+pub fn total_wirelength(llhd_world: &LLHDWorld, unit_id: UnitId) -> usize {
+    unit_wirelength(llhd_world, unit_id)
+        .iter()
+        .map(|net_wirelength| net_wirelength.hpwl)
+        .sum()
+}
+
+pub fn congestion_map(
+    llhd_world: &LLHDWorld,
+    unit_id: UnitId,
+    config: &CongestionConfig,
+) -> CongestionMap {
+    congestion_from_nets(&unit_wirelength(llhd_world, unit_id), config)
+}
+
+fn congestion_from_nets(nets: &[NetWirelength], config: &CongestionConfig) -> CongestionMap {
+    let mut congestion = CongestionMap::new(config);
+    nets.iter().for_each(|net_wirelength| {
+        if let Some(bb) = net_wirelength.bb {
+            congestion.add_net(&bb, net_wirelength.hpwl);
+        }
+    });
+    congestion
+}
+
+pub fn wirelength_report(
+    llhd_world: &LLHDWorld,
+    unit_id: UnitId,
+    config: &CongestionConfig,
+) -> WirelengthReport {
+    let nets = unit_wirelength(llhd_world, unit_id);
+    let total_hpwl = nets.iter().map(|net_wirelength| net_wirelength.hpwl).sum();
+    let congestion = congestion_from_nets(&nets, config);
+    WirelengthReport {
+        unit_name: llhd_world.module().unit(unit_id).name().to_string(),
+        nets,
+        total_hpwl,
+        congestion,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use llhd::ir::Opcode;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::llhd::module::LLHDModule;
+
+    fn placed_llhd_world() -> (LLHDWorld, UnitId) {
+        let test_module = utilities::load_llhd_module("2and_1or_common.llhd");
+        let mut llhd_world = LLHDWorld::new(LLHDModule::from(test_module));
+        let unit_id = llhd_world.module().units().next().unwrap().id();
+        let gates = llhd_world
+            .module()
+            .unit(unit_id)
+            .all_insts()
+            .filter(|inst| {
+                matches!(
+                    llhd_world.module().unit(unit_id)[*inst].opcode(),
+                    Opcode::And | Opcode::Or
+                )
+            })
+            .collect_vec();
+        let locations = [(0, 0), (4800, 0), (2400, 3330)];
+        gates.iter().zip(locations).for_each(|(inst, (x, y))| {
+            let bb = Box2D::new(Point2D::new(x, y), Point2D::new(x + 2400, y + 3330));
+            llhd_world
+                .set_inst(unit_id, *inst, LLHDInstPlacement::new(bb))
+                .unwrap();
+        });
+        (llhd_world, unit_id)
+    }
+
+    fn congestion_config() -> CongestionConfig {
+        CongestionConfig::builder()
+            .region(Box2D::new(Point2D::new(0, 0), Point2D::new(7200, 6660)))
+            .bin_width(3600)
+            .bin_height(3330)
+            .build()
+    }
+
+    #[test]
+    fn unit_nets_from_value_refs() {
+        let (llhd_world, unit_id) = placed_llhd_world();
+        let nets = unit_nets(&llhd_world, unit_id);
+        assert_eq!(
+            8,
+            nets.len(),
+            "in1, in2, in3, out1, null, and1, and2 and or1 should each be a net."
+        );
+        let in2_net = nets
+            .iter()
+            .find(|net| net.driver().is_none() && net.sinks().len() == 2)
+            .expect("in2 should be shared by both and gates.");
+        assert_eq!(2, in2_net.insts().count());
+        let driven_nets = nets.iter().filter(|net| net.driver().is_some()).count();
+        assert_eq!(
+            4, driven_nets,
+            "null, and1, and2 and or1 are defined by insts."
+        );
+    }
+
+    #[test]
+    fn unit_total_hpwl() {
+        let (llhd_world, unit_id) = placed_llhd_world();
+        // in2: 4800, and1 -> or1: 2400 + 3330, and2 -> or1: 2400 + 3330
+        assert_eq!(16260, total_wirelength(&llhd_world, unit_id));
+    }
+
+    #[test]
+    fn unplaced_unit_has_no_wirelength() {
+        let test_module = utilities::load_llhd_module("2and_1or_common.llhd");
+        let llhd_world = LLHDWorld::new(LLHDModule::from(test_module));
+        let unit_id = llhd_world.module().units().next().unwrap().id();
+        assert_eq!(0, total_wirelength(&llhd_world, unit_id));
+    }
+
+    #[test]
+    fn congestion_map_conserves_wirelength() {
+        let (llhd_world, unit_id) = placed_llhd_world();
+        let congestion = congestion_map(&llhd_world, unit_id, &congestion_config());
+        assert_eq!(2, *congestion.columns());
+        assert_eq!(2, *congestion.rows());
+        assert!(
+            (congestion.total_demand() - 16260.0).abs() < 1e-6,
+            "Every net lies within the region, so all wirelength should be binned."
+        );
+        assert!(congestion.bin(2, 0).is_none(), "Bin outside of the grid.");
+        // Bottom bins: half of in2 (2400) and half of an and -> or1 net (2865) each.
+        assert!((congestion.bin(0, 0).unwrap() - 5265.0).abs() < 1e-6);
+        assert!((congestion.bin(1, 1).unwrap() - 2865.0).abs() < 1e-6);
+        assert_eq!(
+            2,
+            congestion.overflowing_bins(4000.0),
+            "Only the bottom row bins should exceed a capacity of 4000."
+        );
+    }
+
+    #[test]
+    fn wirelength_report_display() {
+        let (llhd_world, unit_id) = placed_llhd_world();
+        let report = wirelength_report(&llhd_world, unit_id, &congestion_config());
+        assert_eq!(16260, *report.total_hpwl());
+        let report_str = report.to_string();
+        assert!(report_str.contains("unit: @test_entity"));
+        assert!(report_str.contains("total hpwl: 16260"));
+        assert!(report_str.contains("congestion: 2x2 bins of 3600x3330"));
+    }
+}
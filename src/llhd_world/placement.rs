@@ -0,0 +1,756 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use bevy_ecs::prelude::Component;
+use derive_getters::Getters;
+use euclid::default::{Box2D, Point2D};
+use itertools::Itertools;
+use llhd::ir::{Inst, InstData, LinkedUnit, Module, Opcode, UnitId, Value};
+use serde::{Deserialize, Serialize};
+use typed_builder::TypedBuilder;
+
+use crate::engine::lattice::chatterjee_brayton_physical_synthesis_example::{
+    optimum_region, SortedRectEdgeList, TopBottom,
+};
+use crate::llhd_library::lef_library::LLefLibrary;
+use crate::llhd_world::components::inst::LLHDInstComponent;
+use crate::llhd_world::world::{LLHDWorld, LLHDWorldError};
+
+/// Database Units per LEF Micron
+pub const DBU_PER_MICRON: usize = 1000;
+
+type PlacementPoint = (usize, usize);
+type CellFootprint = (usize, usize);
+
+/// Legal Cell Location of a Gate Inst, in Database Units
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Component, Serialize, Deserialize)]
+pub struct LLHDInstPlacement {
+    pub(crate) bb: Box2D<usize>,
+}
+
+impl LLHDInstPlacement {
+    pub const fn new(bb: Box2D<usize>) -> Self {
+        Self { bb }
+    }
+
+    pub const fn bb(&self) -> &Box2D<usize> {
+        &self.bb
+    }
+
+    pub fn center(&self) -> Point2D<usize> {
+        Point2D::new(
+            (self.bb.min.x + self.bb.max.x) / 2,
+            (self.bb.min.y + self.bb.max.y) / 2,
+        )
+    }
+}
+
+/// Cell Footprints(width, height) in Database Units, Keyed by LEF Macro Name
+#[derive(Debug, Clone, Default)]
+pub struct PlacementLibrary(HashMap<String, CellFootprint>);
+
+impl PlacementLibrary {
+    pub fn insert(&mut self, cell_name: &str, width: usize, height: usize) {
+        self.0.insert(cell_name.to_owned(), (width, height));
+    }
+
+    pub fn size(&self, cell_name: &str) -> Option<CellFootprint> {
+        self.0.get(cell_name).copied()
+    }
+}
+
+fn microns_to_dbu(microns: &str) -> Option<usize> {
+    microns
+        .parse::<f64>()
+        .ok()
+        .filter(|microns| microns.is_finite() && microns.is_sign_positive())
+        .map(|microns| (microns * DBU_PER_MICRON as f64).round() as usize)
+}
+
+impl TryFrom<&LLefLibrary> for PlacementLibrary {
+    type Error = PlacementError;
+
+    fn try_from(lef: &LLefLibrary) -> Result<Self, Self::Error> {
+        lef.macros
+            .iter()
+            .filter_map(|lef_macro| {
+                lef_macro.size.as_ref().map(|(width, height)| {
+                    microns_to_dbu(&width.to_string())
+                        .zip(microns_to_dbu(&height.to_string()))
+                        .map(|footprint| (lef_macro.name.to_owned(), footprint))
+                        .ok_or_else(|| PlacementError::MalformedSize(lef_macro.name.to_owned()))
+                })
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+/// Die Geometry & Knobs for Placing a Unit
+///
+/// Gate insts are mapped to LEF macros through `cell_map`, keyed by opcode. Cell instantiations
+/// (`inst`) are mapped to the macro with the same name as the instantiated unit.
+#[derive(Debug, Clone, TypedBuilder, Getters)]
+pub struct PlacementConfig {
+    die: Box2D<usize>,
+    row_height: usize,
+    site_width: usize,
+    #[builder(default)]
+    cell_map: HashMap<Opcode, String>,
+    #[builder(default = 32)]
+    global_iterations: usize,
+    #[builder(default = 4)]
+    detailed_passes: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlacementError {
+    MissingCell(String),
+    MalformedSize(String),
+    DieOverflow(Inst),
+    World(LLHDWorldError),
+}
+
+impl fmt::Display for PlacementError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingCell(cell_name) => {
+                write!(f, "Cell `{}` has no footprint in the library.", cell_name)
+            }
+            Self::MalformedSize(cell_name) => {
+                write!(f, "Cell `{}` has a malformed LEF `SIZE`.", cell_name)
+            }
+            Self::DieOverflow(inst) => write!(f, "No row has room left for inst `{}`.", inst),
+            Self::World(world_error) => write!(f, "{}", world_error),
+        }
+    }
+}
+
+impl std::error::Error for PlacementError {}
+
+impl From<LLHDWorldError> for PlacementError {
+    fn from(world_error: LLHDWorldError) -> Self {
+        Self::World(world_error)
+    }
+}
+
+/// Half-Perimeter Wirelength After Each Placement Stage
+///
+/// The placer's nets merge operands through wire insts and include the fixed IO pins, see
+/// `pinned_net_hpwl`, so `hpwl` is not the `total_wirelength` of the `metrics` module, which
+/// bounds each LLHD value by its placed cells only.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Getters)]
+pub struct PlacementReport {
+    cells: usize,
+    nets: usize,
+    legal_hpwl: usize,
+    hpwl: usize,
+    moves: usize,
+}
+
+#[derive(Debug, Clone)]
+struct PlacementCell {
+    inst: Inst,
+    width: usize,
+    height: usize,
+}
+
+/// Cells & fixed IO pins connected by one net.
+#[derive(Debug, Clone, Default)]
+struct PlacementNet {
+    cells: Vec<usize>,
+    pins: Vec<PlacementPoint>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct PlacementNetlist {
+    cells: Vec<PlacementCell>,
+    nets: Vec<PlacementNet>,
+    cell_nets: Vec<Vec<usize>>,
+}
+
+/// Cell bound to an inst: the callee of a `call`, otherwise the cell mapped to its opcode.
+pub(crate) fn cell_name(
+    module: &Module,
+    unit_id: UnitId,
+    inst_data: &InstData,
+    cell_map: &HashMap<Opcode, String>,
+) -> Option<String> {
+    if let InstData::Call { unit, .. } = inst_data {
+        let cell_unit_name = match module.lookup_ext_unit(*unit, unit_id)? {
+            LinkedUnit::Def(cell_unit_id) => module.unit(cell_unit_id).name().clone(),
+            LinkedUnit::Decl(decl_id) => module[decl_id].name.clone(),
+        };
+        cell_unit_name.get_name().map(str::to_owned)
+    } else {
+        cell_map.get(&inst_data.opcode()).cloned()
+    }
+}
+
+fn find_net_root(value_parents: &mut HashMap<Value, Value>, value: Value) -> Value {
+    let parent = *value_parents.entry(value).or_insert(value);
+    if parent == value {
+        value
+    } else {
+        let root = find_net_root(value_parents, parent);
+        value_parents.insert(value, root);
+        root
+    }
+}
+
+fn io_pin_locations(count: usize, x: usize, die: &Box2D<usize>) -> Vec<PlacementPoint> {
+    let pitch = die.height() / (count + 1);
+    (1..=count)
+        .map(|pin_idx| (x, die.min.y + pin_idx * pitch))
+        .collect()
+}
+
+/// Collect the cells of a unit and the nets connecting them.
+///
+/// Insts without a cell(`prb`, `sig`, `drv`, ...) act as wires, so their non-constant operands are
+/// merged into a single net. This connects gates to the unit's IO pins, which are fixed along the
+/// left(inputs) and right(outputs) die edges.
+fn build_netlist(
+    llhd_world: &LLHDWorld,
+    unit_id: UnitId,
+    library: &PlacementLibrary,
+    config: &PlacementConfig,
+) -> Result<PlacementNetlist, PlacementError> {
+    let module: &Module = llhd_world.module();
+    let unit = module.unit(unit_id);
+    let mut cells: Vec<PlacementCell> = Vec::new();
+    let mut cell_operands: Vec<(Value, usize)> = Vec::new();
+    let mut value_parents: HashMap<Value, Value> = HashMap::new();
+    let inst_components = llhd_world
+        .unit_program_inst::<LLHDInstComponent>(unit_id)?
+        .map(|(_inst_idx, inst_component, _inst_data)| inst_component)
+        .collect_vec();
+    for inst_component in inst_components {
+        let inst_id = inst_component.id.expect("Inst should have Id.");
+        let operands = inst_component
+            .value
+            .iter()
+            .chain(inst_component.data.args().iter())
+            .copied()
+            .filter(|operand| unit.get_const(*operand).is_none())
+            .collect_vec();
+        if let Some(cell) = cell_name(module, unit_id, &inst_component.data, config.cell_map()) {
+            let (width, height) = library
+                .size(&cell)
+                .ok_or(PlacementError::MissingCell(cell))?;
+            let cell_idx = cells.len();
+            cells.push(PlacementCell {
+                inst: inst_id,
+                width,
+                height,
+            });
+            operands
+                .into_iter()
+                .for_each(|operand| cell_operands.push((operand, cell_idx)));
+        } else if let Some((first_operand, other_operands)) = operands.split_first() {
+            let first_root = find_net_root(&mut value_parents, *first_operand);
+            other_operands.iter().for_each(|operand| {
+                let operand_root = find_net_root(&mut value_parents, *operand);
+                value_parents.insert(operand_root, first_root);
+            });
+        }
+    }
+
+    let mut nets: BTreeMap<Value, PlacementNet> = BTreeMap::new();
+    cell_operands.into_iter().for_each(|(operand, cell_idx)| {
+        let root = find_net_root(&mut value_parents, operand);
+        nets.entry(root).or_default().cells.push(cell_idx);
+    });
+    let die = config.die();
+    let inputs = unit.input_args().collect_vec();
+    let outputs = unit.output_args().collect_vec();
+    inputs
+        .iter()
+        .zip(io_pin_locations(inputs.len(), die.min.x, die))
+        .chain(
+            outputs
+                .iter()
+                .zip(io_pin_locations(outputs.len(), die.max.x, die)),
+        )
+        .for_each(|(arg, pin_location)| {
+            let root = find_net_root(&mut value_parents, *arg);
+            nets.entry(root).or_default().pins.push(pin_location);
+        });
+
+    let nets = nets
+        .into_values()
+        .map(|mut net| {
+            net.cells.sort_unstable();
+            net.cells.dedup();
+            net
+        })
+        .filter(|net| !net.cells.is_empty() && net.cells.len() + net.pins.len() > 1)
+        .collect_vec();
+    let mut cell_nets = vec![Vec::new(); cells.len()];
+    nets.iter().enumerate().for_each(|(net_idx, net)| {
+        net.cells
+            .iter()
+            .for_each(|cell_idx| cell_nets[*cell_idx].push(net_idx));
+    });
+    Ok(PlacementNetlist {
+        cells,
+        nets,
+        cell_nets,
+    })
+}
+
+fn cell_centers(netlist: &PlacementNetlist, locations: &[PlacementPoint]) -> Vec<PlacementPoint> {
+    netlist
+        .cells
+        .iter()
+        .zip(locations)
+        .map(|(cell, location)| (location.0 + cell.width / 2, location.1 + cell.height / 2))
+        .collect()
+}
+
+/// HPWL of a placer net over its cell centers and IO pin locations, the placement objective.
+fn pinned_net_hpwl(net: &PlacementNet, centers: &[PlacementPoint]) -> usize {
+    let points = net
+        .cells
+        .iter()
+        .map(|cell_idx| centers[*cell_idx])
+        .chain(net.pins.iter().copied())
+        .collect_vec();
+    let width = points
+        .iter()
+        .map(|point| point.0)
+        .minmax()
+        .into_option()
+        .map_or(0, |(min_x, max_x)| max_x - min_x);
+    let height = points
+        .iter()
+        .map(|point| point.1)
+        .minmax()
+        .into_option()
+        .map_or(0, |(min_y, max_y)| max_y - min_y);
+    width + height
+}
+
+fn total_hpwl(netlist: &PlacementNetlist, locations: &[PlacementPoint]) -> usize {
+    let centers = cell_centers(netlist, locations);
+    netlist
+        .nets
+        .iter()
+        .map(|net| pinned_net_hpwl(net, &centers))
+        .sum()
+}
+
+/// Quadratic(star model) placement, solved by Gauss-Seidel iteration of net centroids.
+fn global_placement(netlist: &PlacementNetlist, config: &PlacementConfig) -> Vec<(f64, f64)> {
+    let die = config.die();
+    let cell_count = netlist.cells.len();
+    let columns = (cell_count as f64).sqrt().ceil().max(1.0);
+    let rows = (cell_count as f64 / columns).ceil().max(1.0);
+    let mut centers = (0..cell_count)
+        .map(|cell_idx| {
+            let column = (cell_idx as f64) % columns;
+            let row = (cell_idx as f64 / columns).floor();
+            (
+                die.min.x as f64 + die.width() as f64 * (column + 0.5) / columns,
+                die.min.y as f64 + die.height() as f64 * (row + 0.5) / rows,
+            )
+        })
+        .collect_vec();
+    for _iteration in 0..*config.global_iterations() {
+        for cell_idx in 0..cell_count {
+            let net_centroids = netlist.cell_nets[cell_idx]
+                .iter()
+                .filter_map(|net_idx| {
+                    let net = &netlist.nets[*net_idx];
+                    let points = net
+                        .cells
+                        .iter()
+                        .filter(|other_idx| **other_idx != cell_idx)
+                        .map(|other_idx| centers[*other_idx])
+                        .chain(net.pins.iter().map(|pin| (pin.0 as f64, pin.1 as f64)))
+                        .collect_vec();
+                    (!points.is_empty()).then(|| {
+                        let count = points.len() as f64;
+                        (
+                            points.iter().map(|point| point.0).sum::<f64>() / count,
+                            points.iter().map(|point| point.1).sum::<f64>() / count,
+                        )
+                    })
+                })
+                .collect_vec();
+            if !net_centroids.is_empty() {
+                let count = net_centroids.len() as f64;
+                centers[cell_idx] = (
+                    net_centroids.iter().map(|point| point.0).sum::<f64>() / count,
+                    net_centroids.iter().map(|point| point.1).sum::<f64>() / count,
+                );
+            }
+        }
+    }
+    centers
+}
+
+/// Tetris legalization: cells are packed left to right, each into the row with room that is
+/// closest to its global location.
+fn legalize(
+    netlist: &PlacementNetlist,
+    global_centers: &[(f64, f64)],
+    config: &PlacementConfig,
+) -> Result<Vec<PlacementPoint>, PlacementError> {
+    let die = config.die();
+    let site_width = (*config.site_width()).max(1);
+    let row_height = (*config.row_height()).max(1);
+    let row_count = die.height() / row_height;
+    let mut row_fill = vec![die.min.x; row_count];
+    let mut locations = vec![(die.min.x, die.min.y); netlist.cells.len()];
+    let desired_locations = netlist
+        .cells
+        .iter()
+        .zip(global_centers)
+        .map(|(cell, center)| {
+            let max_x = die.max.x.saturating_sub(cell.width).max(die.min.x);
+            let max_y = die.max.y.saturating_sub(cell.height).max(die.min.y);
+            (
+                (center.0 - cell.width as f64 / 2.0).clamp(die.min.x as f64, max_x as f64),
+                (center.1 - cell.height as f64 / 2.0).clamp(die.min.y as f64, max_y as f64),
+            )
+        })
+        .collect_vec();
+    let cell_order = (0..netlist.cells.len()).sorted_by(|lhs, rhs| {
+        desired_locations[*lhs]
+            .0
+            .total_cmp(&desired_locations[*rhs].0)
+    });
+    for cell_idx in cell_order {
+        let cell = &netlist.cells[cell_idx];
+        let (desired_x, desired_y) = desired_locations[cell_idx];
+        let snapped_x = die.min.x + (desired_x as usize - die.min.x) / site_width * site_width;
+        let best_row = row_fill
+            .iter()
+            .enumerate()
+            .filter_map(|(row_idx, fill)| {
+                let x = snapped_x.max(*fill);
+                let y = die.min.y + row_idx * row_height;
+                (x + cell.width <= die.max.x).then(|| {
+                    let cost = (x as f64 - desired_x).abs() + (y as f64 - desired_y).abs();
+                    (row_idx, x, y, cost)
+                })
+            })
+            .min_by(|lhs, rhs| lhs.3.total_cmp(&rhs.3));
+        let (row_idx, x, y, _cost) = best_row.ok_or(PlacementError::DieOverflow(cell.inst))?;
+        row_fill[row_idx] = x + cell.width.div_ceil(site_width) * site_width;
+        locations[cell_idx] = (x, y);
+    }
+    Ok(locations)
+}
+
+fn interval_edges(intervals: &[(usize, usize)]) -> SortedRectEdgeList {
+    intervals
+        .iter()
+        .flat_map(|(lo, hi)| {
+            [
+                (
+                    i32::try_from(*lo).expect("Coordinate should fit in i32."),
+                    TopBottom::Top,
+                ),
+                (
+                    i32::try_from(*hi).expect("Coordinate should fit in i32."),
+                    TopBottom::Bottom,
+                ),
+            ]
+        })
+        .sorted_by_key(|edge| edge.0)
+        .collect()
+}
+
+/// Center of the Chatterjee-Brayton optimum region of a cell: the region minimizing the summed
+/// distance to the bounding boxes of the other pins of each of its nets.
+fn optimum_location(
+    netlist: &PlacementNetlist,
+    centers: &[PlacementPoint],
+    cell_idx: usize,
+) -> Option<PlacementPoint> {
+    let net_boxes = netlist.cell_nets[cell_idx]
+        .iter()
+        .filter_map(|net_idx| {
+            let net = &netlist.nets[*net_idx];
+            let points = net
+                .cells
+                .iter()
+                .filter(|other_idx| **other_idx != cell_idx)
+                .map(|other_idx| centers[*other_idx])
+                .chain(net.pins.iter().copied())
+                .collect_vec();
+            let (min_x, max_x) = points.iter().map(|point| point.0).minmax().into_option()?;
+            let (min_y, max_y) = points.iter().map(|point| point.1).minmax().into_option()?;
+            Some(((min_x, max_x), (min_y, max_y)))
+        })
+        .collect_vec();
+    let x_intervals = net_boxes.iter().map(|net_box| net_box.0).collect_vec();
+    let y_intervals = net_boxes.iter().map(|net_box| net_box.1).collect_vec();
+    let (x1, x2, _x_cost) = optimum_region(interval_edges(&x_intervals))?;
+    let (y1, y2, _y_cost) = optimum_region(interval_edges(&y_intervals))?;
+    let to_coordinate =
+        |lo: i32, hi: i32| usize::try_from((lo + hi) / 2).expect("Coordinate should be positive.");
+    Some((to_coordinate(x1, x2), to_coordinate(y1, y2)))
+}
+
+fn swapped_nets_hpwl(
+    netlist: &PlacementNetlist,
+    locations: &[PlacementPoint],
+    net_ids: &[usize],
+) -> usize {
+    let centers = cell_centers(netlist, locations);
+    net_ids
+        .iter()
+        .map(|net_idx| pinned_net_hpwl(&netlist.nets[*net_idx], &centers))
+        .sum()
+}
+
+/// Detailed placement: move each cell towards its optimum region by swapping it with the
+/// equally sized cell closest to that region, whenever the swap reduces wirelength.
+fn detailed_placement(
+    netlist: &PlacementNetlist,
+    locations: &mut [PlacementPoint],
+    config: &PlacementConfig,
+) -> usize {
+    let mut moves = 0;
+    for _pass in 0..*config.detailed_passes() {
+        for cell_idx in 0..netlist.cells.len() {
+            let centers = cell_centers(netlist, locations);
+            let Some(target) = optimum_location(netlist, &centers, cell_idx) else {
+                continue;
+            };
+            let cell = &netlist.cells[cell_idx];
+            let candidate = netlist
+                .cells
+                .iter()
+                .enumerate()
+                .filter(|(other_idx, other)| {
+                    *other_idx != cell_idx
+                        && other.width == cell.width
+                        && other.height == cell.height
+                })
+                .min_by_key(|(other_idx, _other)| {
+                    let other_center = centers[*other_idx];
+                    other_center.0.abs_diff(target.0) + other_center.1.abs_diff(target.1)
+                })
+                .map(|(other_idx, _other)| other_idx);
+            let Some(other_idx) = candidate else {
+                continue;
+            };
+            let current_center = centers[cell_idx];
+            let other_center = centers[other_idx];
+            let current_distance =
+                current_center.0.abs_diff(target.0) + current_center.1.abs_diff(target.1);
+            let other_distance =
+                other_center.0.abs_diff(target.0) + other_center.1.abs_diff(target.1);
+            if other_distance >= current_distance {
+                continue;
+            }
+            let touched_nets = netlist.cell_nets[cell_idx]
+                .iter()
+                .chain(netlist.cell_nets[other_idx].iter())
+                .copied()
+                .unique()
+                .collect_vec();
+            let hpwl_before = swapped_nets_hpwl(netlist, locations, &touched_nets);
+            locations.swap(cell_idx, other_idx);
+            let hpwl_after = swapped_nets_hpwl(netlist, locations, &touched_nets);
+            if hpwl_after < hpwl_before {
+                moves += 1;
+            } else {
+                locations.swap(cell_idx, other_idx);
+            }
+        }
+    }
+    moves
+}
+
+/// Place the gate insts of a unit onto the rows of a die.
+///
+/// 1) Global: quadratic wirelength minimization against the fixed IO pins
+/// 2) Legalization: snap cells into non-overlapping row/site locations
+/// 3) Detailed: optimum region driven swaps, accepted when HPWL improves
+///
+/// Each placed inst receives an `LLHDInstPlacement` component.
+pub fn place_unit(
+    llhd_world: &mut LLHDWorld,
+    unit_id: UnitId,
+    library: &PlacementLibrary,
+    config: &PlacementConfig,
+) -> Result<PlacementReport, PlacementError> {
+    let netlist = build_netlist(llhd_world, unit_id, library, config)?;
+    let global_centers = global_placement(&netlist, config);
+    let mut locations = legalize(&netlist, &global_centers, config)?;
+    let legal_hpwl = total_hpwl(&netlist, &locations);
+    let moves = detailed_placement(&netlist, &mut locations, config);
+    let hpwl = total_hpwl(&netlist, &locations);
+    netlist
+        .cells
+        .iter()
+        .zip(&locations)
+        .try_for_each(|(cell, location)| {
+            let bb = Box2D::new(
+                Point2D::new(location.0, location.1),
+                Point2D::new(location.0 + cell.width, location.1 + cell.height),
+            );
+            llhd_world.set_inst(unit_id, cell.inst, LLHDInstPlacement::new(bb))
+        })?;
+    Ok(PlacementReport {
+        cells: netlist.cells.len(),
+        nets: netlist.nets.len(),
+        legal_hpwl,
+        hpwl,
+        moves,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use layout21::lef21::{LefDecimal, LefLibrary, LefMacro};
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::llhd::module::LLHDModule;
+
+    const AND2: &str = "sky130_fd_sc_ls__and2_1";
+    const OR2: &str = "sky130_fd_sc_ls__or2_1";
+
+    fn test_library() -> PlacementLibrary {
+        let mut library = PlacementLibrary::default();
+        library.insert(AND2, 2400, 3330);
+        library.insert(OR2, 2400, 3330);
+        library
+    }
+
+    fn test_config(die: Box2D<usize>) -> PlacementConfig {
+        PlacementConfig::builder()
+            .die(die)
+            .row_height(3330)
+            .site_width(480)
+            .cell_map(HashMap::from([
+                (Opcode::And, AND2.to_owned()),
+                (Opcode::Or, OR2.to_owned()),
+            ]))
+            .build()
+    }
+
+    fn test_world() -> (LLHDWorld, UnitId) {
+        let input = indoc::indoc! {"
+            entity @test_placement (i1 %in1, i1 %in2, i1 %in3, i1 %in4) -> (i1$ %out1) {
+                %instant = const time 0s 1e
+                %and1 = and i1 %in1, %in2
+                %and2 = and i1 %in3, %in4
+                %or1 = or i1 %and1, %and2
+                drv i1$ %out1, %or1, %instant
+            }
+        "};
+        let module = llhd::assembly::parse_module(input).unwrap();
+        let llhd_world = LLHDWorld::new(LLHDModule::from(module));
+        let unit_id = llhd_world.module().units().next().unwrap().id();
+        (llhd_world, unit_id)
+    }
+
+    fn placed_insts(llhd_world: &LLHDWorld, unit_id: UnitId) -> Vec<LLHDInstPlacement> {
+        llhd_world
+            .unit_program_inst::<LLHDInstPlacement>(unit_id)
+            .unwrap()
+            .map(|(_inst_idx, _inst_component, placement)| placement)
+            .collect()
+    }
+
+    #[test]
+    fn placement_library_from_lef() {
+        let mut library_lef_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        library_lef_path.push(
+            "resources/libraries_no_liberty/sky130_fd_sc_ls/latest/cells/a211o/\
+             sky130_fd_sc_ls__a211o_2.magic.lef",
+        );
+        let lef = LLefLibrary::from(LefLibrary::open(library_lef_path).unwrap());
+        let library = PlacementLibrary::try_from(&lef).unwrap();
+        assert_eq!(
+            Some((3840, 3330)),
+            library.size("sky130_fd_sc_ls__a211o_2"),
+            "a211o_2 should be 3.84um x 3.33um."
+        );
+    }
+
+    #[test]
+    fn placement_library_from_malformed_lef() {
+        let mut lef = LLefLibrary::default();
+        lef.macros.push(LefMacro {
+            name: AND2.to_owned(),
+            size: Some((LefDecimal::new(-24, 1), LefDecimal::new(333, 2))),
+            ..LefMacro::default()
+        });
+        assert_eq!(
+            Err(PlacementError::MalformedSize(AND2.to_owned())),
+            PlacementLibrary::try_from(&lef).map(|library| library.size(AND2)),
+            "A negative width is not a valid LEF `SIZE`."
+        );
+    }
+
+    #[test]
+    fn place_unit_into_rows() {
+        let (mut llhd_world, unit_id) = test_world();
+        let die = Box2D::new(Point2D::new(0, 0), Point2D::new(9600, 6660));
+        let report = place_unit(&mut llhd_world, unit_id, &test_library(), &test_config(die))
+            .expect("Placement should fit into the die.");
+        assert_eq!(3, *report.cells(), "Two ands and an or should be placed.");
+        assert_eq!(
+            7,
+            *report.nets(),
+            "Four inputs, two and outputs and one output net should connect the cells."
+        );
+        assert!(
+            report.hpwl() <= report.legal_hpwl(),
+            "Detailed placement should never increase wirelength."
+        );
+
+        let placements = placed_insts(&llhd_world, unit_id);
+        assert_eq!(3, placements.len(), "Every gate should have a placement.");
+        placements.iter().for_each(|placement| {
+            let bb = placement.bb();
+            assert!(die.contains_box(bb), "Cells should lie within the die.");
+            assert_eq!(0, bb.min.y % 3330, "Cells should be aligned to rows.");
+            assert_eq!(0, bb.min.x % 480, "Cells should be aligned to sites.");
+        });
+        placements
+            .iter()
+            .tuple_combinations()
+            .for_each(|(lhs, rhs)| {
+                assert!(
+                    !lhs.bb().intersects(rhs.bb()),
+                    "Legalized cells should not overlap."
+                );
+            });
+    }
+
+    #[test]
+    fn place_unit_die_overflow() {
+        let (mut llhd_world, unit_id) = test_world();
+        let die = Box2D::new(Point2D::new(0, 0), Point2D::new(4800, 3330));
+        let placement = place_unit(&mut llhd_world, unit_id, &test_library(), &test_config(die));
+        assert!(
+            matches!(placement, Err(PlacementError::DieOverflow(_))),
+            "Three 2.4um cells should not fit into a single 4.8um row."
+        );
+    }
+
+    #[test]
+    fn place_unit_missing_cell() {
+        let (mut llhd_world, unit_id) = test_world();
+        let die = Box2D::new(Point2D::new(0, 0), Point2D::new(9600, 6660));
+        let mut library = PlacementLibrary::default();
+        library.insert(AND2, 2400, 3330);
+        let placement = place_unit(&mut llhd_world, unit_id, &library, &test_config(die));
+        assert_eq!(
+            Err(PlacementError::MissingCell(OR2.to_owned())),
+            placement.map(|report| *report.cells()),
+            "The or gate has no footprint in the library."
+        );
+    }
+}
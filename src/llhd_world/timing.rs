@@ -0,0 +1,489 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use derive_getters::Getters;
+use llhd::ir::{Inst, Module, Opcode, UnitId};
+use typed_builder::TypedBuilder;
+
+use crate::llhd_world::components::inst::LLHDInstComponent;
+use crate::llhd_world::graph::{LLHDGraphError, LLHDInstGraph};
+use crate::llhd_world::placement::cell_name;
+use crate::llhd_world::world::{ECSEntityName, LLHDWorld, LLHDWorldError};
+
+/// Inst Delays, in arbitrary but consistent time units
+///
+/// A bound cell(`call` callee, or `cell_map` entry for the opcode) takes precedence over the
+/// opcode delay, and any inst matching neither gets `default_delay`.
+#[derive(Debug, Clone, Default, TypedBuilder, Getters)]
+pub struct DelayTable {
+    #[builder(default)]
+    opcode_delays: HashMap<Opcode, f64>,
+    #[builder(default)]
+    cell_delays: HashMap<String, f64>,
+    #[builder(default)]
+    cell_map: HashMap<Opcode, String>,
+    #[builder(default)]
+    default_delay: f64,
+}
+
+impl DelayTable {
+    pub fn inst_delay(
+        &self,
+        module: &Module,
+        unit_id: UnitId,
+        inst_component: &LLHDInstComponent,
+    ) -> f64 {
+        cell_name(module, unit_id, &inst_component.data, &self.cell_map)
+            .and_then(|cell| self.cell_delays.get(&cell).copied())
+            .or_else(|| {
+                self.opcode_delays
+                    .get(&inst_component.data.opcode())
+                    .copied()
+            })
+            .unwrap_or(self.default_delay)
+    }
+}
+
+#[derive(Debug, Clone, Default, TypedBuilder, Getters)]
+pub struct TimingConstraints {
+    /// Arrival time of every unit input argument.
+    #[builder(default)]
+    input_arrival: f64,
+    /// Required time at every endpoint, defaults to the critical path delay.
+    #[builder(default, setter(strip_option))]
+    required_time: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimingError {
+    CombinationalLoop(Inst),
+    World(LLHDWorldError),
+}
+
+impl fmt::Display for TimingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CombinationalLoop(inst) => {
+                write!(f, "Inst `{}` is part of a combinational loop.", inst)
+            }
+            Self::World(world_error) => write!(f, "{}", world_error),
+        }
+    }
+}
+
+impl std::error::Error for TimingError {}
+
+impl From<LLHDWorldError> for TimingError {
+    fn from(world_error: LLHDWorldError) -> Self {
+        Self::World(world_error)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Getters)]
+pub struct InstTiming {
+    inst: Inst,
+    opcode: Opcode,
+    name: String,
+    delay: f64,
+    arrival: f64,
+    required: f64,
+    slack: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Getters)]
+pub struct TimingReport {
+    unit_name: String,
+    required_time: f64,
+    insts: BTreeMap<Inst, InstTiming>,
+    critical_path: Vec<Inst>,
+}
+
+impl TimingReport {
+    pub fn inst(&self, inst: Inst) -> Option<&InstTiming> {
+        self.insts.get(&inst)
+    }
+
+    pub fn critical_path_delay(&self) -> f64 {
+        self.critical_path
+            .last()
+            .and_then(|endpoint| self.insts.get(endpoint))
+            .map_or(0.0, |inst_timing| inst_timing.arrival)
+    }
+
+    pub fn worst_slack(&self) -> f64 {
+        self.insts
+            .values()
+            .map(|inst_timing| inst_timing.slack)
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    /// Hierarchical names of the critical path, from startpoint to endpoint.
+    pub fn critical_path_names(&self) -> Vec<&str> {
+        self.critical_path
+            .iter()
+            .filter_map(|inst| self.insts.get(inst))
+            .map(|inst_timing| inst_timing.name.as_str())
+            .collect()
+    }
+
+    /// `1.0` for insts on a zero(or negative) slack path, falling to `0.0` as the slack reaches
+    /// the required time.
+    pub fn criticality(&self, inst: Inst) -> f64 {
+        self.insts.get(&inst).map_or(0.0, |inst_timing| {
+            if self.required_time <= 0.0 {
+                1.0
+            } else {
+                (1.0 - inst_timing.slack / self.required_time).clamp(0.0, 1.0)
+            }
+        })
+    }
+
+    /// Extraction cost per opcode, `1 + scale * criticality` of its most critical inst.
+    ///
+    /// Meant for `LLHDEgglogSorts::with_costs`, steering extraction away from operators on
+    /// critical paths rather than simply minimizing the node count.
+    pub fn opcode_costs(&self, scale: usize) -> HashMap<Opcode, usize> {
+        let mut opcode_costs: HashMap<Opcode, usize> = HashMap::new();
+        self.insts.values().for_each(|inst_timing| {
+            let cost = 1 + (scale as f64 * self.criticality(inst_timing.inst)).round() as usize;
+            let opcode_cost = opcode_costs.entry(inst_timing.opcode).or_insert(cost);
+            *opcode_cost = (*opcode_cost).max(cost);
+        });
+        opcode_costs
+    }
+}
+
+impl fmt::Display for TimingReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "unit: {}", self.unit_name)?;
+        writeln!(f, "required time: {:.3}", self.required_time)?;
+        writeln!(f, "critical path delay: {:.3}", self.critical_path_delay())?;
+        writeln!(f, "worst slack: {:.3}", self.worst_slack())?;
+        writeln!(f, "critical path:")?;
+        for inst_timing in self
+            .critical_path
+            .iter()
+            .filter_map(|inst| self.insts.get(inst))
+        {
+            writeln!(
+                f,
+                "  {:<24} {:>8} delay={:.3} arrival={:.3} required={:.3} slack={:.3}",
+                inst_timing.name,
+                inst_timing.opcode.to_string(),
+                inst_timing.delay,
+                inst_timing.arrival,
+                inst_timing.required,
+                inst_timing.slack
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Static Timing Analysis of a Unit's DFG
+///
+/// Arrival times propagate forward in topological order from unit inputs and constants, required
+/// times propagate backward from endpoints(insts without fanout), and the critical path is traced
+/// back from the endpoint with the worst slack through its latest-arriving fanins.
+pub fn analyze_unit_timing(
+    llhd_world: &LLHDWorld,
+    unit_id: UnitId,
+    delays: &DelayTable,
+    constraints: &TimingConstraints,
+) -> Result<TimingReport, TimingError> {
+    llhd_world.unit_entity(unit_id)?;
+    let module: &Module = llhd_world.module();
+    let unit = module.unit(unit_id);
+    let graph = LLHDInstGraph::new(llhd_world, unit_id)?;
+    let order: Vec<Inst> = graph
+        .topological_order()
+        .map_err(|LLHDGraphError::Cycle((_unit_id, inst))| TimingError::CombinationalLoop(inst))?
+        .into_iter()
+        .map(|(_unit_id, inst)| inst)
+        .collect();
+    let insts: BTreeMap<Inst, &LLHDInstComponent> = order
+        .iter()
+        .map(|inst| {
+            llhd_world
+                .get_inst::<LLHDInstComponent>(unit_id, *inst)
+                .map(|inst_component| (*inst, inst_component))
+        })
+        .collect::<Result<_, _>>()?;
+
+    let inst_delays: BTreeMap<Inst, f64> = insts
+        .iter()
+        .map(|(inst, inst_component)| {
+            let is_const = inst_component
+                .value
+                .is_some_and(|value| unit.get_const(value).is_some());
+            let delay = if is_const {
+                0.0
+            } else {
+                delays.inst_delay(module, unit_id, inst_component)
+            };
+            (*inst, delay)
+        })
+        .collect();
+
+    let mut arrivals: BTreeMap<Inst, f64> = BTreeMap::new();
+    for inst in &order {
+        let input_arrival = if graph.is_input_driven(*inst) {
+            *constraints.input_arrival()
+        } else {
+            0.0
+        };
+        let fanin_arrival = graph
+            .fanins(*inst)
+            .iter()
+            .map(|(_unit_id, fanin)| arrivals[fanin])
+            .fold(input_arrival, f64::max);
+        arrivals.insert(*inst, fanin_arrival + inst_delays[inst]);
+    }
+
+    let required_time = constraints
+        .required_time()
+        .unwrap_or_else(|| arrivals.values().copied().fold(0.0, f64::max));
+    let mut requireds: BTreeMap<Inst, f64> = BTreeMap::new();
+    for inst in order.iter().rev() {
+        let required = graph
+            .fanouts(*inst)
+            .iter()
+            .map(|(_unit_id, fanout)| requireds[fanout] - inst_delays[fanout])
+            .fold(required_time, f64::min);
+        requireds.insert(*inst, required);
+    }
+
+    let slack = |inst: Inst| requireds[&inst] - arrivals[&inst];
+    let mut critical_path = Vec::new();
+    let mut path_inst = order
+        .iter()
+        .copied()
+        .filter(|inst| graph.fanouts(*inst).is_empty())
+        .min_by(|lhs, rhs| {
+            slack(*lhs)
+                .total_cmp(&slack(*rhs))
+                .then(arrivals[rhs].total_cmp(&arrivals[lhs]))
+        });
+    while let Some(inst) = path_inst {
+        critical_path.push(inst);
+        path_inst = graph
+            .fanins(inst)
+            .into_iter()
+            .map(|(_unit_id, fanin)| fanin)
+            .max_by(|lhs, rhs| arrivals[lhs].total_cmp(&arrivals[rhs]));
+    }
+    critical_path.reverse();
+
+    let inst_timings = insts
+        .iter()
+        .map(|(inst, inst_component)| {
+            let name = llhd_world
+                .get_inst::<ECSEntityName>(unit_id, *inst)
+                .map_or_else(|_err| inst.to_string(), |name| name.as_str().to_owned());
+            let inst_timing = InstTiming {
+                inst: *inst,
+                opcode: inst_component.data.opcode(),
+                name,
+                delay: inst_delays[inst],
+                arrival: arrivals[inst],
+                required: requireds[inst],
+                slack: slack(*inst),
+            };
+            (*inst, inst_timing)
+        })
+        .collect();
+    Ok(TimingReport {
+        unit_name: unit.name().to_string(),
+        required_time,
+        insts: inst_timings,
+        critical_path,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+    use llhd::table::TableKey;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::llhd::module::LLHDModule;
+
+    fn test_world(input: &str) -> (LLHDWorld, UnitId) {
+        let module = llhd::assembly::parse_module(input).unwrap();
+        let llhd_world = LLHDWorld::new(LLHDModule::from(module));
+        let unit_id = llhd_world.module().units().next().unwrap().id();
+        (llhd_world, unit_id)
+    }
+
+    fn gate_delays() -> DelayTable {
+        DelayTable::builder()
+            .opcode_delays(HashMap::from([
+                (Opcode::And, 1.0),
+                (Opcode::Or, 2.0),
+                (Opcode::Not, 0.5),
+            ]))
+            .build()
+    }
+
+    fn opcode_inst(llhd_world: &LLHDWorld, unit_id: UnitId, opcode: Opcode) -> Inst {
+        let unit = llhd_world.module().unit(unit_id);
+        unit.all_insts()
+            .find(|inst| unit[*inst].opcode() == opcode)
+            .unwrap()
+    }
+
+    #[test]
+    fn delay_table_lookup_order() {
+        let (llhd_world, unit_id) = test_world(indoc::indoc! {"
+            entity @test_entity (i1 %in1, i1 %in2) -> (i1$ %out1) {
+                %null = const time 0s 1e
+                %and1 = and i1 %in1, %in2
+                %or1 = or i1 %in1, %in2
+                %xor1 = xor i1 %and1, %or1
+                drv i1$ %out1, %xor1, %null
+            }
+        "});
+        let delays = DelayTable::builder()
+            .opcode_delays(HashMap::from([(Opcode::And, 1.0), (Opcode::Or, 2.0)]))
+            .cell_delays(HashMap::from([("OR2".to_owned(), 3.0)]))
+            .cell_map(HashMap::from([(Opcode::Or, "OR2".to_owned())]))
+            .default_delay(0.25)
+            .build();
+        let module: &Module = llhd_world.module();
+        let inst_delay = |opcode| {
+            let inst = opcode_inst(&llhd_world, unit_id, opcode);
+            let inst_component = llhd_world
+                .get_inst::<LLHDInstComponent>(unit_id, inst)
+                .unwrap();
+            delays.inst_delay(module, unit_id, inst_component)
+        };
+        assert_eq!(1.0, inst_delay(Opcode::And), "And has an opcode delay.");
+        assert_eq!(3.0, inst_delay(Opcode::Or), "OR2 cell delay overrides Or.");
+        assert_eq!(0.25, inst_delay(Opcode::Xor), "Xor falls back to default.");
+    }
+
+    #[test]
+    fn unit_arrival_required_slack() {
+        let (llhd_world, unit_id) = test_world(indoc::indoc! {"
+            entity @test_entity (i1 %in1, i1 %in2, i1 %in3) -> (i1$ %out1) {
+                %null = const time 0s 1e
+                %and1 = and i1 %in1, %in2
+                %not1 = not i1 %in3
+                %or1 = or i1 %and1, %not1
+                drv i1$ %out1, %or1, %null
+            }
+        "});
+        let report = analyze_unit_timing(
+            &llhd_world,
+            unit_id,
+            &gate_delays(),
+            &TimingConstraints::default(),
+        )
+        .unwrap();
+        let and1 = report
+            .inst(opcode_inst(&llhd_world, unit_id, Opcode::And))
+            .unwrap();
+        let not1 = report
+            .inst(opcode_inst(&llhd_world, unit_id, Opcode::Not))
+            .unwrap();
+        let or1 = report
+            .inst(opcode_inst(&llhd_world, unit_id, Opcode::Or))
+            .unwrap();
+        assert_eq!(3.0, report.critical_path_delay());
+        assert_eq!(3.0, *report.required_time());
+        assert_eq!(
+            (1.0, 1.0, 0.0),
+            (*and1.arrival(), *and1.required(), *and1.slack())
+        );
+        assert_eq!(
+            (0.5, 1.0, 0.5),
+            (*not1.arrival(), *not1.required(), *not1.slack())
+        );
+        assert_eq!(
+            (3.0, 3.0, 0.0),
+            (*or1.arrival(), *or1.required(), *or1.slack())
+        );
+        assert_eq!(0.0, report.worst_slack());
+    }
+
+    #[test]
+    fn unit_critical_path_names() {
+        let (llhd_world, unit_id) = test_world(indoc::indoc! {"
+            entity @test_entity (i1 %in1, i1 %in2, i1 %in3) -> (i1$ %out1) {
+                %null = const time 0s 1e
+                %and1 = and i1 %in1, %in2
+                %not1 = not i1 %in3
+                %or1 = or i1 %and1, %not1
+                drv i1$ %out1, %or1, %null
+            }
+        "});
+        let report = analyze_unit_timing(
+            &llhd_world,
+            unit_id,
+            &gate_delays(),
+            &TimingConstraints::builder().required_time(4.0).build(),
+        )
+        .unwrap();
+        let expected_path = [Opcode::And, Opcode::Or, Opcode::Drv]
+            .into_iter()
+            .map(|opcode| format!("@test_entity.{}", opcode_inst(&llhd_world, unit_id, opcode)))
+            .collect_vec();
+        assert_eq!(expected_path, report.critical_path_names());
+        assert_eq!(
+            1.0,
+            report.worst_slack(),
+            "Required time leaves 1.0 of slack."
+        );
+        assert!(report.to_string().contains("critical path delay: 3.000"));
+    }
+
+    #[test]
+    fn stale_unit_timing() {
+        let (llhd_world, _unit_id) = test_world(indoc::indoc! {"
+            entity @test_entity (i1 %in1, i1 %in2) -> (i1$ %out1) {
+                %null = const time 0s 1e
+                %and1 = and i1 %in1, %in2
+                drv i1$ %out1, %and1, %null
+            }
+        "});
+        let stale_unit_id = UnitId::new(1);
+        assert_eq!(
+            Err(TimingError::World(LLHDWorldError::MissingUnit(
+                stale_unit_id
+            ))),
+            analyze_unit_timing(
+                &llhd_world,
+                stale_unit_id,
+                &gate_delays(),
+                &TimingConstraints::default(),
+            )
+        );
+    }
+
+    #[test]
+    fn criticality_opcode_costs() {
+        let (llhd_world, unit_id) = test_world(indoc::indoc! {"
+            entity @test_entity (i1 %in1, i1 %in2, i1 %in3) -> (i1$ %out1) {
+                %null = const time 0s 1e
+                %and1 = and i1 %in1, %in2
+                %not1 = not i1 %in3
+                %or1 = or i1 %and1, %not1
+                drv i1$ %out1, %or1, %null
+            }
+        "});
+        let report = analyze_unit_timing(
+            &llhd_world,
+            unit_id,
+            &gate_delays(),
+            &TimingConstraints::default(),
+        )
+        .unwrap();
+        let not1 = opcode_inst(&llhd_world, unit_id, Opcode::Not);
+        assert!((report.criticality(not1) - (1.0 - 0.5 / 3.0)).abs() < 1e-9);
+        let opcode_costs = report.opcode_costs(6);
+        assert_eq!(Some(&7), opcode_costs.get(&Opcode::And));
+        assert_eq!(Some(&6), opcode_costs.get(&Opcode::Not));
+        assert_eq!(Some(&1), opcode_costs.get(&Opcode::ConstTime));
+    }
+}
@@ -1,22 +1,37 @@
+use std::any::type_name;
 use std::collections::{BTreeSet, HashMap};
+use std::fmt;
 use std::ops::Add;
 
-use bevy_ecs::prelude::{Component, Entity, QueryState};
+use bevy_ecs::prelude::{Commands, Component, Entity, QueryState, World};
 use bevy_ecs::query::QueryData;
-use bevy_hierarchy::{BuildWorldChildren, Children};
-use llhd::ir::{Inst, UnitId, Value};
+use bevy_ecs::system::CommandQueue;
+use bevy_hierarchy::{BuildChildren, Children};
+use llhd::ir::{Block, Inst, Unit, UnitId, Value};
+use rayon::iter::ParallelIterator;
 
 // use super::components::inst::LLHDInstComponent;
 // use super::components::unit::LLHDUnitComponent;
 // use super::components::block::LLHDBlockComponent;
+use self::edit::EditHistory;
+use self::names::LLHDNameIndex;
+use super::components::block::LLHDBlockComponent;
 use super::components::inst::LLHDInstComponent;
+use super::components::value::LLHDValueRefComponent;
 use crate::circuit::graph::LCircuitEdgeID;
 use crate::llhd::module::LLHDModule;
 use crate::llhd_world::initializer::{
-    build_blocks, build_insts, build_units, build_value_defs, build_value_refs,
+    build_blocks, build_insts, build_unit, build_value_defs, build_value_refs,
 };
 use crate::world::LWorld;
 
+pub(crate) mod analog;
+pub(crate) mod edit;
+pub(crate) mod names;
+pub(crate) mod passes;
+pub(crate) mod snapshot;
+pub(crate) mod update;
+
 pub type InstIndex = (UnitId, Inst);
 pub type ValueDefIndex = (UnitId, Value);
 pub type ValueRefIndex = (UnitId, Inst, Value);
@@ -29,6 +44,12 @@ type ValueRefMapper = HashMap<ValueRefIndex, Entity>;
 #[derive(Debug, Clone, Default, Component)]
 pub struct ECSEntityName(String);
 
+impl ECSEntityName {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
 impl Add for ECSEntityName {
     type Output = Self;
 
@@ -37,6 +58,137 @@ impl Add for ECSEntityName {
     }
 }
 
+/// Error of an `LLHDWorld` accessor, an id without entity or an entity without the component.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LLHDWorldError {
+    MissingUnit(UnitId),
+    MissingInst(UnitId, Inst),
+    MissingValueDef(UnitId, Value),
+    MissingValueRef(UnitId, Inst, Value),
+    MissingComponent {
+        entity: Entity,
+        component: &'static str,
+    },
+}
+
+impl fmt::Display for LLHDWorldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingUnit(unit_id) => write!(f, "No entity for unit {}", unit_id),
+            Self::MissingInst(unit_id, inst) => {
+                write!(f, "No entity for inst {} in unit {}", inst, unit_id)
+            }
+            Self::MissingValueDef(unit_id, value) => {
+                write!(f, "No entity for value {} in unit {}", value, unit_id)
+            }
+            Self::MissingValueRef(unit_id, inst, value) => write!(
+                f,
+                "No entity for use of value {} by inst {} in unit {}",
+                value, inst, unit_id
+            ),
+            Self::MissingComponent { entity, component } => {
+                write!(f, "Entity {:?} has no {}", entity, component)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LLHDWorldError {}
+
+/// Entities of a unit, reserved up front and spawned once `commands` are applied.
+struct UnitSpawn {
+    commands: CommandQueue,
+    unit: (UnitId, Entity),
+    insts: Vec<(InstIndex, Entity)>,
+    value_defs: Vec<(ValueDefIndex, Entity)>,
+    value_refs: Vec<(ValueRefIndex, Entity)>,
+}
+
+impl UnitSpawn {
+    /// Unit entity, parent of its block and value def entities, blocks parents of their insts
+    /// and insts of their value refs.
+    fn new(world: &World, unit: &Unit) -> Self {
+        let unit_id = unit.id();
+        let mut command_queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut command_queue, world);
+        let unit_name = ECSEntityName(unit.name().to_string());
+        let unit_entity = commands
+            .spawn((build_unit(unit), unit_name.to_owned()))
+            .id();
+        let (mut insts, mut value_defs, mut value_refs) = (Vec::new(), Vec::new(), Vec::new());
+        let mut unit_children = Vec::new();
+        for block_component in build_blocks(unit) {
+            let Some(block_id) = block_component.id else {
+                continue;
+            };
+            let block_name = unit_name.to_owned()
+                + ECSEntityName(".".to_owned())
+                + ECSEntityName(
+                    block_component
+                        .data
+                        .name
+                        .to_owned()
+                        .unwrap_or(block_id.to_string()),
+                );
+            let block_entity = commands.spawn((block_component, block_name)).id();
+            let mut block_children = Vec::new();
+            for inst_component in build_insts(unit, block_id) {
+                let Some(inst_id) = inst_component.id else {
+                    continue;
+                };
+                let inst_name = unit_name.to_owned()
+                    + ECSEntityName(".".to_owned())
+                    + ECSEntityName(inst_id.to_string());
+                let inst_value_refs =
+                    build_value_refs(inst_id, &inst_component.data).collect::<Vec<_>>();
+                let inst_entity = commands.spawn((inst_component, inst_name)).id();
+                let inst_children = inst_value_refs
+                    .into_iter()
+                    .map(|value_ref_component| {
+                        let value_def_id = value_ref_component
+                            .id
+                            .expect("Unexpected missing Value Def in ValueRef Component.");
+                        let value_ref_entity = commands.spawn(value_ref_component).id();
+                        value_refs.push(((unit_id, inst_id, value_def_id), value_ref_entity));
+                        value_ref_entity
+                    })
+                    .collect::<Vec<_>>();
+                push_children(&mut commands, inst_entity, &inst_children);
+                insts.push(((unit_id, inst_id), inst_entity));
+                block_children.push(inst_entity);
+            }
+            push_children(&mut commands, block_entity, &block_children);
+            unit_children.push(block_entity);
+        }
+        for value_component in build_value_defs(unit) {
+            let Some(value_id) = value_component.id else {
+                continue;
+            };
+            let value_name = unit_name.to_owned()
+                + ECSEntityName(".".to_owned())
+                + ECSEntityName(value_id.to_string());
+            let value_def_entity = commands.spawn((value_component, value_name)).id();
+            value_defs.push(((unit_id, value_id), value_def_entity));
+            unit_children.push(value_def_entity);
+        }
+        push_children(&mut commands, unit_entity, &unit_children);
+        Self {
+            commands: command_queue,
+            unit: (unit_id, unit_entity),
+            insts,
+            value_defs,
+            value_refs,
+        }
+    }
+}
+
+/// Parents `children` to `parent`, leaving childless entities without `Children`.
+fn push_children(commands: &mut Commands, parent: Entity, children: &[Entity]) {
+    if !children.is_empty() {
+        commands.entity(parent).push_children(children);
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct LLHDWorld {
     world: LWorld,
@@ -44,106 +196,46 @@ pub struct LLHDWorld {
     inst_map: InstMapper,
     value_def_map: ValueDefMapper,
     value_ref_map: ValueRefMapper,
+    history: EditHistory,
+    names: LLHDNameIndex,
 }
 
 impl LLHDWorld {
+    /// Spawns the entities of every unit, each unit recording its spawns into a `CommandQueue` of
+    /// its own in parallel, applied to the world one unit after another.
     pub fn new(module: LLHDModule) -> Self {
         let mut world = LWorld::default();
-        let mut unit_map = UnitMapper::default();
-        let mut inst_map = InstMapper::default();
-        let mut value_def_map = ValueDefMapper::default();
-        let mut value_ref_map = ValueRefMapper::default();
-        build_units(&module).for_each(|unit_component| {
-            if let Some(unit_id) = unit_component.id {
-                let unit_name = ECSEntityName(unit_component.name.to_string());
-                let mut unit_entity = world.spawn(unit_component);
-                unit_entity
-                    .insert(unit_name.to_owned())
-                    .with_children(|parent_unit| {
-                        build_blocks(&module.unit(unit_id)).for_each(|block_component| {
-                            if let Some(block_id) = block_component.id {
-                                let block_name = unit_name.to_owned()
-                                    + ECSEntityName(".".to_string())
-                                    + ECSEntityName(
-                                        block_component
-                                            .to_owned()
-                                            .data
-                                            .name
-                                            .unwrap_or(block_id.to_string()),
-                                    );
-                                parent_unit
-                                    .spawn(block_component)
-                                    .insert(block_name)
-                                    .with_children(|parent_block| {
-                                        build_insts(&module.unit(unit_id), block_id).for_each(
-                                            |inst_component| {
-                                                if let Some(inst_id) = inst_component.id {
-                                                    let inst_name = unit_name.to_owned()
-                                                        + ECSEntityName(".".to_string())
-                                                        + ECSEntityName(inst_id.to_string());
-                                                    let inst_data = inst_component.data.to_owned();
-                                                    let mut inst_entity =
-                                                        parent_block.spawn(inst_component);
-                                                    inst_entity.insert(inst_name).with_children(
-                                                        |parent_inst| {
-                                                            build_value_refs(inst_id, &inst_data)
-                                                                .for_each(|value_ref_component| {
-                                                                    let value_def_id =
-                                                                        value_ref_component
-                                                                            .id
-                                                                            .expect(
-                                                                                "Unexpected \
-                                                                                 missing Value \
-                                                                                 Def in ValueRef \
-                                                                                 Component.",
-                                                                            );
-                                                                    let value_ref_entity =
-                                                                        parent_inst.spawn(
-                                                                            value_ref_component,
-                                                                        );
-                                                                    value_ref_map.insert(
-                                                                        (
-                                                                            unit_id,
-                                                                            inst_id,
-                                                                            value_def_id,
-                                                                        ),
-                                                                        value_ref_entity.id(),
-                                                                    );
-                                                                });
-                                                        },
-                                                    );
-                                                    inst_map.insert(
-                                                        (unit_id, inst_id),
-                                                        inst_entity.id(),
-                                                    );
-                                                }
-                                            },
-                                        );
-                                    });
-                            }
-                        });
-                        build_value_defs(&module.unit(unit_id)).for_each(|value_component| {
-                            if let Some(value_id) = value_component.id {
-                                let value_name = unit_name.to_owned()
-                                    + ECSEntityName(".".to_string())
-                                    + ECSEntityName(value_id.to_string());
-                                let mut value_def_entity = parent_unit.spawn(value_component);
-                                value_def_entity.insert(value_name);
-                                value_def_map.insert((unit_id, value_id), value_def_entity.id());
-                            }
-                        });
-                    });
-                unit_map.insert(unit_id, unit_entity.id());
-            }
-        });
+        let unit_spawns = module
+            .par_units()
+            .map(|unit| UnitSpawn::new(&world, &unit))
+            .collect::<Vec<_>>();
         world.insert_resource(module);
-        Self {
+        let mut llhd_world = Self {
             world,
-            unit_map,
-            inst_map,
-            value_def_map,
-            value_ref_map,
+            ..Self::default()
+        };
+        let mut unit_ids = Vec::with_capacity(unit_spawns.len());
+        for mut unit_spawn in unit_spawns {
+            unit_spawn.commands.apply(&mut llhd_world.world);
+            let (unit_id, unit_entity) = unit_spawn.unit;
+            llhd_world.unit_map.insert(unit_id, unit_entity);
+            llhd_world.inst_map.extend(unit_spawn.insts);
+            llhd_world.value_def_map.extend(unit_spawn.value_defs);
+            llhd_world.value_ref_map.extend(unit_spawn.value_refs);
+            unit_ids.push(unit_id);
         }
+        unit_ids
+            .into_iter()
+            .for_each(|unit_id| llhd_world.index_unit_names(unit_id));
+        llhd_world
+    }
+
+    /// Units of the module with their entities, for passes working on units in parallel.
+    pub fn par_units(&self) -> impl ParallelIterator<Item = (Unit<'_>, Entity)> + '_ {
+        self.module().par_units().filter_map(|unit| {
+            let unit_entity = self.unit_map.get(&unit.id())?;
+            Some((unit, *unit_entity))
+        })
     }
 
     pub fn module(&self) -> &LLHDModule {
@@ -156,28 +248,44 @@ impl LLHDWorld {
         &self.world
     }
 
-    pub fn get_unit<T: Component>(&self, unit_id: UnitId) -> Option<&T> {
-        let entity = self.unit_map[&unit_id];
-        self.world.get::<T>(entity)
+    pub fn get_unit<T: Component>(&self, unit_id: UnitId) -> Result<&T, LLHDWorldError> {
+        let entity = self.unit_entity(unit_id)?;
+        self.get_component(entity)
     }
 
-    pub fn get_inst<T: Component>(&self, unit_id: UnitId, inst_id: Inst) -> Option<&T> {
-        let entity = self.inst_map[&(unit_id, inst_id)];
-        self.world.get::<T>(entity)
+    pub fn get_inst<T: Component>(
+        &self,
+        unit_id: UnitId,
+        inst_id: Inst,
+    ) -> Result<&T, LLHDWorldError> {
+        let entity = self.inst_entity(unit_id, inst_id)?;
+        self.get_component(entity)
     }
 
-    pub fn set_inst<T: Component>(&mut self, unit_id: UnitId, inst_id: Inst, value: T) {
-        let entity = self.inst_map[&(unit_id, inst_id)];
-        let mut entity_mut = self
-            .world
+    pub fn set_inst<T: Component>(
+        &mut self,
+        unit_id: UnitId,
+        inst_id: Inst,
+        value: T,
+    ) -> Result<(), LLHDWorldError> {
+        let entity = self.inst_entity(unit_id, inst_id)?;
+        self.world
             .get_entity_mut(entity)
-            .expect("Unexpected missing entity.");
-        entity_mut.insert(value);
+            .ok_or(LLHDWorldError::MissingInst(unit_id, inst_id))?
+            .insert(value);
+        Ok(())
     }
 
-    pub fn get_value_def<T: Component>(&self, unit_id: UnitId, value_id: Value) -> Option<&T> {
-        let entity = self.value_def_map[&(unit_id, value_id)];
-        self.world.get::<T>(entity)
+    pub fn get_value_def<T: Component>(
+        &self,
+        unit_id: UnitId,
+        value_id: Value,
+    ) -> Result<&T, LLHDWorldError> {
+        let entity = self
+            .value_def_map
+            .get(&(unit_id, value_id))
+            .ok_or(LLHDWorldError::MissingValueDef(unit_id, value_id))?;
+        self.get_component(*entity)
     }
 
     pub fn get_value_ref<T: Component>(
@@ -185,63 +293,112 @@ impl LLHDWorld {
         unit_id: UnitId,
         inst_id: Inst,
         value_id: Value,
-    ) -> Option<&T> {
-        let entity = self.value_ref_map[&(unit_id, inst_id, value_id)];
-        self.world.get::<T>(entity)
+    ) -> Result<&T, LLHDWorldError> {
+        let entity = self
+            .value_ref_map
+            .get(&(unit_id, inst_id, value_id))
+            .ok_or(LLHDWorldError::MissingValueRef(unit_id, inst_id, value_id))?;
+        self.get_component(*entity)
     }
 
-    pub fn slow_lookup(&mut self, name: &str) -> Option<Entity> {
-        let mut entity_name_query = self.world.query::<(Entity, &ECSEntityName)>();
-        entity_name_query
-            .iter(&self.world)
-            .find(|(_entity, ecs_entity_name_component)| ecs_entity_name_component.0 == name)
-            .unzip()
-            .0
+    pub fn unit_entity(&self, unit_id: UnitId) -> Result<Entity, LLHDWorldError> {
+        self.unit_map
+            .get(&unit_id)
+            .copied()
+            .ok_or(LLHDWorldError::MissingUnit(unit_id))
+    }
+
+    pub fn inst_entity(&self, unit_id: UnitId, inst_id: Inst) -> Result<Entity, LLHDWorldError> {
+        self.inst_map
+            .get(&(unit_id, inst_id))
+            .copied()
+            .ok_or(LLHDWorldError::MissingInst(unit_id, inst_id))
+    }
+
+    pub fn value_def_entity(
+        &self,
+        unit_id: UnitId,
+        value_id: Value,
+    ) -> Result<Entity, LLHDWorldError> {
+        self.value_def_map
+            .get(&(unit_id, value_id))
+            .copied()
+            .ok_or(LLHDWorldError::MissingValueDef(unit_id, value_id))
+    }
+
+    pub fn value_ref_entity(
+        &self,
+        unit_id: UnitId,
+        inst_id: Inst,
+        value_id: Value,
+    ) -> Result<Entity, LLHDWorldError> {
+        self.value_ref_map
+            .get(&(unit_id, inst_id, value_id))
+            .copied()
+            .ok_or(LLHDWorldError::MissingValueRef(unit_id, inst_id, value_id))
+    }
+
+    /// Block entities among the children of `unit_entity`.
+    fn block_entities(&self, unit_entity: Entity) -> HashMap<Block, Entity> {
+        self.world
+            .get::<Children>(unit_entity)
+            .into_iter()
+            .flatten()
+            .filter_map(|entity| {
+                self.world
+                    .get::<LLHDBlockComponent>(*entity)
+                    .and_then(|block_component| block_component.id)
+                    .map(|block| (block, *entity))
+            })
+            .collect()
+    }
+
+    fn get_component<T: Component>(&self, entity: Entity) -> Result<&T, LLHDWorldError> {
+        self.world
+            .get::<T>(entity)
+            .ok_or(LLHDWorldError::MissingComponent {
+                entity,
+                component: type_name::<T>(),
+            })
+    }
+
+    pub fn unit_value_refs(
+        &self,
+        unit_id: UnitId,
+    ) -> impl Iterator<Item = &LLHDValueRefComponent> + '_ {
+        self.value_ref_map
+            .iter()
+            .filter(move |((ref_unit_id, _inst_id, _value_id), _entity)| *ref_unit_id == unit_id)
+            .filter_map(|(_value_ref_idx, entity)| self.world.get::<LLHDValueRefComponent>(*entity))
     }
 
     pub fn query<D: QueryData>(&mut self) -> QueryState<D, ()> {
         self.world.query::<D>()
     }
 
+    /// Insts of `unit_id` holding a `T`, in block order, with their `LLHDInstComponent`.
     pub fn unit_program_inst<T: Component + Clone>(
         &self,
         unit_id: UnitId,
-    ) -> impl Iterator<Item = (InstIndex, LLHDInstComponent, T)> + '_ {
-        let unit_entity = self.unit_map[&unit_id];
-        let unit_children = self
+    ) -> Result<impl Iterator<Item = (InstIndex, LLHDInstComponent, T)> + '_, LLHDWorldError> {
+        let unit_entity = self.unit_entity(unit_id)?;
+        let block_entities = self
             .world
             .get::<Children>(unit_entity)
-            .expect("Unit should contain child entities.");
-        unit_children
-            .iter()
-            .filter(|block_entity| self.world.get::<Children>(**block_entity).is_some())
-            .flat_map(move |block_entity| {
-                let block_children = self
-                    .world
-                    .get::<Children>(*block_entity)
-                    .expect("Block should contain child entities.");
-                block_children
-                    .iter()
-                    .map(move |inst_entity| {
-                        let inst_component = self
-                            .world
-                            .get::<LLHDInstComponent>(*inst_entity)
-                            .expect("Inst entity should be present.");
-                        let inst_id = inst_component.id.expect("Inst should have Id.");
-                        (inst_id, inst_entity, inst_component.to_owned())
-                    })
-                    .filter(|(_inst_id, inst_entity, _inst_component)| {
-                        self.world.get::<T>(**inst_entity).is_some()
-                    })
-                    .map(move |(inst_id, inst_entity, inst_component)| {
-                        let inst_data = self
-                            .world
-                            .get::<T>(*inst_entity)
-                            .cloned()
-                            .expect("Inst Component data should be present.");
-                        ((unit_id, inst_id), inst_component.to_owned(), inst_data)
-                    })
-            })
+            .into_iter()
+            .flat_map(|unit_children| unit_children.iter());
+        Ok(block_entities
+            .filter_map(|block_entity| self.world.get::<Children>(*block_entity))
+            .flat_map(|block_children| block_children.iter())
+            .filter_map(move |inst_entity| {
+                let inst_component = self.world.get::<LLHDInstComponent>(*inst_entity)?;
+                let inst_data = self.world.get::<T>(*inst_entity)?;
+                Some((
+                    (unit_id, inst_component.id?),
+                    inst_component.to_owned(),
+                    inst_data.to_owned(),
+                ))
+            }))
     }
 }
 
@@ -314,11 +471,11 @@ mod tests {
         let sub_module_name = "%top.and";
         let top_module_name = "@top";
         assert!(
-            llhd_world.slow_lookup(&sub_module_name).is_some(),
+            llhd_world.lookup(&sub_module_name).is_some(),
             "%top.and should be present name to lookup in ECS."
         );
         assert!(
-            llhd_world.slow_lookup(&top_module_name).is_some(),
+            llhd_world.lookup(&top_module_name).is_some(),
             "@top should be present name to lookup in ECS."
         );
 
@@ -335,13 +492,13 @@ mod tests {
         let top_module_name_last_value_full_name =
             top_module_name.to_owned() + "." + top_module_name_last_value;
         let sub_module_name_first_value_entity =
-            llhd_world.slow_lookup(&sub_module_name_first_value_full_name);
+            llhd_world.lookup(&sub_module_name_first_value_full_name);
         let sub_module_name_last_value_entity =
-            llhd_world.slow_lookup(&sub_module_name_last_value_full_name);
+            llhd_world.lookup(&sub_module_name_last_value_full_name);
         let top_module_name_first_value_entity =
-            llhd_world.slow_lookup(&top_module_name_first_value_full_name);
+            llhd_world.lookup(&top_module_name_first_value_full_name);
         let top_module_name_last_value_entity =
-            llhd_world.slow_lookup(&top_module_name_last_value_full_name);
+            llhd_world.lookup(&top_module_name_last_value_full_name);
         assert!(
             sub_module_name_first_value_entity.is_some(),
             "%top.and.v0 should be present name to lookup in ECS."
@@ -366,9 +523,9 @@ mod tests {
         let top_module_name_first_block_full_name =
             top_module_name.to_owned() + "." + top_module_name_first_block;
         let sub_module_name_first_block_entity =
-            llhd_world.slow_lookup(&sub_module_name_first_block_full_name);
+            llhd_world.lookup(&sub_module_name_first_block_full_name);
         let top_module_name_first_block_entity =
-            llhd_world.slow_lookup(&top_module_name_first_block_full_name);
+            llhd_world.lookup(&top_module_name_first_block_full_name);
         assert!(
             sub_module_name_first_block_entity.is_some(),
             "%top.and.init should be present name to lookup in ECS."
@@ -391,13 +548,13 @@ mod tests {
         let top_module_name_last_inst_full_name =
             top_module_name.to_owned() + "." + top_module_name_last_inst;
         let sub_module_name_first_inst_entity =
-            llhd_world.slow_lookup(&sub_module_name_first_inst_full_name);
+            llhd_world.lookup(&sub_module_name_first_inst_full_name);
         let sub_module_name_last_inst_entity =
-            llhd_world.slow_lookup(&sub_module_name_last_inst_full_name);
+            llhd_world.lookup(&sub_module_name_last_inst_full_name);
         let top_module_name_first_inst_entity =
-            llhd_world.slow_lookup(&top_module_name_first_inst_full_name);
+            llhd_world.lookup(&top_module_name_first_inst_full_name);
         let top_module_name_last_inst_entity =
-            llhd_world.slow_lookup(&top_module_name_last_inst_full_name);
+            llhd_world.lookup(&top_module_name_last_inst_full_name);
         assert!(
             sub_module_name_first_inst_entity.is_some(),
             "%top.and.i0 should be present name to lookup in ECS."
@@ -699,19 +856,19 @@ mod tests {
         let func_entry_full_name = func_name.to_owned() + "." + func_entry_name;
         let func_next_full_name = func_name.to_owned() + "." + func_next_name;
         assert!(
-            llhd_world.slow_lookup(&magic_entity_name).is_some(),
+            llhd_world.lookup(&magic_entity_name).is_some(),
             "@magic should be present name to lookup in ECS."
         );
         assert!(
-            llhd_world.slow_lookup(&func_name).is_some(),
+            llhd_world.lookup(&func_name).is_some(),
             "@foo should be present name to lookup in ECS."
         );
         assert!(
-            llhd_world.slow_lookup(&func_entry_full_name).is_some(),
+            llhd_world.lookup(&func_entry_full_name).is_some(),
             "@foo.entry should be present name to lookup in ECS."
         );
         assert!(
-            llhd_world.slow_lookup(&func_next_full_name).is_some(),
+            llhd_world.lookup(&func_next_full_name).is_some(),
             "@foo.next should be present name to lookup in ECS."
         );
 
@@ -838,6 +995,7 @@ mod tests {
         let unit_id = UnitId::new(0);
         let unit_program_inst = llhd_world
             .unit_program_inst::<LLHDInstComponent>(unit_id)
+            .unwrap()
             .collect::<Vec<(InstIndex, LLHDInstComponent, LLHDInstComponent)>>();
         assert_eq!(
             6,
@@ -845,4 +1003,57 @@ mod tests {
             "There should be 6 Instructions in the Unit Program."
         );
     }
+
+    #[test]
+    fn llhd_world_missing_ids() {
+        let input = indoc::indoc! {"
+            entity @test_entity (i1 %in1, i1 %in2) -> (i1$ %out1) {
+                %null = const time 0s 1e
+                %and1 = and i1 %in1, %in2
+                drv i1$ %out1, %and1, %null
+            }
+        "};
+
+        let module = llhd::assembly::parse_module(input).unwrap();
+        let mut llhd_world = LLHDWorld::new(LLHDModule::from(module));
+        let (unit_id, stale_unit_id) = (UnitId::new(0), UnitId::new(1));
+        let (inst_id, stale_inst_id) = (Inst::new(1), Inst::new(9));
+        let stale_value_id = Value::new(9);
+        assert_eq!(
+            Err(LLHDWorldError::MissingUnit(stale_unit_id)),
+            llhd_world.get_unit::<LLHDUnitComponent>(stale_unit_id)
+        );
+        assert_eq!(
+            Err(LLHDWorldError::MissingInst(unit_id, stale_inst_id)),
+            llhd_world.get_inst::<LLHDInstComponent>(unit_id, stale_inst_id)
+        );
+        assert_eq!(
+            Err(LLHDWorldError::MissingValueDef(unit_id, stale_value_id)),
+            llhd_world.get_value_def::<LLHDValueDefComponent>(unit_id, stale_value_id)
+        );
+        assert_eq!(
+            Err(LLHDWorldError::MissingValueRef(
+                unit_id,
+                inst_id,
+                stale_value_id
+            )),
+            llhd_world.get_value_ref::<LLHDValueRefComponent>(unit_id, inst_id, stale_value_id)
+        );
+        assert_eq!(
+            Err(LLHDWorldError::MissingInst(unit_id, stale_inst_id)),
+            llhd_world.set_inst(unit_id, stale_inst_id, ECSEntityName::default())
+        );
+        assert!(llhd_world
+            .unit_program_inst::<LLHDInstComponent>(stale_unit_id)
+            .is_err());
+        let missing_component = llhd_world.get_inst::<LLHDUnitComponent>(unit_id, inst_id);
+        assert!(
+            matches!(
+                missing_component,
+                Err(LLHDWorldError::MissingComponent { component, .. })
+                    if component.ends_with("LLHDUnitComponent")
+            ),
+            "Inst entities have no unit component."
+        );
+    }
 }
@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+
+use bevy_ecs::prelude::Entity;
+use llhd::ir::{Opcode, UnitId, Value};
+
+use super::names::LLHDEntityId;
+use super::{AnalogCircuitIndex, LLHDWorld, LLHDWorldError};
+use crate::circuit::graph::hierarchy::SubcircuitTemplate;
+use crate::circuit::graph::LCircuit;
+use crate::index::unit::{AnalogNetComponent, CircuitComponent};
+use crate::llhd_library::circuit_library::LCircuitLibrary;
+
+/// Nets of `cell` bound to each arg, by name where the args are named and by position where
+/// none is and the arg count matches the port count.
+fn port_nets(cell: &SubcircuitTemplate, arg_names: &[Option<&str>]) -> Vec<AnalogCircuitIndex> {
+    let positional = arg_names.iter().all(Option::is_none) && arg_names.len() == cell.ports().len();
+    arg_names
+        .iter()
+        .enumerate()
+        .map(|(arg_idx, arg_name)| {
+            let port = match arg_name {
+                Some(arg_name) => cell
+                    .ports()
+                    .iter()
+                    .find(|port| port.to_string().eq_ignore_ascii_case(arg_name)),
+                None if positional => cell.ports().get(arg_idx),
+                None => None,
+            };
+            port.and_then(|port| cell.circuit().net(port))
+                .into_iter()
+                .collect()
+        })
+        .collect()
+}
+
+impl LLHDWorld {
+    /// Binds every unit named after a cell of `library` and every `inst` of such a unit to the
+    /// cell circuit, returning the bound units and insts.
+    ///
+    /// Units and insts get a `CircuitComponent`, the args of a cell unit and the values passed
+    /// to an `inst` an `AnalogNetComponent` with the cell nets of their ports. Bindings of a unit
+    /// are dropped once an edit respawns its entities.
+    pub fn bind_circuits(
+        &mut self,
+        library: &LCircuitLibrary,
+    ) -> Result<Vec<LLHDEntityId>, LLHDWorldError> {
+        let module = self.module();
+        let cell_units = module
+            .units()
+            .filter_map(|unit| {
+                let cell = unit.name().get_name().and_then(|name| library.cell(name))?;
+                Some((unit.name().to_owned(), (unit.id(), cell)))
+            })
+            .collect::<HashMap<_, _>>();
+        let mut circuits: Vec<(LLHDEntityId, Entity, &LCircuit)> = Vec::new();
+        let mut port_bindings: Vec<(Entity, AnalogCircuitIndex)> = Vec::new();
+        for (unit_id, cell) in cell_units.values() {
+            let unit = module.unit(*unit_id);
+            let args = unit.args().collect::<Vec<_>>();
+            let arg_names = args
+                .iter()
+                .map(|arg| unit.get_name(*arg))
+                .collect::<Vec<_>>();
+            circuits.push((
+                LLHDEntityId::Unit(*unit_id),
+                self.unit_entity(*unit_id)?,
+                cell.circuit(),
+            ));
+            for (arg, arg_nets) in args.into_iter().zip(port_nets(cell, &arg_names)) {
+                port_bindings.push((self.value_def_entity(*unit_id, arg)?, arg_nets));
+            }
+        }
+        for unit in module.units() {
+            let unit_id = unit.id();
+            for inst in unit.all_insts() {
+                if unit[inst].opcode() != Opcode::Inst {
+                    continue;
+                }
+                let Some(ext_unit) = unit[inst].get_ext_unit() else {
+                    continue;
+                };
+                let cell_name = unit.extern_name(ext_unit);
+                let Some(cell) = cell_name.get_name().and_then(|name| library.cell(name)) else {
+                    continue;
+                };
+                let arg_names = match cell_units.get(cell_name) {
+                    Some((cell_unit_id, _cell)) => {
+                        let cell_unit = module.unit(*cell_unit_id);
+                        cell_unit
+                            .args()
+                            .map(|arg| cell_unit.get_name(arg))
+                            .collect::<Vec<_>>()
+                    }
+                    None => vec![None; unit.extern_sig(ext_unit).args().count()],
+                };
+                circuits.push((
+                    LLHDEntityId::Inst(unit_id, inst),
+                    self.inst_entity(unit_id, inst)?,
+                    cell.circuit(),
+                ));
+                for (value, ref_nets) in unit[inst].args().iter().zip(port_nets(cell, &arg_names)) {
+                    port_bindings.push((self.value_ref_entity(unit_id, inst, *value)?, ref_nets));
+                }
+            }
+        }
+        let mut value_nets: HashMap<Entity, AnalogCircuitIndex> = HashMap::new();
+        port_bindings.into_iter().for_each(|(entity, bound_nets)| {
+            value_nets.entry(entity).or_default().extend(bound_nets);
+        });
+        let mut bound = Vec::new();
+        for (id, entity, circuit) in circuits {
+            self.world
+                .entity_mut(entity)
+                .insert(CircuitComponent::new(circuit.to_owned()));
+            bound.push(id);
+        }
+        for (entity, nets) in value_nets {
+            self.world
+                .entity_mut(entity)
+                .insert(AnalogNetComponent::new(nets));
+        }
+        bound.sort_unstable();
+        Ok(bound)
+    }
+
+    /// Cell circuit bound to `unit_id` by `bind_circuits`.
+    pub fn unit_circuit(&self, unit_id: UnitId) -> Result<&LCircuit, LLHDWorldError> {
+        self.get_unit::<CircuitComponent>(unit_id)
+            .map(CircuitComponent::circuit)
+    }
+
+    /// Cell nets bound to the port value `value` of `unit_id` by `bind_circuits`.
+    pub fn value_nets(
+        &self,
+        unit_id: UnitId,
+        value: Value,
+    ) -> Result<&AnalogCircuitIndex, LLHDWorldError> {
+        self.get_value_def::<AnalogNetComponent>(unit_id, value)
+            .map(AnalogNetComponent::nets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::str::FromStr;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::circuit::equations::DeviceEquationMap;
+    use crate::circuit::nodes::CircuitNode;
+    use crate::circuit::spice::SPICENetlist;
+    use crate::llhd::module::LLHDModule;
+    use crate::llhd_world::components::inst::LLHDInstComponent;
+
+    const NAND2: &str = "sky130_fd_sc_ls__nand2_1";
+
+    fn nand2_library() -> LCircuitLibrary {
+        let mut spice_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        spice_path.push("resources/libraries_no_liberty/sky130_fd_sc_ls/latest/cells/nand2");
+        spice_path.push(format!("{}.spice", NAND2));
+        let spice_netlist =
+            SPICENetlist::parse_deck(&std::fs::read_to_string(spice_path).unwrap()).unwrap();
+        LCircuitLibrary::from_spice(&spice_netlist, &DeviceEquationMap::new()).unwrap()
+    }
+
+    fn nand2_world() -> LLHDWorld {
+        let module = llhd::assembly::parse_module(indoc::indoc! {"
+            entity @sky130_fd_sc_ls__nand2_1 (i1$ %A, i1$ %B) -> (i1$ %Y) {
+                %epsilon = const time 0s 1e
+                %a = prb i1$ %A
+                %b = prb i1$ %B
+                %and = and i1 %a, %b
+                %nand = not i1 %and
+                drv i1$ %Y, %nand, %epsilon
+            }
+
+            entity @top (i1$ %in1, i1$ %in2) -> (i1$ %out1) {
+                inst @sky130_fd_sc_ls__nand2_1 (i1$ %in1, i1$ %in2) -> (i1$ %out1)
+            }
+        "})
+        .unwrap();
+        LLHDWorld::new(LLHDModule::from(module))
+    }
+
+    fn unit_id(llhd_world: &LLHDWorld, name: &str) -> UnitId {
+        llhd_world
+            .module()
+            .units()
+            .find(|unit| unit.name().get_name() == Some(name))
+            .unwrap()
+            .id()
+    }
+
+    #[test]
+    fn bind_cell_circuits() {
+        let library = nand2_library();
+        let mut llhd_world = nand2_world();
+        let (nand2_id, top_id) = (unit_id(&llhd_world, NAND2), unit_id(&llhd_world, "top"));
+        let instance = llhd_world.module().unit(top_id).all_insts().next().unwrap();
+        assert_eq!(
+            vec![
+                LLHDEntityId::Unit(nand2_id),
+                LLHDEntityId::Inst(top_id, instance)
+            ],
+            llhd_world.bind_circuits(&library).unwrap()
+        );
+
+        let cell = library.cell(NAND2).unwrap();
+        let port_net = |port: &str| {
+            cell.circuit()
+                .net(&CircuitNode::from_str(port).unwrap())
+                .unwrap()
+        };
+        assert_eq!(
+            cell.circuit().elements().count(),
+            llhd_world
+                .unit_circuit(nand2_id)
+                .unwrap()
+                .elements()
+                .count()
+        );
+        let nand2_args = llhd_world
+            .module()
+            .unit(nand2_id)
+            .args()
+            .collect::<Vec<_>>();
+        ["A", "B", "Y"]
+            .into_iter()
+            .zip(nand2_args)
+            .for_each(|(port, arg)| {
+                assert_eq!(
+                    &AnalogCircuitIndex::from([port_net(port)]),
+                    llhd_world.value_nets(nand2_id, arg).unwrap()
+                );
+            });
+        assert!(llhd_world.unit_circuit(top_id).is_err());
+
+        let top_args = llhd_world.module().unit(top_id).args().collect::<Vec<_>>();
+        assert_eq!(
+            &AnalogNetComponent::new(AnalogCircuitIndex::from([port_net("Y")])),
+            llhd_world
+                .get_value_ref::<AnalogNetComponent>(top_id, instance, top_args[2])
+                .unwrap()
+        );
+        let mut instances = llhd_world
+            .world
+            .query::<(&LLHDInstComponent, &CircuitComponent)>();
+        let views = instances.iter(&llhd_world.world).collect::<Vec<_>>();
+        assert_eq!(1, views.len(), "Only the nand2 instance has a circuit.");
+        assert_eq!(Some(instance), views[0].0.id);
+    }
+}
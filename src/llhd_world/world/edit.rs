@@ -0,0 +1,635 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use bevy_ecs::prelude::Entity;
+use llhd::ir::{
+    Block, ExtUnit, Inst, InstData, Unit, UnitBuilder, UnitData, UnitId, UnitKind, UnitName, Value,
+};
+use llhd::ty::Type;
+
+use super::{LLHDWorld, LLHDWorldError};
+use crate::llhd::module::LLHDModule;
+use crate::llhd::LLHDUtils;
+
+/// Error of an edit, the transaction it is part of is rolled back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LLHDEditError {
+    MissingUnit(UnitId),
+    MissingInst(UnitId, Inst),
+    MissingBlock(UnitId, Block),
+    MissingValue(UnitId, Value),
+    MissingExtUnit(UnitId, ExtUnit),
+    /// Uses of `from` replaced by a value of another type.
+    TypeMismatch {
+        unit_id: UnitId,
+        from: Value,
+        to: Value,
+    },
+    /// Deleted inst whose result is still read.
+    InstInUse(UnitId, Inst),
+    DuplicateUnit(UnitName),
+    /// Output port added to a function, which returns its result instead.
+    FunctionOutput(UnitId),
+}
+
+impl fmt::Display for LLHDEditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingUnit(unit_id) => write!(f, "No unit {} in module", unit_id),
+            Self::MissingInst(unit_id, inst) => write!(f, "No inst {} in unit {}", inst, unit_id),
+            Self::MissingBlock(unit_id, block) => {
+                write!(f, "No block {} in unit {}", block, unit_id)
+            }
+            Self::MissingValue(unit_id, value) => {
+                write!(f, "No value {} in unit {}", value, unit_id)
+            }
+            Self::MissingExtUnit(unit_id, ext_unit) => {
+                write!(f, "No external unit {} in unit {}", ext_unit, unit_id)
+            }
+            Self::TypeMismatch { unit_id, from, to } => write!(
+                f,
+                "Uses of {} in unit {} cannot be replaced by {} of another type",
+                from, unit_id, to
+            ),
+            Self::InstInUse(unit_id, inst) => {
+                write!(
+                    f,
+                    "Result of inst {} in unit {} is still used",
+                    inst, unit_id
+                )
+            }
+            Self::DuplicateUnit(name) => write!(f, "Unit {} already in module", name),
+            Self::FunctionOutput(unit_id) => {
+                write!(f, "Function {} cannot have output ports", unit_id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LLHDEditError {}
+
+/// Where `LLHDTransaction::insert_inst` places an inst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstPosition {
+    Before(Inst),
+    After(Inst),
+    /// End of the block, ahead of its terminator when it has one.
+    Append(Block),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PortDirection {
+    Input,
+    Output,
+}
+
+/// Unit state from before a transaction, restored by a rollback or an undo.
+enum UnitSnapshot {
+    /// Data of a unit before its first edit in the transaction.
+    Edited(UnitId, UnitData),
+    /// Unit added by the transaction.
+    Added(UnitId),
+}
+
+impl UnitSnapshot {
+    const fn unit_id(&self) -> UnitId {
+        match self {
+            Self::Edited(unit_id, _) | Self::Added(unit_id) => *unit_id,
+        }
+    }
+}
+
+impl fmt::Debug for UnitSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Edited(unit_id, unit_data) => f
+                .debug_tuple("Edited")
+                .field(unit_id)
+                .field(&unit_data.name)
+                .finish(),
+            Self::Added(unit_id) => f.debug_tuple("Added").field(unit_id).finish(),
+        }
+    }
+}
+
+/// Committed transactions of an `LLHDWorld`, latest last.
+#[derive(Debug, Default)]
+pub(crate) struct EditHistory(Vec<Vec<UnitSnapshot>>);
+
+/// Edits of one `LLHDWorld::transaction`, applied to the `LLHDModule` as they are made and to
+/// the ECS hierarchy once the transaction commits.
+///
+/// The first edit of a unit moves its data aside and continues on an exact copy, ids of the
+/// unit stay valid across edits other than `add_port`.
+pub struct LLHDTransaction<'world> {
+    llhd_world: &'world mut LLHDWorld,
+    snapshots: Vec<UnitSnapshot>,
+}
+
+impl LLHDTransaction<'_> {
+    /// Module with the edits of the transaction so far.
+    pub fn module(&self) -> &LLHDModule {
+        self.llhd_world.module()
+    }
+
+    /// Inserts an inst built from `inst_data`, with a result of type `ty` unless void.
+    pub fn insert_inst(
+        &mut self,
+        unit_id: UnitId,
+        position: InstPosition,
+        inst_data: InstData,
+        ty: Type,
+    ) -> Result<Inst, LLHDEditError> {
+        let unit = self.unit(unit_id)?;
+        check_operands(&unit, &inst_data)?;
+        let position = match position {
+            InstPosition::Before(inst) | InstPosition::After(inst)
+                if !unit.is_inst_inserted(inst) =>
+            {
+                return Err(LLHDEditError::MissingInst(unit_id, inst));
+            }
+            InstPosition::Append(block) if !unit.is_block_inserted(block) => {
+                return Err(LLHDEditError::MissingBlock(unit_id, block));
+            }
+            InstPosition::Append(block) => unit
+                .last_inst(block)
+                .filter(|inst| unit[*inst].opcode().is_terminator())
+                .map_or(position, InstPosition::Before),
+            position => position,
+        };
+        let mut builder = self.unit_mut(unit_id);
+        match position {
+            InstPosition::Before(inst) => builder.insert_before(inst),
+            InstPosition::After(inst) => builder.insert_after(inst),
+            InstPosition::Append(block) => builder.append_to(block),
+        }
+        Ok(builder.build_inst(inst_data, ty))
+    }
+
+    /// Replaces every use of `from` by `to`, returns the number of replaced uses.
+    pub fn replace_uses(
+        &mut self,
+        unit_id: UnitId,
+        from: Value,
+        to: Value,
+    ) -> Result<usize, LLHDEditError> {
+        let unit = self.unit(unit_id)?;
+        let values = unit_values(&unit);
+        if let Some(value) = [from, to].into_iter().find(|value| !values.contains(value)) {
+            return Err(LLHDEditError::MissingValue(unit_id, value));
+        }
+        if unit.value_type(from) != unit.value_type(to) {
+            return Err(LLHDEditError::TypeMismatch { unit_id, from, to });
+        }
+        Ok(self.unit_mut(unit_id).replace_use(from, to))
+    }
+
+    /// Replaces the opcode and operands of `inst` by `inst_data`, its result is kept.
+    pub fn replace_inst(
+        &mut self,
+        unit_id: UnitId,
+        inst: Inst,
+        inst_data: InstData,
+    ) -> Result<(), LLHDEditError> {
+        let unit = self.unit(unit_id)?;
+        if !unit.is_inst_inserted(inst) {
+            return Err(LLHDEditError::MissingInst(unit_id, inst));
+        }
+        check_operands(&unit, &inst_data)?;
+        self.unit_mut(unit_id)[inst] = inst_data;
+        Ok(())
+    }
+
+    /// Deletes `inst`, whose result must no longer be read.
+    pub fn delete_inst(&mut self, unit_id: UnitId, inst: Inst) -> Result<(), LLHDEditError> {
+        let unit = self.unit(unit_id)?;
+        if !unit.is_inst_inserted(inst) {
+            return Err(LLHDEditError::MissingInst(unit_id, inst));
+        }
+        if unit
+            .get_inst_result(inst)
+            .is_some_and(|result| unit.has_uses(result))
+        {
+            return Err(LLHDEditError::InstInUse(unit_id, inst));
+        }
+        self.unit_mut(unit_id).delete_inst(inst);
+        Ok(())
+    }
+
+    pub fn add_unit(&mut self, unit_data: UnitData) -> Result<UnitId, LLHDEditError> {
+        if self
+            .module()
+            .units()
+            .any(|unit| unit.name() == &unit_data.name)
+        {
+            return Err(LLHDEditError::DuplicateUnit(unit_data.name));
+        }
+        let unit_id = self.module_mut().add_unit(unit_data);
+        self.snapshots.push(UnitSnapshot::Added(unit_id));
+        Ok(unit_id)
+    }
+
+    /// Adds a trailing input or output of type `ty` to `unit_id`, returns its argument value.
+    ///
+    /// The unit is rebuilt for its new signature, which renumbers its insts, blocks and values.
+    /// Instantiations of the unit are left as they are.
+    pub fn add_port(
+        &mut self,
+        unit_id: UnitId,
+        direction: PortDirection,
+        ty: Type,
+    ) -> Result<Value, LLHDEditError> {
+        let unit = self.unit(unit_id)?;
+        let mut sig = unit.sig().to_owned();
+        let arg = match direction {
+            PortDirection::Input => sig.add_input(ty),
+            PortDirection::Output if unit.kind() == UnitKind::Function => {
+                return Err(LLHDEditError::FunctionOutput(unit_id));
+            }
+            PortDirection::Output => sig.add_output(ty),
+        };
+        let unit_data = LLHDUtils::rebuild_unit_data(&unit, sig);
+        let mut builder = self.unit_mut(unit_id);
+        *builder.data() = unit_data;
+        Ok(builder.arg_value(arg))
+    }
+
+    fn unit(&self, unit_id: UnitId) -> Result<Unit<'_>, LLHDEditError> {
+        self.module()
+            .units()
+            .find(|unit| unit.id() == unit_id)
+            .ok_or(LLHDEditError::MissingUnit(unit_id))
+    }
+
+    /// Builder of `unit_id`, set aside in a snapshot on its first edit.
+    fn unit_mut(&mut self, unit_id: UnitId) -> UnitBuilder<'_> {
+        let snapshotted = self
+            .snapshots
+            .iter()
+            .any(|snapshot| snapshot.unit_id() == unit_id);
+        let module = self
+            .llhd_world
+            .world
+            .resource_mut::<LLHDModule>()
+            .into_inner();
+        if !snapshotted {
+            let unit_data = LLHDUtils::clone_unit_data(module.unit(unit_id).data());
+            let original = std::mem::replace(module.unit_mut(unit_id).data(), unit_data);
+            self.snapshots.push(UnitSnapshot::Edited(unit_id, original));
+        }
+        module.unit_mut(unit_id)
+    }
+
+    fn module_mut(&mut self) -> &mut LLHDModule {
+        self.llhd_world
+            .world
+            .resource_mut::<LLHDModule>()
+            .into_inner()
+    }
+}
+
+/// Arguments and inst results of `unit`.
+fn unit_values(unit: &Unit) -> HashSet<Value> {
+    unit.args()
+        .chain(
+            unit.all_insts()
+                .filter_map(|inst| unit.get_inst_result(inst)),
+        )
+        .collect()
+}
+
+/// Every value, block and external unit read by `inst_data` belongs to `unit`.
+fn check_operands(unit: &Unit, inst_data: &InstData) -> Result<(), LLHDEditError> {
+    let values = unit_values(unit);
+    if let Some(value) = inst_data.args().iter().find(|arg| !values.contains(arg)) {
+        return Err(LLHDEditError::MissingValue(unit.id(), *value));
+    }
+    if let Some(block) = inst_data
+        .blocks()
+        .iter()
+        .find(|block| !unit.is_block_inserted(**block))
+    {
+        return Err(LLHDEditError::MissingBlock(unit.id(), *block));
+    }
+    match inst_data.get_ext_unit() {
+        Some(ext_unit)
+            if !unit
+                .extern_units()
+                .any(|(unit_ext, _data)| unit_ext == ext_unit) =>
+        {
+            Err(LLHDEditError::MissingExtUnit(unit.id(), ext_unit))
+        }
+        _ => Ok(()),
+    }
+}
+
+impl LLHDWorld {
+    /// Runs `edits` as one transaction, its edits reach the ECS hierarchy when it returns `Ok`
+    /// and are rolled back from the `LLHDModule` when it returns an error.
+    ///
+    /// An edited unit without entities in the world rolls the transaction back with
+    /// `LLHDEditError::MissingUnit`.
+    ///
+    /// Committed transactions can be reverted with `LLHDWorld::undo`.
+    pub fn transaction<R>(
+        &mut self,
+        edits: impl FnOnce(&mut LLHDTransaction) -> Result<R, LLHDEditError>,
+    ) -> Result<R, LLHDEditError> {
+        let mut transaction = LLHDTransaction {
+            llhd_world: self,
+            snapshots: Vec::new(),
+        };
+        let result = edits(&mut transaction);
+        let snapshots = transaction.snapshots;
+        let unit_entities = self
+            .snapshot_entities(&snapshots)
+            .map_err(LLHDEditError::MissingUnit);
+        match result.and_then(|value| Ok((value, unit_entities?))) {
+            Ok((value, unit_entities)) => {
+                snapshots.iter().for_each(|snapshot| match snapshot {
+                    UnitSnapshot::Edited(unit_id, unit_data) => {
+                        self.sync_unit(*unit_id, unit_entities[unit_id], unit_data);
+                    }
+                    UnitSnapshot::Added(unit_id) => {
+                        self.spawn_unit(*unit_id);
+                    }
+                });
+                if !snapshots.is_empty() {
+                    self.history.0.push(snapshots);
+                }
+                Ok(value)
+            }
+            Err(error) => {
+                let module = self.world.resource_mut::<LLHDModule>().into_inner();
+                snapshots
+                    .into_iter()
+                    .rev()
+                    .for_each(|snapshot| match snapshot {
+                        UnitSnapshot::Edited(unit_id, unit_data) => {
+                            *module.unit_mut(unit_id).data() = unit_data;
+                        }
+                        UnitSnapshot::Added(unit_id) => module.remove_unit(unit_id),
+                    });
+                Err(error)
+            }
+        }
+    }
+
+    /// Reverts the latest committed transaction, `false` when there is none left.
+    ///
+    /// A transaction whose edited units lost their entities is kept in the history.
+    pub fn undo(&mut self) -> Result<bool, LLHDWorldError> {
+        let Some(snapshots) = self.history.0.pop() else {
+            return Ok(false);
+        };
+        let unit_entities = match self.snapshot_entities(&snapshots) {
+            Ok(unit_entities) => unit_entities,
+            Err(unit_id) => {
+                self.history.0.push(snapshots);
+                return Err(LLHDWorldError::MissingUnit(unit_id));
+            }
+        };
+        snapshots
+            .into_iter()
+            .rev()
+            .for_each(|snapshot| match snapshot {
+                UnitSnapshot::Edited(unit_id, unit_data) => {
+                    self.replace_unit(unit_id, unit_entities[&unit_id], unit_data);
+                }
+                UnitSnapshot::Added(unit_id) => {
+                    self.despawn_unit(unit_id);
+                    self.world.resource_mut::<LLHDModule>().remove_unit(unit_id);
+                }
+            });
+        Ok(true)
+    }
+
+    /// Unit entities of the `Edited` snapshots, or the first edited unit without one.
+    fn snapshot_entities(
+        &self,
+        snapshots: &[UnitSnapshot],
+    ) -> Result<HashMap<UnitId, Entity>, UnitId> {
+        snapshots
+            .iter()
+            .filter_map(|snapshot| match snapshot {
+                UnitSnapshot::Edited(unit_id, _) => Some(*unit_id),
+                UnitSnapshot::Added(_) => None,
+            })
+            .map(|unit_id| {
+                self.unit_map
+                    .get(&unit_id)
+                    .map(|unit_entity| (unit_id, *unit_entity))
+                    .ok_or(unit_id)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use llhd::ir::{Opcode, Signature};
+    use llhd::ty::{int_ty, signal_ty};
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::llhd_world::components::inst::LLHDInstComponent;
+    use crate::llhd_world::components::unit::LLHDUnitComponent;
+
+    const ENTITY: &str = indoc::indoc! {"
+        entity @test_entity (i1 %in1, i1 %in2) -> (i1$ %out1) {
+            %null = const time 0s 1e
+            %and1 = and i1 %in1, %in2
+            drv i1$ %out1, %and1, %null
+        }
+    "};
+
+    fn test_world() -> (LLHDWorld, UnitId) {
+        let module = llhd::assembly::parse_module(ENTITY).unwrap();
+        let llhd_world = LLHDWorld::new(LLHDModule::from(module));
+        let unit_id = llhd_world.module().units().next().unwrap().id();
+        (llhd_world, unit_id)
+    }
+
+    fn dump(llhd_world: &LLHDWorld) -> String {
+        llhd_world.module().dump().to_string()
+    }
+
+    /// Every inst of the module has an entity holding its data.
+    fn assert_synced(llhd_world: &LLHDWorld) {
+        let module = llhd_world.module();
+        let module_insts = module
+            .units()
+            .flat_map(|unit| unit.all_insts().map(move |inst| (unit.id(), inst)))
+            .collect::<HashSet<_>>();
+        assert_eq!(
+            module_insts,
+            llhd_world.inst_map.keys().copied().collect::<HashSet<_>>()
+        );
+        module_insts.into_iter().for_each(|(unit_id, inst)| {
+            assert_eq!(
+                module.unit(unit_id)[inst],
+                llhd_world
+                    .get_inst::<LLHDInstComponent>(unit_id, inst)
+                    .unwrap()
+                    .data
+            );
+        });
+    }
+
+    #[test]
+    fn llhd_world_transaction() {
+        let (mut llhd_world, unit_id) = test_world();
+        let (and_inst, and_entity) = {
+            let unit = llhd_world.module().unit(unit_id);
+            let and_inst = unit
+                .all_insts()
+                .find(|inst| unit[*inst].opcode() == Opcode::And)
+                .unwrap();
+            (and_inst, llhd_world.inst_map[&(unit_id, and_inst)])
+        };
+        let or_inst = llhd_world
+            .transaction(|transaction| {
+                let unit = transaction.module().unit(unit_id);
+                let args = [unit.input_arg(0), unit.input_arg(1)];
+                let and_value = unit.inst_result(and_inst);
+                let or_inst = transaction.insert_inst(
+                    unit_id,
+                    InstPosition::After(and_inst),
+                    InstData::Binary {
+                        opcode: Opcode::Or,
+                        args,
+                    },
+                    int_ty(1),
+                )?;
+                let or_value = transaction.module().unit(unit_id).inst_result(or_inst);
+                assert_eq!(1, transaction.replace_uses(unit_id, and_value, or_value)?);
+                transaction.delete_inst(unit_id, and_inst)?;
+                Ok(or_inst)
+            })
+            .unwrap();
+        assert!(dump(&llhd_world).contains("or i1 %in1, %in2"));
+        assert!(!dump(&llhd_world).contains("and i1"));
+        assert!(llhd_world.world().get_entity(and_entity).is_none());
+        assert_eq!(
+            Some(or_inst),
+            llhd_world
+                .get_inst::<LLHDInstComponent>(unit_id, or_inst)
+                .unwrap()
+                .id
+        );
+        assert_synced(&llhd_world);
+
+        assert!(llhd_world.undo().unwrap());
+        assert_eq!(
+            llhd::assembly::parse_module(ENTITY)
+                .unwrap()
+                .dump()
+                .to_string(),
+            dump(&llhd_world)
+        );
+        assert_synced(&llhd_world);
+        assert!(!llhd_world.undo().unwrap());
+    }
+
+    #[test]
+    fn transaction_on_unit_without_entity() {
+        let (mut llhd_world, unit_id) = test_world();
+        let module_dump = dump(&llhd_world);
+        let unit_entity = llhd_world.unit_map.remove(&unit_id).unwrap();
+        let error = llhd_world.transaction(|transaction| {
+            let unit = transaction.module().unit(unit_id);
+            transaction.insert_inst(
+                unit_id,
+                InstPosition::Append(unit.entry()),
+                InstData::Unary {
+                    opcode: Opcode::Not,
+                    args: [unit.input_arg(0)],
+                },
+                int_ty(1),
+            )
+        });
+        assert_eq!(Err(LLHDEditError::MissingUnit(unit_id)), error);
+        assert_eq!(module_dump, dump(&llhd_world));
+        llhd_world.unit_map.insert(unit_id, unit_entity);
+        assert_synced(&llhd_world);
+    }
+
+    #[test]
+    fn failed_llhd_world_transaction() {
+        let (mut llhd_world, unit_id) = test_world();
+        let module_dump = dump(&llhd_world);
+        let inst_map = llhd_world.inst_map.to_owned();
+        let error = llhd_world.transaction(|transaction| {
+            let unit = transaction.module().unit(unit_id);
+            let null_inst = unit.all_insts().next().unwrap();
+            let in1 = unit.input_arg(0);
+            transaction.insert_inst(
+                unit_id,
+                InstPosition::Append(unit.entry()),
+                InstData::Unary {
+                    opcode: Opcode::Not,
+                    args: [in1],
+                },
+                int_ty(1),
+            )?;
+            transaction.delete_inst(unit_id, null_inst)
+        });
+        assert!(matches!(error, Err(LLHDEditError::InstInUse(_, _))));
+        assert_eq!(module_dump, dump(&llhd_world));
+        assert_eq!(inst_map, llhd_world.inst_map);
+
+        let in1 = llhd_world.module().unit(unit_id).input_arg(0);
+        let out1 = llhd_world.module().unit(unit_id).output_arg(0);
+        assert_eq!(
+            Err(LLHDEditError::TypeMismatch {
+                unit_id,
+                from: in1,
+                to: out1,
+            }),
+            llhd_world.transaction(|transaction| transaction.replace_uses(unit_id, in1, out1))
+        );
+    }
+
+    #[test]
+    fn add_llhd_world_unit_and_port() {
+        let (mut llhd_world, unit_id) = test_world();
+        let mut sig = Signature::new();
+        sig.add_input(int_ty(1));
+        sig.add_output(signal_ty(int_ty(1)));
+        let name = UnitName::global("test_added");
+        let added_unit_id = llhd_world
+            .transaction(|transaction| {
+                let port = transaction.add_port(unit_id, PortDirection::Input, int_ty(1))?;
+                assert_eq!(
+                    Some(port),
+                    transaction.module().unit(unit_id).input_args().last()
+                );
+                transaction.add_unit(UnitData::new(UnitKind::Entity, name.to_owned(), sig))
+            })
+            .unwrap();
+        assert_eq!(3, llhd_world.module().unit(unit_id).input_args().count());
+        assert_eq!(
+            Ok(&name),
+            llhd_world
+                .get_unit::<LLHDUnitComponent>(added_unit_id)
+                .map(|unit_component| &unit_component.name)
+        );
+        assert_synced(&llhd_world);
+        assert_eq!(
+            Err(LLHDEditError::DuplicateUnit(name.to_owned())),
+            llhd_world.transaction(|transaction| {
+                transaction.add_unit(UnitData::new(
+                    UnitKind::Entity,
+                    name.to_owned(),
+                    Signature::new(),
+                ))
+            })
+        );
+
+        assert!(llhd_world.undo().unwrap());
+        assert_eq!(1, llhd_world.module().units().count());
+        assert!(!llhd_world.unit_map.contains_key(&added_unit_id));
+        assert_eq!(2, llhd_world.module().unit(unit_id).input_args().count());
+        assert_synced(&llhd_world);
+    }
+}
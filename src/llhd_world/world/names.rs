@@ -0,0 +1,336 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use bevy_ecs::prelude::Entity;
+use llhd::ir::{Block, Inst, Unit, UnitId, Value};
+use serde::{Deserialize, Serialize};
+
+use super::LLHDWorld;
+use crate::llhd::module::LLHDModule;
+
+/// LLHD object behind an entity, stable across rebuilds of the world unlike its `Entity`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum LLHDEntityId {
+    Unit(UnitId),
+    Block(UnitId, Block),
+    Inst(UnitId, Inst),
+    Value(UnitId, Value),
+    /// Use of a value by an inst, unnamed.
+    ValueRef(UnitId, Inst, Value),
+}
+
+#[derive(Debug, Default)]
+struct NameNode {
+    entry: Option<(Entity, LLHDEntityId)>,
+    children: BTreeMap<String, NameNode>,
+}
+
+impl NameNode {
+    const fn new(entity: Entity, id: LLHDEntityId) -> Self {
+        Self {
+            entry: Some((entity, id)),
+            children: BTreeMap::new(),
+        }
+    }
+
+    /// Entries below `self` along `segments`, a child key spans as many segments as it has
+    /// `.`-separated parts, e.g. the unit `%top.and`.
+    fn collect(&self, segments: &[&str], matches: &mut Vec<(Entity, LLHDEntityId)>) {
+        let Some(first) = segments.first() else {
+            matches.extend(self.entry);
+            return;
+        };
+        if segments.iter().any(|segment| is_pattern(segment)) {
+            self.children.iter().for_each(|(key, child)| {
+                let key_segments = key.split('.').collect::<Vec<_>>();
+                let spanned = segments.get(..key_segments.len());
+                if spanned.is_some_and(|spanned| {
+                    spanned
+                        .iter()
+                        .zip(&key_segments)
+                        .all(|(pattern, key_segment)| glob_match(pattern, key_segment))
+                }) {
+                    child.collect(&segments[key_segments.len()..], matches);
+                }
+            });
+        } else {
+            let mut key = (*first).to_owned();
+            for span in 1..=segments.len() {
+                if span > 1 {
+                    key.push('.');
+                    key.push_str(segments[span - 1]);
+                }
+                if let Some(child) = self.children.get(&key) {
+                    child.collect(&segments[span..], matches);
+                }
+            }
+        }
+    }
+}
+
+/// Hierarchical index of the `ECSEntityName`s of an `LLHDWorld`, kept up to date as units are
+/// spawned, updated and despawned.
+///
+/// Every entity sits at its `ECSEntityName`, `unit.block`, `unit.inst` or `unit.value`, insts
+/// also below their block as `unit.block.inst`. Insts and values with a named result are
+/// reachable by that name too, e.g. `@top.bb0.%and1` and `@top.%and1`.
+#[derive(Debug, Default)]
+pub(crate) struct LLHDNameIndex {
+    root: NameNode,
+    unit_keys: HashMap<UnitId, String>,
+}
+
+impl LLHDNameIndex {
+    fn remove_unit(&mut self, unit_id: UnitId) {
+        if let Some(unit_key) = self.unit_keys.remove(&unit_id) {
+            self.root.children.remove(&unit_key);
+        }
+    }
+
+    /// Entities matching `pattern`, `.`-separated names where `*` matches any run of
+    /// characters and `?` any single character within a name, each entity once in index order.
+    fn find(&self, pattern: &str) -> Vec<(Entity, LLHDEntityId)> {
+        let segments = pattern.split('.').collect::<Vec<_>>();
+        let mut matches = Vec::new();
+        self.root.collect(&segments, &mut matches);
+        let mut found = HashSet::new();
+        matches.retain(|(entity, _id)| found.insert(*entity));
+        matches
+    }
+}
+
+fn is_pattern(segment: &str) -> bool {
+    segment.contains(['*', '?'])
+}
+
+/// Whether `name` matches `pattern` as a whole, `*` matching any run of characters and `?` a
+/// single one.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+    let (mut pattern_idx, mut name_idx) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while name_idx < name.len() {
+        match pattern.get(pattern_idx) {
+            Some('*') => {
+                backtrack = Some((pattern_idx, name_idx));
+                pattern_idx += 1;
+            }
+            Some(ch) if *ch == '?' || *ch == name[name_idx] => {
+                pattern_idx += 1;
+                name_idx += 1;
+            }
+            _ => match backtrack {
+                Some((star_idx, star_name_idx)) => {
+                    pattern_idx = star_idx + 1;
+                    name_idx = star_name_idx + 1;
+                    backtrack = Some((star_idx, star_name_idx + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[pattern_idx..].iter().all(|ch| *ch == '*')
+}
+
+/// Alias of a value by its name, `%name`.
+fn value_alias(unit: &Unit, value: Value) -> Option<String> {
+    unit.get_name(value).map(|name| format!("%{}", name))
+}
+
+impl LLHDWorld {
+    /// Entity named `name`, an `ECSEntityName` or one of its aliases in the name index.
+    pub fn lookup(&self, name: &str) -> Option<Entity> {
+        self.names
+            .find(name)
+            .into_iter()
+            .next()
+            .map(|(entity, _id)| entity)
+    }
+
+    /// Entities and LLHD ids matching `pattern`, e.g. `@top.*.%and*` for the `and` insts of
+    /// every block of `@top`, see `LLHDNameIndex`.
+    pub fn find(&self, pattern: &str) -> Vec<(Entity, LLHDEntityId)> {
+        self.names.find(pattern)
+    }
+
+    /// Rebuilds the name index entries of `unit_id` from its entities.
+    pub(super) fn index_unit_names(&mut self, unit_id: UnitId) {
+        self.names.remove_unit(unit_id);
+        let Some(unit_entity) = self.unit_map.get(&unit_id) else {
+            return;
+        };
+        let unit = self.world.resource::<LLHDModule>().unit(unit_id);
+        let mut unit_node = NameNode::new(*unit_entity, LLHDEntityId::Unit(unit_id));
+        let block_entities = self.block_entities(*unit_entity);
+        for block in unit.blocks() {
+            let Some(block_entity) = block_entities.get(&block) else {
+                continue;
+            };
+            let mut block_node = NameNode::new(*block_entity, LLHDEntityId::Block(unit_id, block));
+            for inst in unit.insts(block) {
+                let Some(inst_entity) = self.inst_map.get(&(unit_id, inst)) else {
+                    continue;
+                };
+                let entry = (*inst_entity, LLHDEntityId::Inst(unit_id, inst));
+                let alias = unit
+                    .get_inst_result(inst)
+                    .and_then(|result| value_alias(&unit, result));
+                [Some(inst.to_string()), alias]
+                    .into_iter()
+                    .flatten()
+                    .for_each(|key| {
+                        block_node
+                            .children
+                            .insert(key, NameNode::new(entry.0, entry.1));
+                    });
+                unit_node
+                    .children
+                    .insert(inst.to_string(), NameNode::new(entry.0, entry.1));
+            }
+            let block_key = unit[block].name.to_owned().unwrap_or(block.to_string());
+            unit_node.children.insert(block_key, block_node);
+        }
+        for value in unit.args().chain(
+            unit.all_insts()
+                .filter_map(|inst| unit.get_inst_result(inst)),
+        ) {
+            let Some(value_entity) = self.value_def_map.get(&(unit_id, value)) else {
+                continue;
+            };
+            [Some(value.to_string()), value_alias(&unit, value)]
+                .into_iter()
+                .flatten()
+                .for_each(|key| {
+                    unit_node.children.insert(
+                        key,
+                        NameNode::new(*value_entity, LLHDEntityId::Value(unit_id, value)),
+                    );
+                });
+        }
+        let unit_key = unit.name().to_string();
+        self.names
+            .root
+            .children
+            .insert(unit_key.to_owned(), unit_node);
+        self.names.unit_keys.insert(unit_id, unit_key);
+    }
+
+    pub(super) fn unindex_unit_names(&mut self, unit_id: UnitId) {
+        self.names.remove_unit(unit_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    const MODULE: &str = indoc::indoc! {"
+        proc %top.and (i1$ %in1, i1$ %in2) -> (i1$ %out1) {
+        %init:
+            %epsilon = const time 0s 1e
+            %in1_prb = prb i1$ %in1
+            %in2_prb = prb i1$ %in2
+            %and1 = and i1 %in1_prb, %in2_prb
+            drv i1$ %out1, %and1, %epsilon
+            wait %init for %epsilon
+        }
+
+        entity @top (i1 %a, i1 %b, i1 %c) -> (i1$ %out1) {
+            %epsilon = const time 0s 1e
+            %and1 = and i1 %a, %b
+            %and2 = and i1 %and1, %c
+            %or1 = or i1 %and1, %and2
+            drv i1$ %out1, %or1, %epsilon
+        }
+    "};
+
+    fn names_world() -> LLHDWorld {
+        LLHDWorld::new(LLHDModule::from(
+            llhd::assembly::parse_module(MODULE).unwrap(),
+        ))
+    }
+
+    #[test]
+    fn glob_match_names() {
+        assert!(glob_match("%and*", "%and1"));
+        assert!(glob_match("*", "bb0"));
+        assert!(glob_match("i?", "i4"));
+        assert!(glob_match("*d*", "%and2"));
+        assert!(!glob_match("%and*", "%or1"));
+        assert!(!glob_match("i?", "i10"));
+    }
+
+    #[test]
+    fn lookup_llhd_world_names() {
+        let llhd_world = names_world();
+        let top_id = llhd_world
+            .module()
+            .units()
+            .find(|unit| unit.name().to_string() == "@top")
+            .unwrap()
+            .id();
+        assert_eq!(
+            Some(llhd_world.unit_map[&top_id]),
+            llhd_world.lookup("@top")
+        );
+        assert!(llhd_world.lookup("%top.and").is_some());
+        assert!(llhd_world.lookup("%top.and.init").is_some());
+        assert!(llhd_world.lookup("%top.and.%in1_prb").is_some());
+        assert!(llhd_world.lookup("@top.missing").is_none());
+
+        let and_insts = llhd_world
+            .find("@top.*.%and*")
+            .into_iter()
+            .map(|(entity, id)| {
+                let LLHDEntityId::Inst(unit_id, inst) = id else {
+                    panic!("Only insts should match, found {:?}.", id);
+                };
+                assert_eq!(llhd_world.inst_map[&(unit_id, inst)], entity);
+                llhd_world.module().unit(unit_id)[inst].opcode()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(vec![llhd::ir::Opcode::And; 2], and_insts);
+        assert_eq!(3, llhd_world.find("@top.%?").len(), "Values %a, %b and %c.");
+        assert_eq!(
+            1,
+            llhd_world.find("*.%out1").len(),
+            "`*` spans one part of `%top.and`."
+        );
+        assert_eq!(
+            2,
+            llhd_world.find("*.%out1").len() + llhd_world.find("%top.*.%out1").len()
+        );
+        assert!(llhd_world.find("@top.*.%in1_prb").is_empty());
+    }
+
+    #[test]
+    fn llhd_world_names_follow_edits() {
+        let mut llhd_world = names_world();
+        let top_id = llhd_world
+            .module()
+            .units()
+            .find(|unit| unit.name().to_string() == "@top")
+            .unwrap()
+            .id();
+        let or_entity = llhd_world.lookup("@top.bb0.%or1").unwrap();
+        llhd_world
+            .transaction(|transaction| {
+                let unit = transaction.module().unit(top_id);
+                let (or_inst, and_inst) = (
+                    unit.all_insts().nth(3).unwrap(),
+                    unit.all_insts().nth(2).unwrap(),
+                );
+                let (or_value, and_value) = (unit.inst_result(or_inst), unit.inst_result(and_inst));
+                transaction.replace_uses(top_id, or_value, and_value)?;
+                transaction.delete_inst(top_id, or_inst)
+            })
+            .unwrap();
+        assert!(llhd_world.lookup("@top.bb0.%or1").is_none());
+        assert!(llhd_world.world().get_entity(or_entity).is_none());
+        assert_eq!(2, llhd_world.find("@top.*.%and*").len());
+        assert!(llhd_world.undo().unwrap());
+        assert!(llhd_world.lookup("@top.bb0.%or1").is_some());
+    }
+}
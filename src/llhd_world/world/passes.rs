@@ -0,0 +1,491 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use bevy_ecs::prelude::{Changed, Query, Resource};
+use bevy_ecs::schedule::{ExecutorKind, IntoSystemConfigs, Schedule, ScheduleLabel};
+use llhd::ir::{UnitData, UnitId};
+
+use super::update::UnitUpdate;
+use super::{LLHDWorld, LLHDWorldError};
+use crate::llhd_world::components::unit::LLHDUnitComponent;
+use crate::llhd_world::graph::kahn_order;
+
+/// Passes run by `Flow::synthesize`.
+pub const SYNTHESIS_SCHEDULE: &str = "synthesis";
+
+/// Units added or edited since the last run of the querying pass, Bevy change detection over the
+/// unit entities.
+pub type LLHDChangedUnits<'world, 'state> =
+    Query<'world, 'state, &'static LLHDUnitComponent, Changed<LLHDUnitComponent>>;
+
+/// Bevy schedule holding the systems of a pass.
+#[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
+struct LLHDPassLabel(String);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LLHDPassKind {
+    /// Reads the world, e.g. filling components of its entities.
+    Analysis,
+    /// Rewrites units through `LLHDUnitEdits`.
+    Transform,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LLHDPassError {
+    DuplicatePass(String),
+    UnknownDependency {
+        pass: String,
+        dependency: String,
+    },
+    DependencyCycle(String),
+    /// Analysis pass which queued unit edits.
+    AnalysisEdit(String),
+    World(LLHDWorldError),
+}
+
+impl fmt::Display for LLHDPassError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DuplicatePass(pass) => write!(f, "Pass `{}` is already registered.", pass),
+            Self::UnknownDependency { pass, dependency } => write!(
+                f,
+                "Pass `{}` depends on `{}`, which is not in its schedule.",
+                pass, dependency
+            ),
+            Self::DependencyCycle(pass) => {
+                write!(f, "Pass `{}` is part of a dependency cycle.", pass)
+            }
+            Self::AnalysisEdit(pass) => write!(f, "Analysis pass `{}` edited units.", pass),
+            Self::World(world_error) => write!(f, "{}", world_error),
+        }
+    }
+}
+
+impl std::error::Error for LLHDPassError {}
+
+impl From<LLHDWorldError> for LLHDPassError {
+    fn from(world_error: LLHDWorldError) -> Self {
+        Self::World(world_error)
+    }
+}
+
+/// Unit rewrites queued by transform passes, applied with `LLHDWorld::update_unit` once the pass
+/// has run.
+#[derive(Default, Resource)]
+pub struct LLHDUnitEdits(Vec<(UnitId, UnitData)>);
+
+impl LLHDUnitEdits {
+    pub fn replace(&mut self, unit_id: UnitId, unit_data: UnitData) {
+        self.0.push((unit_id, unit_data));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Debug for LLHDUnitEdits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.0.iter().map(|(unit_id, _unit_data)| unit_id))
+            .finish()
+    }
+}
+
+/// Run time of a pass, and the unit updates applied from its edits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LLHDPassTiming {
+    pub pass: String,
+    pub elapsed: Duration,
+    pub updates: Vec<(UnitId, UnitUpdate)>,
+}
+
+struct LLHDPass {
+    name: String,
+    kind: LLHDPassKind,
+    after: Vec<String>,
+    schedule: Schedule,
+}
+
+impl fmt::Debug for LLHDPass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LLHDPass")
+            .field("name", &self.name)
+            .field("kind", &self.kind)
+            .field("after", &self.after)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Analyses and transforms of an `LLHDWorld`, Bevy systems grouped in named schedules.
+///
+/// Every pass gets a Bevy `Schedule` of its own, kept across runs so its systems see only the
+/// changes made since they last ran, see `LLHDChangedUnits`. Passes of a schedule run after the
+/// passes they depend on, otherwise in registration order.
+#[derive(Debug, Default)]
+pub struct LLHDPassManager {
+    schedules: BTreeMap<String, Vec<LLHDPass>>,
+}
+
+impl LLHDPassManager {
+    pub fn add_pass<M>(
+        &mut self,
+        schedule: &str,
+        name: &str,
+        kind: LLHDPassKind,
+        after: &[&str],
+        systems: impl IntoSystemConfigs<M>,
+    ) -> Result<&mut Self, LLHDPassError> {
+        if self
+            .schedules
+            .values()
+            .flatten()
+            .any(|pass| pass.name == name)
+        {
+            return Err(LLHDPassError::DuplicatePass(name.to_owned()));
+        }
+        let mut pass_schedule = Schedule::new(LLHDPassLabel(name.to_owned()));
+        pass_schedule
+            .set_executor_kind(ExecutorKind::SingleThreaded)
+            .add_systems(systems);
+        self.schedules
+            .entry(schedule.to_owned())
+            .or_default()
+            .push(LLHDPass {
+                name: name.to_owned(),
+                kind,
+                after: after.iter().map(|pass| (*pass).to_owned()).collect(),
+                schedule: pass_schedule,
+            });
+        Ok(self)
+    }
+
+    /// Passes of `schedule` in run order.
+    pub fn passes(&self, schedule: &str) -> Result<Vec<&str>, LLHDPassError> {
+        let order = self.pass_order(schedule)?;
+        let passes = self.schedules.get(schedule).map_or(&[][..], Vec::as_slice);
+        Ok(order
+            .into_iter()
+            .filter_map(|pass_idx| passes.get(pass_idx))
+            .map(|pass| pass.name.as_str())
+            .collect())
+    }
+
+    fn pass_order(&self, schedule: &str) -> Result<Vec<usize>, LLHDPassError> {
+        let Some(passes) = self.schedules.get(schedule) else {
+            return Ok(Vec::new());
+        };
+        let pass_idxs: BTreeMap<&str, usize> = passes
+            .iter()
+            .enumerate()
+            .map(|(pass_idx, pass)| (pass.name.as_str(), pass_idx))
+            .collect();
+        let mut dependents: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::new();
+        for (pass_idx, pass) in passes.iter().enumerate() {
+            for dependency in &pass.after {
+                let dependency_idx = pass_idxs.get(dependency.as_str()).ok_or_else(|| {
+                    LLHDPassError::UnknownDependency {
+                        pass: pass.name.to_owned(),
+                        dependency: dependency.to_owned(),
+                    }
+                })?;
+                dependents
+                    .entry(*dependency_idx)
+                    .or_default()
+                    .insert(pass_idx);
+            }
+        }
+        kahn_order(0..passes.len(), &dependents).map_err(|looped_idx| {
+            LLHDPassError::DependencyCycle(passes[looped_idx].name.to_owned())
+        })
+    }
+}
+
+impl LLHDWorld {
+    /// Runs the passes of `schedule`, applying the edits of each transform before the next pass.
+    ///
+    /// Every unit edited by a pass is checked before any of its edits is applied, so a stale
+    /// `UnitId` leaves the world as the previous pass left it.
+    pub fn run_passes(
+        &mut self,
+        pass_manager: &mut LLHDPassManager,
+        schedule: &str,
+    ) -> Result<Vec<LLHDPassTiming>, LLHDPassError> {
+        let order = pass_manager.pass_order(schedule)?;
+        let Some(passes) = pass_manager.schedules.get_mut(schedule) else {
+            return Ok(Vec::new());
+        };
+        self.world.init_resource::<LLHDUnitEdits>();
+        let mut timings = Vec::with_capacity(order.len());
+        for pass_idx in order {
+            let pass = &mut passes[pass_idx];
+            let start = Instant::now();
+            pass.schedule.run(&mut self.world);
+            let edits = std::mem::take(&mut self.world.resource_mut::<LLHDUnitEdits>().0);
+            if pass.kind == LLHDPassKind::Analysis && !edits.is_empty() {
+                return Err(LLHDPassError::AnalysisEdit(pass.name.to_owned()));
+            }
+            let unit_entities = edits
+                .iter()
+                .map(|(unit_id, _unit_data)| self.unit_entity(*unit_id))
+                .collect::<Result<Vec<_>, _>>()?;
+            let updates = edits
+                .into_iter()
+                .zip(unit_entities)
+                .map(|((unit_id, unit_data), unit_entity)| {
+                    (unit_id, self.replace_unit(unit_id, unit_entity, unit_data))
+                })
+                .collect();
+            timings.push(LLHDPassTiming {
+                pass: pass.name.to_owned(),
+                elapsed: start.elapsed(),
+                updates,
+            });
+        }
+        Ok(timings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::prelude::{Component, Entity, Res, ResMut};
+    use llhd::ir::{Inst, Opcode};
+    use llhd::table::TableKey;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::llhd::module::LLHDModule;
+    use crate::llhd::LLHDUtils;
+    use crate::llhd_world::components::inst::LLHDInstComponent;
+
+    #[derive(Debug, Default, Resource)]
+    struct Visits(Vec<String>);
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Component)]
+    struct OrCount(usize);
+
+    fn pass_world() -> LLHDWorld {
+        let input = indoc::indoc! {"
+            entity @test_entity (i1 %in1, i1 %in2, i1 %in3, i1 %in4) -> (i1$ %out1) {
+                %null = const time 0s 1e
+                %and1 = and i1 %in1, %in2
+                %and2 = and i1 %in3, %in4
+                %or1 = or i1 %and1, %and2
+                drv i1$ %out1, %or1, %null
+            }
+
+            entity @other_entity (i1 %in1) -> (i1$ %out1) {
+                %null = const time 0s 1e
+                drv i1$ %out1, %in1, %null
+            }
+        "};
+        let mut llhd_world = LLHDWorld::new(LLHDModule::from(
+            llhd::assembly::parse_module(input).unwrap(),
+        ));
+        llhd_world.world.init_resource::<Visits>();
+        llhd_world
+    }
+
+    /// Counts the `or` insts of changed units into their `OrCount`.
+    fn count_ors(
+        changed_units: LLHDChangedUnits,
+        module: Res<LLHDModule>,
+        mut visits: ResMut<Visits>,
+    ) {
+        changed_units.iter().for_each(|unit_component| {
+            visits.0.push(unit_component.name.to_string());
+            let unit_id = unit_component.id.expect("Unit should have Id.");
+            let unit = module.unit(unit_id);
+            let ors = unit
+                .all_insts()
+                .filter(|inst| unit[*inst].opcode() == Opcode::Or)
+                .count();
+            visits.0.push(format!("{} or", ors));
+        });
+    }
+
+    /// Swaps the first `or` of every unit into an `and`.
+    fn or_to_and(module: Res<LLHDModule>, mut edits: ResMut<LLHDUnitEdits>) {
+        module.units().for_each(|unit| {
+            let Some(or_inst) = unit
+                .all_insts()
+                .find(|inst| unit[*inst].opcode() == Opcode::Or)
+            else {
+                return;
+            };
+            let mut unit_data = LLHDUtils::clone_unit_data(unit.data());
+            let mut inst_data = unit[or_inst].clone();
+            if let llhd::ir::InstData::Binary { opcode, .. } = &mut inst_data {
+                *opcode = Opcode::And;
+            }
+            llhd::ir::UnitBuilder::new_anonymous(&mut unit_data)[or_inst] = inst_data;
+            edits.replace(unit.id(), unit_data);
+        });
+    }
+
+    /// Swaps the first `or` of the first unit into an `and`, and queues a unit that does not exist.
+    fn or_to_and_stale_unit(module: Res<LLHDModule>, mut edits: ResMut<LLHDUnitEdits>) {
+        let unit = module.units().next().expect("Module should have a unit.");
+        let or_inst = unit
+            .all_insts()
+            .find(|inst| unit[*inst].opcode() == Opcode::Or)
+            .expect("First unit should have an or.");
+        let mut unit_data = LLHDUtils::clone_unit_data(unit.data());
+        let mut inst_data = unit[or_inst].clone();
+        if let llhd::ir::InstData::Binary { opcode, .. } = &mut inst_data {
+            *opcode = Opcode::And;
+        }
+        llhd::ir::UnitBuilder::new_anonymous(&mut unit_data)[or_inst] = inst_data;
+        edits.replace(unit.id(), unit_data);
+        edits.replace(UnitId::new(7), LLHDUtils::clone_unit_data(unit.data()));
+    }
+
+    fn and_insts(llhd_world: &mut LLHDWorld) -> Vec<Inst> {
+        let mut inst_query = llhd_world.query::<(Entity, &LLHDInstComponent)>();
+        let mut and_insts = inst_query
+            .iter(llhd_world.world())
+            .filter(|(_entity, inst_component)| inst_component.data.opcode() == Opcode::And)
+            .filter_map(|(_entity, inst_component)| inst_component.id)
+            .collect::<Vec<_>>();
+        and_insts.sort();
+        and_insts
+    }
+
+    #[test]
+    fn pass_dependency_order() {
+        let mut pass_manager = LLHDPassManager::default();
+        let visit =
+            |name: &'static str| move |mut visits: ResMut<Visits>| visits.0.push(name.to_owned());
+        pass_manager
+            .add_pass("flow", "c", LLHDPassKind::Analysis, &["b", "a"], visit("c"))
+            .unwrap()
+            .add_pass("flow", "b", LLHDPassKind::Analysis, &["a"], visit("b"))
+            .unwrap()
+            .add_pass("flow", "a", LLHDPassKind::Analysis, &[], visit("a"))
+            .unwrap()
+            .add_pass("other", "d", LLHDPassKind::Analysis, &[], visit("d"))
+            .unwrap();
+        assert_eq!(vec!["a", "b", "c"], pass_manager.passes("flow").unwrap());
+        assert_eq!(
+            Err(LLHDPassError::DuplicatePass("a".to_owned())),
+            pass_manager
+                .add_pass("other", "a", LLHDPassKind::Analysis, &[], visit("a"))
+                .map(|_pass_manager| ())
+        );
+
+        let mut llhd_world = pass_world();
+        let timings = llhd_world.run_passes(&mut pass_manager, "flow").unwrap();
+        assert_eq!(
+            vec!["a", "b", "c"],
+            timings
+                .iter()
+                .map(|timing| timing.pass.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(vec!["a", "b", "c"], llhd_world.world.resource::<Visits>().0);
+        assert!(llhd_world
+            .run_passes(&mut pass_manager, "missing")
+            .unwrap()
+            .is_empty());
+
+        pass_manager
+            .add_pass("cycle", "e", LLHDPassKind::Analysis, &["f"], visit("e"))
+            .unwrap()
+            .add_pass("cycle", "f", LLHDPassKind::Analysis, &["e"], visit("f"))
+            .unwrap()
+            .add_pass("unknown", "g", LLHDPassKind::Analysis, &["a"], visit("g"))
+            .unwrap();
+        assert!(matches!(
+            llhd_world.run_passes(&mut pass_manager, "cycle"),
+            Err(LLHDPassError::DependencyCycle(_))
+        ));
+        assert_eq!(
+            Err(LLHDPassError::UnknownDependency {
+                pass: "g".to_owned(),
+                dependency: "a".to_owned()
+            }),
+            llhd_world.run_passes(&mut pass_manager, "unknown")
+        );
+    }
+
+    #[test]
+    fn transform_passes_skip_unchanged_units() {
+        let mut pass_manager = LLHDPassManager::default();
+        pass_manager
+            .add_pass(
+                "flow",
+                "or_to_and",
+                LLHDPassKind::Transform,
+                &["count_ors"],
+                or_to_and,
+            )
+            .unwrap()
+            .add_pass("flow", "count_ors", LLHDPassKind::Analysis, &[], count_ors)
+            .unwrap();
+        let mut llhd_world = pass_world();
+        let timings = llhd_world.run_passes(&mut pass_manager, "flow").unwrap();
+        assert_eq!(
+            vec![("count_ors", 0), ("or_to_and", 1)],
+            timings
+                .iter()
+                .map(|timing| (timing.pass.as_str(), timing.updates.len()))
+                .collect::<Vec<_>>()
+        );
+        assert!(timings[1]
+            .updates
+            .iter()
+            .all(|(_unit_id, update)| !update.is_unchanged()));
+        assert_eq!(
+            vec!["@test_entity", "1 or", "@other_entity", "0 or"],
+            std::mem::take(&mut llhd_world.world.resource_mut::<Visits>().0)
+        );
+        assert_eq!(3, and_insts(&mut llhd_world).len());
+
+        llhd_world.run_passes(&mut pass_manager, "flow").unwrap();
+        assert_eq!(
+            vec!["@test_entity", "0 or"],
+            std::mem::take(&mut llhd_world.world.resource_mut::<Visits>().0),
+            "Only the unit rewritten by the last run changed."
+        );
+
+        llhd_world.run_passes(&mut pass_manager, "flow").unwrap();
+        assert!(llhd_world.world.resource::<Visits>().0.is_empty());
+
+        let mut analysis_manager = LLHDPassManager::default();
+        analysis_manager
+            .add_pass("flow", "or_to_and", LLHDPassKind::Analysis, &[], or_to_and)
+            .unwrap();
+        let mut or_world = pass_world();
+        assert_eq!(
+            Err(LLHDPassError::AnalysisEdit("or_to_and".to_owned())),
+            or_world.run_passes(&mut analysis_manager, "flow")
+        );
+    }
+
+    #[test]
+    fn transform_stale_unit_applies_no_edits() {
+        let mut pass_manager = LLHDPassManager::default();
+        pass_manager
+            .add_pass(
+                "flow",
+                "or_to_and_stale_unit",
+                LLHDPassKind::Transform,
+                &[],
+                or_to_and_stale_unit,
+            )
+            .unwrap();
+        let mut llhd_world = pass_world();
+        assert_eq!(
+            Err(LLHDPassError::World(LLHDWorldError::MissingUnit(
+                UnitId::new(7)
+            ))),
+            llhd_world.run_passes(&mut pass_manager, "flow")
+        );
+        assert_eq!(
+            2,
+            and_insts(&mut llhd_world).len(),
+            "The valid edit queued with the stale unit should not be applied."
+        );
+    }
+}
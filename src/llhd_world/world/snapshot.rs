@@ -0,0 +1,285 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{Read, Write};
+
+use bevy_ecs::prelude::{Component, Entity, World};
+use ciborium::Value as CborValue;
+use llhd::ir::Module;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use super::names::LLHDEntityId;
+use super::LLHDWorld;
+
+type SaveComponent = fn(&World, Entity) -> Option<Result<CborValue, String>>;
+type LoadComponent = fn(&mut World, Entity, &CborValue) -> Result<(), String>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LLHDSnapshotError {
+    Encode(String),
+    Decode(String),
+    UnknownComponent(String),
+    MissingEntity(LLHDEntityId),
+}
+
+impl fmt::Display for LLHDSnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Encode(message) => write!(f, "Snapshot encoding failed: {}", message),
+            Self::Decode(message) => write!(f, "Snapshot decoding failed: {}", message),
+            Self::UnknownComponent(name) => write!(f, "Component {} is not registered", name),
+            Self::MissingEntity(id) => write!(f, "No entity for {:?}", id),
+        }
+    }
+}
+
+impl std::error::Error for LLHDSnapshotError {}
+
+fn save_component<T: Component + Serialize>(
+    world: &World,
+    entity: Entity,
+) -> Option<Result<CborValue, String>> {
+    world
+        .get::<T>(entity)
+        .map(|component| CborValue::serialized(component).map_err(|error| error.to_string()))
+}
+
+fn load_component<T: Component + DeserializeOwned>(
+    world: &mut World,
+    entity: Entity,
+    value: &CborValue,
+) -> Result<(), String> {
+    let component = value
+        .deserialized::<T>()
+        .map_err(|error| error.to_string())?;
+    world.entity_mut(entity).insert(component);
+    Ok(())
+}
+
+/// Components saved into and restored from an `LLHDWorldSnapshot`, by name.
+#[derive(Debug, Clone, Default)]
+pub struct LLHDComponentRegistry {
+    components: BTreeMap<String, (SaveComponent, LoadComponent)>,
+}
+
+impl LLHDComponentRegistry {
+    pub fn register<T: Component + Serialize + DeserializeOwned>(
+        &mut self,
+        name: &str,
+    ) -> &mut Self {
+        self.components
+            .insert(name.to_owned(), (save_component::<T>, load_component::<T>));
+        self
+    }
+}
+
+/// LLHD module of an `LLHDWorld` and its registered components, each keyed by the
+/// `LLHDEntityId` of its entity so it survives the entities being spawned anew.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LLHDWorldSnapshot {
+    module: CborValue,
+    components: Vec<(LLHDEntityId, String, CborValue)>,
+}
+
+impl LLHDWorldSnapshot {
+    pub fn to_writer<W: Write>(&self, writer: W) -> Result<(), LLHDSnapshotError> {
+        ciborium::into_writer(self, writer)
+            .map_err(|error| LLHDSnapshotError::Encode(error.to_string()))
+    }
+
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self, LLHDSnapshotError> {
+        ciborium::from_reader(reader).map_err(|error| LLHDSnapshotError::Decode(error.to_string()))
+    }
+}
+
+impl LLHDWorld {
+    /// Every LLHD object with an entity, units, blocks, insts, value defs and value refs.
+    fn keyed_entities(&self) -> Vec<(LLHDEntityId, Entity)> {
+        let units = self
+            .unit_map
+            .iter()
+            .map(|(unit_id, entity)| (LLHDEntityId::Unit(*unit_id), *entity));
+        let blocks = self.unit_map.iter().flat_map(|(unit_id, unit_entity)| {
+            self.block_entities(*unit_entity)
+                .into_iter()
+                .map(|(block, entity)| (LLHDEntityId::Block(*unit_id, block), entity))
+        });
+        let insts = self
+            .inst_map
+            .iter()
+            .map(|((unit_id, inst), entity)| (LLHDEntityId::Inst(*unit_id, *inst), *entity));
+        let value_defs = self
+            .value_def_map
+            .iter()
+            .map(|((unit_id, value), entity)| (LLHDEntityId::Value(*unit_id, *value), *entity));
+        let value_refs = self
+            .value_ref_map
+            .iter()
+            .map(|((unit_id, inst, value), entity)| {
+                (LLHDEntityId::ValueRef(*unit_id, *inst, *value), *entity)
+            });
+        let mut keyed_entities = units
+            .chain(blocks)
+            .chain(insts)
+            .chain(value_defs)
+            .chain(value_refs)
+            .collect::<Vec<_>>();
+        keyed_entities.sort_unstable_by_key(|(id, _entity)| *id);
+        keyed_entities
+    }
+
+    fn keyed_entity(&self, id: LLHDEntityId) -> Option<Entity> {
+        match id {
+            LLHDEntityId::Unit(unit_id) => self.unit_map.get(&unit_id).copied(),
+            LLHDEntityId::Block(unit_id, block) => self
+                .unit_map
+                .get(&unit_id)
+                .and_then(|unit_entity| self.block_entities(*unit_entity).get(&block).copied()),
+            LLHDEntityId::Inst(unit_id, inst) => self.inst_map.get(&(unit_id, inst)).copied(),
+            LLHDEntityId::Value(unit_id, value) => {
+                self.value_def_map.get(&(unit_id, value)).copied()
+            }
+            LLHDEntityId::ValueRef(unit_id, inst, value) => {
+                self.value_ref_map.get(&(unit_id, inst, value)).copied()
+            }
+        }
+    }
+
+    /// Module and `registry` components of the world, the edit history is not kept.
+    pub fn snapshot(
+        &self,
+        registry: &LLHDComponentRegistry,
+    ) -> Result<LLHDWorldSnapshot, LLHDSnapshotError> {
+        let module = CborValue::serialized::<Module>(&**self.module())
+            .map_err(|error| LLHDSnapshotError::Encode(error.to_string()))?;
+        let mut components = Vec::new();
+        for (id, entity) in self.keyed_entities() {
+            for (name, (save, _load)) in &registry.components {
+                if let Some(value) = save(&self.world, entity) {
+                    let value = value.map_err(|error| {
+                        LLHDSnapshotError::Encode(format!("{} of {:?}: {}", name, id, error))
+                    })?;
+                    components.push((id, name.to_owned(), value));
+                }
+            }
+        }
+        Ok(LLHDWorldSnapshot { module, components })
+    }
+
+    /// World of the snapshot module, its entities given the components of `snapshot`.
+    pub fn restore(
+        snapshot: &LLHDWorldSnapshot,
+        registry: &LLHDComponentRegistry,
+    ) -> Result<Self, LLHDSnapshotError> {
+        let module = snapshot
+            .module
+            .deserialized::<Module>()
+            .map_err(|error| LLHDSnapshotError::Decode(error.to_string()))?;
+        let mut llhd_world = Self::new(module.into());
+        for (id, name, value) in &snapshot.components {
+            let (_save, load) = registry
+                .components
+                .get(name)
+                .ok_or_else(|| LLHDSnapshotError::UnknownComponent(name.to_owned()))?;
+            let entity = llhd_world
+                .keyed_entity(*id)
+                .ok_or(LLHDSnapshotError::MissingEntity(*id))?;
+            load(&mut llhd_world.world, entity, value).map_err(|error| {
+                LLHDSnapshotError::Decode(format!("{} of {:?}: {}", name, id, error))
+            })?;
+        }
+        Ok(llhd_world)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use euclid::default::{Box2D, Point2D};
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::llhd::module::LLHDModule;
+    use crate::llhd_world::placement::LLHDInstPlacement;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Component, Serialize, Deserialize)]
+    struct Slack(i64);
+
+    fn snapshot_world() -> LLHDWorld {
+        LLHDWorld::new(LLHDModule::from(
+            llhd::assembly::parse_module(indoc::indoc! {"
+                entity @top (i1 %a, i1 %b, i1 %c) -> (i1$ %out1) {
+                    %epsilon = const time 0s 1e
+                    %and1 = and i1 %a, %b
+                    %and2 = and i1 %and1, %c
+                    drv i1$ %out1, %and2, %epsilon
+                }
+            "})
+            .unwrap(),
+        ))
+    }
+
+    fn registry() -> LLHDComponentRegistry {
+        let mut registry = LLHDComponentRegistry::default();
+        registry
+            .register::<LLHDInstPlacement>("placement")
+            .register::<Slack>("slack");
+        registry
+    }
+
+    #[test]
+    fn llhd_world_snapshot_round_trip() {
+        let mut llhd_world = snapshot_world();
+        let unit_id = llhd_world.module().units().next().unwrap().id();
+        let insts = llhd_world
+            .module()
+            .unit(unit_id)
+            .all_insts()
+            .collect::<Vec<_>>();
+        let placement = LLHDInstPlacement::new(Box2D::new(Point2D::new(0, 0), Point2D::new(4, 2)));
+        llhd_world.set_inst(unit_id, insts[1], placement).unwrap();
+        llhd_world.set_inst(unit_id, insts[2], Slack(-3)).unwrap();
+
+        let mut bytes = Vec::new();
+        llhd_world
+            .snapshot(&registry())
+            .unwrap()
+            .to_writer(&mut bytes)
+            .unwrap();
+        let snapshot = LLHDWorldSnapshot::from_reader(bytes.as_slice()).unwrap();
+        assert_eq!(2, snapshot.components.len());
+        let restored = LLHDWorld::restore(&snapshot, &registry()).unwrap();
+
+        assert_eq!(
+            llhd_world.module().dump().to_string(),
+            restored.module().dump().to_string()
+        );
+        assert_eq!(
+            Ok(&placement),
+            restored.get_inst::<LLHDInstPlacement>(unit_id, insts[1])
+        );
+        assert_eq!(
+            Ok(&Slack(-3)),
+            restored.get_inst::<Slack>(unit_id, insts[2])
+        );
+        assert!(restored.get_inst::<Slack>(unit_id, insts[1]).is_err());
+        assert!(restored.lookup("@top.%and2").is_some());
+    }
+
+    #[test]
+    fn llhd_world_snapshot_unknown_component() {
+        let mut llhd_world = snapshot_world();
+        let unit_id = llhd_world.module().units().next().unwrap().id();
+        let inst = llhd_world
+            .module()
+            .unit(unit_id)
+            .all_insts()
+            .nth(1)
+            .unwrap();
+        llhd_world.set_inst(unit_id, inst, Slack(1)).unwrap();
+        let snapshot = llhd_world.snapshot(&registry()).unwrap();
+        assert_eq!(
+            Some(LLHDSnapshotError::UnknownComponent("slack".to_owned())),
+            LLHDWorld::restore(&snapshot, &LLHDComponentRegistry::default()).err()
+        );
+    }
+}
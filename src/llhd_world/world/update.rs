@@ -0,0 +1,540 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy_ecs::prelude::Entity;
+use bevy_hierarchy::{despawn_with_children_recursive, BuildWorldChildren, Children};
+use llhd::ir::{Block, Inst, InstData, Unit, UnitData, UnitId, Value};
+
+use super::{ECSEntityName, LLHDWorld, LLHDWorldError};
+use crate::llhd::module::LLHDModule;
+use crate::llhd::LLHDUtils;
+use crate::llhd_world::components::block::LLHDBlockComponent;
+use crate::llhd_world::components::unit::LLHDUnitComponent;
+use crate::llhd_world::components::value::LLHDValueRefComponent;
+use crate::llhd_world::initializer::{
+    build_blocks, build_insts, build_value_defs, build_value_refs,
+};
+
+/// Inst changes applied by `LLHDWorld::update_unit`.
+///
+/// `kept` pairs the previous `Inst` of an entity with its `Inst` in the updated unit, `removed`
+/// holds previous `Inst`s and `added` the `Inst`s of the updated unit.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UnitUpdate {
+    pub kept: Vec<(Inst, Inst)>,
+    pub removed: Vec<Inst>,
+    pub added: Vec<Inst>,
+}
+
+impl UnitUpdate {
+    pub fn is_unchanged(&self) -> bool {
+        self.removed.is_empty() && self.added.is_empty()
+    }
+}
+
+/// Structural correspondence between a unit and its rewrite, previous ids keyed by new ids.
+struct UnitDiff {
+    blocks: Vec<(Option<Block>, Block)>,
+    insts: HashMap<Inst, Inst>,
+    values: HashMap<Value, Value>,
+}
+
+/// Matches arguments by position and type, then every inst of `new` whose data, once its
+/// arguments, blocks and external units are renamed to `old` ids, equals a not yet matched inst
+/// of `old`, the inst with the same id first.
+fn diff_units(old: &Unit, new: &Unit) -> UnitDiff {
+    let old_blocks = old.blocks().collect::<Vec<_>>();
+    let blocks = new
+        .blocks()
+        .enumerate()
+        .map(|(position, block)| (old_blocks.get(position).copied(), block))
+        .collect::<Vec<_>>();
+    let block_map = blocks
+        .iter()
+        .filter_map(|(old_block, new_block)| old_block.map(|old_block| (*new_block, old_block)))
+        .collect::<HashMap<_, _>>();
+    let mut values = old
+        .args()
+        .zip(new.args())
+        .filter(|(old_arg, new_arg)| old.value_type(*old_arg) == new.value_type(*new_arg))
+        .map(|(old_arg, new_arg)| (new_arg, old_arg))
+        .collect::<HashMap<_, _>>();
+    let old_externs = old
+        .extern_units()
+        .map(|(ext_unit, ext_unit_data)| (&ext_unit_data.name, ext_unit))
+        .collect::<HashMap<_, _>>();
+    let mut candidates: HashMap<InstData, Vec<Inst>> = HashMap::new();
+    old.all_insts()
+        .for_each(|inst| candidates.entry(old[inst].clone()).or_default().push(inst));
+
+    let mut insts = HashMap::new();
+    for new_inst in new.all_insts() {
+        let Some(data) = LLHDUtils::rename_inst_data(
+            &new[new_inst],
+            |arg| values.get(&arg).copied(),
+            |block| block_map.get(&block).copied(),
+            |ext_unit| old_externs.get(new.extern_name(ext_unit)).copied(),
+        ) else {
+            continue;
+        };
+        let Some(old_insts) = candidates.get_mut(&data) else {
+            continue;
+        };
+        let same_type = |old_inst: &Inst| old.inst_type(*old_inst) == new.inst_type(new_inst);
+        let Some(position) = old_insts
+            .iter()
+            .position(|old_inst| *old_inst == new_inst && same_type(old_inst))
+            .or_else(|| old_insts.iter().position(same_type))
+        else {
+            continue;
+        };
+        let old_inst = old_insts.remove(position);
+        if let (Some(old_value), Some(new_value)) =
+            (old.get_inst_result(old_inst), new.get_inst_result(new_inst))
+        {
+            values.insert(new_value, old_value);
+        }
+        insts.insert(new_inst, old_inst);
+    }
+    UnitDiff {
+        blocks,
+        insts,
+        values,
+    }
+}
+
+impl LLHDWorld {
+    /// Replaces the data of `unit_id` by `unit_data`, e.g. a unit extracted from an e-graph,
+    /// without rebuilding the world.
+    ///
+    /// Insts and values structurally unchanged by the rewrite keep their entities, with every
+    /// component other than the LLHD ones left in place, and only the difference is despawned
+    /// or spawned.
+    pub fn update_unit(
+        &mut self,
+        unit_id: UnitId,
+        unit_data: UnitData,
+    ) -> Result<UnitUpdate, LLHDWorldError> {
+        let unit_entity = self.unit_entity(unit_id)?;
+        Ok(self.replace_unit(unit_id, unit_entity, unit_data))
+    }
+
+    /// `update_unit` of a unit whose `unit_entity` was already looked up.
+    pub(super) fn replace_unit(
+        &mut self,
+        unit_id: UnitId,
+        unit_entity: Entity,
+        unit_data: UnitData,
+    ) -> UnitUpdate {
+        let old_unit_data = std::mem::replace(
+            self.world
+                .resource_mut::<LLHDModule>()
+                .unit_mut(unit_id)
+                .data(),
+            unit_data,
+        );
+        self.sync_unit(unit_id, unit_entity, &old_unit_data)
+    }
+
+    /// Brings `unit_entity` of `unit_id`, built from `old_unit_data`, in line with the unit now
+    /// held by the `LLHDModule`.
+    pub(super) fn sync_unit(
+        &mut self,
+        unit_id: UnitId,
+        unit_entity: Entity,
+        old_unit_data: &UnitData,
+    ) -> UnitUpdate {
+        let module = self
+            .world
+            .remove_resource::<LLHDModule>()
+            .expect("Missing LLHDModule");
+        let new_unit = module.unit(unit_id);
+        let unit_name = ECSEntityName(new_unit.name().to_string());
+        let diff = diff_units(&Unit::new(unit_id, old_unit_data), &new_unit);
+        let kept_insts = diff
+            .insts
+            .iter()
+            .map(|(new_inst, old_inst)| (*old_inst, *new_inst))
+            .collect::<HashMap<_, _>>();
+        let kept_values = diff
+            .values
+            .iter()
+            .map(|(new_value, old_value)| (*old_value, *new_value))
+            .collect::<HashMap<_, _>>();
+        let mut update = UnitUpdate::default();
+
+        let old_insts = take_unit_entries(&mut self.inst_map, unit_id);
+        let old_value_defs = take_unit_entries(&mut self.value_def_map, unit_id);
+        let old_value_refs = take_unit_entries(&mut self.value_ref_map, unit_id);
+        let mut inst_entities = HashMap::new();
+        for ((_unit_id, old_inst), entity) in old_insts {
+            match kept_insts.get(&old_inst) {
+                Some(new_inst) => {
+                    inst_entities.insert(*new_inst, entity);
+                    update.kept.push((old_inst, *new_inst));
+                }
+                None => {
+                    despawn_with_children_recursive(&mut self.world, entity);
+                    update.removed.push(old_inst);
+                }
+            }
+        }
+        for ((_unit_id, old_inst, old_value), entity) in old_value_refs {
+            let kept_ref = kept_insts.get(&old_inst).zip(kept_values.get(&old_value));
+            if let Some((new_inst, new_value)) = kept_ref {
+                self.world.entity_mut(entity).insert(LLHDValueRefComponent {
+                    id: Some(*new_value),
+                    inst: Some(*new_inst),
+                });
+                self.value_ref_map
+                    .insert((unit_id, *new_inst, *new_value), entity);
+            }
+        }
+        let mut value_entities = HashMap::new();
+        for ((_unit_id, old_value), entity) in old_value_defs {
+            match kept_values.get(&old_value) {
+                Some(new_value) => {
+                    value_entities.insert(*new_value, entity);
+                }
+                None => despawn_with_children_recursive(&mut self.world, entity),
+            }
+        }
+
+        let old_block_entities = self
+            .world
+            .get::<Children>(unit_entity)
+            .map(|children| children.to_vec())
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|entity| {
+                self.world
+                    .get::<LLHDBlockComponent>(entity)
+                    .and_then(|block_component| block_component.id)
+                    .map(|block_id| (block_id, entity))
+            })
+            .collect::<HashMap<_, _>>();
+        let mut unit_children = Vec::new();
+        for (block_component, (old_block, _new_block)) in build_blocks(&new_unit).zip(&diff.blocks)
+        {
+            let block_id = block_component.id.expect("Block should have Id.");
+            let block_name = unit_name.to_owned()
+                + ECSEntityName(".".to_owned())
+                + ECSEntityName(
+                    block_component
+                        .data
+                        .name
+                        .to_owned()
+                        .unwrap_or(block_id.to_string()),
+                );
+            let block_entity = match old_block.and_then(|block| old_block_entities.get(&block)) {
+                Some(entity) => {
+                    self.world
+                        .entity_mut(*entity)
+                        .insert((block_component, block_name));
+                    *entity
+                }
+                None => self.world.spawn((block_component, block_name)).id(),
+            };
+            let block_children = build_insts(&new_unit, block_id)
+                .map(|inst_component| {
+                    let inst_id = inst_component.id.expect("Inst should have Id.");
+                    let inst_name = unit_name.to_owned()
+                        + ECSEntityName(".".to_owned())
+                        + ECSEntityName(inst_id.to_string());
+                    let inst_entity = match inst_entities.get(&inst_id) {
+                        Some(entity) => {
+                            self.world
+                                .entity_mut(*entity)
+                                .insert((inst_component, inst_name));
+                            *entity
+                        }
+                        None => {
+                            let inst_data = inst_component.data.to_owned();
+                            let entity = self.world.spawn((inst_component, inst_name)).id();
+                            let value_refs = build_value_refs(inst_id, &inst_data)
+                                .map(|value_ref_component| {
+                                    let value_id = value_ref_component
+                                        .id
+                                        .expect("Unexpected missing Value Def in ValueRef.");
+                                    let value_ref_entity =
+                                        self.world.spawn(value_ref_component).id();
+                                    self.value_ref_map
+                                        .insert((unit_id, inst_id, value_id), value_ref_entity);
+                                    value_ref_entity
+                                })
+                                .collect::<Vec<_>>();
+                            self.world.entity_mut(entity).push_children(&value_refs);
+                            update.added.push(inst_id);
+                            entity
+                        }
+                    };
+                    self.inst_map.insert((unit_id, inst_id), inst_entity);
+                    inst_entity
+                })
+                .collect::<Vec<_>>();
+            self.world
+                .entity_mut(block_entity)
+                .replace_children(&block_children);
+            unit_children.push(block_entity);
+        }
+        let kept_blocks = unit_children.iter().collect::<HashSet<_>>();
+        old_block_entities
+            .values()
+            .filter(|entity| !kept_blocks.contains(entity))
+            .for_each(|entity| despawn_with_children_recursive(&mut self.world, *entity));
+
+        for value_component in build_value_defs(&new_unit) {
+            let value_id = value_component.id.expect("Value should have Id.");
+            let value_name = unit_name.to_owned()
+                + ECSEntityName(".".to_owned())
+                + ECSEntityName(value_id.to_string());
+            let value_entity = match value_entities.get(&value_id) {
+                Some(entity) => {
+                    self.world
+                        .entity_mut(*entity)
+                        .insert((value_component, value_name));
+                    *entity
+                }
+                None => self.world.spawn((value_component, value_name)).id(),
+            };
+            self.value_def_map.insert((unit_id, value_id), value_entity);
+            unit_children.push(value_entity);
+        }
+        self.world
+            .entity_mut(unit_entity)
+            .replace_children(&unit_children);
+        self.world.entity_mut(unit_entity).insert((
+            LLHDUnitComponent {
+                id: Some(unit_id),
+                name: new_unit.name().to_owned(),
+                kind: new_unit.kind(),
+            },
+            unit_name,
+        ));
+
+        self.world.insert_resource(module);
+        self.index_unit_names(unit_id);
+        update.kept.sort();
+        update.removed.sort();
+        update.added.sort();
+        update
+    }
+
+    /// Spawns the entities of `unit_id`, a unit added to the `LLHDModule` after the world was
+    /// built.
+    pub(super) fn spawn_unit(&mut self, unit_id: UnitId) -> UnitUpdate {
+        let unit_entity = self.world.spawn_empty().id();
+        self.unit_map.insert(unit_id, unit_entity);
+        let unit = self.module().unit(unit_id);
+        let empty_unit_data =
+            UnitData::new(unit.kind(), unit.name().to_owned(), unit.sig().to_owned());
+        self.sync_unit(unit_id, unit_entity, &empty_unit_data)
+    }
+
+    /// Despawns the entities of `unit_id`, before the unit is removed from the `LLHDModule`.
+    pub(super) fn despawn_unit(&mut self, unit_id: UnitId) {
+        self.unindex_unit_names(unit_id);
+        if let Some(unit_entity) = self.unit_map.remove(&unit_id) {
+            despawn_with_children_recursive(&mut self.world, unit_entity);
+        }
+        take_unit_entries(&mut self.inst_map, unit_id);
+        take_unit_entries(&mut self.value_def_map, unit_id);
+        take_unit_entries(&mut self.value_ref_map, unit_id);
+    }
+}
+
+/// Removes and returns the entries of `unit_id` in one of the index maps.
+fn take_unit_entries<K>(map: &mut HashMap<K, Entity>, unit_id: UnitId) -> Vec<(K, Entity)>
+where
+    K: Copy + Eq + std::hash::Hash + UnitKey,
+{
+    let keys = map
+        .keys()
+        .filter(|key| key.unit_id() == unit_id)
+        .copied()
+        .collect::<Vec<_>>();
+    keys.into_iter()
+        .filter_map(|key| map.remove(&key).map(|entity| (key, entity)))
+        .collect()
+}
+
+/// Index map keys scoped by a unit.
+trait UnitKey {
+    fn unit_id(&self) -> UnitId;
+}
+
+impl UnitKey for (UnitId, Inst) {
+    fn unit_id(&self) -> UnitId {
+        self.0
+    }
+}
+
+impl UnitKey for (UnitId, Value) {
+    fn unit_id(&self) -> UnitId {
+        self.0
+    }
+}
+
+impl UnitKey for (UnitId, Inst, Value) {
+    fn unit_id(&self) -> UnitId {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::prelude::Component;
+    use bevy_hierarchy::Parent;
+    use llhd::ir::Opcode;
+    use llhd::table::TableKey;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::llhd_world::components::inst::LLHDInstComponent;
+    use crate::llhd_world::components::value::LLHDValueDefComponent;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Component)]
+    struct Tag(usize);
+
+    const ENTITY: &str = indoc::indoc! {"
+        entity @test_entity (i1 %in1, i1 %in2, i1 %in3, i1 %in4) -> (i1$ %out1) {
+            %null = const time 0s 1e
+            %and1 = and i1 %in1, %in2
+            %and2 = and i1 %in3, %in4
+            %or1 = or i1 %and1, %and2
+            drv i1$ %out1, %or1, %null
+        }
+    "};
+
+    fn unit_data(input: &str) -> UnitData {
+        let mut module = llhd::assembly::parse_module(input).unwrap();
+        let unit_id = module.units().next().unwrap().id();
+        let mut unit = module.unit_mut(unit_id);
+        let empty_unit = UnitData::new(unit.kind(), unit.name().clone(), unit.sig().clone());
+        std::mem::replace(unit.data(), empty_unit)
+    }
+
+    fn tagged_world() -> (LLHDWorld, UnitId) {
+        let module = llhd::assembly::parse_module(ENTITY).unwrap();
+        let mut llhd_world = LLHDWorld::new(LLHDModule::from(module));
+        let unit_id = llhd_world.module().units().next().unwrap().id();
+        let insts = llhd_world
+            .module()
+            .unit(unit_id)
+            .all_insts()
+            .collect::<Vec<_>>();
+        insts.into_iter().enumerate().for_each(|(index, inst)| {
+            llhd_world.set_inst(unit_id, inst, Tag(index)).unwrap();
+        });
+        (llhd_world, unit_id)
+    }
+
+    /// Every inst, value and argument of the module unit is indexed, parented and named.
+    fn assert_consistent(llhd_world: &LLHDWorld, unit_id: UnitId) {
+        let unit = llhd_world.module().unit(unit_id);
+        let world = llhd_world.world();
+        assert_eq!(
+            unit.all_insts().count(),
+            llhd_world
+                .inst_map
+                .keys()
+                .filter(|(inst_unit, _inst)| *inst_unit == unit_id)
+                .count()
+        );
+        for inst in unit.all_insts() {
+            let entity = llhd_world.inst_map[&(unit_id, inst)];
+            let inst_component = world.get::<LLHDInstComponent>(entity).unwrap();
+            assert_eq!(Some(inst), inst_component.id);
+            assert_eq!(unit[inst], inst_component.data);
+            assert!(world.get::<Parent>(entity).is_some());
+            for arg in unit[inst].args() {
+                let value_ref = llhd_world.value_ref_map[&(unit_id, inst, *arg)];
+                assert_eq!(
+                    Some(entity),
+                    world.get::<Parent>(value_ref).map(|parent| parent.get())
+                );
+            }
+        }
+        for value in unit.args().chain(
+            unit.all_insts()
+                .filter_map(|inst| unit.get_inst_result(inst)),
+        ) {
+            let entity = llhd_world.value_def_map[&(unit_id, value)];
+            assert_eq!(
+                Some(value),
+                world.get::<LLHDValueDefComponent>(entity).unwrap().id
+            );
+        }
+    }
+
+    #[test]
+    fn update_unchanged_unit() {
+        let (mut llhd_world, unit_id) = tagged_world();
+        let entities = llhd_world.inst_map.to_owned();
+        let update = llhd_world.update_unit(unit_id, unit_data(ENTITY)).unwrap();
+        assert!(update.is_unchanged());
+        assert_eq!(6, update.kept.len());
+        assert_eq!(entities, llhd_world.inst_map);
+        assert_consistent(&llhd_world, unit_id);
+    }
+
+    #[test]
+    fn update_stale_unit() {
+        let (mut llhd_world, unit_id) = tagged_world();
+        let stale_unit_id = UnitId::new(unit_id.index() + 1);
+        assert_eq!(
+            Err(LLHDWorldError::MissingUnit(stale_unit_id)),
+            llhd_world.update_unit(stale_unit_id, unit_data(ENTITY))
+        );
+        assert_consistent(&llhd_world, unit_id);
+    }
+
+    #[test]
+    fn update_rewritten_unit() {
+        let (mut llhd_world, unit_id) = tagged_world();
+        let or_entity = {
+            let unit = llhd_world.module().unit(unit_id);
+            let or_inst = unit
+                .all_insts()
+                .find(|inst| unit[*inst].opcode() == Opcode::Or)
+                .unwrap();
+            llhd_world.inst_map[&(unit_id, or_inst)]
+        };
+        let rewritten = indoc::indoc! {"
+            entity @test_entity (i1 %in1, i1 %in2, i1 %in3, i1 %in4) -> (i1$ %out1) {
+                %null = const time 0s 1e
+                %and1 = and i1 %in1, %in2
+                %or2 = or i1 %in3, %in4
+                %not2 = not i1 %or2
+                %or1 = or i1 %and1, %not2
+                drv i1$ %out1, %or1, %null
+            }
+        "};
+        let update = llhd_world
+            .update_unit(unit_id, unit_data(rewritten))
+            .unwrap();
+        assert_eq!(3, update.kept.len());
+        assert_eq!(3, update.removed.len());
+        assert_eq!(4, update.added.len());
+        assert_consistent(&llhd_world, unit_id);
+        assert!(
+            llhd_world.world().get_entity(or_entity).is_none(),
+            "Rewritten `or` inst should be despawned."
+        );
+
+        let unit = llhd_world.module().unit(unit_id);
+        let tagged = unit
+            .all_insts()
+            .filter_map(|inst| llhd_world.get_inst::<Tag>(unit_id, inst).ok().copied())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![Tag(0), Tag(1), Tag(5)],
+            tagged,
+            "Only unchanged insts keep their user components."
+        );
+        let or_count = unit
+            .all_insts()
+            .filter(|inst| unit[*inst].opcode() == Opcode::Or)
+            .count();
+        assert_eq!(2, or_count);
+    }
+}
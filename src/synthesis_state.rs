@@ -45,12 +45,14 @@ use typestate::typestate;
 #[typestate]
 pub mod builder {
     use crate::llhd::module::LLHDModule;
+    use crate::llhd_world::world::passes::{LLHDPassError, LLHDPassManager, SYNTHESIS_SCHEDULE};
     use crate::llhd_world::world::LLHDWorld;
 
     #[derive(Debug)]
     #[automaton]
     pub struct Flow {
         world: LLHDWorld,
+        passes: LLHDPassManager,
     }
 
     #[state]
@@ -59,6 +61,16 @@ pub mod builder {
     pub struct Technology;
     #[state]
     pub struct Synthesis;
+    #[state]
+    pub struct Failed {
+        error: LLHDPassError,
+    }
+
+    /// Outcome of `synthesize`, the flow fails when a pass of `SYNTHESIS_SCHEDULE` errors.
+    pub enum Synthesized {
+        Design,
+        Failed,
+    }
 
     pub trait Design {
         fn load(module: LLHDModule) -> Technology;
@@ -70,7 +82,11 @@ pub mod builder {
     }
 
     pub trait Synthesis {
-        fn synthesize(self) -> Design;
+        fn synthesize(self) -> Synthesized;
+    }
+
+    pub trait Failed {
+        fn error(self) -> LLHDPassError;
     }
 
     impl DesignState for Flow<Design> {
@@ -78,6 +94,7 @@ pub mod builder {
             let world = LLHDWorld::new(module);
             Flow::<Technology> {
                 world,
+                passes: LLHDPassManager::default(),
                 state: Technology,
             }
         }
@@ -89,13 +106,48 @@ pub mod builder {
 
     impl TechnologyState for Flow<Technology> {
         fn constrain(self) -> Flow<Synthesis> {
-            todo!()
+            Flow::<Synthesis> {
+                world: self.world,
+                passes: self.passes,
+                state: Synthesis,
+            }
         }
     }
 
     impl SynthesisState for Flow<Synthesis> {
-        fn synthesize(self) -> Flow<Design> {
-            todo!()
+        /// Runs the passes of the `synthesis` schedule, see `Flow::<Synthesis>::passes_mut`.
+        fn synthesize(mut self) -> Synthesized {
+            match self.world.run_passes(&mut self.passes, SYNTHESIS_SCHEDULE) {
+                Ok(_) => Synthesized::Design(Flow::<Design> {
+                    world: self.world,
+                    passes: self.passes,
+                    state: Design,
+                }),
+                Err(error) => Synthesized::Failed(Flow::<Failed> {
+                    world: self.world,
+                    passes: self.passes,
+                    state: Failed { error },
+                }),
+            }
+        }
+    }
+
+    impl FailedState for Flow<Failed> {
+        /// The pass error that stopped `synthesize`.
+        fn error(self) -> LLHDPassError {
+            self.state.error
+        }
+    }
+
+    impl Flow<Synthesis> {
+        /// Pass manager of the flow, custom passes join `SYNTHESIS_SCHEDULE` to run on
+        /// `synthesize`.
+        pub fn passes_mut(&mut self) -> &mut LLHDPassManager {
+            &mut self.passes
+        }
+
+        pub const fn world(&self) -> &LLHDWorld {
+            &self.world
         }
     }
 }